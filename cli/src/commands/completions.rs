@@ -0,0 +1,69 @@
+// Copyright (C) 2019-2023 Aleo Systems Inc.
+// This file is part of the snarkOS library.
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at:
+// http://www.apache.org/licenses/LICENSE-2.0
+
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::commands::CLI;
+
+use anyhow::Result;
+use clap::{CommandFactory, Parser};
+use clap_complete::Shell;
+
+/// Generates the shell completion script for snarkOS.
+#[derive(Debug, Parser)]
+pub struct Completions {
+    /// Specify the shell to generate the completion script for [options: bash, zsh, fish, powershell, elvish]
+    #[clap(value_enum)]
+    pub shell: Shell,
+}
+
+impl Completions {
+    /// The name of the snarkOS binary, which the completions are registered for.
+    const BINARY_NAME: &'static str = "snarkos";
+
+    /// Generates the shell completion script.
+    pub fn parse(self) -> Result<String> {
+        // Retrieve the command from the derived CLI.
+        let mut command = CLI::command();
+        // Generate the completion script.
+        let mut script = Vec::new();
+        clap_complete::generate(self.shell, &mut command, Self::BINARY_NAME, &mut script);
+
+        Ok(String::from_utf8(script)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::Command;
+
+    #[test]
+    fn test_completions() {
+        for shell in ["bash", "zsh", "fish", "powershell", "elvish"] {
+            let cli = CLI::try_parse_from(["snarkos", "completions", shell]).unwrap();
+            let Command::Completions(completions) = cli.command else {
+                panic!("Unexpected result of clap parsing!");
+            };
+            let script = completions.parse().unwrap();
+            assert!(script.contains(Completions::BINARY_NAME));
+        }
+    }
+
+    #[test]
+    fn test_unsupported_shell() {
+        let error = CLI::try_parse_from(["snarkos", "completions", "tcsh"]).unwrap_err();
+        assert_eq!(error.kind(), clap::error::ErrorKind::InvalidValue);
+        // Ensure the error lists the supported shells.
+        assert!(error.to_string().contains("possible values"));
+    }
+}
@@ -0,0 +1,156 @@
+// Copyright (C) 2019-2023 Aleo Systems Inc.
+// This file is part of the snarkOS library.
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at:
+// http://www.apache.org/licenses/LICENSE-2.0
+
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use anyhow::{anyhow, bail, Result};
+use clap::{parser::ValueSource, ArgMatches, CommandFactory};
+use serde_json::{Map, Value};
+use std::path::{Path, PathBuf};
+
+/// The options of a command, as specified in a TOML or YAML config file.
+///
+/// The keys are the long names of the command's options (e.g. `private-key`), and the values
+/// are only applied to the options which were not specified on the command line.
+#[derive(Clone, Debug)]
+pub struct ConfigFile {
+    /// The path to the config file.
+    path: PathBuf,
+    /// The options specified in the config file.
+    options: Map<String, Value>,
+}
+
+impl ConfigFile {
+    /// Loads the config file from the given path; the format is determined by the file extension.
+    pub fn load(path: &Path) -> Result<Self> {
+        let contents = std::fs::read_to_string(path)
+            .map_err(|error| anyhow!("Failed to read the config file '{}': {error}", path.display()))?;
+        let extension = path.extension().and_then(|extension| extension.to_str()).unwrap_or_default();
+        let options = match extension.to_lowercase().as_str() {
+            "toml" => toml::from_str::<Value>(&contents)?,
+            "yaml" | "yml" => serde_yaml::from_str::<Value>(&contents)?,
+            _ => bail!("Unsupported config file '{}' (expected a .toml, .yaml, or .yml file)", path.display()),
+        };
+        match options {
+            Value::Object(options) => Ok(Self { path: path.to_path_buf(), options }),
+            _ => bail!("The config file '{}' must contain a table of options", path.display()),
+        }
+    }
+
+    /// Returns the path to the config file.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Returns the command line arguments corresponding to the config file, skipping the options
+    /// of the given command which were specified on the command line, as they take precedence.
+    pub fn to_args<T: CommandFactory>(&self, matches: &ArgMatches) -> Result<Vec<String>> {
+        // Retrieve the command's definition.
+        let definition = T::command();
+
+        // Prepare the arguments corresponding to the config file.
+        let mut args = vec![];
+        for (key, value) in &self.options {
+            // Ensure the key corresponds to one of the command's options; nested config files are not supported.
+            let Some(arg) =
+                definition.get_arguments().find(|arg| arg.get_long() == Some(key.as_str()) && key != "config")
+            else {
+                bail!("Unknown key '{key}' in the config file '{}'", self.path.display());
+            };
+            // Skip the options which were specified on the command line.
+            if matches.value_source(arg.get_id().as_str()) == Some(ValueSource::CommandLine) {
+                continue;
+            }
+            match value {
+                // A flag is only passed if it is enabled.
+                Value::Bool(true) => args.push(format!("--{key}")),
+                Value::Bool(false) | Value::Null => (),
+                // A list of values is passed as a comma-separated value.
+                Value::Array(values) => {
+                    let values = values.iter().map(Self::to_arg).collect::<Vec<_>>();
+                    args.push(format!("--{key}={}", values.join(",")));
+                }
+                // Note: The values are attached to the keys, so that e.g. negative numbers aren't mistaken for flags.
+                value => args.push(format!("--{key}={}", Self::to_arg(value))),
+            }
+        }
+        Ok(args)
+    }
+
+    /// Converts the given value into a command line argument.
+    fn to_arg(value: &Value) -> String {
+        match value {
+            Value::String(string) => string.clone(),
+            value => value.to_string(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::{Command, Start, CLI};
+
+    /// Writes the given contents to a temporary config file with the given extension.
+    fn config_file(extension: &str, contents: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!("snarkos-config-{}.{extension}", rand::random::<u64>()));
+        std::fs::write(&path, contents).unwrap();
+        path
+    }
+
+    /// Parses the `start` command with the given arguments, layered over the given config file.
+    fn parse_start(path: &Path, args: &[&str]) -> Result<Start> {
+        let config = path.to_str().unwrap();
+        let args = ["snarkos", "start", "--config", config].into_iter().chain(args.iter().copied());
+        let cli = CLI::try_parse_with_config_from(args)?;
+        match cli.command {
+            Command::Start(start) => Ok(*start),
+            _ => panic!("Unexpected result of clap parsing!"),
+        }
+    }
+
+    #[test]
+    fn test_config_precedence() {
+        let toml = "peers = [\"1.2.3.4:5\", \"6.7.8.9:0\"]\nrest-rps = 20\nnodisplay = true\nverbosity = 3\n";
+        let yaml = "peers:\n  - 1.2.3.4:5\n  - 6.7.8.9:0\nrest-rps: 20\nnodisplay: true\nverbosity: 3\n";
+
+        for file in [config_file("toml", toml), config_file("yaml", yaml)] {
+            // The config file overrides the defaults.
+            let start = parse_start(&file, &[]).unwrap();
+            assert_eq!(start.peers, "1.2.3.4:5,6.7.8.9:0");
+            assert_eq!(start.rest_rps, 20);
+            assert!(start.nodisplay);
+            assert_eq!(start.verbosity, 3);
+            assert_eq!(start.network, 0);
+
+            // The command line flags override the config file.
+            let start = parse_start(&file, &["--rest-rps", "30", "--verbosity", "0"]).unwrap();
+            assert_eq!(start.peers, "1.2.3.4:5,6.7.8.9:0");
+            assert_eq!(start.rest_rps, 30);
+            assert_eq!(start.verbosity, 0);
+        }
+    }
+
+    #[test]
+    fn test_config_errors() {
+        // Unknown keys are rejected.
+        let error = parse_start(&config_file("toml", "unknown-key = 1\n"), &[]).unwrap_err();
+        assert!(error.to_string().contains("Unknown key 'unknown-key'"));
+        // Nested config files are rejected.
+        let error = parse_start(&config_file("toml", "config = \"other.toml\"\n"), &[]).unwrap_err();
+        assert!(error.to_string().contains("Unknown key 'config'"));
+        // Invalid values are rejected.
+        assert!(parse_start(&config_file("toml", "rest-rps = \"many\"\n"), &[]).is_err());
+        // Unsupported formats are rejected.
+        assert!(parse_start(&config_file("json", "{}"), &[]).is_err());
+    }
+}
@@ -0,0 +1,57 @@
+// Copyright (C) 2019-2023 Aleo Systems Inc.
+// This file is part of the snarkOS library.
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at:
+// http://www.apache.org/licenses/LICENSE-2.0
+
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::helpers::{ControlRequest, DEFAULT_CONTROL_ADDR};
+
+use anyhow::{anyhow, Result};
+use clap::Parser;
+use std::net::SocketAddr;
+
+/// Changes the log filter of a running snarkOS node.
+#[derive(Debug, Parser)]
+pub struct LogLevel {
+    /// Specify the new filter directives, e.g. "info,snarkos_node_tcp=trace"
+    pub directives: String,
+    /// Specify the IP address and port of the control interface of the node (see `start --control`)
+    #[clap(default_value = DEFAULT_CONTROL_ADDR, long = "control")]
+    pub control: SocketAddr,
+}
+
+impl LogLevel {
+    /// Sends the new log filter to the node.
+    pub fn parse(self) -> Result<String> {
+        ControlRequest::LogFilter(self.directives.clone())
+            .send(self.control)
+            .map_err(|error| anyhow!("Failed to change the log filter to '{}': {error}", self.directives))?;
+        Ok(format!("✅ Changed the log filter to '{}'", self.directives))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::{Command, CLI};
+
+    #[test]
+    fn test_parse_log_level() {
+        let cli = CLI::try_parse_from(["snarkos", "log-level", "info,snarkos_node_tcp=trace"]).unwrap();
+        let Command::LogLevel(log_level) = cli.command else { panic!("Unexpected command") };
+        assert_eq!(log_level.directives, "info,snarkos_node_tcp=trace");
+        assert_eq!(log_level.control, DEFAULT_CONTROL_ADDR.parse().unwrap());
+
+        let cli = CLI::try_parse_from(["snarkos", "log-level", "debug", "--control", "127.0.0.1:4000"]).unwrap();
+        let Command::LogLevel(log_level) = cli.command else { panic!("Unexpected command") };
+        assert_eq!(log_level.control, "127.0.0.1:4000".parse().unwrap());
+    }
+}
@@ -18,18 +18,33 @@ pub use account::*;
 mod clean;
 pub use clean::*;
 
+mod completions;
+pub use completions::*;
+
+mod config;
+pub use config::*;
+
 mod developer;
 pub use developer::*;
 
+mod log_level;
+pub use log_level::*;
+
 mod start;
 pub use start::*;
 
+mod status;
+pub use status::*;
+
 mod update;
 pub use update::*;
 
+use crate::helpers::ReleaseChannel;
+
 use anstyle::{AnsiColor, Color, Style};
-use anyhow::Result;
-use clap::{builder::Styles, Parser};
+use anyhow::{anyhow, Result};
+use clap::{builder::Styles, ArgMatches, CommandFactory, FromArgMatches, Parser};
+use std::ffi::OsString;
 
 const HEADER_COLOR: Option<Color> = Some(Color::Ansi(AnsiColor::Yellow));
 const LITERAL_COLOR: Option<Color> = Some(Color::Ansi(AnsiColor::Green));
@@ -49,28 +64,93 @@ pub struct CLI {
     pub command: Command,
 }
 
+impl CLI {
+    /// Parses the command line arguments, layered over the config file of the command (if one is specified).
+    /// The precedence of the options is: command line flag > config file > default value.
+    pub fn parse_with_config() -> Result<Self> {
+        let args = std::env::args_os().collect::<Vec<_>>();
+        let matches = Self::command().get_matches_from(&args);
+        Self::from_matches_with_config(args, matches)
+    }
+
+    /// Parses the given arguments, layered over the config file of the command (if one is specified).
+    pub fn try_parse_with_config_from<I, T>(args: I) -> Result<Self>
+    where
+        I: IntoIterator<Item = T>,
+        T: Into<OsString> + Clone,
+    {
+        let args = args.into_iter().map(Into::into).collect::<Vec<OsString>>();
+        let matches = Self::command().try_get_matches_from(&args)?;
+        Self::from_matches_with_config(args, matches)
+    }
+
+    /// Constructs the CLI from the given arguments and their matches, inserting the options from the
+    /// config file of the command (if one is specified) right after the subcommand.
+    fn from_matches_with_config(mut args: Vec<OsString>, matches: ArgMatches) -> Result<Self> {
+        let cli = Self::from_arg_matches(&matches)?;
+        // Retrieve the config file, if one is specified.
+        let (config_file, file_args) = match (&cli.command, matches.subcommand()) {
+            (Command::Start(start), Some(("start", start_matches))) => match &start.config {
+                Some(path) => {
+                    let config_file = ConfigFile::load(path)?;
+                    let file_args = config_file.to_args::<Start>(start_matches)?;
+                    (config_file, file_args)
+                }
+                None => return Ok(cli),
+            },
+            _ => return Ok(cli),
+        };
+        // Insert the arguments from the config file right after the subcommand; they can't clash with the
+        // ones from the command line, as those were skipped.
+        let Some(position) = args.iter().position(|arg| arg == "start") else {
+            return Ok(cli);
+        };
+        args.splice(position + 1..position + 1, file_args.into_iter().map(OsString::from));
+        // Parse the arguments again.
+        Self::try_parse_from(args)
+            .map_err(|error| anyhow!("Invalid option in the config file '{}': {error}", config_file.path().display()))
+    }
+}
+
 #[derive(Debug, Parser)]
 pub enum Command {
     #[clap(subcommand)]
     Account(Account),
     #[clap(name = "clean")]
     Clean(Clean),
+    #[clap(name = "completions")]
+    Completions(Completions),
     #[clap(subcommand)]
     Developer(Developer),
+    #[clap(name = "log-level")]
+    LogLevel(LogLevel),
     #[clap(name = "start")]
     Start(Box<Start>),
+    #[clap(name = "status")]
+    Status(Status),
     #[clap(name = "update")]
     Update(Update),
 }
 
 impl Command {
+    /// Returns the release channel used to check for updates.
+    pub fn release_channel(&self) -> ReleaseChannel {
+        match self {
+            Self::Start(command) => command.channel,
+            _ => ReleaseChannel::default(),
+        }
+    }
+
     /// Parses the command.
     pub fn parse(self) -> Result<String> {
         match self {
             Self::Account(command) => command.parse(),
             Self::Clean(command) => command.parse(),
+            Self::Completions(command) => command.parse(),
             Self::Developer(command) => command.parse(),
+            Self::LogLevel(command) => command.parse(),
             Self::Start(command) => command.parse(),
+            Self::Status(command) => command.parse(),
             Self::Update(command) => command.parse(),
         }
     }
@@ -12,9 +12,10 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+use crate::helpers::{ControlServer, LogFilterHandle, LogFormat, ReleaseChannel};
 use snarkos_account::Account;
 use snarkos_display::Display;
-use snarkos_node::{bft::MEMORY_POOL_PORT, router::messages::NodeType, Node};
+use snarkos_node::{bft::MEMORY_POOL_PORT, router::messages::NodeType, tcp::Tcp, Node};
 use snarkvm::{
     console::{
         account::{Address, PrivateKey},
@@ -32,7 +33,7 @@ use snarkvm::{
 };
 
 use aleo_std::StorageMode;
-use anyhow::{bail, ensure, Result};
+use anyhow::{anyhow, bail, ensure, Result};
 use clap::Parser;
 use colored::Colorize;
 use core::str::FromStr;
@@ -44,6 +45,7 @@ use std::{
     net::SocketAddr,
     path::PathBuf,
     sync::{atomic::AtomicBool, Arc},
+    time::Duration,
 };
 use tokio::runtime::{self, Runtime};
 
@@ -72,6 +74,10 @@ impl FromStr for BondedBalances {
 /// Starts the snarkOS node.
 #[derive(Clone, Debug, Parser)]
 pub struct Start {
+    /// Specify the path to a TOML or YAML file containing the options; the command line flags take precedence
+    #[clap(long = "config")]
+    pub config: Option<PathBuf>,
+
     /// Specify the network ID of this node
     #[clap(default_value = "0", long = "network")]
     pub network: u16,
@@ -92,6 +98,9 @@ pub struct Start {
     /// Specify the path to a file containing the account private key of the node
     #[clap(long = "private-key-file")]
     pub private_key_file: Option<PathBuf>,
+    /// Specify the permitted (octal) mode of the directory containing the private key file, e.g. 750 for group access
+    #[clap(default_value = "700", long = "private-key-dir-mode", value_parser = parse_private_key_dir_mode)]
+    pub private_key_dir_mode: u32,
 
     /// Specify the IP address and port for the node server
     #[clap(long = "node")]
@@ -108,6 +117,12 @@ pub struct Start {
     /// If the flag is set, a node will allow untrusted peers to connect
     #[clap(long = "allow-external-peers")]
     pub allow_external_peers: bool,
+    /// Specify the path to a file where the known peers are saved on shutdown and loaded from on startup
+    #[clap(long = "peer-cache")]
+    pub peer_cache: Option<PathBuf>,
+    /// Specify the age (in seconds) beyond which the peers loaded from the peer cache are discarded
+    #[clap(default_value = "604800", long = "peer-cache-max-age")]
+    pub peer_cache_max_age: u64,
 
     /// Specify the IP address and port for the REST server
     #[clap(default_value = "0.0.0.0:3030", long = "rest")]
@@ -125,12 +140,27 @@ pub struct Start {
     /// Specify the verbosity of the node [options: 0, 1, 2, 3, 4]
     #[clap(default_value = "1", long = "verbosity")]
     pub verbosity: u8,
+    /// Specify the format of the logs [options: text, json]
+    #[clap(default_value = "text", long = "log-format", value_enum)]
+    pub log_format: LogFormat,
     /// Specify the path to the file where logs will be stored
     #[clap(default_value_os_t = std::env::temp_dir().join("snarkos.log"), long = "logfile")]
     pub logfile: PathBuf,
+    /// Specify the localhost IP address and port for the control interface of the node, e.g. 127.0.0.1:3040
+    #[clap(long = "control")]
+    pub control: Option<SocketAddr>,
     /// Enables the metrics exporter
     #[clap(default_value = "false", long = "metrics")]
     pub metrics: bool,
+    /// Specify the release channel to check for updates [options: stable, beta, nightly]
+    #[clap(default_value = "stable", long = "channel", value_enum)]
+    pub channel: ReleaseChannel,
+    /// Specify the path to a directory where jemalloc heap profiles will be periodically dumped
+    #[clap(long = "memory-profile")]
+    pub memory_profile: Option<PathBuf>,
+    /// Specify the interval (in seconds) between the heap profile dumps
+    #[clap(default_value = "600", long = "memory-profile-interval")]
+    pub memory_profile_interval: u64,
 
     /// Specify the path to a directory containing the storage database for the ledger
     #[clap(long = "storage")]
@@ -162,9 +192,33 @@ impl Start {
         // Prepare the shutdown flag.
         let shutdown: Arc<AtomicBool> = Default::default();
 
+        // If requested, start the heap profiling; this is done first, so that an invalid setup fails early.
+        if let Some(directory) = &self.memory_profile {
+            ensure!(self.memory_profile_interval > 0, "The memory profile interval must be greater than 0");
+            let profiler = crate::helpers::MemoryProfiler::enable(directory)?;
+            profiler.spawn(Duration::from_secs(self.memory_profile_interval))?;
+        }
+
+        // If requested, bind the control listener; only local connections are permitted.
+        let control = match self.control {
+            Some(addr) => {
+                ensure!(addr.ip().is_loopback(), "The control address '{addr}' must be a localhost address");
+                let listener = std::net::TcpListener::bind(addr)
+                    .map_err(|error| anyhow!("Failed to bind the control address '{addr}': {error}"))?;
+                listener.set_nonblocking(true)?;
+                Some(listener)
+            }
+            None => None,
+        };
+
         // Initialize the logger.
-        let log_receiver =
-            crate::helpers::initialize_logger(self.verbosity, self.nodisplay, self.logfile.clone(), shutdown.clone());
+        let (log_receiver, log_filter) = crate::helpers::initialize_logger(
+            self.verbosity,
+            self.log_format,
+            self.nodisplay,
+            self.logfile.clone(),
+            shutdown.clone(),
+        );
         // Initialize the runtime.
         Self::runtime().block_on(async move {
             // Clone the configurations.
@@ -174,6 +228,10 @@ impl Start {
                 MainnetV0::ID => {
                     // Parse the node from the configurations.
                     let node = cli.parse_node::<MainnetV0>(shutdown.clone()).await.expect("Failed to parse the node");
+                    // If requested, serve the control interface.
+                    if let Some(listener) = control {
+                        Self::serve_control(listener, log_filter, node.tcp().clone());
+                    }
                     // If the display is enabled, render the display.
                     if !cli.nodisplay {
                         // Initialize the display.
@@ -183,6 +241,10 @@ impl Start {
                 TestnetV0::ID => {
                     // Parse the node from the configurations.
                     let node = cli.parse_node::<TestnetV0>(shutdown.clone()).await.expect("Failed to parse the node");
+                    // If requested, serve the control interface.
+                    if let Some(listener) = control {
+                        Self::serve_control(listener, log_filter, node.tcp().clone());
+                    }
                     // If the display is enabled, render the display.
                     if !cli.nodisplay {
                         // Initialize the display.
@@ -265,7 +327,7 @@ impl Start {
                 (Some(private_key), None) => Account::from_str(private_key.trim()),
                 // Parse the private key from a file.
                 (None, Some(path)) => {
-                    check_permissions(path)?;
+                    check_permissions(path, self.private_key_dir_mode)?;
                     Account::from_str(std::fs::read_to_string(path)?.trim())
                 }
                 // Ensure the private key is provided to the CLI, except for clients or nodes in development mode.
@@ -576,11 +638,28 @@ impl Start {
         };
 
         // Initialize the node.
-        match node_type {
+        let node = match node_type {
             NodeType::Validator => Node::new_validator(node_ip, bft_ip, rest_ip, self.rest_rps, account, &trusted_peers, &trusted_validators, genesis, cdn, storage_mode, self.allow_external_peers, dev_txs, shutdown.clone()).await,
             NodeType::Prover => Node::new_prover(node_ip, account, &trusted_peers, genesis, storage_mode, shutdown.clone()).await,
             NodeType::Client => Node::new_client(node_ip, rest_ip, self.rest_rps, account, &trusted_peers, genesis, cdn, storage_mode, shutdown).await,
+        }?;
+
+        // If requested, restore the peers saved in the previous run; they are saved again on shutdown.
+        if let Some(path) = &self.peer_cache {
+            let max_age = Duration::from_secs(self.peer_cache_max_age);
+            match node.load_peer_cache(path.clone(), max_age) {
+                Ok(num_peers) => println!("📇 Restored {num_peers} peer(s) from '{}'.\n", path.display()),
+                Err(error) => eprintln!("Failed to load the peer cache: {error:?}"),
+            }
         }
+
+        Ok(node)
+    }
+
+    /// Serves the control interface of the node from the given listener.
+    fn serve_control(listener: std::net::TcpListener, log_filter: LogFilterHandle, tcp: Tcp) {
+        let listener = tokio::net::TcpListener::from_std(listener).expect("Failed to initialize the control listener");
+        tokio::spawn(ControlServer::new(log_filter, tcp).serve(listener));
     }
 
     /// Returns a runtime for the node.
@@ -612,23 +691,40 @@ impl Start {
     }
 }
 
-fn check_permissions(path: &PathBuf) -> Result<(), snarkvm::prelude::Error> {
+/// Parses the given octal mode mask of the private key directory, ensuring it doesn't allow access to other users.
+fn parse_private_key_dir_mode(mode: &str) -> Result<u32> {
+    let mode = u32::from_str_radix(mode.trim_start_matches("0o"), 8)
+        .map_err(|error| anyhow::anyhow!("Invalid octal mode '{mode}': {error}"))?;
+    ensure!(mode & !0o777 == 0, "The mode {mode:04o} must be within 0777");
+    ensure!(mode & 0o700 == 0o700, "The mode {mode:04o} must grant full access to the owner (0700)");
+    ensure!(mode & 0o007 == 0, "The mode {mode:04o} must not grant any access to other users");
+    Ok(mode)
+}
+
+/// Ensures the given private key file is readable only by the owner, and that its directory
+/// doesn't allow any access beyond the given mode mask.
+fn check_permissions(path: &PathBuf, dir_mode: u32) -> Result<(), snarkvm::prelude::Error> {
+    ensure!(path.exists(), "The file '{:?}' does not exist", path);
+    let parent = path.parent().filter(|parent| !parent.as_os_str().is_empty()).unwrap_or(std::path::Path::new("."));
+    ensure!(parent.exists(), "The folder {:?} does not exist", parent);
+
     #[cfg(target_family = "unix")]
     {
         use std::os::unix::fs::PermissionsExt;
-        ensure!(path.exists(), "The file '{:?}' does not exist", path);
-        let parent = path.parent();
-        if let Some(parent) = parent {
-            let parent_permissions = parent.metadata()?.permissions().mode();
-            ensure!(
-                parent_permissions & 0o777 == 0o700,
-                "The folder {:?} must be readable only by the owner (0700)",
-                parent
-            );
-        }
+        let parent_permissions = parent.metadata()?.permissions().mode() & 0o777;
+        ensure!(
+            parent_permissions & 0o700 == 0o700 && parent_permissions & !dir_mode == 0,
+            "The folder {:?} must only be accessible within the mode {:04o} (found {:04o})",
+            parent,
+            dir_mode,
+            parent_permissions
+        );
         let permissions = path.metadata()?.permissions().mode();
         ensure!(permissions & 0o777 == 0o600, "The file {:?} must be readable only by the owner (0600)", path);
     }
+    #[cfg(not(target_family = "unix"))]
+    let _ = dir_mode;
+
     Ok(())
 }
 
@@ -750,6 +846,50 @@ mod tests {
         ]);
     }
 
+    #[test]
+    fn test_parse_private_key_dir_mode() {
+        let config = Start::try_parse_from(["snarkos"].iter()).unwrap();
+        assert_eq!(config.private_key_dir_mode, 0o700);
+        let config = Start::try_parse_from(["snarkos", "--private-key-dir-mode", "750"].iter()).unwrap();
+        assert_eq!(config.private_key_dir_mode, 0o750);
+        let config = Start::try_parse_from(["snarkos", "--private-key-dir-mode", "0o750"].iter()).unwrap();
+        assert_eq!(config.private_key_dir_mode, 0o750);
+
+        // Ensure the modes granting access to other users, or not granting full access to the owner, are rejected.
+        assert!(Start::try_parse_from(["snarkos", "--private-key-dir-mode", "755"].iter()).is_err());
+        assert!(Start::try_parse_from(["snarkos", "--private-key-dir-mode", "550"].iter()).is_err());
+        assert!(Start::try_parse_from(["snarkos", "--private-key-dir-mode", "1750"].iter()).is_err());
+        assert!(Start::try_parse_from(["snarkos", "--private-key-dir-mode", "abc"].iter()).is_err());
+    }
+
+    #[cfg(target_family = "unix")]
+    #[test]
+    fn test_check_permissions() {
+        use std::os::unix::fs::PermissionsExt;
+
+        // Prepare a private key file in a group-readable directory.
+        let dir = std::env::temp_dir().join(format!("snarkos-key-{}", rand::random::<u64>()));
+        std::fs::create_dir(&dir).unwrap();
+        std::fs::set_permissions(&dir, std::fs::Permissions::from_mode(0o750)).unwrap();
+        let path = dir.join("private-key");
+        std::fs::write(&path, "").unwrap();
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o600)).unwrap();
+
+        // Ensure the directory is only accepted if the mode mask permits group access.
+        let error = check_permissions(&path, 0o700).unwrap_err();
+        assert!(error.to_string().contains("within the mode 0700 (found 0750)"));
+        assert!(check_permissions(&path, 0o750).is_ok());
+
+        // Ensure the world-readable directories are still rejected.
+        std::fs::set_permissions(&dir, std::fs::Permissions::from_mode(0o755)).unwrap();
+        assert!(check_permissions(&path, 0o750).is_err());
+
+        // Ensure a missing file is rejected.
+        assert!(check_permissions(&dir.join("missing"), 0o750).is_err());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
     #[test]
     fn test_parse_cdn() {
         // Validator (Prod)
@@ -939,6 +1079,10 @@ mod tests {
             "IP1,IP2,IP3",
             "--rest",
             "127.0.0.1:3030",
+            "--memory-profile",
+            "/tmp/profiles",
+            "--peer-cache",
+            "/tmp/peers.json",
         ];
         let cli = CLI::parse_from(arg_vec);
 
@@ -952,6 +1096,10 @@ mod tests {
             assert_eq!(start.network, 0);
             assert_eq!(start.peers, "IP1,IP2,IP3");
             assert_eq!(start.validators, "IP1,IP2,IP3");
+            assert_eq!(start.memory_profile, Some(PathBuf::from("/tmp/profiles")));
+            assert_eq!(start.memory_profile_interval, 600);
+            assert_eq!(start.peer_cache, Some(PathBuf::from("/tmp/peers.json")));
+            assert_eq!(start.peer_cache_max_age, 604800);
         } else {
             panic!("Unexpected result of clap parsing!");
         }
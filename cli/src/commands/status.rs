@@ -0,0 +1,188 @@
+// Copyright (C) 2019-2023 Aleo Systems Inc.
+// This file is part of the snarkOS library.
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at:
+// http://www.apache.org/licenses/LICENSE-2.0
+
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::helpers::{ControlRequest, MemoryStats, DEFAULT_CONTROL_ADDR};
+use snarkos_node::tcp::TcpSnapshot;
+
+use anyhow::{anyhow, Result};
+use clap::Parser;
+use serde::Serialize;
+use std::{fmt::Write, net::SocketAddr};
+
+/// Prints the connections of a running snarkOS node.
+#[derive(Debug, Parser)]
+pub struct Status {
+    /// Specify the IP address and port of the control interface of the node (see `start --control`)
+    #[clap(default_value = DEFAULT_CONTROL_ADDR, long = "control")]
+    pub control: SocketAddr,
+    /// If the flag is set, the status is printed as JSON
+    #[clap(long)]
+    pub json: bool,
+}
+
+/// The status of the node, as printed with `--json`.
+#[derive(Serialize)]
+struct JsonStatus<'a> {
+    #[serde(flatten)]
+    snapshot: &'a TcpSnapshot,
+    /// The memory stats, if the node is able to report them.
+    memory: Option<MemoryStats>,
+}
+
+impl Status {
+    /// Retrieves the status from the node.
+    pub fn parse(self) -> Result<String> {
+        let payload = ControlRequest::Status
+            .send(self.control)
+            .map_err(|error| anyhow!("Failed to retrieve the status of the node: {error}"))?;
+        let snapshot = serde_json::from_str::<TcpSnapshot>(&payload)?;
+        // The memory stats are optional, as they are unavailable on builds without jemalloc.
+        let memory = ControlRequest::Memory
+            .send(self.control)
+            .ok()
+            .and_then(|payload| serde_json::from_str::<MemoryStats>(&payload).ok());
+
+        match self.json {
+            true => Ok(serde_json::to_string_pretty(&JsonStatus { snapshot: &snapshot, memory })?),
+            false => Ok(Self::format_table(&snapshot, memory)),
+        }
+    }
+
+    /// Formats the snapshot as a table of the known peers, preceded by a summary of the connections and memory.
+    fn format_table(snapshot: &TcpSnapshot, memory: Option<MemoryStats>) -> String {
+        const MIB: u64 = 1024 * 1024;
+
+        const HEADERS: [&str; 9] = [
+            "PEER",
+            "STATE",
+            "MSGS SENT",
+            "BYTES SENT",
+            "MSGS RECEIVED",
+            "BYTES RECEIVED",
+            "FAILURES",
+            "SCORE",
+            "LATENCY",
+        ];
+
+        let mut out = String::new();
+        // Writing to a `String` can't fail.
+        let _ = writeln!(out, "Node: {}", snapshot.name);
+        let _ = writeln!(out, "Connected: {}", snapshot.num_connected);
+        let _ = writeln!(out, "Connecting: {}", snapshot.num_connecting);
+        match memory {
+            Some(stats) => {
+                let _ = writeln!(
+                    out,
+                    "Memory: {} MiB allocated, {} MiB resident, {} MiB retained",
+                    stats.allocated / MIB,
+                    stats.resident / MIB,
+                    stats.retained / MIB
+                );
+            }
+            None => {
+                let _ = writeln!(out, "Memory: unavailable");
+            }
+        }
+        if snapshot.known_peers.is_empty() {
+            let _ = write!(out, "\nNo known peers");
+            return out;
+        }
+
+        // Prepare the rows of the table.
+        let rows = snapshot
+            .known_peers
+            .iter()
+            .map(|peer| {
+                let state = if snapshot.connected_addrs.contains(&peer.addr) {
+                    "connected"
+                } else if snapshot.connecting_addrs.contains(&peer.addr) {
+                    "connecting"
+                } else {
+                    "disconnected"
+                };
+                let latency = peer.stats.latency_ms.map(|ms| format!("{ms}ms")).unwrap_or_else(|| "-".to_string());
+                [
+                    peer.addr.to_string(),
+                    state.to_string(),
+                    peer.stats.msgs_sent.to_string(),
+                    peer.stats.bytes_sent.to_string(),
+                    peer.stats.msgs_received.to_string(),
+                    peer.stats.bytes_received.to_string(),
+                    peer.stats.failures.to_string(),
+                    peer.stats.score.to_string(),
+                    latency,
+                ]
+            })
+            .collect::<Vec<_>>();
+
+        // Align the columns to their widest cells.
+        let widths: [usize; 9] =
+            std::array::from_fn(|i| rows.iter().map(|row| row[i].len()).chain([HEADERS[i].len()]).max().unwrap_or(0));
+        let _ = writeln!(out);
+        let header = HEADERS.iter().zip(widths).map(|(cell, width)| format!("{cell:<width$}")).collect::<Vec<_>>();
+        let _ = write!(out, "{}", header.join("  ").trim_end());
+        for row in &rows {
+            let row = row.iter().zip(widths).map(|(cell, width)| format!("{cell:<width$}")).collect::<Vec<_>>();
+            let _ = write!(out, "\n{}", row.join("  ").trim_end());
+        }
+
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use snarkos_node::tcp::{PeerSnapshot, StatsSnapshot};
+
+    #[test]
+    fn test_format_table() {
+        let connected = "127.0.0.1:4130".parse().unwrap();
+        let disconnected = "127.0.0.1:4131".parse().unwrap();
+        let snapshot = TcpSnapshot {
+            name: "node".to_string(),
+            num_connected: 1,
+            num_connecting: 0,
+            connected_addrs: vec![connected],
+            connecting_addrs: vec![],
+            stats: Default::default(),
+            known_peers: vec![
+                PeerSnapshot {
+                    addr: connected,
+                    stats: StatsSnapshot { msgs_sent: 2, bytes_sent: 100, latency_ms: Some(12), ..Default::default() },
+                },
+                PeerSnapshot {
+                    addr: disconnected,
+                    stats: StatsSnapshot { failures: 3, score: -3, ..Default::default() },
+                },
+            ],
+        };
+
+        let memory = MemoryStats { allocated: 64 << 20, resident: 96 << 20, retained: 32 << 20 };
+        let table = Status::format_table(&snapshot, Some(memory));
+        let lines = table.lines().collect::<Vec<_>>();
+        assert_eq!(&lines[..3], ["Node: node", "Connected: 1", "Connecting: 0"]);
+        assert_eq!(lines[3], "Memory: 64 MiB allocated, 96 MiB resident, 32 MiB retained");
+        assert!(lines[5].starts_with("PEER            STATE         MSGS SENT"));
+        assert!(lines[6].starts_with("127.0.0.1:4130  connected     2          100"));
+        assert!(lines[6].ends_with("12ms"));
+        assert!(lines[7].starts_with("127.0.0.1:4131  disconnected  0          0"));
+        assert!(lines[7].ends_with("-"));
+        assert_eq!(lines.len(), 8);
+
+        // Ensure the missing memory stats are reported as such.
+        let table = Status::format_table(&snapshot, None);
+        assert_eq!(table.lines().nth(3), Some("Memory: unavailable"));
+    }
+}
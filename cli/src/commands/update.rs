@@ -12,7 +12,7 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use crate::helpers::Updater;
+use crate::helpers::{ReleaseChannel, UpdateCheck, Updater};
 
 use anyhow::Result;
 use clap::Parser;
@@ -29,18 +29,42 @@ pub struct Update {
     /// Update to specified version
     #[clap(short = 'v', long)]
     version: Option<String>,
+    /// Check whether a newer version is available, without installing it; exits with a non-zero code if outdated
+    #[clap(long, conflicts_with_all = ["list", "version"])]
+    check: bool,
+    /// Report the result of the check in JSON
+    #[clap(long, requires = "check")]
+    json: bool,
+    /// Specify the release channel to update from [options: stable, beta, nightly]
+    #[clap(default_value = "stable", long = "channel", value_enum)]
+    channel: ReleaseChannel,
 }
 
 impl Update {
+    /// The exit code used when a newer version is available in check mode.
+    const OUTDATED_EXIT_CODE: i32 = 1;
+
     /// Update snarkOS.
     pub fn parse(self) -> Result<String> {
+        // Check for an update, without installing it.
+        if self.check {
+            let check = Updater::check_for_update(self.channel)?;
+            let output = Self::format_check(&check, self.json)?;
+            // Exit with a non-zero code if outdated, so that scripts can act upon it.
+            if check.update_available {
+                println!("{output}");
+                std::process::exit(Self::OUTDATED_EXIT_CODE);
+            }
+            return Ok(output);
+        }
+
         match self.list {
-            true => match Updater::show_available_releases() {
+            true => match Updater::show_available_releases(self.channel) {
                 Ok(output) => Ok(output),
                 Err(error) => Ok(format!("Failed to list the available versions of snarkOS\n{error}\n")),
             },
             false => {
-                let result = Updater::update_to_release(!self.quiet, self.version);
+                let result = Updater::update_to_release(!self.quiet, self.version, self.channel);
                 if !self.quiet {
                     match result {
                         Ok(status) => {
@@ -61,3 +85,56 @@ impl Update {
         }
     }
 }
+
+impl Update {
+    /// Formats the result of the update check, either as a message or as JSON.
+    fn format_check(check: &UpdateCheck, json: bool) -> Result<String> {
+        if json {
+            return Ok(serde_json::to_string(check)?);
+        }
+        match check.update_available {
+            true => Ok(format!(
+                "A new version of snarkOS is available on the {} channel: v{} (current: v{})",
+                check.channel, check.latest_version, check.current_version
+            )),
+            false => Ok(format!(
+                "snarkOS is already on the latest version of the {} channel (v{})",
+                check.channel, check.current_version
+            )),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_check() {
+        let update = Update::try_parse_from(["update", "--check", "--json"]).unwrap();
+        assert!(update.check && update.json);
+        // Ensure the check mode can't be combined with the installation options.
+        assert!(Update::try_parse_from(["update", "--check", "--version", "v2.2.7"]).is_err());
+        assert!(Update::try_parse_from(["update", "--check", "--list"]).is_err());
+        assert!(Update::try_parse_from(["update", "--json"]).is_err());
+        // Ensure unknown release channels are rejected early.
+        assert!(Update::try_parse_from(["update", "--channel", "beta"]).is_ok());
+        assert!(Update::try_parse_from(["update", "--channel", "canary"]).is_err());
+    }
+
+    #[test]
+    fn test_format_check() {
+        let check = UpdateCheck {
+            current_version: "2.2.7".to_string(),
+            channel: ReleaseChannel::Stable,
+            latest_version: "2.2.8".to_string(),
+            update_available: true,
+        };
+        let json = Update::format_check(&check, true).unwrap();
+        assert_eq!(
+            json,
+            r#"{"current_version":"2.2.7","channel":"stable","latest_version":"2.2.8","update_available":true}"#
+        );
+        assert!(Update::format_check(&check, false).unwrap().contains("v2.2.8"));
+    }
+}
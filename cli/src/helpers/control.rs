@@ -0,0 +1,221 @@
+// Copyright (C) 2019-2023 Aleo Systems Inc.
+// This file is part of the snarkOS library.
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at:
+// http://www.apache.org/licenses/LICENSE-2.0
+
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::helpers::{LogFilterHandle, MemoryStats};
+use snarkos_node::tcp::{accept_error_delay, Tcp};
+
+use anyhow::{anyhow, bail, Result};
+use std::{
+    io::{BufRead, BufReader, Write},
+    net::{Shutdown, SocketAddr, TcpStream},
+};
+use tokio::{
+    io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt},
+    net::TcpListener,
+    time::sleep,
+};
+use tracing::{info, warn};
+
+/// The default IP address and port of the control interface of a node.
+pub const DEFAULT_CONTROL_ADDR: &str = "127.0.0.1:3040";
+
+/// A request to the control interface of a node.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ControlRequest {
+    /// Replaces the log filter with the given directives.
+    LogFilter(String),
+    /// Returns the snapshot of the connections of the node.
+    Status,
+    /// Returns the memory stats of the node.
+    Memory,
+}
+
+impl ControlRequest {
+    /// Parses a request from a single line.
+    pub fn parse(line: &str) -> Result<Self> {
+        let line = line.trim();
+        let (command, argument) = line.split_once(' ').unwrap_or((line, ""));
+        match (command, argument.trim()) {
+            ("log-filter", "") => bail!("The log filter directives are missing"),
+            ("log-filter", directives) => Ok(Self::LogFilter(directives.to_string())),
+            ("status", "") => Ok(Self::Status),
+            ("memory", "") => Ok(Self::Memory),
+            _ => bail!("Invalid control request '{line}'"),
+        }
+    }
+
+    /// Returns the request as a single line.
+    pub fn to_line(&self) -> String {
+        match self {
+            Self::LogFilter(directives) => format!("log-filter {directives}\n"),
+            Self::Status => "status\n".to_string(),
+            Self::Memory => "memory\n".to_string(),
+        }
+    }
+
+    /// Sends the request to the control interface at the given address, returning the payload of the response.
+    /// Each response is a single line, either `ok [<payload>]` or `error: <reason>`.
+    pub fn send(&self, addr: SocketAddr) -> Result<String> {
+        let mut stream = TcpStream::connect(addr)
+            .map_err(|error| anyhow!("Failed to connect to the control interface at '{addr}': {error}"))?;
+        stream.write_all(self.to_line().as_bytes())?;
+        stream.shutdown(Shutdown::Write)?;
+
+        // Read the response of the node.
+        let mut response = String::new();
+        BufReader::new(stream).read_line(&mut response)?;
+        let response = response.trim_end();
+        match (response.strip_prefix("ok"), response.strip_prefix("error: ")) {
+            (Some(payload), _) => Ok(payload.trim_start().to_string()),
+            (_, Some(error)) => bail!("{error}"),
+            _ => bail!("Unexpected response from the node: '{response}'"),
+        }
+    }
+}
+
+/// Serves the control interface of a node; only local connections are permitted.
+#[derive(Clone)]
+pub struct ControlServer {
+    /// The handle to the filters of the logger.
+    log_filter: LogFilterHandle,
+    /// The TCP stack of the node.
+    tcp: Tcp,
+}
+
+impl ControlServer {
+    /// The maximum length of a request.
+    const MAX_REQUEST_LEN: u64 = 4096;
+
+    /// Initializes a new control server.
+    pub fn new(log_filter: LogFilterHandle, tcp: Tcp) -> Self {
+        Self { log_filter, tcp }
+    }
+
+    /// Accepts the requests from the given listener.
+    pub async fn serve(self, listener: TcpListener) {
+        let mut consecutive_errors = 0u32;
+        loop {
+            let (stream, peer_addr) = match listener.accept().await {
+                Ok(connection) => {
+                    consecutive_errors = 0;
+                    connection
+                }
+                Err(error) => {
+                    warn!("Failed to accept a control connection: {error}");
+                    // Back off, so that a persistent error doesn't result in a busy loop.
+                    sleep(accept_error_delay(consecutive_errors)).await;
+                    consecutive_errors = consecutive_errors.saturating_add(1);
+                    continue;
+                }
+            };
+            // Ensure the request originates from this machine.
+            if !peer_addr.ip().is_loopback() {
+                warn!("Rejected a control connection from '{peer_addr}'");
+                continue;
+            }
+
+            let server = self.clone();
+            tokio::spawn(async move {
+                let (reader, mut writer) = stream.into_split();
+                // Read the request.
+                let mut reader = tokio::io::BufReader::new(reader.take(Self::MAX_REQUEST_LEN));
+                let mut line = String::new();
+                let request = reader.read_line(&mut line).await.map_err(anyhow::Error::from);
+                let response = match request.and_then(|_| server.handle(&line)) {
+                    Ok(payload) if payload.is_empty() => "ok".to_string(),
+                    Ok(payload) => format!("ok {payload}"),
+                    Err(error) => format!("error: {error}"),
+                };
+                // Send the response.
+                if let Err(error) = writer.write_all(format!("{response}\n").as_bytes()).await {
+                    warn!("Failed to respond to a control request: {error}");
+                }
+            });
+        }
+    }
+
+    /// Handles the given request, returning the payload of the response.
+    fn handle(&self, line: &str) -> Result<String> {
+        match ControlRequest::parse(line)? {
+            ControlRequest::LogFilter(directives) => {
+                self.log_filter.reload(&directives)?;
+                info!("Changed the log filter to '{directives}'");
+                Ok(String::new())
+            }
+            ControlRequest::Status => Ok(serde_json::to_string(&self.tcp.snapshot())?),
+            ControlRequest::Memory => Ok(serde_json::to_string(&MemoryStats::read()?)?),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use snarkos_node::tcp::{Config, TcpSnapshot};
+
+    #[test]
+    fn test_parse_request() {
+        assert_eq!(ControlRequest::parse("status\n").unwrap(), ControlRequest::Status);
+        assert_eq!(
+            ControlRequest::parse("log-filter info,snarkos_node_tcp=trace\n").unwrap(),
+            ControlRequest::LogFilter("info,snarkos_node_tcp=trace".to_string())
+        );
+        assert!(ControlRequest::parse("log-filter").is_err());
+        assert!(ControlRequest::parse("status now").is_err());
+        assert!(ControlRequest::parse("restart").is_err());
+
+        // Ensure the requests survive the round trip.
+        assert_eq!(ControlRequest::parse("memory\n").unwrap(), ControlRequest::Memory);
+        for request in [ControlRequest::Status, ControlRequest::Memory, ControlRequest::LogFilter("debug".to_string())]
+        {
+            assert_eq!(ControlRequest::parse(&request.to_line()).unwrap(), request);
+        }
+    }
+
+    #[test]
+    fn test_control_server() {
+        let (_subscriber, log_filter) = LogFilterHandle::new_for_test();
+        let tcp = Tcp::new(Config { name: Some("control".into()), ..Default::default() });
+
+        // Start the control server.
+        let runtime = tokio::runtime::Builder::new_current_thread().enable_all().build().unwrap();
+        let listener = runtime.block_on(TcpListener::bind("127.0.0.1:0")).unwrap();
+        let addr = listener.local_addr().unwrap();
+        runtime.spawn(ControlServer::new(log_filter.clone(), tcp).serve(listener));
+
+        let send = |request: ControlRequest| {
+            runtime.block_on(async move { tokio::task::spawn_blocking(move || request.send(addr)).await.unwrap() })
+        };
+
+        // Change the log filter.
+        assert_eq!(send(ControlRequest::LogFilter("debug,snarkos_node_tcp=trace".to_string())).unwrap(), "");
+        assert!(send(ControlRequest::LogFilter("snarkos_node_tcp=loud".to_string())).is_err());
+        for filter in log_filter.current() {
+            assert!(filter.contains("snarkos_node_tcp=trace"));
+        }
+
+        // Retrieve the status.
+        let snapshot = serde_json::from_str::<TcpSnapshot>(&send(ControlRequest::Status).unwrap()).unwrap();
+        assert_eq!(snapshot.name, "control");
+        assert_eq!(snapshot.num_connected, 0);
+        assert!(snapshot.known_peers.is_empty());
+
+        // Retrieve the memory stats; they are only available with jemalloc.
+        let memory = send(ControlRequest::Memory);
+        #[cfg(all(target_os = "linux", target_arch = "x86_64"))]
+        assert!(serde_json::from_str::<MemoryStats>(&memory.unwrap()).is_ok());
+        #[cfg(not(all(target_os = "linux", target_arch = "x86_64")))]
+        assert!(memory.is_err());
+    }
+}
@@ -0,0 +1,197 @@
+// Copyright (C) 2019-2023 Aleo Systems Inc.
+// This file is part of the snarkOS library.
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at:
+// http://www.apache.org/licenses/LICENSE-2.0
+
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use serde_json::{Map, Value};
+use std::fmt;
+use time::{
+    format_description::{self, OwnedFormatItem},
+    OffsetDateTime,
+};
+use tracing::{
+    field::{Field, Visit},
+    Event,
+    Subscriber,
+};
+use tracing_subscriber::{
+    fmt::{
+        format::{JsonFields, Writer},
+        FmtContext,
+        FormatEvent,
+        FormattedFields,
+    },
+    registry::LookupSpan,
+};
+
+/// A formatter emitting the events as newline-delimited JSON objects, with the fields
+/// `timestamp`, `level`, `target`, `span`, `message`, and (if there are any others) `fields`.
+///
+/// The `span` is the name of the node if the event was emitted within its `Tcp` span,
+/// and the name of the innermost span otherwise.
+pub struct JsonFormatter {
+    fmt: OwnedFormatItem,
+}
+
+impl JsonFormatter {
+    /// The name of the spans created by the `Tcp` stack, which carry the name of the node.
+    const TCP_SPAN_NAME: &'static str = "tcp";
+
+    pub fn new() -> Self {
+        let format =
+            format_description::parse_owned::<2>("[year]-[month]-[day]T[hour]:[minute]:[second].[subsecond digits:6]Z")
+                .expect("failed to set timestamp format");
+        Self { fmt: format }
+    }
+}
+
+impl Default for JsonFormatter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<S> FormatEvent<S, JsonFields> for JsonFormatter
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+{
+    fn format_event(
+        &self,
+        ctx: &FmtContext<'_, S, JsonFields>,
+        mut writer: Writer<'_>,
+        event: &Event<'_>,
+    ) -> fmt::Result {
+        let meta = event.metadata();
+
+        let mut object = Map::new();
+        let timestamp = OffsetDateTime::now_utc().format(&self.fmt).map_err(|_| fmt::Error)?;
+        object.insert("timestamp".to_string(), timestamp.into());
+        object.insert("level".to_string(), meta.level().to_string().into());
+        object.insert("target".to_string(), meta.target().into());
+
+        // Find the name of the node, going from the innermost span outwards.
+        for span in ctx.event_scope().into_iter().flatten() {
+            if span.name() == Self::TCP_SPAN_NAME {
+                let extensions = span.extensions();
+                let node_name = extensions
+                    .get::<FormattedFields<JsonFields>>()
+                    .and_then(|fields| serde_json::from_str::<Map<String, Value>>(fields).ok())
+                    .and_then(|mut fields| fields.remove("name"));
+                if let Some(node_name) = node_name {
+                    object.insert("span".to_string(), node_name);
+                    break;
+                }
+            }
+            object.entry("span").or_insert_with(|| span.name().into());
+        }
+
+        // Record the message and the other fields of the event.
+        let mut visitor = JsonVisitor::default();
+        event.record(&mut visitor);
+        let mut fields = visitor.0;
+        object.insert("message".to_string(), fields.remove("message").unwrap_or_default());
+        if !fields.is_empty() {
+            object.insert("fields".to_string(), fields.into());
+        }
+
+        writeln!(writer, "{}", Value::Object(object))
+    }
+}
+
+/// Collects the fields of an event into a JSON object.
+#[derive(Default)]
+struct JsonVisitor(Map<String, Value>);
+
+impl Visit for JsonVisitor {
+    fn record_f64(&mut self, field: &Field, value: f64) {
+        self.0.insert(field.name().to_string(), value.into());
+    }
+
+    fn record_i64(&mut self, field: &Field, value: i64) {
+        self.0.insert(field.name().to_string(), value.into());
+    }
+
+    fn record_u64(&mut self, field: &Field, value: u64) {
+        self.0.insert(field.name().to_string(), value.into());
+    }
+
+    fn record_bool(&mut self, field: &Field, value: bool) {
+        self.0.insert(field.name().to_string(), value.into());
+    }
+
+    fn record_str(&mut self, field: &Field, value: &str) {
+        self.0.insert(field.name().to_string(), value.into());
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        self.0.insert(field.name().to_string(), format!("{value:?}").into());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use snarkos_node::tcp::create_span;
+
+    use parking_lot::Mutex;
+    use std::{io, sync::Arc};
+    use tracing::info;
+
+    /// A writer collecting the logs in memory.
+    #[derive(Clone, Default)]
+    struct Buffer(Arc<Mutex<Vec<u8>>>);
+
+    impl io::Write for Buffer {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.lock().extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_json_format() {
+        let buffer = Buffer::default();
+        let writer = buffer.clone();
+        let subscriber = tracing_subscriber::fmt()
+            .fmt_fields(JsonFields::new())
+            .event_format(JsonFormatter::new())
+            .with_writer(move || writer.clone())
+            .with_max_level(tracing::Level::INFO)
+            .finish();
+
+        tracing::subscriber::with_default(subscriber, || {
+            info!(height = 5, "Outside of the node");
+            let _guard = create_span("node-7").entered();
+            info!("Within the node");
+        });
+
+        let logs = String::from_utf8(buffer.0.lock().clone()).unwrap();
+        let logs = logs.lines().map(|line| serde_json::from_str::<Value>(line).unwrap()).collect::<Vec<_>>();
+        assert_eq!(logs.len(), 2);
+
+        assert_eq!(logs[0]["level"], "INFO");
+        assert_eq!(logs[0]["target"], module_path!());
+        assert_eq!(logs[0]["message"], "Outside of the node");
+        assert_eq!(logs[0]["fields"]["height"], 5);
+        assert!(logs[0].get("span").is_none());
+        assert!(logs[0]["timestamp"].is_string());
+
+        // Ensure the name of the node is a structured field.
+        assert_eq!(logs[1]["span"], "node-7");
+        assert_eq!(logs[1]["message"], "Within the node");
+        assert!(logs[1].get("fields").is_none());
+    }
+}
@@ -12,8 +12,10 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use crate::helpers::{DynamicFormatter, LogWriter};
+use crate::helpers::{DynamicFormatter, JsonFormatter, LogWriter};
 
+use anyhow::Result;
+use clap::ValueEnum;
 use crossterm::tty::IsTty;
 use std::{
     fs::File,
@@ -23,11 +25,54 @@ use std::{
 };
 use tokio::sync::mpsc;
 use tracing_subscriber::{
+    fmt::format::JsonFields,
     layer::{Layer, SubscriberExt},
+    reload,
     util::SubscriberInitExt,
     EnvFilter,
+    Registry,
 };
 
+/// The format of the logs.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, ValueEnum)]
+pub enum LogFormat {
+    /// The human-readable text.
+    #[default]
+    Text,
+    /// The newline-delimited JSON objects.
+    Json,
+}
+
+/// A handle to the filters of the logger, which allows changing them at runtime.
+#[derive(Clone)]
+pub struct LogFilterHandle {
+    handles: [reload::Handle<EnvFilter, Registry>; 2],
+}
+
+impl LogFilterHandle {
+    /// Replaces the filters of the logger with the given directives, e.g. `info,snarkos_node_tcp=trace`.
+    /// If the directives are invalid, the current filters are left unchanged.
+    pub fn reload(&self, directives: &str) -> Result<()> {
+        for handle in &self.handles {
+            // Note: The directives are parsed before any of the filters is replaced.
+            let filter = quiet(EnvFilter::try_new(directives)?);
+            handle.reload(filter)?;
+        }
+        Ok(())
+    }
+}
+
+/// Turns off the logs of the noisy dependencies in the given filter.
+fn quiet(filter: EnvFilter) -> EnvFilter {
+    filter
+        .add_directive("mio=off".parse().unwrap())
+        .add_directive("tokio_util=off".parse().unwrap())
+        .add_directive("hyper=off".parse().unwrap())
+        .add_directive("reqwest=off".parse().unwrap())
+        .add_directive("want=off".parse().unwrap())
+        .add_directive("warp=off".parse().unwrap())
+}
+
 /// Initializes the logger.
 ///
 /// ```ignore
@@ -41,10 +86,11 @@ use tracing_subscriber::{
 /// ```
 pub fn initialize_logger<P: AsRef<Path>>(
     verbosity: u8,
+    log_format: LogFormat,
     nodisplay: bool,
     logfile: P,
     shutdown: Arc<AtomicBool>,
-) -> mpsc::Receiver<Vec<u8>> {
+) -> (mpsc::Receiver<Vec<u8>>, LogFilterHandle) {
     match verbosity {
         0 => std::env::set_var("RUST_LOG", "info"),
         1 => std::env::set_var("RUST_LOG", "debug"),
@@ -53,13 +99,7 @@ pub fn initialize_logger<P: AsRef<Path>>(
 
     // Filter out undesirable logs. (unfortunately EnvFilter cannot be cloned)
     let [filter, filter2] = std::array::from_fn(|_| {
-        let filter = EnvFilter::from_default_env()
-            .add_directive("mio=off".parse().unwrap())
-            .add_directive("tokio_util=off".parse().unwrap())
-            .add_directive("hyper=off".parse().unwrap())
-            .add_directive("reqwest=off".parse().unwrap())
-            .add_directive("want=off".parse().unwrap())
-            .add_directive("warp=off".parse().unwrap());
+        let filter = quiet(EnvFilter::from_default_env());
 
         let filter = if verbosity >= 2 {
             filter.add_directive("snarkos_node_sync=trace".parse().unwrap())
@@ -94,6 +134,10 @@ pub fn initialize_logger<P: AsRef<Path>>(
         }
     });
 
+    // Allow the filters to be changed at runtime.
+    let (filter, filter_handle) = reload::Layer::new(filter);
+    let (filter2, filter2_handle) = reload::Layer::new(filter2);
+
     // Create the directories tree for a logfile if it doesn't exist.
     let logfile_dir = logfile.as_ref().parent().expect("Root directory passed as a logfile");
     if !logfile_dir.exists() {
@@ -113,28 +157,47 @@ pub fn initialize_logger<P: AsRef<Path>>(
         false => Some(log_sender),
     };
 
-    // Initialize tracing.
-    let _ = tracing_subscriber::registry()
-        .with(
+    // Initialize the layers for the selected log format.
+    let (stdout_layer, file_layer) = match log_format {
+        LogFormat::Text => (
             // Add layer using LogWriter for stdout / terminal
             tracing_subscriber::fmt::Layer::default()
                 .with_ansi(log_sender.is_none() && io::stdout().is_tty())
                 .with_writer(move || LogWriter::new(&log_sender))
                 .with_target(verbosity > 2)
                 .event_format(DynamicFormatter::new(shutdown))
-                .with_filter(filter),
-        )
-        .with(
+                .with_filter(filter)
+                .boxed(),
             // Add layer redirecting logs to the file
             tracing_subscriber::fmt::Layer::default()
                 .with_ansi(false)
                 .with_writer(logfile)
                 .with_target(verbosity > 2)
-                .with_filter(filter2),
-        )
-        .try_init();
+                .with_filter(filter2)
+                .boxed(),
+        ),
+        LogFormat::Json => (
+            // Add layer using LogWriter for stdout / terminal
+            tracing_subscriber::fmt::Layer::default()
+                .with_writer(move || LogWriter::new(&log_sender))
+                .fmt_fields(JsonFields::new())
+                .event_format(JsonFormatter::new())
+                .with_filter(filter)
+                .boxed(),
+            // Add layer redirecting logs to the file
+            tracing_subscriber::fmt::Layer::default()
+                .with_writer(logfile)
+                .fmt_fields(JsonFields::new())
+                .event_format(JsonFormatter::new())
+                .with_filter(filter2)
+                .boxed(),
+        ),
+    };
 
-    log_receiver
+    // Initialize tracing.
+    let _ = tracing_subscriber::registry().with(stdout_layer.and_then(file_layer)).try_init();
+
+    (log_receiver, LogFilterHandle { handles: [filter_handle, filter2_handle] })
 }
 
 /// Returns the welcome message as a string.
@@ -162,3 +225,46 @@ pub fn welcome_message() -> String {
     output += &"👋 Welcome to Aleo! We thank you for running a node and supporting privacy.\n".bold();
     output
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    impl LogFilterHandle {
+        /// Initializes a subscriber with reloadable filters, which must be kept alive for the handle to work.
+        pub(crate) fn new_for_test() -> (impl tracing::Subscriber + Send + Sync, Self) {
+            let (filter, filter_handle) = reload::Layer::new(EnvFilter::new("info"));
+            let (filter2, filter2_handle) = reload::Layer::new(EnvFilter::new("info"));
+            let subscriber = tracing_subscriber::registry().with(
+                tracing_subscriber::fmt::layer()
+                    .with_writer(io::sink)
+                    .with_filter(filter)
+                    .and_then(tracing_subscriber::fmt::layer().with_writer(io::sink).with_filter(filter2)),
+            );
+            (subscriber, Self { handles: [filter_handle, filter2_handle] })
+        }
+
+        /// Returns the current filters.
+        pub(crate) fn current(&self) -> Vec<String> {
+            self.handles.iter().map(|handle| handle.with_current(|filter| filter.to_string()).unwrap()).collect()
+        }
+    }
+
+    #[test]
+    fn test_reload_filter() {
+        let (_subscriber, log_filter) = LogFilterHandle::new_for_test();
+
+        // Change the filters.
+        log_filter.reload("info,snarkos_node_tcp=trace").unwrap();
+        for filter in log_filter.current() {
+            assert!(filter.contains("snarkos_node_tcp=trace"));
+            assert!(filter.contains("hyper=off"));
+        }
+
+        // Ensure invalid directives leave the filters unchanged.
+        assert!(log_filter.reload("snarkos_node_tcp=loud").is_err());
+        for filter in log_filter.current() {
+            assert!(filter.contains("snarkos_node_tcp=trace"));
+        }
+    }
+}
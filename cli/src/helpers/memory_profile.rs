@@ -0,0 +1,144 @@
+// Copyright (C) 2019-2023 Aleo Systems Inc.
+// This file is part of the snarkOS library.
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at:
+// http://www.apache.org/licenses/LICENSE-2.0
+
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use anyhow::{bail, Result};
+use serde::{Deserialize, Serialize};
+use std::{
+    path::{Path, PathBuf},
+    thread,
+    time::Duration,
+};
+use tracing::{debug, warn};
+
+/// The memory usage of the process as reported by jemalloc, in bytes.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MemoryStats {
+    /// The bytes allocated by the application.
+    pub allocated: u64,
+    /// The bytes in the physically resident pages mapped by the allocator.
+    pub resident: u64,
+    /// The bytes in the virtual memory mappings retained by the allocator for future reuse.
+    pub retained: u64,
+}
+
+impl MemoryStats {
+    /// Reads the current memory stats from jemalloc.
+    #[cfg(all(target_os = "linux", target_arch = "x86_64"))]
+    pub fn read() -> Result<Self> {
+        use tikv_jemalloc_ctl::{epoch, stats};
+
+        // Refresh the stats, as jemalloc only updates its cached values when the epoch is advanced.
+        if let Err(error) = epoch::advance() {
+            bail!("Failed to refresh the memory stats ({error})");
+        }
+        let read = || -> Result<Self, tikv_jemalloc_ctl::Error> {
+            Ok(Self {
+                allocated: stats::allocated::read()? as u64,
+                resident: stats::resident::read()? as u64,
+                retained: stats::retained::read()? as u64,
+            })
+        };
+        read().map_err(|error| anyhow::anyhow!("Failed to read the memory stats ({error})"))
+    }
+
+    /// Returns an error, as the memory stats are only available when jemalloc is the global allocator.
+    #[cfg(not(all(target_os = "linux", target_arch = "x86_64")))]
+    pub fn read() -> Result<Self> {
+        bail!("Memory stats are unavailable, as jemalloc is only used on Linux x86_64")
+    }
+}
+
+/// Periodically dumps the jemalloc heap profiles to a directory.
+#[derive(Clone, Debug)]
+pub struct MemoryProfiler {
+    /// The directory the heap profiles are dumped to.
+    directory: PathBuf,
+}
+
+impl MemoryProfiler {
+    /// Activates the jemalloc heap profiling, with the profiles to be dumped to the given directory.
+    #[cfg(all(target_os = "linux", target_arch = "x86_64"))]
+    pub fn enable(directory: &Path) -> Result<Self> {
+        use tikv_jemalloc_ctl::raw;
+
+        // Ensure jemalloc was started with the profiling enabled; it can't be enabled at runtime.
+        // Safety: `opt.prof` is a read-only boolean option.
+        match unsafe { raw::read::<bool>(b"opt.prof\0") } {
+            Ok(true) => (),
+            Ok(false) => bail!("jemalloc was started with the heap profiling disabled"),
+            Err(error) => bail!("jemalloc doesn't support heap profiling ({error})"),
+        }
+        // Prepare the directory for the heap profiles.
+        std::fs::create_dir_all(directory)?;
+        // Activate the heap profiling.
+        // Safety: `prof.active` is a read-write boolean control.
+        if let Err(error) = unsafe { raw::write(b"prof.active\0", true) } {
+            bail!("Failed to activate the heap profiling ({error})");
+        }
+
+        Ok(Self { directory: directory.to_path_buf() })
+    }
+
+    /// Returns an error, as the heap profiling is only available when jemalloc is the global allocator.
+    #[cfg(not(all(target_os = "linux", target_arch = "x86_64")))]
+    pub fn enable(_directory: &Path) -> Result<Self> {
+        bail!("Memory profiling requires jemalloc, which is only used on Linux x86_64")
+    }
+
+    /// Returns the directory the heap profiles are dumped to.
+    pub fn directory(&self) -> &Path {
+        &self.directory
+    }
+
+    /// Dumps the current heap profile, and returns the path to it.
+    #[cfg(all(target_os = "linux", target_arch = "x86_64"))]
+    pub fn dump(&self) -> Result<PathBuf> {
+        use std::{
+            ffi::CString,
+            os::unix::ffi::OsStrExt,
+            time::{SystemTime, UNIX_EPOCH},
+        };
+        use tikv_jemalloc_ctl::raw;
+
+        // Name the profile after the current time, so that subsequent dumps don't overwrite each other.
+        let timestamp = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs();
+        let path = self.directory.join(format!("snarkos.{timestamp}.heap"));
+        let c_path = CString::new(path.as_os_str().as_bytes())?;
+        // Safety: `prof.dump` expects a pointer to a NUL-terminated path, which outlives the call.
+        if let Err(error) = unsafe { raw::write(b"prof.dump\0", c_path.as_ptr()) } {
+            bail!("Failed to dump the heap profile to '{}' ({error})", path.display());
+        }
+
+        Ok(path)
+    }
+
+    /// Returns an error, as the heap profiling is only available when jemalloc is the global allocator.
+    #[cfg(not(all(target_os = "linux", target_arch = "x86_64")))]
+    pub fn dump(&self) -> Result<PathBuf> {
+        bail!("Memory profiling requires jemalloc, which is only used on Linux x86_64")
+    }
+
+    /// Spawns a background thread which dumps the heap profile once every `interval`.
+    pub fn spawn(self, interval: Duration) -> Result<()> {
+        thread::Builder::new().name("memory-profiler".to_string()).spawn(move || loop {
+            thread::sleep(interval);
+            match self.dump() {
+                Ok(path) => debug!("Dumped the heap profile to '{}'", path.display()),
+                Err(error) => warn!("{error}"),
+            }
+        })?;
+
+        Ok(())
+    }
+}
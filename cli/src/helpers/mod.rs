@@ -18,12 +18,21 @@ pub use bech32m::*;
 mod log_writer;
 use log_writer::*;
 
+mod control;
+pub use control::*;
+
 mod dynamic_format;
 use dynamic_format::*;
 
+mod json_format;
+use json_format::*;
+
 pub mod logger;
 pub use logger::*;
 
+mod memory_profile;
+pub use memory_profile::*;
+
 pub mod updater;
 pub use updater::*;
 
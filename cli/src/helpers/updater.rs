@@ -12,9 +12,63 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+use clap::ValueEnum;
 use colored::Colorize;
-use self_update::{backends::github, version::bump_is_greater, Status};
-use std::fmt::Write;
+use self_update::{backends::github, update::Release, version::bump_is_greater, Status};
+use serde::Serialize;
+use std::fmt::{self, Write};
+
+/// The release channel `snarkos` is updated from.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Serialize, ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum ReleaseChannel {
+    /// The stable releases.
+    #[default]
+    Stable,
+    /// The beta releases (and release candidates), as well as the stable ones.
+    Beta,
+    /// All the releases, including the nightly ones.
+    Nightly,
+}
+
+impl ReleaseChannel {
+    /// Returns the channel the given release version belongs to.
+    pub fn of(version: &str) -> Self {
+        match version.split_once('-') {
+            None => Self::Stable,
+            Some((_, pre_release)) if pre_release.starts_with("beta") || pre_release.starts_with("rc") => Self::Beta,
+            Some(_) => Self::Nightly,
+        }
+    }
+
+    /// Returns `true` if the given release version is available on this channel.
+    pub fn includes(&self, version: &str) -> bool {
+        Self::of(version) <= *self
+    }
+}
+
+impl fmt::Display for ReleaseChannel {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Stable => write!(f, "stable"),
+            Self::Beta => write!(f, "beta"),
+            Self::Nightly => write!(f, "nightly"),
+        }
+    }
+}
+
+/// The result of checking for an available update.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct UpdateCheck {
+    /// The current version of `snarkos`.
+    pub current_version: String,
+    /// The release channel that was checked.
+    pub channel: ReleaseChannel,
+    /// The version of the latest release on the channel.
+    pub latest_version: String,
+    /// Whether the latest release is newer than the current version.
+    pub update_available: bool,
+}
 
 pub struct Updater;
 
@@ -23,23 +77,46 @@ impl Updater {
     const SNARKOS_REPO_NAME: &'static str = "snarkOS";
     const SNARKOS_REPO_OWNER: &'static str = "AleoHQ";
 
-    /// Show all available releases for `snarkos`.
-    pub fn show_available_releases() -> Result<String, UpdaterError> {
-        let releases = github::ReleaseList::configure()
+    /// Returns all the releases of `snarkos`, starting with the newest one.
+    fn releases() -> Result<Vec<Release>, UpdaterError> {
+        Ok(github::ReleaseList::configure()
             .repo_owner(Self::SNARKOS_REPO_OWNER)
             .repo_name(Self::SNARKOS_REPO_NAME)
             .build()?
-            .fetch()?;
+            .fetch()?)
+    }
+
+    /// Returns the tag of the newest release on the given channel; the stable releases are resolved
+    /// by the default updater, so `None` is returned for them.
+    fn release_tag(channel: ReleaseChannel) -> Result<Option<String>, UpdaterError> {
+        if channel == ReleaseChannel::Stable {
+            return Ok(None);
+        }
+        let release = Self::releases()?
+            .into_iter()
+            .find(|release| channel.includes(&release.version))
+            .ok_or(UpdaterError::NoRelease(channel))?;
+        // Note: The release tags of snarkOS are the versions prefixed with a 'v'.
+        Ok(Some(format!("v{}", release.version)))
+    }
 
-        let mut output = "List of available versions\n".to_string();
-        for release in releases {
+    /// Show all available releases for `snarkos` on the given channel.
+    pub fn show_available_releases(channel: ReleaseChannel) -> Result<String, UpdaterError> {
+        let releases = Self::releases()?;
+
+        let mut output = format!("List of available versions ({channel})\n");
+        for release in releases.into_iter().filter(|release| channel.includes(&release.version)) {
             let _ = writeln!(output, "  * {}", release.version);
         }
         Ok(output)
     }
 
-    /// Update `snarkOS` to the specified release.
-    pub fn update_to_release(show_output: bool, version: Option<String>) -> Result<Status, UpdaterError> {
+    /// Update `snarkOS` to the specified release, or to the newest release on the given channel.
+    pub fn update_to_release(
+        show_output: bool,
+        version: Option<String>,
+        channel: ReleaseChannel,
+    ) -> Result<Status, UpdaterError> {
         let mut update_builder = github::Update::configure();
 
         update_builder
@@ -51,7 +128,7 @@ impl Updater {
             .no_confirm(true)
             .show_output(show_output);
 
-        let status = match version {
+        let status = match version.map_or_else(|| Self::release_tag(channel), |version| Ok(Some(version)))? {
             None => update_builder.build()?.update()?,
             Some(v) => update_builder.target_version_tag(&v).build()?.update()?,
         };
@@ -59,8 +136,9 @@ impl Updater {
         Ok(status)
     }
 
-    /// Check if there is an available update for `snarkos` and return the newest release.
-    pub fn update_available() -> Result<String, UpdaterError> {
+    /// Compare the current version of `snarkos` against the latest release on the given channel,
+    /// without downloading anything.
+    pub fn check_for_update(channel: ReleaseChannel) -> Result<UpdateCheck, UpdaterError> {
         let updater = github::Update::configure()
             .repo_owner(Self::SNARKOS_REPO_OWNER)
             .repo_name(Self::SNARKOS_REPO_NAME)
@@ -69,20 +147,37 @@ impl Updater {
             .build()?;
 
         let current_version = updater.current_version();
-        let latest_release = updater.get_latest_release()?;
+        let latest_version = match Self::release_tag(channel)? {
+            None => updater.get_latest_release()?.version,
+            Some(tag) => tag.trim_start_matches('v').to_string(),
+        };
+        let update_available = bump_is_greater(&current_version, &latest_version)?;
 
-        if bump_is_greater(&current_version, &latest_release.version)? {
-            Ok(latest_release.version)
+        Ok(UpdateCheck { current_version, channel, latest_version, update_available })
+    }
+
+    /// Check if there is an available update for `snarkos` on the given channel and return the newest release.
+    pub fn update_available(channel: ReleaseChannel) -> Result<String, UpdaterError> {
+        let check = Self::check_for_update(channel)?;
+
+        if check.update_available {
+            Ok(check.latest_version)
         } else {
-            Err(UpdaterError::OldReleaseVersion(current_version, latest_release.version))
+            Err(UpdaterError::OldReleaseVersion(check.current_version, check.latest_version))
         }
     }
 
-    /// Display the CLI message.
-    pub fn print_cli() -> String {
-        if let Ok(latest_version) = Self::update_available() {
-            let mut output = "🟢 A new version is available! Run".bold().green().to_string();
-            output += &" `snarkos update` ".bold().white();
+    /// Display the CLI message for the given release channel.
+    pub fn print_cli(channel: ReleaseChannel) -> String {
+        if let Ok(latest_version) = Self::update_available(channel) {
+            let mut output =
+                format!("🟢 A new version is available on the {channel} channel! Run").bold().green().to_string();
+            output += &match channel {
+                ReleaseChannel::Stable => " `snarkos update` ".to_string(),
+                channel => format!(" `snarkos update --channel {channel}` "),
+            }
+            .bold()
+            .white();
             output += &format!("to update to v{latest_version}.").bold().green();
             output
         } else {
@@ -98,6 +193,9 @@ pub enum UpdaterError {
 
     #[error("The current version {} is more recent than the release version {}", _0, _1)]
     OldReleaseVersion(String, String),
+
+    #[error("There are no releases on the {} channel", _0)]
+    NoRelease(ReleaseChannel),
 }
 
 impl From<self_update::errors::Error> for UpdaterError {
@@ -105,3 +203,28 @@ impl From<self_update::errors::Error> for UpdaterError {
         UpdaterError::Crate("self_update", error.to_string())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_release_channels() {
+        assert_eq!(ReleaseChannel::of("2.2.7"), ReleaseChannel::Stable);
+        assert_eq!(ReleaseChannel::of("2.2.7-beta.1"), ReleaseChannel::Beta);
+        assert_eq!(ReleaseChannel::of("2.2.7-rc.2"), ReleaseChannel::Beta);
+        assert_eq!(ReleaseChannel::of("2.2.7-nightly.20240101"), ReleaseChannel::Nightly);
+
+        // Ensure the channels include the releases of the more stable ones.
+        assert!(ReleaseChannel::Stable.includes("2.2.7"));
+        assert!(!ReleaseChannel::Stable.includes("2.2.7-beta.1"));
+        assert!(ReleaseChannel::Beta.includes("2.2.7"));
+        assert!(ReleaseChannel::Beta.includes("2.2.7-beta.1"));
+        assert!(!ReleaseChannel::Beta.includes("2.2.7-nightly.20240101"));
+        assert!(ReleaseChannel::Nightly.includes("2.2.7-nightly.20240101"));
+
+        // Ensure unknown channels are rejected.
+        assert!(ReleaseChannel::from_str("beta", true).is_ok());
+        assert!(ReleaseChannel::from_str("canary", true).is_err());
+    }
+}
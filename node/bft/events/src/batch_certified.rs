@@ -39,6 +39,12 @@ impl<N: Network> EventTrait for BatchCertified<N> {
     fn name(&self) -> Cow<'static, str> {
         "BatchCertified".into()
     }
+
+    /// Returns the event priority.
+    #[inline]
+    fn priority(&self) -> Priority {
+        Priority::High
+    }
 }
 
 impl<N: Network> ToBytes for BatchCertified<N> {
@@ -40,6 +40,12 @@ impl<N: Network> EventTrait for BatchPropose<N> {
     fn name(&self) -> Cow<'static, str> {
         "BatchPropose".into()
     }
+
+    /// Returns the event priority.
+    #[inline]
+    fn priority(&self) -> Priority {
+        Priority::High
+    }
 }
 
 impl<N: Network> ToBytes for BatchPropose<N> {
@@ -33,6 +33,12 @@ impl<N: Network> EventTrait for BatchSignature<N> {
     fn name(&self) -> Cow<'static, str> {
         "BatchSignature".into()
     }
+
+    /// Returns the event priority.
+    #[inline]
+    fn priority(&self) -> Priority {
+        Priority::High
+    }
 }
 
 impl<N: Network> ToBytes for BatchSignature<N> {
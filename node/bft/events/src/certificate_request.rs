@@ -39,6 +39,12 @@ impl<N: Network> EventTrait for CertificateRequest<N> {
     fn name(&self) -> Cow<'static, str> {
         "CertificateRequest".into()
     }
+
+    /// Returns the event priority.
+    #[inline]
+    fn priority(&self) -> Priority {
+        Priority::High
+    }
 }
 
 impl<N: Network> ToBytes for CertificateRequest<N> {
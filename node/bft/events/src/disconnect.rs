@@ -47,6 +47,12 @@ impl EventTrait for Disconnect {
     fn name(&self) -> Cow<'static, str> {
         "Disconnect".into()
     }
+
+    /// Returns the event priority.
+    #[inline]
+    fn priority(&self) -> Priority {
+        Priority::High
+    }
 }
 
 impl ToBytes for Disconnect {
@@ -65,6 +65,8 @@ pub use validators_response::ValidatorsResponse;
 mod worker_ping;
 pub use worker_ping::WorkerPing;
 
+pub use snarkos_node_tcp::protocols::Priority;
+
 use snarkos_node_sync_locators::BlockLocators;
 use snarkvm::{
     console::prelude::{error, FromBytes, Network, Read, ToBytes, Write},
@@ -84,6 +86,13 @@ use std::{borrow::Cow, net::SocketAddr};
 pub trait EventTrait: ToBytes + FromBytes {
     /// Returns the event name.
     fn name(&self) -> Cow<'static, str>;
+
+    /// Returns the event priority; the high-priority events, i.e. the ones driving the consensus, are sent ahead
+    /// of the queued low-priority ones.
+    #[inline]
+    fn priority(&self) -> Priority {
+        Priority::Low
+    }
 }
 
 #[derive(Clone, Debug, PartialEq, Eq)]
@@ -142,6 +151,29 @@ impl<N: Network> Event<N> {
         }
     }
 
+    /// Returns the event priority.
+    #[inline]
+    pub fn priority(&self) -> Priority {
+        match self {
+            Self::BatchPropose(event) => event.priority(),
+            Self::BatchSignature(event) => event.priority(),
+            Self::BatchCertified(event) => event.priority(),
+            Self::BlockRequest(event) => event.priority(),
+            Self::BlockResponse(event) => event.priority(),
+            Self::CertificateRequest(event) => event.priority(),
+            Self::CertificateResponse(event) => event.priority(),
+            Self::ChallengeRequest(event) => event.priority(),
+            Self::ChallengeResponse(event) => event.priority(),
+            Self::Disconnect(event) => event.priority(),
+            Self::PrimaryPing(event) => event.priority(),
+            Self::TransmissionRequest(event) => event.priority(),
+            Self::TransmissionResponse(event) => event.priority(),
+            Self::ValidatorsRequest(event) => event.priority(),
+            Self::ValidatorsResponse(event) => event.priority(),
+            Self::WorkerPing(event) => event.priority(),
+        }
+    }
+
     /// Returns the event ID.
     #[inline]
     pub fn id(&self) -> u16 {
@@ -47,6 +47,7 @@ use snarkos_node_tcp::{
     Config,
     Connection,
     ConnectionSide,
+    Stream,
     Tcp,
     P2P,
 };
@@ -64,9 +65,8 @@ use futures::SinkExt;
 use indexmap::{IndexMap, IndexSet};
 use parking_lot::{Mutex, RwLock};
 use rand::seq::{IteratorRandom, SliceRandom};
-use std::{collections::HashSet, future::Future, io, net::SocketAddr, sync::Arc, time::Duration};
+use std::{borrow::Cow, collections::HashSet, future::Future, io, net::SocketAddr, sync::Arc, time::Duration};
 use tokio::{
-    net::TcpStream,
     sync::{oneshot, OnceCell},
     task::{self, JoinHandle},
 };
@@ -516,11 +516,12 @@ impl<N: Network> Gateway<N> {
             warn!("Unable to resolve the listener IP address '{peer_ip}'");
             return None;
         };
-        // Retrieve the event name.
+        // Retrieve the event name and priority; the consensus events are sent ahead of the queued ones.
         let name = event.name();
+        let priority = event.priority();
         // Send the event to the peer.
         trace!("{CONTEXT} Sending '{name}' to '{peer_ip}'");
-        let result = self.unicast(peer_addr, event);
+        let result = self.unicast_with_priority(peer_addr, event, priority);
         // If the event was unable to be sent, disconnect.
         if let Err(e) = &result {
             warn!("{CONTEXT} Failed to send '{name}' to '{peer_ip}': {e}");
@@ -1043,6 +1044,11 @@ impl<N: Network> Reading for Gateway<N> {
         Default::default()
     }
 
+    /// Returns the type of the given message, which is used to gather the statistics per message type.
+    fn message_type(message: &Self::Message) -> Option<Cow<'static, str>> {
+        Some(message.name())
+    }
+
     /// Processes a message received from the network.
     async fn process_message(&self, peer_addr: SocketAddr, message: Self::Message) -> io::Result<()> {
         // Process the message. Disconnect if the peer violated the protocol.
@@ -1077,6 +1083,11 @@ impl<N: Network> Writing for Gateway<N> {
     fn codec(&self, _peer_addr: SocketAddr, _side: ConnectionSide) -> Self::Codec {
         Default::default()
     }
+
+    /// Returns the type of the given message, which is used to gather the statistics per message type.
+    fn message_type(message: &Self::Message) -> Option<Cow<'static, str>> {
+        Some(message.name())
+    }
 }
 
 #[async_trait]
@@ -1169,7 +1180,7 @@ macro_rules! expect_event {
 
 /// Send the given message to the peer.
 async fn send_event<N: Network>(
-    framed: &mut Framed<&mut TcpStream, EventCodec<N>>,
+    framed: &mut Framed<&mut Stream, EventCodec<N>>,
     peer_addr: SocketAddr,
     event: Event<N>,
 ) -> io::Result<()> {
@@ -1183,8 +1194,8 @@ impl<N: Network> Gateway<N> {
         &'a self,
         peer_addr: SocketAddr,
         peer_ip: Option<SocketAddr>,
-        stream: &'a mut TcpStream,
-    ) -> io::Result<(SocketAddr, Framed<&mut TcpStream, EventCodec<N>>)> {
+        stream: &'a mut Stream,
+    ) -> io::Result<(SocketAddr, Framed<&mut Stream, EventCodec<N>>)> {
         // This value is immediately guaranteed to be present, so it can be unwrapped.
         let peer_ip = peer_ip.unwrap();
 
@@ -1245,8 +1256,8 @@ impl<N: Network> Gateway<N> {
         &'a self,
         peer_addr: SocketAddr,
         peer_ip: &mut Option<SocketAddr>,
-        stream: &'a mut TcpStream,
-    ) -> io::Result<(SocketAddr, Framed<&mut TcpStream, EventCodec<N>>)> {
+        stream: &'a mut Stream,
+    ) -> io::Result<(SocketAddr, Framed<&mut Stream, EventCodec<N>>)> {
         // Construct the stream.
         let mut framed = Framed::new(stream, EventCodec::<N>::handshake());
 
@@ -1369,9 +1380,10 @@ mod prop_tests {
         MEMORY_POOL_PORT,
     };
     use snarkos_account::Account;
+    use snarkos_node_bft_events::{CertificateRequest, Event, EventCodec, WorkerPing};
     use snarkos_node_bft_ledger_service::MockLedgerService;
     use snarkos_node_bft_storage_service::BFTMemoryService;
-    use snarkos_node_tcp::P2P;
+    use snarkos_node_tcp::{protocols::Writing, P2P};
     use snarkvm::{
         ledger::{
             committee::{
@@ -1381,7 +1393,7 @@ mod prop_tests {
             },
             narwhal::{batch_certificate::test_helpers::sample_batch_certificate_for_round, BatchHeader},
         },
-        prelude::{MainnetV0, PrivateKey},
+        prelude::{Field, MainnetV0, PrivateKey},
         utilities::TestRng,
     };
 
@@ -1396,6 +1408,9 @@ mod prop_tests {
         sync::Arc,
     };
     use test_strategy::proptest;
+    use tokio::net::TcpListener;
+    use tokio_stream::StreamExt;
+    use tokio_util::codec::FramedRead;
 
     type CurrentNetwork = MainnetV0;
 
@@ -1611,4 +1626,43 @@ mod prop_tests {
             }
         }
     }
+
+    #[tokio::test]
+    async fn test_consensus_events_overtake_queued_events() {
+        let rng = &mut TestRng::default();
+
+        // Initialize the committee.
+        let accounts = (0..4).map(|_| Account::new(rng).unwrap()).collect::<Vec<_>>();
+        let addresses = accounts.iter().map(|account| account.address()).collect();
+        let committee = sample_committee_for_round_and_members(1, addresses, rng);
+        // Initialize the ledger and the storage.
+        let ledger = Arc::new(MockLedgerService::new(committee));
+        let max_gc_rounds = BatchHeader::<CurrentNetwork>::MAX_GC_ROUNDS as u64;
+        let storage = Storage::new(ledger.clone(), Arc::new(BFTMemoryService::new()), max_gc_rounds);
+        // Initialize the gateway; only the writing side is needed here.
+        let gateway = Gateway::new(accounts[0].clone(), storage, ledger, None, &[], Some(0)).unwrap();
+        gateway.enable_writing().await;
+
+        // Connect the gateway to a peer, and register it as a connected one.
+        let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, 0)).await.unwrap();
+        let peer_ip = listener.local_addr().unwrap();
+        gateway.tcp().connect(peer_ip).await.unwrap();
+        let (stream, _) = listener.accept().await.unwrap();
+        gateway.insert_connected_peer(peer_ip, peer_ip, accounts[1].address());
+
+        // Queue a few low-priority events, followed by a consensus event; the writer can't run in the meantime.
+        for _ in 0..3 {
+            gateway.send_inner(peer_ip, Event::WorkerPing(WorkerPing::new(Default::default()))).unwrap();
+        }
+        let certificate_id = Field::from_u64(1);
+        gateway.send_inner(peer_ip, Event::CertificateRequest(CertificateRequest::new(certificate_id))).unwrap();
+
+        // Ensure the consensus event was sent ahead of the queued ones.
+        let mut framed = FramedRead::new(stream, EventCodec::<CurrentNetwork>::default());
+        let mut names = vec![];
+        for _ in 0..4 {
+            names.push(framed.next().await.unwrap().unwrap().name());
+        }
+        assert_eq!(names, ["CertificateRequest", "WorkerPing", "WorkerPing", "WorkerPing"]);
+    }
 }
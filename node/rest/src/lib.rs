@@ -179,6 +179,9 @@ impl<N: Network, C: ConsensusStorage<N>, R: Routing<N>> Rest<N, C, R> {
             .route(&format!("/{network}/peers/all"), get(Self::get_peers_all))
             .route(&format!("/{network}/peers/all/metrics"), get(Self::get_peers_all_metrics))
 
+            // GET ../node/..
+            .route(&format!("/{network}/node/status"), get(Self::get_node_status))
+
             // GET ../program/..
             .route(&format!("/{network}/program/:id"), get(Self::get_program))
             .route(&format!("/{network}/program/:id/mappings"), get(Self::get_mapping_names))
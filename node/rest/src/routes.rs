@@ -317,6 +317,11 @@ impl<N: Network, C: ConsensusStorage<N>, R: Routing<N>> Rest<N, C, R> {
         ErasedJson::pretty(rest.routing.router().address())
     }
 
+    // GET /<network>/node/status
+    pub(crate) async fn get_node_status(State(rest): State<Self>) -> ErasedJson {
+        ErasedJson::pretty(rest.routing.router().status())
+    }
+
     // GET /<network>/find/blockHash/{transactionID}
     pub(crate) async fn find_block_hash(
         State(rest): State<Self>,
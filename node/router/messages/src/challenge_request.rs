@@ -25,6 +25,8 @@ pub struct ChallengeRequest<N: Network> {
     pub node_type: NodeType,
     pub address: Address<N>,
     pub nonce: u64,
+    pub node_nonce: u64,
+    pub capabilities: Capabilities,
 }
 
 impl<N: Network> MessageTrait for ChallengeRequest<N> {
@@ -42,6 +44,12 @@ impl<N: Network> ToBytes for ChallengeRequest<N> {
         self.node_type.write_le(&mut writer)?;
         self.address.write_le(&mut writer)?;
         self.nonce.write_le(&mut writer)?;
+        if self.version >= Self::NODE_NONCE_VERSION {
+            self.node_nonce.write_le(&mut writer)?;
+        }
+        if self.version >= Self::CAPABILITIES_VERSION {
+            self.capabilities.bits().write_le(&mut writer)?;
+        }
         Ok(())
     }
 }
@@ -53,20 +61,38 @@ impl<N: Network> FromBytes for ChallengeRequest<N> {
         let node_type = NodeType::read_le(&mut reader)?;
         let address = Address::<N>::read_le(&mut reader)?;
         let nonce = u64::read_le(&mut reader)?;
-
-        Ok(Self { version, listener_port, node_type, address, nonce })
+        let node_nonce = if version >= Self::NODE_NONCE_VERSION { u64::read_le(&mut reader)? } else { 0 };
+        // The unknown capabilities are retained, so that they can be ignored rather than rejected.
+        let capabilities = match version >= Self::CAPABILITIES_VERSION {
+            true => Capabilities::from_bits_retain(u64::read_le(&mut reader)?),
+            false => Capabilities::empty(),
+        };
+
+        Ok(Self { version, listener_port, node_type, address, nonce, node_nonce, capabilities })
     }
 }
 
 impl<N: Network> ChallengeRequest<N> {
-    pub fn new(listener_port: u16, node_type: NodeType, address: Address<N>, nonce: u64) -> Self {
-        Self { version: Message::<N>::VERSION, listener_port, node_type, address, nonce }
+    /// The first message version in which the `ChallengeRequest` messages carry the node capabilities.
+    pub const CAPABILITIES_VERSION: u32 = 20;
+    /// The first message version in which the `ChallengeRequest` messages carry the node nonce.
+    pub const NODE_NONCE_VERSION: u32 = 18;
+
+    pub fn new(
+        listener_port: u16,
+        node_type: NodeType,
+        address: Address<N>,
+        nonce: u64,
+        node_nonce: u64,
+        capabilities: Capabilities,
+    ) -> Self {
+        Self { version: Message::<N>::VERSION, listener_port, node_type, address, nonce, node_nonce, capabilities }
     }
 }
 
 #[cfg(test)]
 pub mod prop_tests {
-    use crate::{ChallengeRequest, NodeType};
+    use crate::{Capabilities, ChallengeRequest, NodeType};
     use snarkvm::{
         console::prelude::{FromBytes, ToBytes},
         prelude::{Address, TestRng, Uniform},
@@ -94,13 +120,16 @@ pub mod prop_tests {
     }
 
     pub fn any_challenge_request() -> BoxedStrategy<ChallengeRequest<CurrentNetwork>> {
-        (any_valid_address(), any::<u64>(), any::<u32>(), any::<u16>(), any_node_type())
-            .prop_map(|(address, nonce, version, listener_port, node_type)| ChallengeRequest {
-                address,
-                nonce,
-                version,
-                listener_port,
-                node_type,
+        (any_valid_address(), any::<u64>(), any::<u32>(), any::<u16>(), any_node_type(), any::<u64>(), any::<u64>())
+            .prop_map(|(address, nonce, version, listener_port, node_type, node_nonce, capabilities)| {
+                // The node nonce and capabilities are only carried by the messages of a sufficiently recent version.
+                let node_nonce =
+                    if version >= ChallengeRequest::<CurrentNetwork>::NODE_NONCE_VERSION { node_nonce } else { 0 };
+                let capabilities = match version >= ChallengeRequest::<CurrentNetwork>::CAPABILITIES_VERSION {
+                    true => Capabilities::from_bits_retain(capabilities),
+                    false => Capabilities::empty(),
+                };
+                ChallengeRequest { address, nonce, version, listener_port, node_type, node_nonce, capabilities }
             })
             .boxed()
     }
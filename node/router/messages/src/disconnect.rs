@@ -35,6 +35,12 @@ impl MessageTrait for Disconnect {
     fn name(&self) -> Cow<'static, str> {
         "Disconnect".into()
     }
+
+    /// Returns the message priority.
+    #[inline]
+    fn priority(&self) -> Priority {
+        Priority::High
+    }
 }
 
 impl ToBytes for Disconnect {
@@ -78,6 +84,9 @@ mod tests {
             DisconnectReason::TooManyPeers,
             DisconnectReason::YouNeedToSyncFirst,
             DisconnectReason::YourPortIsClosed(TestRng::default().gen()),
+            DisconnectReason::VersionMismatch,
+            DisconnectReason::SelfConnect,
+            DisconnectReason::UnexpectedIdentity,
         ];
 
         for reason in all_reasons.iter() {
@@ -15,19 +15,34 @@
 use crate::Message;
 use snarkvm::prelude::{FromBytes, Network, ToBytes};
 
-use ::bytes::{Buf, BufMut, BytesMut};
+use ::bytes::{Buf, BufMut, Bytes, BytesMut};
 use core::marker::PhantomData;
+use std::io::{self, Read};
 use tokio_util::codec::{Decoder, Encoder, LengthDelimitedCodec};
 
 /// The maximum size of a message that can be transmitted during the handshake.
-const MAXIMUM_HANDSHAKE_MESSAGE_SIZE: usize = 1024 * 1024; // 1 MiB
+pub(crate) const MAXIMUM_HANDSHAKE_MESSAGE_SIZE: usize = 1024 * 1024; // 1 MiB
 
 /// The maximum size of a message that can be transmitted in the network.
 pub(crate) const MAXIMUM_MESSAGE_SIZE: usize = 128 * 1024 * 1024; // 128 MiB
 
+/// The size of the optional CRC32 checksum appended to the payload of each frame.
+const CHECKSUM_SIZE: usize = 4;
+
+/// The flag prepended to the payload of each frame when compression is enabled, indicating a raw payload.
+const UNCOMPRESSED_FLAG: u8 = 0;
+/// The flag prepended to the payload of each frame when compression is enabled, indicating a zstd payload.
+const COMPRESSED_FLAG: u8 = 1;
+
 /// The codec used to decode and encode network `Message`s.
 pub struct MessageCodec<N: Network> {
     codec: LengthDelimitedCodec,
+    /// If `true`, each frame carries a CRC32 checksum of its payload.
+    verify_checksums: bool,
+    /// If set, each frame carries a compression flag, and the payloads above the threshold are compressed.
+    compression_level: Option<i32>,
+    /// The payload size (in bytes) above which the payloads are compressed.
+    compression_threshold: usize,
     _phantom: PhantomData<N>,
 }
 
@@ -37,12 +52,47 @@ impl<N: Network> MessageCodec<N> {
         codec.codec.set_max_frame_length(MAXIMUM_HANDSHAKE_MESSAGE_SIZE);
         codec
     }
+
+    /// Enables or disables the CRC32 checksum of the frame payloads.
+    pub fn with_checksums(mut self, verify_checksums: bool) -> Self {
+        self.verify_checksums = verify_checksums;
+        self
+    }
+
+    /// Lowers the maximum size of the frames, which is enforced before their bytes are buffered.
+    pub fn with_max_message_size(mut self, max_message_size: usize) -> Self {
+        let max_frame_length = self.codec.max_frame_length().min(max_message_size);
+        self.codec.set_max_frame_length(max_frame_length);
+        self
+    }
+
+    /// Enables or disables the zstd compression of the frame payloads larger than the given threshold.
+    pub fn with_compression(mut self, compression_level: Option<i32>, compression_threshold: usize) -> Self {
+        self.compression_level = compression_level;
+        self.compression_threshold = compression_threshold;
+        self
+    }
+
+    /// Decompresses the given payload, ensuring its decompressed size doesn't exceed the maximum frame length.
+    fn decompress(&self, compressed: &[u8]) -> io::Result<Bytes> {
+        let max_size = self.codec.max_frame_length();
+        let mut decompressed = Vec::new();
+        // Bound the output in order to prevent decompression bombs.
+        zstd::stream::read::Decoder::new(compressed)?.take(max_size as u64 + 1).read_to_end(&mut decompressed)?;
+        if decompressed.len() > max_size {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "decompressed message is too large"));
+        }
+        Ok(decompressed.into())
+    }
 }
 
 impl<N: Network> Default for MessageCodec<N> {
     fn default() -> Self {
         Self {
             codec: LengthDelimitedCodec::builder().max_frame_length(MAXIMUM_MESSAGE_SIZE).little_endian().new_codec(),
+            verify_checksums: false,
+            compression_level: None,
+            compression_threshold: 0,
             _phantom: Default::default(),
         }
     }
@@ -52,15 +102,36 @@ impl<N: Network> Encoder<Message<N>> for MessageCodec<N> {
     type Error = std::io::Error;
 
     fn encode(&mut self, message: Message<N>, dst: &mut BytesMut) -> Result<(), Self::Error> {
+        // Reserve the compression flag, if enabled.
+        if self.compression_level.is_some() {
+            dst.put_u8(UNCOMPRESSED_FLAG);
+        }
+
         // Serialize the payload directly into dst.
         message
             .write_le(&mut dst.writer())
             // This error should never happen, the conversion is for greater compatibility.
             .map_err(|_| std::io::Error::new(std::io::ErrorKind::InvalidData, "serialization error"))?;
 
-        let serialized_message = dst.split_to(dst.len()).freeze();
+        let mut serialized_message = dst.split_to(dst.len());
+
+        // Compress the payload, if enabled and large enough.
+        if let Some(level) = self.compression_level {
+            if serialized_message.len() - 1 > self.compression_threshold {
+                let compressed = zstd::bulk::compress(&serialized_message[1..], level)?;
+                serialized_message.truncate(1);
+                serialized_message[0] = COMPRESSED_FLAG;
+                serialized_message.extend_from_slice(&compressed);
+            }
+        }
 
-        self.codec.encode(serialized_message, dst)
+        // Append the checksum of the payload, if enabled.
+        if self.verify_checksums {
+            let checksum = crc32fast::hash(&serialized_message);
+            serialized_message.put_u32_le(checksum);
+        }
+
+        self.codec.encode(serialized_message.freeze(), dst)
     }
 }
 
@@ -70,11 +141,40 @@ impl<N: Network> Decoder for MessageCodec<N> {
 
     fn decode(&mut self, source: &mut BytesMut) -> Result<Option<Self::Item>, Self::Error> {
         // Decode a frame containing bytes belonging to a message.
-        let bytes = match self.codec.decode(source)? {
+        let mut bytes = match self.codec.decode(source)? {
             Some(bytes) => bytes,
             None => return Ok(None),
         };
 
+        // Verify and strip the checksum of the payload, if enabled.
+        if self.verify_checksums {
+            if bytes.len() < CHECKSUM_SIZE {
+                return Err(io::Error::new(io::ErrorKind::InvalidData, "missing checksum"));
+            }
+            let checksum = bytes.split_off(bytes.len() - CHECKSUM_SIZE).get_u32_le();
+            if crc32fast::hash(&bytes) != checksum {
+                warn!("Failed to verify the checksum of a message");
+                return Err(io::Error::new(io::ErrorKind::InvalidData, "checksum mismatch"));
+            }
+        }
+
+        // Decompress the payload, if enabled and compressed.
+        let bytes = if self.compression_level.is_some() {
+            if bytes.is_empty() {
+                return Err(io::Error::new(io::ErrorKind::InvalidData, "missing compression flag"));
+            }
+            match bytes.get_u8() {
+                UNCOMPRESSED_FLAG => bytes.freeze(),
+                COMPRESSED_FLAG => self.decompress(&bytes).map_err(|error| {
+                    warn!("Failed to decompress a message - {}", error);
+                    io::Error::from(io::ErrorKind::InvalidData)
+                })?,
+                _ => return Err(io::Error::new(io::ErrorKind::InvalidData, "invalid compression flag")),
+            }
+        } else {
+            bytes.freeze()
+        };
+
         Self::Item::check_size(&bytes)?;
 
         // Convert the bytes to a message, or fail if it is not valid.
@@ -94,10 +194,15 @@ mod tests {
     use super::*;
 
     use crate::{
+        ping::prop_tests::any_ping,
         unconfirmed_transaction::prop_tests::{any_large_unconfirmed_transaction, any_unconfirmed_transaction},
+        Ping,
+        Pong,
         UnconfirmedTransaction,
     };
 
+    use snarkvm::ledger::narwhal::Data;
+
     use proptest::prelude::ProptestConfig;
     use test_strategy::proptest;
 
@@ -111,6 +216,109 @@ mod tests {
         assert!(codec.decode(&mut bytes).is_ok());
     }
 
+    #[proptest]
+    fn ping(#[strategy(any_ping())] ping: Ping<CurrentNetwork>) {
+        let message = Message::Ping(ping);
+        let mut bytes = BytesMut::new();
+        let mut codec = MessageCodec::<CurrentNetwork>::default();
+        codec.encode(message.clone(), &mut bytes).unwrap();
+
+        // Ensure the frame is laid out as [len][id][payload].
+        let len = u32::from_le_bytes(bytes[..4].try_into().unwrap()) as usize;
+        assert_eq!(len, bytes.len() - 4);
+        assert_eq!(u16::from_le_bytes(bytes[4..6].try_into().unwrap()), message.id());
+
+        assert_eq!(codec.decode(&mut bytes).unwrap(), Some(message));
+        assert!(bytes.is_empty());
+    }
+
+    #[test]
+    fn unknown_message_id() {
+        let mut bytes = BytesMut::new();
+        bytes.put_u32_le(2);
+        bytes.put_u16_le(u16::MAX);
+
+        let mut codec = MessageCodec::<CurrentNetwork>::default();
+        assert!(matches!(codec.decode(&mut bytes), Err(err) if err.kind() == io::ErrorKind::InvalidData));
+
+        // The message deserialization itself reports it as invalid data too.
+        let err = Message::<CurrentNetwork>::read_le(&u16::MAX.to_le_bytes()[..]).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn checksums() {
+        let message = Message::<CurrentNetwork>::Pong(Pong { is_fork: Some(false), nonce: Some(1), timestamps: None });
+
+        // Ensure a message with a checksum can be decoded.
+        let mut bytes = BytesMut::new();
+        let mut codec = MessageCodec::<CurrentNetwork>::default().with_checksums(true);
+        codec.encode(message.clone(), &mut bytes).unwrap();
+        let frame = bytes.clone();
+        assert_eq!(codec.decode(&mut bytes).unwrap(), Some(message.clone()));
+
+        // Ensure a corrupted payload is rejected.
+        let mut corrupted = frame.clone();
+        let last = corrupted.len() - 1;
+        corrupted[last - CHECKSUM_SIZE] ^= 1;
+        assert!(matches!(codec.decode(&mut corrupted), Err(err) if err.kind() == io::ErrorKind::InvalidData));
+
+        // Ensure a corrupted checksum is rejected.
+        let mut corrupted = frame;
+        corrupted[last] ^= 1;
+        assert!(matches!(codec.decode(&mut corrupted), Err(err) if err.kind() == io::ErrorKind::InvalidData));
+
+        // Ensure the frames without a checksum are still supported when it's disabled.
+        let mut bytes = BytesMut::new();
+        let mut codec = MessageCodec::<CurrentNetwork>::default();
+        codec.encode(message.clone(), &mut bytes).unwrap();
+        assert_eq!(codec.decode(&mut bytes).unwrap(), Some(message));
+    }
+
+    #[test]
+    fn compression() {
+        // Prepare a large, compressible message.
+        let tx = UnconfirmedTransaction {
+            transaction_id: Default::default(),
+            transaction: Data::Buffer(Bytes::from(vec![0u8; 64 * 1024])),
+        };
+        let message = Message::<CurrentNetwork>::UnconfirmedTransaction(tx);
+
+        // Encode it without compression, for reference.
+        let mut uncompressed = BytesMut::new();
+        MessageCodec::<CurrentNetwork>::default().encode(message.clone(), &mut uncompressed).unwrap();
+
+        // Ensure the round trip works with compression (and checksums) enabled.
+        let mut codec = MessageCodec::<CurrentNetwork>::default().with_checksums(true).with_compression(Some(3), 1024);
+        let mut bytes = BytesMut::new();
+        codec.encode(message.clone(), &mut bytes).unwrap();
+        let compressed_len = bytes.len();
+        assert_eq!(codec.decode(&mut bytes).unwrap(), Some(message));
+        assert!(compressed_len < uncompressed.len() / 10);
+
+        // Ensure small messages are sent uncompressed; the length prefix is followed by the flag.
+        let ping = Message::<CurrentNetwork>::Pong(Pong { is_fork: None, nonce: Some(0), timestamps: None });
+        let mut bytes = BytesMut::new();
+        codec.encode(ping.clone(), &mut bytes).unwrap();
+        assert_eq!(bytes[4], UNCOMPRESSED_FLAG);
+        assert_eq!(codec.decode(&mut bytes).unwrap(), Some(ping));
+    }
+
+    #[test]
+    fn decompression_bomb() {
+        let mut codec = MessageCodec::<CurrentNetwork>::handshake().with_compression(Some(3), 0);
+
+        // Prepare a compressed payload exceeding the maximum frame length once decompressed.
+        let compressed = zstd::bulk::compress(&vec![0u8; MAXIMUM_HANDSHAKE_MESSAGE_SIZE + 1], 3).unwrap();
+        let mut bytes = BytesMut::new();
+        bytes.put_u32_le(compressed.len() as u32 + 1);
+        bytes.put_u8(COMPRESSED_FLAG);
+        bytes.extend_from_slice(&compressed);
+
+        // Ensure it's rejected.
+        assert!(matches!(codec.decode(&mut bytes), Err(err) if err.kind() == io::ErrorKind::InvalidData));
+    }
+
     #[proptest(ProptestConfig { cases : 10, ..ProptestConfig::default() })]
     fn overly_large_unconfirmed_transaction(
         #[strategy(any_large_unconfirmed_transaction())] tx: UnconfirmedTransaction<CurrentNetwork>,
@@ -49,6 +49,12 @@ pub enum DisconnectReason {
     YouNeedToSyncFirst,
     /// The peer's listening port is closed.
     YourPortIsClosed(u16),
+    /// The peer's protocol version is not supported.
+    VersionMismatch,
+    /// The peer is the node itself.
+    SelfConnect,
+    /// The peer proved a different identity than the one it is expected to have.
+    UnexpectedIdentity,
 }
 
 impl ToBytes for DisconnectReason {
@@ -72,6 +78,9 @@ impl ToBytes for DisconnectReason {
                 14u8.write_le(&mut writer)?;
                 port.write_le(writer)
             }
+            Self::VersionMismatch => 15u8.write_le(writer),
+            Self::SelfConnect => 16u8.write_le(writer),
+            Self::UnexpectedIdentity => 17u8.write_le(writer),
         }
     }
 }
@@ -97,6 +106,9 @@ impl FromBytes for DisconnectReason {
                 let port = u16::read_le(reader)?;
                 Ok(Self::YourPortIsClosed(port))
             }
+            15 => Ok(Self::VersionMismatch),
+            16 => Ok(Self::SelfConnect),
+            17 => Ok(Self::UnexpectedIdentity),
             _ => Err(error("Invalid disconnect reason")),
         }
     }
@@ -0,0 +1,111 @@
+// Copyright (C) 2019-2023 Aleo Systems Inc.
+// This file is part of the snarkOS library.
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at:
+// http://www.apache.org/licenses/LICENSE-2.0
+
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use snarkvm::prelude::{error, FromBytes, ToBytes};
+
+use serde::{Deserialize, Serialize};
+use std::io;
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Serialize, Deserialize, Hash)]
+#[repr(u8)]
+pub enum Status {
+    /// The node is starting up.
+    Initializing = 0,
+    /// The node is catching up with the network.
+    Syncing,
+    /// The node is caught up with the network.
+    Ready,
+    /// The node is shutting down.
+    ShuttingDown,
+}
+
+impl Status {
+    /// Returns `true` if the node is allowed to move from this status to the given one.
+    /// Remaining in the same status is always allowed, except for `ShuttingDown`, which is final.
+    pub const fn can_transition_to(&self, next: Status) -> bool {
+        match (self, next) {
+            (Self::ShuttingDown, _) => false,
+            (_, Self::Initializing) => matches!(self, Self::Initializing),
+            (_, Self::Syncing | Self::Ready | Self::ShuttingDown) => true,
+        }
+    }
+
+    /// Returns `true` if the status is ready.
+    pub const fn is_ready(&self) -> bool {
+        matches!(self, Self::Ready)
+    }
+
+    /// Returns `true` if the status is shutting down.
+    pub const fn is_shutting_down(&self) -> bool {
+        matches!(self, Self::ShuttingDown)
+    }
+}
+
+impl core::fmt::Display for Status {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(f, "{}", match self {
+            Self::Initializing => "Initializing",
+            Self::Syncing => "Syncing",
+            Self::Ready => "Ready",
+            Self::ShuttingDown => "ShuttingDown",
+        })
+    }
+}
+
+impl ToBytes for Status {
+    fn write_le<W: io::Write>(&self, writer: W) -> io::Result<()> {
+        (*self as u8).write_le(writer)
+    }
+}
+
+impl FromBytes for Status {
+    fn read_le<R: io::Read>(reader: R) -> io::Result<Self> {
+        match u8::read_le(reader)? {
+            0 => Ok(Self::Initializing),
+            1 => Ok(Self::Syncing),
+            2 => Ok(Self::Ready),
+            3 => Ok(Self::ShuttingDown),
+            _ => Err(error("Invalid status")),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn status_transitions() {
+        use Status::*;
+
+        // Ensure the status can't return to `Initializing`.
+        assert!(Initializing.can_transition_to(Initializing));
+        assert!(!Syncing.can_transition_to(Initializing));
+        assert!(!Ready.can_transition_to(Initializing));
+
+        // Ensure the node can move between syncing and being ready.
+        assert!(Initializing.can_transition_to(Syncing));
+        assert!(Initializing.can_transition_to(Ready));
+        assert!(Syncing.can_transition_to(Ready));
+        assert!(Ready.can_transition_to(Syncing));
+        assert!(Ready.can_transition_to(Ready));
+
+        // Ensure shutting down is possible from any status, and is final.
+        for status in [Initializing, Syncing, Ready] {
+            assert!(status.can_transition_to(ShuttingDown));
+            assert!(!ShuttingDown.can_transition_to(status));
+        }
+        assert!(!ShuttingDown.can_transition_to(ShuttingDown));
+    }
+}
@@ -45,7 +45,7 @@ mod ping;
 pub use ping::Ping;
 
 mod pong;
-pub use pong::Pong;
+pub use pong::{Pong, PongTimestamps};
 
 mod puzzle_request;
 pub use puzzle_request::PuzzleRequest;
@@ -60,6 +60,7 @@ mod unconfirmed_transaction;
 pub use unconfirmed_transaction::UnconfirmedTransaction;
 
 pub use snarkos_node_bft_events::DataBlocks;
+pub use snarkos_node_tcp::{protocols::Priority, Capabilities};
 
 use snarkos_node_sync_locators::BlockLocators;
 use snarkvm::prelude::{
@@ -84,6 +85,12 @@ use std::{
 pub trait MessageTrait: ToBytes + FromBytes {
     /// Returns the message name.
     fn name(&self) -> Cow<'static, str>;
+
+    /// Returns the message priority; the high-priority messages are sent ahead of the queued low-priority ones.
+    #[inline]
+    fn priority(&self) -> Priority {
+        Priority::Low
+    }
 }
 
 #[derive(Clone, Debug, PartialEq, Eq)]
@@ -110,8 +117,10 @@ impl<N: Network> From<DisconnectReason> for Message<N> {
 }
 
 impl<N: Network> Message<N> {
+    /// The magic bytes exchanged at the start of every connection; they differ between the networks.
+    pub const NETWORK_MAGIC: [u8; 4] = [b'A', b'L', (N::ID >> 8) as u8, N::ID as u8];
     /// The version of the network protocol; it can be incremented in order to force users to update.
-    pub const VERSION: u32 = 15;
+    pub const VERSION: u32 = 20;
 
     /// Returns the message name.
     #[inline]
@@ -133,6 +142,26 @@ impl<N: Network> Message<N> {
         }
     }
 
+    /// Returns the message priority.
+    #[inline]
+    pub fn priority(&self) -> Priority {
+        match self {
+            Self::BlockRequest(message) => message.priority(),
+            Self::BlockResponse(message) => message.priority(),
+            Self::ChallengeRequest(message) => message.priority(),
+            Self::ChallengeResponse(message) => message.priority(),
+            Self::Disconnect(message) => message.priority(),
+            Self::PeerRequest(message) => message.priority(),
+            Self::PeerResponse(message) => message.priority(),
+            Self::Ping(message) => message.priority(),
+            Self::Pong(message) => message.priority(),
+            Self::PuzzleRequest(message) => message.priority(),
+            Self::PuzzleResponse(message) => message.priority(),
+            Self::UnconfirmedSolution(message) => message.priority(),
+            Self::UnconfirmedTransaction(message) => message.priority(),
+        }
+    }
+
     /// Returns the message ID.
     #[inline]
     pub fn id(&self) -> u16 {
@@ -153,6 +182,23 @@ impl<N: Network> Message<N> {
         }
     }
 
+    /// Returns the maximum byte length of the message with the given ID.
+    pub fn max_size(id: u16) -> usize {
+        /// The maximum size of the messages with a small, fixed-size payload.
+        const MAXIMUM_SMALL_MESSAGE_SIZE: usize = 1024; // 1 KiB
+
+        match id {
+            // BlockRequest, Disconnect, PeerRequest, Pong, PuzzleRequest.
+            0 | 4 | 5 | 8 | 9 => MAXIMUM_SMALL_MESSAGE_SIZE,
+            // ChallengeRequest, ChallengeResponse, PeerResponse, Ping, PuzzleResponse, UnconfirmedSolution.
+            2 | 3 | 6 | 7 | 10 | 11 => MAXIMUM_HANDSHAKE_MESSAGE_SIZE,
+            // UnconfirmedTransaction.
+            12 => N::MAX_TRANSACTION_SIZE,
+            // BlockResponse, and any unknown ID (rejected during deserialization).
+            _ => MAXIMUM_MESSAGE_SIZE,
+        }
+    }
+
     /// Checks the message byte length. To be used before deserialization.
     pub fn check_size(bytes: &[u8]) -> io::Result<()> {
         // Store the length to be checked against the max message size for each variant.
@@ -167,9 +213,9 @@ impl<N: Network> Message<N> {
             .map_err(|_| io::Error::new(io::ErrorKind::InvalidData, "id couldn't be deserialized"))?;
         let id = u16::from_le_bytes(id_bytes);
 
-        // SPECIAL CASE: check the transaction message isn't too large.
-        if id == 12 && len > N::MAX_TRANSACTION_SIZE {
-            return Err(io::Error::new(io::ErrorKind::InvalidData, "transaction is too large"))?;
+        // Check the message isn't too large for its type.
+        if len > Self::max_size(id) {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "message is too large"))?;
         }
 
         Ok(())
@@ -220,7 +266,7 @@ impl<N: Network> FromBytes for Message<N> {
             10 => Self::PuzzleResponse(PuzzleResponse::read_le(&mut reader)?),
             11 => Self::UnconfirmedSolution(UnconfirmedSolution::read_le(&mut reader)?),
             12 => Self::UnconfirmedTransaction(UnconfirmedTransaction::read_le(&mut reader)?),
-            13.. => return Err(error("Unknown message ID {id}")),
+            13.. => return Err(io::Error::new(io::ErrorKind::InvalidData, format!("Unknown message ID {id}"))),
         };
 
         // Ensure that there are no "dangling" bytes.
@@ -23,6 +23,10 @@ pub struct Ping<N: Network> {
     pub version: u32,
     pub node_type: NodeType,
     pub block_locators: Option<BlockLocators<N>>,
+    pub nonce: u64,
+    pub status: Status,
+    /// The time the message was sent at, in milliseconds since the Unix epoch.
+    pub timestamp: i64,
 }
 
 impl<N: Network> MessageTrait for Ping<N> {
@@ -31,6 +35,12 @@ impl<N: Network> MessageTrait for Ping<N> {
     fn name(&self) -> Cow<'static, str> {
         "Ping".into()
     }
+
+    /// Returns the message priority.
+    #[inline]
+    fn priority(&self) -> Priority {
+        Priority::High
+    }
 }
 
 impl<N: Network> ToBytes for Ping<N> {
@@ -43,6 +53,15 @@ impl<N: Network> ToBytes for Ping<N> {
         } else {
             0u8.write_le(&mut writer)?;
         }
+        if self.version >= Self::NONCE_VERSION {
+            self.nonce.write_le(&mut writer)?;
+        }
+        if self.version >= Self::STATUS_VERSION {
+            self.status.write_le(&mut writer)?;
+        }
+        if self.version >= Self::TIMESTAMP_VERSION {
+            self.timestamp.write_le(&mut writer)?;
+        }
 
         Ok(())
     }
@@ -59,20 +78,36 @@ impl<N: Network> FromBytes for Ping<N> {
             1 => Some(BlockLocators::read_le(&mut reader)?),
             _ => return Err(error("Invalid block locators marker")),
         };
+        let nonce = if version >= Self::NONCE_VERSION { u64::read_le(&mut reader)? } else { 0 };
+        let status = if version >= Self::STATUS_VERSION { Status::read_le(&mut reader)? } else { Status::Ready };
+        let timestamp = if version >= Self::TIMESTAMP_VERSION { i64::read_le(&mut reader)? } else { 0 };
 
-        Ok(Self { version, node_type, block_locators })
+        Ok(Self { version, node_type, block_locators, nonce, status, timestamp })
     }
 }
 
 impl<N: Network> Ping<N> {
-    pub fn new(node_type: NodeType, block_locators: Option<BlockLocators<N>>) -> Self {
-        Self { version: <Message<N>>::VERSION, node_type, block_locators }
+    /// The first message version in which the `Ping` messages carry a nonce.
+    pub const NONCE_VERSION: u32 = 16;
+    /// The first message version in which the `Ping` messages carry the status of the node.
+    pub const STATUS_VERSION: u32 = 17;
+    /// The first message version in which the `Ping` messages carry a timestamp, echoed in the `Pong`s.
+    pub const TIMESTAMP_VERSION: u32 = 19;
+
+    pub fn new(
+        node_type: NodeType,
+        block_locators: Option<BlockLocators<N>>,
+        nonce: u64,
+        status: Status,
+        timestamp: i64,
+    ) -> Self {
+        Self { version: <Message<N>>::VERSION, node_type, block_locators, nonce, status, timestamp }
     }
 }
 
 #[cfg(test)]
 pub mod prop_tests {
-    use crate::{challenge_request::prop_tests::any_node_type, Ping};
+    use crate::{challenge_request::prop_tests::any_node_type, Ping, Status};
     use snarkos_node_sync_locators::{test_helpers::sample_block_locators, BlockLocators};
     use snarkvm::utilities::{FromBytes, ToBytes};
 
@@ -86,9 +121,27 @@ pub mod prop_tests {
         any::<u32>().prop_map(sample_block_locators).boxed()
     }
 
+    pub fn any_status() -> BoxedStrategy<Status> {
+        (0..=3)
+            .prop_map(|id| match id {
+                0 => Status::Initializing,
+                1 => Status::Syncing,
+                2 => Status::Ready,
+                3 => Status::ShuttingDown,
+                _ => unreachable!(),
+            })
+            .boxed()
+    }
+
     pub fn any_ping() -> BoxedStrategy<Ping<CurrentNetwork>> {
-        (any::<u32>(), any_block_locators(), any_node_type())
-            .prop_map(|(version, bls, node_type)| Ping { version, block_locators: Some(bls), node_type })
+        (any::<u32>(), any_block_locators(), any_node_type(), any::<u64>(), any_status(), any::<i64>())
+            .prop_map(|(version, bls, node_type, nonce, status, timestamp)| {
+                // The nonce, status, and timestamp are only carried by the messages of a sufficiently recent version.
+                let nonce = if version >= Ping::<CurrentNetwork>::NONCE_VERSION { nonce } else { 0 };
+                let status = if version >= Ping::<CurrentNetwork>::STATUS_VERSION { status } else { Status::Ready };
+                let timestamp = if version >= Ping::<CurrentNetwork>::TIMESTAMP_VERSION { timestamp } else { 0 };
+                Ping { version, block_locators: Some(bls), node_type, nonce, status, timestamp }
+            })
             .boxed()
     }
 
@@ -21,6 +21,36 @@ use std::borrow::Cow;
 #[derive(Clone, Debug, PartialEq, Eq)]
 pub struct Pong {
     pub is_fork: Option<bool>,
+    /// The nonce of the answered `Ping`; it's only included in the `Pong`s answering the `Ping`s that carry one.
+    pub nonce: Option<u64>,
+    /// The timestamps used to estimate the clock offset between the peers; they are only included in the `Pong`s
+    /// answering the `Ping`s that carry a timestamp.
+    pub timestamps: Option<PongTimestamps>,
+}
+
+/// The timestamps carried by a `Pong`, in milliseconds since the Unix epoch.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PongTimestamps {
+    /// The timestamp of the answered `Ping`, as set by its sender.
+    pub ping: i64,
+    /// The time the `Pong` was sent at, according to its sender.
+    pub pong: i64,
+}
+
+impl Pong {
+    /// The flag set in the serialized `is_fork` field if the message carries a nonce.
+    const NONCE_FLAG: u8 = 0b0100_0000;
+    /// The flag set in the serialized `is_fork` field if the message carries timestamps.
+    const TIMESTAMPS_FLAG: u8 = 0b1000_0000;
+
+    /// Creates a `Pong` answering the given `Ping`, echoing its nonce and timestamp (if it carries them), along
+    /// with the given current time.
+    pub fn answering<N: Network>(ping: &Ping<N>, is_fork: Option<bool>, now: i64) -> Self {
+        let nonce = (ping.version >= Ping::<N>::NONCE_VERSION).then_some(ping.nonce);
+        let timestamps = (ping.version >= Ping::<N>::TIMESTAMP_VERSION)
+            .then_some(PongTimestamps { ping: ping.timestamp, pong: now });
+        Self { is_fork, nonce, timestamps }
+    }
 }
 
 impl MessageTrait for Pong {
@@ -29,36 +59,69 @@ impl MessageTrait for Pong {
     fn name(&self) -> Cow<'static, str> {
         "Pong".into()
     }
+
+    /// Returns the message priority.
+    #[inline]
+    fn priority(&self) -> Priority {
+        Priority::High
+    }
 }
 
 impl ToBytes for Pong {
-    fn write_le<W: io::Write>(&self, writer: W) -> io::Result<()> {
+    fn write_le<W: io::Write>(&self, mut writer: W) -> io::Result<()> {
         let serialized_is_fork: u8 = match self.is_fork {
             Some(true) => 0,
             Some(false) => 1,
             None => 2,
         };
 
-        serialized_is_fork.write_le(writer)
+        // The peers that don't send the nonce and the timestamps expect neither of the flags to be set.
+        let mut flags = 0;
+        if self.nonce.is_some() {
+            flags |= Self::NONCE_FLAG;
+        }
+        if self.timestamps.is_some() {
+            flags |= Self::TIMESTAMPS_FLAG;
+        }
+        (serialized_is_fork | flags).write_le(&mut writer)?;
+
+        if let Some(nonce) = self.nonce {
+            nonce.write_le(&mut writer)?;
+        }
+        if let Some(timestamps) = self.timestamps {
+            timestamps.ping.write_le(&mut writer)?;
+            timestamps.pong.write_le(&mut writer)?;
+        }
+
+        Ok(())
     }
 }
 
 impl FromBytes for Pong {
     fn read_le<R: io::Read>(mut reader: R) -> io::Result<Self> {
-        let is_fork = match u8::read_le(&mut reader)? {
+        let serialized_is_fork = u8::read_le(&mut reader)?;
+        let is_fork = match serialized_is_fork & !(Self::TIMESTAMPS_FLAG | Self::NONCE_FLAG) {
             0 => Some(true),
             1 => Some(false),
             2 => None,
             _ => return Err(error("Invalid 'Pong' message")),
         };
+        let nonce = match serialized_is_fork & Self::NONCE_FLAG != 0 {
+            true => Some(u64::read_le(&mut reader)?),
+            false => None,
+        };
+        let timestamps = match serialized_is_fork & Self::TIMESTAMPS_FLAG != 0 {
+            true => Some(PongTimestamps { ping: i64::read_le(&mut reader)?, pong: i64::read_le(&mut reader)? }),
+            false => None,
+        };
 
-        Ok(Self { is_fork })
+        Ok(Self { is_fork, nonce, timestamps })
     }
 }
 
 #[cfg(test)]
 pub mod tests {
-    use crate::Pong;
+    use crate::{NodeType, Ping, Pong, PongTimestamps, Status};
     use snarkvm::utilities::{FromBytes, ToBytes};
 
     use bytes::{Buf, BufMut, BytesMut};
@@ -69,7 +132,13 @@ pub mod tests {
     use test_strategy::proptest;
 
     pub fn any_pong() -> BoxedStrategy<Pong> {
-        of(any::<bool>()).prop_map(|is_fork| Pong { is_fork }).boxed()
+        (of(any::<bool>()), of(any::<u64>()), of((any::<i64>(), any::<i64>())))
+            .prop_map(|(is_fork, nonce, timestamps)| Pong {
+                is_fork,
+                nonce,
+                timestamps: timestamps.map(|(ping, pong)| PongTimestamps { ping, pong }),
+            })
+            .boxed()
     }
 
     #[proptest]
@@ -79,4 +148,26 @@ pub mod tests {
         let decoded = Pong::read_le(&mut bytes.into_inner().reader()).unwrap();
         assert_eq!(pong, decoded);
     }
+
+    #[test]
+    fn pong_answering() {
+        type CurrentNetwork = snarkvm::prelude::MainnetV0;
+
+        // A pong answering a timestamped ping echoes its timestamp.
+        let mut ping = Ping::<CurrentNetwork>::new(NodeType::Client, None, 7, Status::Ready, 1_000);
+        let pong = Pong::answering(&ping, Some(false), 1_100);
+        assert_eq!(pong.nonce, Some(7));
+        assert_eq!(pong.timestamps, Some(PongTimestamps { ping: 1_000, pong: 1_100 }));
+
+        // A pong answering an older ping doesn't carry any timestamps.
+        ping.version = Ping::<CurrentNetwork>::TIMESTAMP_VERSION - 1;
+        assert_eq!(Pong::answering(&ping, Some(false), 1_100).timestamps, None);
+
+        // A pong answering a ping without a nonce is serialized just like it was before the nonces were introduced.
+        ping.version = Ping::<CurrentNetwork>::NONCE_VERSION - 1;
+        let pong = Pong::answering(&ping, Some(false), 1_100);
+        assert_eq!(pong.nonce, None);
+        assert_eq!(pong.to_bytes_le().unwrap(), vec![1]);
+        assert_eq!(Pong::read_le(&[1u8][..]).unwrap(), pong);
+    }
 }
@@ -17,7 +17,7 @@ use crate::{
     Peer,
     Router,
 };
-use snarkos_node_tcp::{ConnectionSide, Tcp, P2P};
+use snarkos_node_tcp::{ConnectionSide, Stream, Tcp, P2P};
 use snarkvm::{
     ledger::narwhal::Data,
     prelude::{block::Header, error, Address, Network},
@@ -27,7 +27,6 @@ use anyhow::{bail, Result};
 use futures::SinkExt;
 use rand::{rngs::OsRng, Rng};
 use std::{io, net::SocketAddr};
-use tokio::net::TcpStream;
 use tokio_stream::StreamExt;
 use tokio_util::codec::Framed;
 
@@ -71,7 +70,7 @@ macro_rules! expect_message {
 
 /// Send the given message to the peer.
 async fn send<N: Network>(
-    framed: &mut Framed<&mut TcpStream, MessageCodec<N>>,
+    framed: &mut Framed<&mut Stream, MessageCodec<N>>,
     peer_addr: SocketAddr,
     message: Message<N>,
 ) -> io::Result<()> {
@@ -84,10 +83,10 @@ impl<N: Network> Router<N> {
     pub async fn handshake<'a>(
         &'a self,
         peer_addr: SocketAddr,
-        stream: &'a mut TcpStream,
+        stream: &'a mut Stream,
         peer_side: ConnectionSide,
         genesis_header: Header<N>,
-    ) -> io::Result<(SocketAddr, Framed<&mut TcpStream, MessageCodec<N>>)> {
+    ) -> io::Result<(SocketAddr, Framed<&mut Stream, MessageCodec<N>>)> {
         // If this is an inbound connection, we log it, but don't know the listening address yet.
         // Otherwise, we can immediately register the listening address.
         let mut peer_ip = if peer_side == ConnectionSide::Initiator {
@@ -123,13 +122,13 @@ impl<N: Network> Router<N> {
         &'a self,
         peer_addr: SocketAddr,
         peer_ip: &mut Option<SocketAddr>,
-        stream: &'a mut TcpStream,
+        stream: &'a mut Stream,
         genesis_header: Header<N>,
-    ) -> io::Result<(SocketAddr, Framed<&mut TcpStream, MessageCodec<N>>)> {
+    ) -> io::Result<(SocketAddr, Framed<&mut Stream, MessageCodec<N>>)> {
         // This value is immediately guaranteed to be present, so it can be unwrapped.
         let peer_ip = peer_ip.unwrap();
         // Construct the stream.
-        let mut framed = Framed::new(stream, MessageCodec::<N>::handshake());
+        let mut framed = Framed::new(stream, self.set_up_codec(MessageCodec::<N>::handshake()));
 
         // Initialize an RNG.
         let rng = &mut OsRng;
@@ -139,7 +138,14 @@ impl<N: Network> Router<N> {
         // Sample a random nonce.
         let our_nonce = rng.gen();
         // Send a challenge request to the peer.
-        let our_request = ChallengeRequest::new(self.local_ip().port(), self.node_type, self.address(), our_nonce);
+        let our_request = ChallengeRequest::new(
+            self.local_ip().port(),
+            self.node_type,
+            self.address(),
+            our_nonce,
+            self.tcp.nonce(),
+            self.capabilities(),
+        );
         send(&mut framed, peer_addr, Message::ChallengeRequest(our_request)).await?;
 
         /* Step 2: Receive the peer's challenge response followed by the challenge request. */
@@ -186,11 +192,11 @@ impl<N: Network> Router<N> {
         &'a self,
         peer_addr: SocketAddr,
         peer_ip: &mut Option<SocketAddr>,
-        stream: &'a mut TcpStream,
+        stream: &'a mut Stream,
         genesis_header: Header<N>,
-    ) -> io::Result<(SocketAddr, Framed<&mut TcpStream, MessageCodec<N>>)> {
+    ) -> io::Result<(SocketAddr, Framed<&mut Stream, MessageCodec<N>>)> {
         // Construct the stream.
-        let mut framed = Framed::new(stream, MessageCodec::<N>::handshake());
+        let mut framed = Framed::new(stream, self.set_up_codec(MessageCodec::<N>::handshake()));
 
         /* Step 1: Receive the challenge request. */
 
@@ -229,7 +235,14 @@ impl<N: Network> Router<N> {
         // Sample a random nonce.
         let our_nonce = rng.gen();
         // Send the challenge request.
-        let our_request = ChallengeRequest::new(self.local_ip().port(), self.node_type, self.address(), our_nonce);
+        let our_request = ChallengeRequest::new(
+            self.local_ip().port(),
+            self.node_type,
+            self.address(),
+            our_nonce,
+            self.tcp.nonce(),
+            self.capabilities(),
+        );
         send(&mut framed, peer_addr, Message::ChallengeRequest(our_request)).await?;
 
         /* Step 3: Receive the challenge response. */
@@ -293,12 +306,29 @@ impl<N: Network> Router<N> {
         message: &ChallengeRequest<N>,
     ) -> Option<DisconnectReason> {
         // Retrieve the components of the challenge request.
-        let &ChallengeRequest { version, listener_port: _, node_type: _, address: _, nonce: _ } = message;
+        let &ChallengeRequest { version, listener_port, node_type: _, address, nonce: _, node_nonce } = message;
 
-        // Ensure the message protocol version is not outdated.
-        if version < Message::<N>::VERSION {
-            warn!("Dropping '{peer_addr}' on version {version} (outdated)");
-            return Some(DisconnectReason::OutdatedClientVersion);
+        // Ensure the peer is not the node itself; this catches the self-connects that the address checks miss,
+        // e.g. the ones via a different network interface.
+        if node_nonce == self.tcp.nonce() {
+            warn!("Dropping '{peer_addr}' (attempted to self-connect)");
+            return Some(DisconnectReason::SelfConnect);
+        }
+
+        // Ensure the message protocol version is supported.
+        if !self.tcp.config().is_version_supported(version) {
+            warn!("Dropping '{peer_addr}' on version {version} (unsupported)");
+            // Restrict the peer for a while, so that it isn't reconnected to right away; the failure itself
+            // is registered by the TCP stack once the handshake is aborted.
+            self.insert_restricted_peer(SocketAddr::new(peer_addr.ip(), listener_port));
+            return Some(DisconnectReason::VersionMismatch);
+        }
+
+        // Ensure a trusted peer proves the expected identity; the proof itself is the signed challenge response.
+        let peer_ip = SocketAddr::new(peer_addr.ip(), listener_port);
+        if self.trusted_identity(&peer_ip).is_some_and(|expected| expected != address) {
+            warn!("Dropping '{peer_addr}' (unexpected identity {address})");
+            return Some(DisconnectReason::UnexpectedIdentity);
         }
         None
     }
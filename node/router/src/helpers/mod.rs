@@ -18,5 +18,11 @@ pub use cache::Cache;
 mod peer;
 pub use peer::*;
 
+mod peer_cache;
+pub use peer_cache::*;
+
+mod pings;
+pub use pings::*;
+
 mod resolver;
 pub use resolver::*;
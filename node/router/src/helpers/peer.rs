@@ -12,7 +12,7 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use crate::messages::{ChallengeRequest, NodeType};
+use crate::messages::{Capabilities, ChallengeRequest, NodeType};
 use snarkvm::prelude::{Address, Network};
 
 use std::{net::SocketAddr, time::Instant};
@@ -28,6 +28,8 @@ pub struct Peer<N: Network> {
     node_type: NodeType,
     /// The message version of the peer.
     version: u32,
+    /// The optional protocol features supported by the peer.
+    capabilities: Capabilities,
     /// The timestamp of the first message received from the peer.
     first_seen: Instant,
     /// The timestamp of the last message received from this peer.
@@ -42,6 +44,7 @@ impl<N: Network> Peer<N> {
             address: challenge_request.address,
             node_type: challenge_request.node_type,
             version: challenge_request.version,
+            capabilities: challenge_request.capabilities,
             first_seen: Instant::now(),
             last_seen: Instant::now(),
         }
@@ -82,6 +85,11 @@ impl<N: Network> Peer<N> {
         self.version
     }
 
+    /// Returns the optional protocol features supported by the peer.
+    pub const fn capabilities(&self) -> Capabilities {
+        self.capabilities
+    }
+
     /// Returns the first seen timestamp of the peer.
     pub fn first_seen(&self) -> Instant {
         self.first_seen
@@ -0,0 +1,67 @@
+// Copyright (C) 2019-2023 Aleo Systems Inc.
+// This file is part of the snarkOS library.
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at:
+// http://www.apache.org/licenses/LICENSE-2.0
+
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+use snarkos_node_tcp::PeerRecord;
+
+use anyhow::{Context, Result};
+use std::{fs, io, path::Path};
+
+/// Reads the peer records persisted at the given path; returns an empty list if the file doesn't exist yet.
+pub fn read_peer_records(path: &Path) -> Result<Vec<PeerRecord>> {
+    let contents = match fs::read_to_string(path) {
+        Ok(contents) => contents,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(vec![]),
+        Err(e) => return Err(e).with_context(|| format!("Failed to read the peer cache at '{}'", path.display())),
+    };
+    serde_json::from_str(&contents).with_context(|| format!("The peer cache at '{}' is malformed", path.display()))
+}
+
+/// Persists the given peer records at the given path; the file is replaced atomically, so that an interrupted
+/// write can't corrupt the previous contents.
+pub fn write_peer_records(path: &Path, records: &[PeerRecord]) -> Result<()> {
+    let temp_path = path.with_extension("tmp");
+    fs::write(&temp_path, serde_json::to_vec_pretty(records)?)
+        .with_context(|| format!("Failed to write the peer cache to '{}'", temp_path.display()))?;
+    fs::rename(&temp_path, path).with_context(|| format!("Failed to replace the peer cache at '{}'", path.display()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_peer_records_roundtrip() {
+        let path = std::env::temp_dir().join(format!("snarkos-peer-cache-{}.json", rand::random::<u64>()));
+
+        // A missing file is not an error.
+        assert!(read_peer_records(&path).unwrap().is_empty());
+
+        let records = vec![
+            PeerRecord {
+                addr: "1.2.3.4:4130".parse().unwrap(),
+                failures: 0,
+                score: 10,
+                last_seen: Some(1_700_000_000),
+            },
+            PeerRecord { addr: "[::1]:4130".parse().unwrap(), failures: 3, score: -3, last_seen: None },
+        ];
+        write_peer_records(&path, &records).unwrap();
+        assert_eq!(read_peer_records(&path).unwrap(), records);
+
+        // A malformed file is an error.
+        fs::write(&path, "not a peer cache").unwrap();
+        assert!(read_peer_records(&path).is_err());
+
+        fs::remove_file(&path).unwrap();
+    }
+}
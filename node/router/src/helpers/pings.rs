@@ -0,0 +1,140 @@
+// Copyright (C) 2019-2023 Aleo Systems Inc.
+// This file is part of the snarkOS library.
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at:
+// http://www.apache.org/licenses/LICENSE-2.0
+
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use parking_lot::RwLock;
+use rand::Rng;
+use std::{
+    collections::HashMap,
+    net::SocketAddr,
+    time::{Duration, Instant},
+};
+use time::OffsetDateTime;
+
+/// Keeps track of the `Ping` messages sent to the connected peers that weren't answered with a `Pong` yet.
+#[derive(Debug, Default)]
+pub struct PingTracker {
+    /// The map of peer IPs to the nonces of their consecutive unanswered pings, along with the times they were sent at.
+    unanswered: RwLock<HashMap<SocketAddr, HashMap<u64, Instant>>>,
+}
+
+impl PingTracker {
+    /// Registers a `Ping` with the given nonce sent to the given peer, and returns the updated number of its
+    /// unanswered pings.
+    pub fn insert_ping(&self, peer_ip: SocketAddr, nonce: u64) -> u32 {
+        let mut unanswered = self.unanswered.write();
+        let pings = unanswered.entry(peer_ip).or_default();
+        pings.insert(nonce, Instant::now());
+        pings.len() as u32
+    }
+
+    /// Registers a `Pong` with the given nonce received from the given peer, which answers all of its pings.
+    /// Returns the round-trip time of the matching `Ping`, if there is one.
+    pub fn insert_pong(&self, peer_ip: SocketAddr, nonce: u64) -> Option<Duration> {
+        let pings = self.unanswered.write().remove(&peer_ip)?;
+        pings.get(&nonce).map(|sent_at| sent_at.elapsed())
+    }
+
+    /// Returns the number of consecutive pings the given peer hasn't answered.
+    pub fn num_unanswered(&self, peer_ip: SocketAddr) -> u32 {
+        self.unanswered.read().get(&peer_ip).map(|pings| pings.len() as u32).unwrap_or(0)
+    }
+
+    /// Removes the given peer from the tracker.
+    pub fn remove_peer(&self, peer_ip: SocketAddr) {
+        self.unanswered.write().remove(&peer_ip);
+    }
+}
+
+/// Returns a random delay of up to the given fraction of the interval, used so that the connected peers
+/// aren't all pinged at the same time.
+pub fn ping_jitter<R: Rng>(interval: Duration, jitter: f64, rng: &mut R) -> Duration {
+    interval.mul_f64(rng.gen_range(0.0..=jitter.clamp(0.0, 1.0)))
+}
+
+/// Returns the current time in milliseconds since the Unix epoch, as included in the `Ping` and `Pong` messages.
+pub fn now_unix_ms() -> i64 {
+    (OffsetDateTime::now_utc().unix_timestamp_nanos() / 1_000_000) as i64
+}
+
+/// Returns the estimated offset (in milliseconds) of the peer's clock relative to ours, based on the time a `Ping`
+/// was sent at, the time its `Pong` was sent at by the peer, and the time the `Pong` was received at; the offset
+/// is positive if the peer's clock is ahead of ours.
+///
+/// note: like in NTP, the `Pong` is assumed to have been sent halfway through the round trip.
+pub fn estimate_clock_offset(ping_sent_at: i64, pong_sent_at: i64, pong_received_at: i64) -> i64 {
+    let midpoint = ping_sent_at.saturating_add(pong_received_at.saturating_sub(ping_sent_at) / 2);
+    pong_sent_at.saturating_sub(midpoint)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use rand::thread_rng;
+    use std::net::{IpAddr, Ipv4Addr};
+
+    #[test]
+    fn test_ping_tracker() {
+        let tracker = PingTracker::default();
+        let peer_ip = SocketAddr::new(IpAddr::V4(Ipv4Addr::LOCALHOST), 1234);
+
+        assert_eq!(tracker.num_unanswered(peer_ip), 0);
+        assert_eq!(tracker.insert_ping(peer_ip, 1), 1);
+        assert_eq!(tracker.insert_ping(peer_ip, 2), 2);
+        assert_eq!(tracker.num_unanswered(peer_ip), 2);
+
+        // A single pong answers all the pings, and the round-trip time of the matching one is returned.
+        assert!(tracker.insert_pong(peer_ip, 2).is_some());
+        assert_eq!(tracker.num_unanswered(peer_ip), 0);
+        assert_eq!(tracker.insert_pong(peer_ip, 1), None);
+
+        // A pong with an unknown nonce still answers the pings, but yields no round-trip time.
+        tracker.insert_ping(peer_ip, 3);
+        assert_eq!(tracker.insert_pong(peer_ip, 4), None);
+        assert_eq!(tracker.num_unanswered(peer_ip), 0);
+
+        tracker.insert_ping(peer_ip, 5);
+        tracker.remove_peer(peer_ip);
+        assert_eq!(tracker.num_unanswered(peer_ip), 0);
+    }
+
+    #[test]
+    fn test_ping_jitter() {
+        let rng = &mut thread_rng();
+        let interval = Duration::from_secs(20);
+
+        for _ in 0..100 {
+            assert!(ping_jitter(interval, 0.25, rng) <= Duration::from_secs(5));
+        }
+        assert_eq!(ping_jitter(interval, 0.0, rng), Duration::ZERO);
+        assert!(ping_jitter(interval, 2.0, rng) <= interval);
+    }
+
+    #[test]
+    fn test_clock_offset() {
+        // A mocked clock: the ping is sent at 1000 and its pong is received 200ms later.
+        let (ping_sent_at, pong_received_at) = (1_000, 1_200);
+
+        // The peer's clock is in sync with ours.
+        assert_eq!(estimate_clock_offset(ping_sent_at, 1_100, pong_received_at), 0);
+        // The peer's clock is ahead of ours.
+        assert_eq!(estimate_clock_offset(ping_sent_at, 6_100, pong_received_at), 5_000);
+        // The peer's clock is behind ours.
+        assert_eq!(estimate_clock_offset(ping_sent_at, -1_900, pong_received_at), -3_000);
+        // An asymmetric route only skews the estimate by up to half of the round-trip time.
+        assert_eq!(estimate_clock_offset(ping_sent_at, 1_190, pong_received_at), 90);
+        // Nonsensical timestamps don't cause an overflow.
+        assert_eq!(estimate_clock_offset(i64::MIN, i64::MAX, i64::MAX), i64::MAX);
+    }
+}
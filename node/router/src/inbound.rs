@@ -13,6 +13,7 @@
 // limitations under the License.
 
 use crate::{
+    estimate_clock_offset,
     messages::{
         BlockRequest,
         BlockResponse,
@@ -24,6 +25,7 @@ use crate::{
         UnconfirmedSolution,
         UnconfirmedTransaction,
     },
+    now_unix_ms,
     Outbound,
     Peer,
 };
@@ -54,6 +56,10 @@ pub trait Inbound<N: Network>: Reading + Outbound<N> {
     const MAXIMUM_BLOCK_REQUESTS_PER_INTERVAL: usize = 256;
     /// The duration in seconds to sleep in between ping requests with a connected peer.
     const PING_SLEEP_IN_SECS: u64 = 20; // 20 seconds
+    /// The maximum fraction of `PING_SLEEP_IN_SECS` by which a ping request can be randomly delayed.
+    const PING_JITTER: f64 = 0.25;
+    /// The maximum number of consecutive ping requests a connected peer can leave unanswered.
+    const MAXIMUM_MISSED_PONGS: u32 = 3;
     /// The time frame to enforce the `MESSAGE_LIMIT`.
     const MESSAGE_LIMIT_TIME_FRAME_IN_SECS: i64 = 5;
     /// The maximum number of messages accepted within `MESSAGE_LIMIT_TIME_FRAME_IN_SECS`.
@@ -126,6 +132,8 @@ pub trait Inbound<N: Network>: Reading + Outbound<N> {
                 bail!("Peer '{peer_ip}' is not following the protocol")
             }
             Message::Disconnect(message) => {
+                // Restrict the peer, so that it isn't immediately reconnected to.
+                self.router().insert_restricted_peer(peer_ip);
                 bail!("{:?}", message.reason)
             }
             Message::PeerRequest(..) => match self.peer_request(peer_ip) {
@@ -147,9 +155,13 @@ pub trait Inbound<N: Network>: Reading + Outbound<N> {
                 }
             }
             Message::Ping(message) => {
-                // Ensure the message protocol version is not outdated.
-                if message.version < Message::<N>::VERSION {
-                    bail!("Dropping '{peer_ip}' on message version {} (outdated)", message.version);
+                // Drop the peer, if it is flooding the node with pings.
+                if !self.router().tcp().register_control_message(peer_addr).await {
+                    bail!("Dropping '{peer_ip}' for flooding pings");
+                }
+                // Ensure the message protocol version is supported.
+                if !self.router().tcp().config().is_version_supported(message.version) {
+                    bail!("Dropping '{peer_ip}' on message version {} (unsupported)", message.version);
                 }
 
                 // If the peer is a client or validator, ensure there are block locators.
@@ -176,16 +188,40 @@ pub trait Inbound<N: Network>: Reading + Outbound<N> {
                     bail!("[Ping] {error}");
                 }
 
+                // Enforce the connection limit for the node type of the peer; the TCP stack disconnects from it.
+                if !self.router().register_node_type(peer_addr, message.node_type) {
+                    debug!("Dropping '{peer_ip}' (too many connections with {}s)", message.node_type);
+                    return Ok(());
+                }
+
                 // Process the ping message.
                 match self.ping(peer_ip, message) {
                     true => Ok(()),
                     false => bail!("Peer '{peer_ip}' sent an invalid ping"),
                 }
             }
-            Message::Pong(message) => match self.pong(peer_ip, message) {
-                true => Ok(()),
-                false => bail!("Peer '{peer_ip}' sent an invalid pong"),
-            },
+            Message::Pong(message) => {
+                // Register the pong, which answers the outstanding pings, and record the round-trip time; the pongs
+                // from the peers predating the nonces can't be matched with the pings.
+                if let Some(latency) = message.nonce.and_then(|nonce| self.router().pings.insert_pong(peer_ip, nonce)) {
+                    trace!("Measured a round-trip time of {latency:?} with '{peer_ip}'");
+                    self.router().tcp().known_peers().register_latency(peer_addr, latency);
+
+                    // Estimate the offset of the peer's clock, if the pong carries the timestamps.
+                    if let Some(timestamps) = message.timestamps {
+                        let offset = estimate_clock_offset(timestamps.ping, timestamps.pong, now_unix_ms());
+                        self.router().tcp().known_peers().register_clock_offset(peer_addr, offset);
+                        if self.router().tcp().is_clock_skewed(peer_addr) {
+                            warn!("The clock of '{peer_ip}' appears to be off by {offset}ms");
+                        }
+                    }
+                }
+
+                match self.pong(peer_ip, message) {
+                    true => Ok(()),
+                    false => bail!("Peer '{peer_ip}' sent an invalid pong"),
+                }
+            }
             Message::PuzzleRequest(..) => {
                 // Insert the puzzle request for the peer, and fetch the recent frequency.
                 let frequency = self.router().cache.insert_inbound_puzzle_request(peer_ip);
@@ -38,9 +38,9 @@ pub use outbound::*;
 mod routing;
 pub use routing::*;
 
-use crate::messages::NodeType;
+use crate::messages::{Message, MessageCodec, NodeType, Status};
 use snarkos_account::Account;
-use snarkos_node_tcp::{is_bogon_ip, is_unspecified_or_broadcast_ip, Config, Tcp};
+use snarkos_node_tcp::{is_bogon_ip, is_unspecified_or_broadcast_ip, Capabilities, Config, Tcp};
 use snarkvm::prelude::{Address, Network, PrivateKey, ViewKey};
 
 use anyhow::{bail, Result};
@@ -50,9 +50,10 @@ use std::{
     future::Future,
     net::SocketAddr,
     ops::Deref,
+    path::PathBuf,
     str::FromStr,
     sync::Arc,
-    time::Instant,
+    time::{Duration, Instant},
 };
 use tokio::task::JoinHandle;
 
@@ -78,8 +79,14 @@ pub struct InnerRouter<N: Network> {
     cache: Cache<N>,
     /// The resolver.
     resolver: Resolver,
+    /// The tracker of the unanswered `Ping` messages.
+    pings: PingTracker,
+    /// The current status of the node, advertised in the `Ping` messages.
+    status: RwLock<Status>,
     /// The set of trusted peers.
     trusted_peers: HashSet<SocketAddr>,
+    /// The addresses the trusted peers are expected to prove during the handshake, where known.
+    trusted_identities: RwLock<HashMap<SocketAddr, Address<N>>>,
     /// The map of connected peer IPs to their peer handlers.
     connected_peers: RwLock<HashMap<SocketAddr, Peer<N>>>,
     /// The set of handshaking peers. While `Tcp` already recognizes the connecting IP addresses
@@ -97,6 +104,8 @@ pub struct InnerRouter<N: Network> {
     allow_external_peers: bool,
     /// The boolean flag for the development mode.
     is_dev: bool,
+    /// The path the known peers are persisted at when the router is shut down, if any.
+    peer_cache: RwLock<Option<PathBuf>>,
 }
 
 impl<N: Network> Router<N> {
@@ -104,6 +113,9 @@ impl<N: Network> Router<N> {
     const MAXIMUM_CANDIDATE_PEERS: usize = 10_000;
     /// The maximum number of connection failures permitted by an inbound connecting peer.
     const MAXIMUM_CONNECTION_FAILURES: usize = 5;
+    /// The maximum number of pings per second accepted from a connected peer; it is far above the regular
+    /// cadence of one ping every `PING_SLEEP_IN_SECS`, so only the flooding peers are dropped.
+    const MAXIMUM_PINGS_PER_SEC: u32 = 5;
     /// The duration in seconds after which a connected peer is considered inactive or
     /// disconnected if no message has been received in the meantime.
     const RADIO_SILENCE_IN_SECS: u64 = 150; // 2.5 minutes
@@ -120,8 +132,14 @@ impl<N: Network> Router<N> {
         allow_external_peers: bool,
         is_dev: bool,
     ) -> Result<Self> {
-        // Initialize the TCP stack.
-        let tcp = Tcp::new(Config::new(node_ip, max_peers));
+        // Initialize the TCP stack; peers speaking an older version of the protocol, or from another network,
+        // are not supported.
+        let tcp = Tcp::new(Config {
+            min_supported_version: Some(Message::<N>::VERSION),
+            network_magic: Some(Message::<N>::NETWORK_MAGIC),
+            max_control_messages_per_sec: Some(Self::MAXIMUM_PINGS_PER_SEC),
+            ..Config::new(node_ip, max_peers)
+        });
         // Initialize the router.
         Ok(Self(Arc::new(InnerRouter {
             tcp,
@@ -129,7 +147,10 @@ impl<N: Network> Router<N> {
             account,
             cache: Default::default(),
             resolver: Default::default(),
+            pings: Default::default(),
+            status: RwLock::new(Status::Initializing),
             trusted_peers: trusted_peers.iter().copied().collect(),
+            trusted_identities: Default::default(),
             connected_peers: Default::default(),
             connecting_peers: Default::default(),
             candidate_peers: Default::default(),
@@ -137,6 +158,7 @@ impl<N: Network> Router<N> {
             handles: Default::default(),
             allow_external_peers,
             is_dev,
+            peer_cache: Default::default(),
         })))
     }
 }
@@ -214,6 +236,29 @@ impl<N: Network> Router<N> {
         })
     }
 
+    /// Returns a message codec set up according to the TCP config.
+    pub fn message_codec(&self) -> MessageCodec<N> {
+        self.set_up_codec(MessageCodec::default())
+    }
+
+    /// Sets up the given message codec according to the TCP config.
+    pub(crate) fn set_up_codec(&self, codec: MessageCodec<N>) -> MessageCodec<N> {
+        let config = self.tcp.config();
+        codec
+            .with_checksums(config.verify_checksums)
+            .with_max_message_size(config.max_message_size)
+            .with_compression(config.message_compression.map(|level| level.0), config.compression_threshold)
+    }
+
+    /// Returns the optional protocol features supported by this node, as advertised during the handshake.
+    pub fn capabilities(&self) -> Capabilities {
+        let mut capabilities = Capabilities::PONG_TIMESTAMPS;
+        if self.tcp.config().message_compression.is_some() {
+            capabilities |= Capabilities::COMPRESSION;
+        }
+        capabilities
+    }
+
     /// Returns the IP address of this node.
     pub fn local_ip(&self) -> SocketAddr {
         self.tcp.listening_addr().expect("The TCP listener is not enabled")
@@ -235,6 +280,36 @@ impl<N: Network> Router<N> {
         self.node_type
     }
 
+    /// Returns the current status of the node.
+    pub fn status(&self) -> Status {
+        *self.status.read()
+    }
+
+    /// Updates the status of the node, ensuring the transition is valid.
+    pub fn set_status(&self, new: Status) -> Result<()> {
+        let mut status = self.status.write();
+        if !status.can_transition_to(new) {
+            bail!("Invalid status transition from {} to {new}", *status);
+        }
+        if *status != new {
+            debug!("The node status changed from {} to {new}", *status);
+            *status = new;
+        }
+        Ok(())
+    }
+
+    /// Records the node type of the given peer address in the TCP stack, which enforces the per-type connection
+    /// limits; returns `false` if the limit for the node type was reached, in which case the TCP stack disconnects
+    /// from the peer, so the caller shouldn't.
+    pub fn register_node_type(&self, peer_addr: SocketAddr, node_type: NodeType) -> bool {
+        let node_type = match node_type {
+            NodeType::Client => snarkos_node_tcp::NodeType::Client,
+            NodeType::Prover => snarkos_node_tcp::NodeType::Prover,
+            NodeType::Validator => snarkos_node_tcp::NodeType::Validator,
+        };
+        self.tcp.set_node_type(peer_addr, node_type)
+    }
+
     /// Returns the account private key of the node.
     pub fn private_key(&self) -> &PrivateKey<N> {
         self.account.private_key()
@@ -309,6 +384,21 @@ impl<N: Network> Router<N> {
         self.trusted_peers.contains(ip)
     }
 
+    /// Returns the address the given trusted peer is expected to prove during the handshake, if it is known.
+    pub fn trusted_identity(&self, ip: &SocketAddr) -> Option<Address<N>> {
+        self.trusted_identities.read().get(ip).copied()
+    }
+
+    /// Sets the address the given trusted peer is expected to prove during the handshake; from then on, the handshakes
+    /// in which the peer proves a different one are aborted.
+    pub fn set_trusted_identity(&self, ip: SocketAddr, address: Address<N>) -> Result<()> {
+        if !self.is_trusted(&ip) {
+            bail!("Unable to set the identity of '{ip}' (not a trusted peer)");
+        }
+        self.trusted_identities.write().insert(ip, address);
+        Ok(())
+    }
+
     /// Returns the maximum number of connected peers.
     pub fn max_connected_peers(&self) -> usize {
         self.tcp.config().max_connections as usize
@@ -463,6 +553,25 @@ impl<N: Network> Router<N> {
         self.update_metrics();
     }
 
+    /// Restores the known peers persisted at the given path, skipping the ones last heard from longer than `max_age`
+    /// ago, and inserts the ones in good standing into the candidate peers; the known peers are persisted at the same
+    /// path once the router is shut down. Returns the number of the restored peers.
+    pub fn load_peer_cache(&self, path: PathBuf, max_age: Duration) -> Result<usize> {
+        let records = read_peer_records(&path)?;
+        let num_restored = self.tcp.known_peers().import(records.clone(), Some(max_age));
+
+        // Only the restored peers with a non-negative score are worth connecting to.
+        let candidate_peers = records
+            .iter()
+            .filter(|record| record.score >= 0 && self.tcp.known_peers().get(record.addr).is_some())
+            .map(|record| record.addr)
+            .collect::<Vec<_>>();
+        self.insert_candidate_peers(&candidate_peers);
+
+        *self.peer_cache.write() = Some(path);
+        Ok(num_restored)
+    }
+
     /// Inserts the given peer into the restricted peers.
     pub fn insert_restricted_peer(&self, peer_ip: SocketAddr) {
         // Remove this peer from the candidate peers, if it exists.
@@ -496,6 +605,8 @@ impl<N: Network> Router<N> {
     pub fn remove_connected_peer(&self, peer_ip: SocketAddr) {
         // Removes the bidirectional map between the listener address and (ambiguous) peer address.
         self.resolver.remove_peer(&peer_ip);
+        // Stop tracking the pings sent to this peer.
+        self.pings.remove_peer(peer_ip);
         // Remove this peer from the connected peers, if it exists.
         self.connected_peers.write().remove(&peer_ip);
         // Add the peer to the candidate peers.
@@ -526,9 +637,20 @@ impl<N: Network> Router<N> {
     /// Shuts down the router.
     pub async fn shut_down(&self) {
         info!("Shutting down the router...");
+        // Update the status; it is advertised in the final `Ping`s, if any.
+        if let Err(e) = self.set_status(Status::ShuttingDown) {
+            debug!("{e}");
+        }
         // Abort the tasks.
         self.handles.lock().iter().for_each(|handle| handle.abort());
         // Close the listener.
         self.tcp.shut_down().await;
+        // Persist the known peers, if requested.
+        if let Some(path) = self.peer_cache.read().clone() {
+            match write_peer_records(&path, &self.tcp.known_peers().export()) {
+                Ok(()) => info!("Saved the peer cache to '{}'", path.display()),
+                Err(error) => warn!("{error:?}"),
+            }
+        }
     }
 }
@@ -13,18 +13,24 @@
 // limitations under the License.
 
 use crate::{
-    messages::{Message, Ping},
+    messages::{DisconnectReason, Message, Ping},
+    now_unix_ms,
     Router,
 };
 use snarkos_node_sync_locators::BlockLocators;
 use snarkos_node_tcp::protocols::Writing;
 use snarkvm::prelude::Network;
+
+use anyhow::Result;
 use std::io;
 
-use std::net::SocketAddr;
-use tokio::sync::oneshot;
+use std::{net::SocketAddr, time::Duration};
+use tokio::{sync::oneshot, task::JoinHandle};
 
 pub trait Outbound<N: Network>: Writing<Message = Message<N>> {
+    /// The maximum time (in milliseconds) to wait for a `Disconnect` message to be flushed before tearing down the connection.
+    const DISCONNECT_FLUSH_TIMEOUT_MS: u64 = 500;
+
     /// Returns a reference to the router.
     fn router(&self) -> &Router<N>;
 
@@ -34,9 +40,24 @@ pub trait Outbound<N: Network>: Writing<Message = Message<N>> {
     /// Returns the number of blocks this node is behind the greatest peer height.
     fn num_blocks_behind(&self) -> u32;
 
+    /// Returns the block locators to be included in the "Ping" messages, if the node has any.
+    fn ping_block_locators(&self) -> Result<Option<BlockLocators<N>>>;
+
     /// Sends a "Ping" message to the given peer.
     fn send_ping(&self, peer_ip: SocketAddr, block_locators: Option<BlockLocators<N>>) {
-        self.send(peer_ip, Message::Ping(Ping::new(self.router().node_type(), block_locators)));
+        // Register the ping, so that it can be matched with a pong.
+        let nonce = rand::random();
+        self.router().pings.insert_ping(peer_ip, nonce);
+        self.send(
+            peer_ip,
+            Message::Ping(Ping::new(
+                self.router().node_type(),
+                block_locators,
+                nonce,
+                self.router().status(),
+                now_unix_ms(),
+            )),
+        );
     }
 
     /// Sends the given message to specified peer.
@@ -69,11 +90,12 @@ pub trait Outbound<N: Network>: Writing<Message = Message<N>> {
         if matches!(message, Message::PeerRequest(_)) {
             self.router().cache.increment_outbound_peer_requests(peer_ip);
         }
-        // Retrieve the message name.
+        // Retrieve the message name and priority.
         let name = message.name();
+        let priority = message.priority();
         // Send the message to the peer.
         trace!("Sending '{name}' to '{peer_ip}'");
-        let result = self.unicast(peer_addr, message);
+        let result = self.unicast_with_priority(peer_addr, message, priority);
         // If the message was unable to be sent, disconnect.
         if let Err(e) = &result {
             warn!("Failed to send '{name}' to '{peer_ip}': {e}");
@@ -83,6 +105,23 @@ pub trait Outbound<N: Network>: Writing<Message = Message<N>> {
         result.ok()
     }
 
+    /// Sends a "Disconnect" message with the given reason to the specified peer, and then disconnects from it.
+    ///
+    /// The teardown is delayed by at most `DISCONNECT_FLUSH_TIMEOUT_MS`, even if the peer stops reading.
+    fn disconnect_with_reason(&self, peer_ip: SocketAddr, reason: DisconnectReason) -> JoinHandle<bool> {
+        let node = self.clone();
+        tokio::spawn(async move {
+            // Resolve the listener IP to the (ambiguous) peer address.
+            let Some(peer_addr) = node.router().resolve_to_ambiguous(&peer_ip) else {
+                return false;
+            };
+            debug!("Disconnecting from '{peer_ip}' for the following reason - {reason:?}");
+            let message = Message::Disconnect(reason.into());
+            let timeout = Duration::from_millis(Self::DISCONNECT_FLUSH_TIMEOUT_MS);
+            node.disconnect_with_message(peer_addr, message, timeout).await
+        })
+    }
+
     /// Sends the given message to every connected peer, excluding the sender and any specified peer IPs.
     fn propagate(&self, message: Message<N>, excluded_peers: &[SocketAddr]) {
         // TODO (howardwu): Serialize large messages once only.
@@ -12,7 +12,13 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use crate::{Heartbeat, Inbound, Outbound};
+use crate::{
+    messages::{DisconnectReason, Status},
+    ping_jitter,
+    Heartbeat,
+    Inbound,
+    Outbound,
+};
 use snarkos_node_tcp::{
     protocols::{Disconnect, Handshake, OnConnect},
     P2P,
@@ -20,11 +26,27 @@ use snarkos_node_tcp::{
 use snarkvm::prelude::Network;
 
 use core::time::Duration;
+use rand::rngs::OsRng;
+use std::net::SocketAddr;
+use tokio::time::Instant;
 
 #[async_trait]
 pub trait Routing<N: Network>:
     P2P + Disconnect + OnConnect + Handshake + Inbound<N> + Outbound<N> + Heartbeat<N>
 {
+    /// Sends a "Disconnect" message with the given reason to all connected peers, and disconnects from them.
+    async fn disconnect_all(&self, reason: DisconnectReason) {
+        let handles = self
+            .router()
+            .connected_peers()
+            .into_iter()
+            .map(|peer_ip| self.disconnect_with_reason(peer_ip, reason))
+            .collect::<Vec<_>>();
+        for handle in handles {
+            let _ = handle.await;
+        }
+    }
+
     /// Initialize the routing.
     async fn initialize_routing(&self) {
         // Enable the TCP protocols.
@@ -37,6 +59,8 @@ pub trait Routing<N: Network>:
         self.enable_listener().await;
         // Initialize the heartbeat.
         self.initialize_heartbeat();
+        // Initialize the periodic pings.
+        self.initialize_pings();
     }
 
     // Start listening for inbound connections.
@@ -56,4 +80,59 @@ pub trait Routing<N: Network>:
             }
         });
     }
+
+    /// Initialize the periodic `Ping` messages to the connected peers.
+    fn initialize_pings(&self) {
+        let self_clone = self.clone();
+        self.router().spawn(async move {
+            let interval = Duration::from_secs(Self::PING_SLEEP_IN_SECS);
+            let mut next_tick = Instant::now() + interval;
+            loop {
+                // Sleep for `PING_SLEEP_IN_SECS` seconds; the ticks are spaced evenly, regardless of the jitter.
+                tokio::time::sleep_until(next_tick).await;
+                let tick = next_tick;
+                next_tick += interval;
+                // Update the status of the node, so that it is advertised to the peers.
+                let status = if self_clone.is_block_synced() { Status::Ready } else { Status::Syncing };
+                if let Err(e) = self_clone.router().set_status(status) {
+                    debug!("Unable to update the node status - {e}");
+                    continue;
+                }
+                // Ping every connected peer after a random delay, so that they aren't all pinged at the same time;
+                // the pings are sent from this task, in the order of their delays.
+                let mut schedule = self_clone
+                    .router()
+                    .connected_peers()
+                    .into_iter()
+                    .map(|peer_ip| (ping_jitter(interval, Self::PING_JITTER, &mut OsRng), peer_ip))
+                    .collect::<Vec<_>>();
+                schedule.sort_unstable_by_key(|(delay, _)| *delay);
+                for (delay, peer_ip) in schedule {
+                    tokio::time::sleep_until(tick + delay).await;
+                    self_clone.ping_peer(peer_ip);
+                }
+            }
+        });
+    }
+
+    /// Sends a `Ping` message to the given peer, or disconnects from it if it missed too many `Pong`s in a row.
+    fn ping_peer(&self, peer_ip: SocketAddr) {
+        // Check that the peer is still connected.
+        if !self.router().is_connected(&peer_ip) {
+            return;
+        }
+        // Disconnect from the peer if it stopped responding.
+        let num_missed_pongs = self.router().pings.num_unanswered(peer_ip);
+        if num_missed_pongs >= Self::MAXIMUM_MISSED_PONGS {
+            warn!("Disconnecting from '{peer_ip}' (missed {num_missed_pongs} pongs in a row)");
+            self.router().disconnect(peer_ip);
+            return;
+        }
+        // Retrieve the block locators.
+        match self.ping_block_locators() {
+            // Send a `Ping` message to the peer.
+            Ok(block_locators) => self.send_ping(peer_ip, block_locators),
+            Err(e) => error!("Failed to get block locators - {e}"),
+        }
+    }
 }
@@ -30,6 +30,7 @@ use snarkos_node_router::{
     Router,
     Routing,
 };
+use snarkos_node_sync_locators::BlockLocators;
 use snarkos_node_tcp::{
     protocols::{Disconnect, Handshake, OnConnect, Reading, Writing},
     Connection,
@@ -43,6 +44,7 @@ use snarkvm::prelude::{
     Network,
 };
 
+use anyhow::Result;
 use async_trait::async_trait;
 use std::{io, net::SocketAddr};
 use tracing::*;
@@ -80,7 +82,12 @@ impl<N: Network> Handshake for TestRouter<N> {
         let conn_side = connection.side();
         let stream = self.borrow_stream(&mut connection);
         let genesis_header = *sample_genesis_block().header();
-        self.router().handshake(peer_addr, stream, conn_side, genesis_header).await?;
+        let (peer_ip, _) = self.router().handshake(peer_addr, stream, conn_side, genesis_header).await?;
+        // Register the verified identity of the peer and the optional protocol features it supports.
+        if let Some(peer) = self.router().get_connected_peer(&peer_ip) {
+            connection.set_identity(peer.address());
+            connection.set_capabilities(peer.capabilities());
+        }
 
         Ok(connection)
     }
@@ -159,6 +166,11 @@ impl<N: Network> Outbound<N> for TestRouter<N> {
     fn num_blocks_behind(&self) -> u32 {
         0
     }
+
+    /// Returns the block locators to be included in the `Ping` messages.
+    fn ping_block_locators(&self) -> Result<Option<BlockLocators<N>>> {
+        Ok(None)
+    }
 }
 
 #[async_trait]
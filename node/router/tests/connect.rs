@@ -15,7 +15,9 @@
 mod common;
 use common::*;
 
+use snarkos_account::Account;
 use snarkos_node_tcp::{protocols::Handshake, P2P};
+use snarkvm::prelude::{Address, MainnetV0 as CurrentNetwork};
 
 use core::time::Duration;
 
@@ -109,6 +111,10 @@ async fn test_connect_with_handshake() {
         // Check the router level.
         assert_eq!(node0.number_of_connected_peers(), 1);
         assert_eq!(node1.number_of_connected_peers(), 1);
+
+        // Check the capabilities advertised and the identity proven during the handshake.
+        assert_eq!(node0.tcp().peer_capabilities(node1.local_ip()), Some(node1.capabilities()));
+        assert_eq!(node0.tcp().peer_identity::<Address<CurrentNetwork>>(node1.local_ip()), Some(node1.address()));
     }
     {
         // Connect node0 to node1 again.
@@ -203,6 +209,48 @@ async fn test_validator_connection() {
     }
 }
 
+#[tokio::test]
+async fn test_trusted_identity() {
+    // Create first router and start listening.
+    let node0 = validator(0, 2, &[], true).await;
+    node0.enable_handshake().await;
+    node0.tcp().enable_listener().await.unwrap();
+    let addr0 = node0.local_ip();
+
+    // Create second router, trusting the first router, and start listening.
+    let node1 = validator(0, 2, &[addr0], false).await;
+    node1.enable_handshake().await;
+    node1.tcp().enable_listener().await.unwrap();
+
+    // Ensure the identities can only be expected of the trusted peers.
+    let other_address = Account::<CurrentNetwork>::new(&mut rand::thread_rng()).unwrap().address();
+    assert!(node1.set_trusted_identity(node1.local_ip(), other_address).is_err());
+
+    {
+        // Expect a different identity from node0, and connect to it.
+        node1.set_trusted_identity(addr0, other_address).unwrap();
+        node1.connect(addr0);
+        // Sleep briefly.
+        tokio::time::sleep(Duration::from_millis(200)).await;
+
+        // Check the router level - connection was not accepted.
+        assert_eq!(node0.number_of_connected_peers(), 0);
+        assert_eq!(node1.number_of_connected_peers(), 0);
+    }
+    {
+        // Expect the actual identity of node0, and connect to it again.
+        node1.set_trusted_identity(addr0, node0.address()).unwrap();
+        node1.connect(addr0);
+        // Sleep briefly.
+        tokio::time::sleep(Duration::from_millis(200)).await;
+
+        // Check the router level - connection was accepted.
+        assert_eq!(node0.number_of_connected_peers(), 1);
+        assert_eq!(node1.number_of_connected_peers(), 1);
+        assert_eq!(node1.tcp().peer_identity::<Address<CurrentNetwork>>(addr0), Some(node0.address()));
+    }
+}
+
 #[ignore]
 #[tokio::test]
 async fn test_connect_simultaneously_with_handshake() {
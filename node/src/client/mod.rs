@@ -19,7 +19,7 @@ use snarkos_account::Account;
 use snarkos_node_bft::ledger_service::CoreLedgerService;
 use snarkos_node_rest::Rest;
 use snarkos_node_router::{
-    messages::{Message, NodeType, UnconfirmedSolution},
+    messages::{DisconnectReason, Message, NodeType, UnconfirmedSolution},
     Heartbeat,
     Inbound,
     Outbound,
@@ -200,6 +200,9 @@ impl<N: Network, C: ConsensusStorage<N>> NodeInterface<N> for Client<N, C> {
         trace!("Shutting down the validator...");
         self.handles.lock().iter().for_each(|handle| handle.abort());
 
+        // Let the peers know that the node is shutting down.
+        self.disconnect_all(DisconnectReason::ShuttingDown).await;
+
         // Shut down the router.
         self.router.shut_down().await;
 
@@ -25,6 +25,7 @@ use snarkos_node_router::{
         PuzzleResponse,
         UnconfirmedTransaction,
     },
+    now_unix_ms,
     Routing,
 };
 use snarkos_node_tcp::{Connection, ConnectionSide, Tcp};
@@ -33,8 +34,8 @@ use snarkvm::{
     prelude::{block::Transaction, Network},
 };
 
-use snarkos_node_sync::communication_service::CommunicationService;
-use std::{io, net::SocketAddr, time::Duration};
+use snarkos_node_sync::{communication_service::CommunicationService, locators::BlockLocators};
+use std::{borrow::Cow, io, net::SocketAddr};
 
 impl<N: Network, C: ConsensusStorage<N>> P2P for Client<N, C> {
     /// Returns a reference to the TCP instance.
@@ -52,7 +53,12 @@ impl<N: Network, C: ConsensusStorage<N>> Handshake for Client<N, C> {
         let conn_side = connection.side();
         let stream = self.borrow_stream(&mut connection);
         let genesis_header = *self.genesis.header();
-        self.router.handshake(peer_addr, stream, conn_side, genesis_header).await?;
+        let (peer_ip, _) = self.router.handshake(peer_addr, stream, conn_side, genesis_header).await?;
+        // Register the verified identity of the peer and the optional protocol features it supports.
+        if let Some(peer) = self.router.get_connected_peer(&peer_ip) {
+            connection.set_identity(peer.address());
+            connection.set_capabilities(peer.capabilities());
+        }
 
         Ok(connection)
     }
@@ -98,7 +104,12 @@ impl<N: Network, C: ConsensusStorage<N>> Writing for Client<N, C> {
     /// Creates an [`Encoder`] used to write the outbound messages to the target stream.
     /// The `side` parameter indicates the connection side **from the node's perspective**.
     fn codec(&self, _addr: SocketAddr, _side: ConnectionSide) -> Self::Codec {
-        Default::default()
+        self.router().message_codec()
+    }
+
+    /// Returns the type of the given message, which is used to gather the statistics per message type.
+    fn message_type(message: &Self::Message) -> Option<Cow<'static, str>> {
+        Some(message.name())
     }
 }
 
@@ -110,7 +121,12 @@ impl<N: Network, C: ConsensusStorage<N>> Reading for Client<N, C> {
     /// Creates a [`Decoder`] used to interpret messages from the network.
     /// The `side` param indicates the connection side **from the node's perspective**.
     fn codec(&self, _peer_addr: SocketAddr, _side: ConnectionSide) -> Self::Codec {
-        Default::default()
+        self.router().message_codec()
+    }
+
+    /// Returns the type of the given message, which is used to gather the statistics per message type.
+    fn message_type(message: &Self::Message) -> Option<Cow<'static, str>> {
+        Some(message.name())
     }
 
     /// Processes a message received from the network.
@@ -119,9 +135,8 @@ impl<N: Network, C: ConsensusStorage<N>> Reading for Client<N, C> {
         if let Err(error) = self.inbound(peer_addr, message).await {
             if let Some(peer_ip) = self.router().resolve_to_listener(&peer_addr) {
                 warn!("Disconnecting from '{peer_ip}' - {error}");
-                Outbound::send(self, peer_ip, Message::Disconnect(DisconnectReason::ProtocolViolation.into()));
-                // Disconnect from this peer.
-                self.router().disconnect(peer_ip);
+                // Let the peer know why it's being disconnected, and disconnect from it.
+                self.disconnect_with_reason(peer_ip, DisconnectReason::ProtocolViolation);
             }
         }
         Ok(())
@@ -173,6 +188,11 @@ impl<N: Network, C: ConsensusStorage<N>> Outbound<N> for Client<N, C> {
     fn num_blocks_behind(&self) -> u32 {
         self.sync.num_blocks_behind()
     }
+
+    /// Returns the block locators to be included in the `Ping` messages.
+    fn ping_block_locators(&self) -> Result<Option<BlockLocators<N>>> {
+        self.sync.get_block_locators().map(Some)
+    }
 }
 
 #[async_trait]
@@ -221,27 +241,12 @@ impl<N: Network, C: ConsensusStorage<N>> Inbound<N> for Client<N, C> {
         }
 
         // Send a `Pong` message to the peer.
-        Outbound::send(self, peer_ip, Message::Pong(Pong { is_fork: Some(false) }));
+        Outbound::send(self, peer_ip, Message::Pong(Pong::answering(&message, Some(false), now_unix_ms())));
         true
     }
 
-    /// Sleeps for a period and then sends a `Ping` message to the peer.
-    fn pong(&self, peer_ip: SocketAddr, _message: Pong) -> bool {
-        // Spawn an asynchronous task for the `Ping` request.
-        let self_ = self.clone();
-        tokio::spawn(async move {
-            // Sleep for the preset time before sending a `Ping` request.
-            tokio::time::sleep(Duration::from_secs(Self::PING_SLEEP_IN_SECS)).await;
-            // Check that the peer is still connected.
-            if self_.router().is_connected(&peer_ip) {
-                // Retrieve the block locators.
-                match self_.sync.get_block_locators() {
-                    // Send a `Ping` message to the peer.
-                    Ok(block_locators) => self_.send_ping(peer_ip, Some(block_locators)),
-                    Err(e) => error!("Failed to get block locators - {e}"),
-                }
-            }
-        });
+    /// Handles a `Pong` message; the subsequent `Ping` messages are sent periodically by the routing.
+    fn pong(&self, _peer_ip: SocketAddr, _message: Pong) -> bool {
         true
     }
 
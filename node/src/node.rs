@@ -14,7 +14,8 @@
 
 use crate::{traits::NodeInterface, Client, Prover, Validator};
 use snarkos_account::Account;
-use snarkos_node_router::messages::NodeType;
+use snarkos_node_router::{messages::NodeType, Outbound};
+use snarkos_node_tcp::{Tcp, P2P};
 use snarkvm::prelude::{
     block::Block,
     store::helpers::{memory::ConsensusMemory, rocksdb::ConsensusDB},
@@ -28,7 +29,9 @@ use aleo_std::StorageMode;
 use anyhow::Result;
 use std::{
     net::SocketAddr,
+    path::PathBuf,
     sync::{atomic::AtomicBool, Arc},
+    time::Duration,
 };
 
 pub enum Node<N: Network> {
@@ -151,4 +154,24 @@ impl<N: Network> Node<N> {
             Self::Client(node) => node.is_dev(),
         }
     }
+
+    /// Returns the TCP stack of the node.
+    pub fn tcp(&self) -> &Tcp {
+        match self {
+            Self::Validator(node) => node.tcp(),
+            Self::Prover(node) => node.tcp(),
+            Self::Client(node) => node.tcp(),
+        }
+    }
+
+    /// Restores the known peers persisted at the given path, skipping the ones last heard from longer than
+    /// `max_age` ago; they are persisted there again once the node is shut down. Returns the number of the
+    /// restored peers.
+    pub fn load_peer_cache(&self, path: PathBuf, max_age: Duration) -> Result<usize> {
+        match self {
+            Self::Validator(node) => node.router().load_peer_cache(path, max_age),
+            Self::Prover(node) => node.router().load_peer_cache(path, max_age),
+            Self::Client(node) => node.router().load_peer_cache(path, max_age),
+        }
+    }
 }
@@ -18,7 +18,7 @@ use crate::traits::NodeInterface;
 use snarkos_account::Account;
 use snarkos_node_bft::ledger_service::ProverLedgerService;
 use snarkos_node_router::{
-    messages::{Message, NodeType, UnconfirmedSolution},
+    messages::{DisconnectReason, Message, NodeType, UnconfirmedSolution},
     Heartbeat,
     Inbound,
     Outbound,
@@ -158,6 +158,9 @@ impl<N: Network, C: ConsensusStorage<N>> NodeInterface<N> for Prover<N, C> {
         debug!("Shutting down the prover...");
         self.handles.lock().iter().for_each(|handle| handle.abort());
 
+        // Let the peers know that the node is shutting down.
+        self.disconnect_all(DisconnectReason::ShuttingDown).await;
+
         // Shut down the router.
         self.router.shut_down().await;
 
@@ -14,20 +14,24 @@
 
 use super::*;
 
-use snarkos_node_router::messages::{
-    BlockRequest,
-    DisconnectReason,
-    Message,
-    MessageCodec,
-    Ping,
-    Pong,
-    PuzzleRequest,
-    UnconfirmedTransaction,
+use snarkos_node_router::{
+    messages::{
+        BlockRequest,
+        DisconnectReason,
+        Message,
+        MessageCodec,
+        Ping,
+        Pong,
+        PuzzleRequest,
+        UnconfirmedTransaction,
+    },
+    now_unix_ms,
 };
+use snarkos_node_sync::locators::BlockLocators;
 use snarkos_node_tcp::{Connection, ConnectionSide, Tcp};
 use snarkvm::prelude::{block::Transaction, Network};
 
-use std::{io, net::SocketAddr};
+use std::{borrow::Cow, io, net::SocketAddr};
 
 impl<N: Network, C: ConsensusStorage<N>> P2P for Prover<N, C> {
     /// Returns a reference to the TCP instance.
@@ -45,7 +49,12 @@ impl<N: Network, C: ConsensusStorage<N>> Handshake for Prover<N, C> {
         let conn_side = connection.side();
         let stream = self.borrow_stream(&mut connection);
         let genesis_header = *self.genesis.header();
-        self.router.handshake(peer_addr, stream, conn_side, genesis_header).await?;
+        let (peer_ip, _) = self.router.handshake(peer_addr, stream, conn_side, genesis_header).await?;
+        // Register the verified identity of the peer and the optional protocol features it supports.
+        if let Some(peer) = self.router.get_connected_peer(&peer_ip) {
+            connection.set_identity(peer.address());
+            connection.set_capabilities(peer.capabilities());
+        }
 
         Ok(connection)
     }
@@ -83,7 +92,12 @@ impl<N: Network, C: ConsensusStorage<N>> Writing for Prover<N, C> {
     /// Creates an [`Encoder`] used to write the outbound messages to the target stream.
     /// The `side` parameter indicates the connection side **from the node's perspective**.
     fn codec(&self, _addr: SocketAddr, _side: ConnectionSide) -> Self::Codec {
-        Default::default()
+        self.router().message_codec()
+    }
+
+    /// Returns the type of the given message, which is used to gather the statistics per message type.
+    fn message_type(message: &Self::Message) -> Option<Cow<'static, str>> {
+        Some(message.name())
     }
 }
 
@@ -95,7 +109,12 @@ impl<N: Network, C: ConsensusStorage<N>> Reading for Prover<N, C> {
     /// Creates a [`Decoder`] used to interpret messages from the network.
     /// The `side` param indicates the connection side **from the node's perspective**.
     fn codec(&self, _peer_addr: SocketAddr, _side: ConnectionSide) -> Self::Codec {
-        Default::default()
+        self.router().message_codec()
+    }
+
+    /// Returns the type of the given message, which is used to gather the statistics per message type.
+    fn message_type(message: &Self::Message) -> Option<Cow<'static, str>> {
+        Some(message.name())
     }
 
     /// Processes a message received from the network.
@@ -104,9 +123,8 @@ impl<N: Network, C: ConsensusStorage<N>> Reading for Prover<N, C> {
         if let Err(error) = self.inbound(peer_addr, message).await {
             if let Some(peer_ip) = self.router().resolve_to_listener(&peer_addr) {
                 warn!("Disconnecting from '{peer_addr}' - {error}");
-                Outbound::send(self, peer_ip, Message::Disconnect(DisconnectReason::ProtocolViolation.into()));
-                // Disconnect from this peer.
-                self.router().disconnect(peer_ip);
+                // Let the peer know why it's being disconnected, and disconnect from it.
+                self.disconnect_with_reason(peer_ip, DisconnectReason::ProtocolViolation);
             }
         }
         Ok(())
@@ -145,6 +163,11 @@ impl<N: Network, C: ConsensusStorage<N>> Outbound<N> for Prover<N, C> {
     fn num_blocks_behind(&self) -> u32 {
         0
     }
+
+    /// Returns the block locators to be included in the `Ping` messages.
+    fn ping_block_locators(&self) -> Result<Option<BlockLocators<N>>> {
+        Ok(None)
+    }
 }
 
 #[async_trait]
@@ -176,23 +199,12 @@ impl<N: Network, C: ConsensusStorage<N>> Inbound<N> for Prover<N, C> {
         }
 
         // Send a `Pong` message to the peer.
-        Outbound::send(self, peer_ip, Message::Pong(Pong { is_fork: Some(false) }));
+        Outbound::send(self, peer_ip, Message::Pong(Pong::answering(&message, Some(false), now_unix_ms())));
         true
     }
 
-    /// Sleeps for a period and then sends a `Ping` message to the peer.
-    fn pong(&self, peer_ip: SocketAddr, _message: Pong) -> bool {
-        // Spawn an asynchronous task for the `Ping` request.
-        let self_clone = self.clone();
-        tokio::spawn(async move {
-            // Sleep for the preset time before sending a `Ping` request.
-            tokio::time::sleep(Duration::from_secs(Self::PING_SLEEP_IN_SECS)).await;
-            // Check that the peer is still connected.
-            if self_clone.router().is_connected(&peer_ip) {
-                // Send a `Ping` message to the peer.
-                self_clone.send_ping(peer_ip, None);
-            }
-        });
+    /// Handles a `Pong` message; the subsequent `Ping` messages are sent periodically by the routing.
+    fn pong(&self, _peer_ip: SocketAddr, _message: Pong) -> bool {
         true
     }
 
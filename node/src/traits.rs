@@ -96,6 +96,14 @@ pub trait NodeInterface<N: Network>: Routing<N> {
                     warn!("⚠️  Attention - Please wait until the shutdown gracefully completes (ETA: 30 seconds)");
                     warn!("==========================================================================================");
 
+                    // If another signal is received in the meantime, terminate the process immediately.
+                    tokio::task::spawn(async move {
+                        if signal_listener().await.is_ok() {
+                            error!("⚠️  Received another signal - forcing an immediate shutdown");
+                            std::process::exit(1);
+                        }
+                    });
+
                     match node_clone.get() {
                         // If the node is already initialized, then shut it down.
                         Some(node) => node.shut_down().await,
@@ -20,7 +20,7 @@ use snarkos_node_bft::{helpers::init_primary_channels, ledger_service::CoreLedge
 use snarkos_node_consensus::Consensus;
 use snarkos_node_rest::Rest;
 use snarkos_node_router::{
-    messages::{NodeType, PuzzleResponse, UnconfirmedSolution, UnconfirmedTransaction},
+    messages::{DisconnectReason, NodeType, PuzzleResponse, UnconfirmedSolution, UnconfirmedTransaction},
     Heartbeat,
     Inbound,
     Outbound,
@@ -427,6 +427,9 @@ impl<N: Network, C: ConsensusStorage<N>> NodeInterface<N> for Validator<N, C> {
         trace!("Shutting down the validator...");
         self.handles.lock().iter().for_each(|handle| handle.abort());
 
+        // Let the peers know that the node is shutting down.
+        self.disconnect_all(DisconnectReason::ShuttingDown).await;
+
         // Shut down the router.
         self.router.shut_down().await;
 
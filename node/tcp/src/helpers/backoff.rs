@@ -0,0 +1,75 @@
+// Copyright (C) 2019-2023 Aleo Systems Inc.
+// This file is part of the snarkOS library.
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at:
+// http://www.apache.org/licenses/LICENSE-2.0
+
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::time::Duration;
+
+#[cfg(doc)]
+use crate::{protocols::RetryingHandshake, Tcp};
+
+/// The policy determining the delays between subsequent connection attempts in [`Tcp::connect_with_retry`], or
+/// handshake attempts in a [`RetryingHandshake`].
+#[derive(Clone, Copy, Debug)]
+pub struct BackoffPolicy {
+    /// The delay before the first retry.
+    pub initial_delay: Duration,
+    /// The factor the delay is multiplied by after every retry.
+    pub multiplier: u32,
+    /// The maximum delay between two attempts.
+    pub max_delay: Duration,
+    /// The maximum number of attempts, including the first one.
+    pub max_attempts: u32,
+}
+
+impl BackoffPolicy {
+    /// Returns the delays preceding each of the retries.
+    pub fn delays(&self) -> impl Iterator<Item = Duration> {
+        let policy = *self;
+        let mut delay = policy.initial_delay.min(policy.max_delay);
+
+        (1..policy.max_attempts).map(move |_| {
+            let current = delay;
+            delay = delay.saturating_mul(policy.multiplier).min(policy.max_delay);
+            current
+        })
+    }
+}
+
+impl Default for BackoffPolicy {
+    /// Initializes a new backoff policy with the default values.
+    fn default() -> Self {
+        Self {
+            initial_delay: Duration::from_millis(500),
+            multiplier: 2,
+            max_delay: Duration::from_secs(30),
+            max_attempts: 5,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_backoff_policy_delays() {
+        let policy = BackoffPolicy {
+            initial_delay: Duration::from_millis(100),
+            multiplier: 3,
+            max_delay: Duration::from_millis(1_000),
+            max_attempts: 5,
+        };
+        let delays = policy.delays().map(|delay| delay.as_millis()).collect::<Vec<_>>();
+        assert_eq!(delays, vec![100, 300, 900, 1_000]);
+    }
+}
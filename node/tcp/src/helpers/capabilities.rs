@@ -0,0 +1,43 @@
+// Copyright (C) 2019-2023 Aleo Systems Inc.
+// This file is part of the snarkOS library.
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at:
+// http://www.apache.org/licenses/LICENSE-2.0
+
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+bitflags::bitflags! {
+    /// The optional protocol features a node supports, advertised during the handshake and stored on the
+    /// [`Connection`](crate::Connection) so that the features a peer doesn't support can be avoided.
+    ///
+    /// note: The bits not defined here are preserved by [`Capabilities::from_bits_retain`] (e.g. when advertised
+    /// by a more recent node), but ignored.
+    #[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+    pub struct Capabilities: u64 {
+        /// The node accepts compressed message payloads.
+        const COMPRESSION = 1 << 0;
+        /// The node answers pings with timestamped pongs.
+        const PONG_TIMESTAMPS = 1 << 1;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unknown_bits_are_retained() {
+        let bits = Capabilities::COMPRESSION.bits() | 1 << 63;
+        let capabilities = Capabilities::from_bits_retain(bits);
+
+        assert_eq!(capabilities.bits(), bits);
+        assert!(capabilities.contains(Capabilities::COMPRESSION));
+        assert!(!capabilities.contains(Capabilities::PONG_TIMESTAMPS));
+        assert!(!capabilities.contains(Capabilities::COMPRESSION | Capabilities::PONG_TIMESTAMPS));
+    }
+}
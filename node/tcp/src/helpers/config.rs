@@ -15,10 +15,22 @@
 use std::{
     io::{self, ErrorKind::*},
     net::{IpAddr, Ipv4Addr, SocketAddr},
+    ops::RangeInclusive,
+    time::Duration,
 };
 
+use ipnetwork::IpNetwork;
+
+use crate::{BackoffPolicy, KnownPeers, NodeType, TlsConfig};
+
 #[cfg(doc)]
-use crate::protocols::{self, Handshake, Reading, Writing};
+use crate::{
+    protocols::{self, Handshake, Reading, Writing},
+    Connection,
+    Tcp,
+};
+#[cfg(doc)]
+use tokio::net::TcpListener;
 
 /// The Tcp's configuration. See the source of [`Config::default`] for the defaults.
 #[derive(Debug, Clone)]
@@ -39,21 +51,367 @@ pub struct Config {
     ///
     /// note: [`Config::listener_ip`] must not be `None` in order for it to have any effect.
     pub desired_listening_port: Option<u16>,
+    /// The additional addresses the Tcp should listen on, e.g. in order to accept both IPv4 and IPv6 connections.
+    ///
+    /// note: Unlike [`Config::desired_listening_port`], these are bound exactly as provided, but a port of `0`
+    /// still results in a random one.
+    pub additional_listeners: Vec<SocketAddr>,
     /// Allow listening on a different port if [`Config::desired_listening_port`] is unavailable.
     ///
     /// note: [`Config::listener_ip`] must not be `None` in order for it to have any effect.
     pub allow_random_port: bool,
+    /// The maximum number of inbound connections the OS queues for each TCP listener before they are accepted.
+    ///
+    /// note: If set to `None`, a backlog of 1024 is used.
+    pub listen_backlog: Option<u32>,
+    /// Sets `SO_REUSEPORT` on the TCP listeners on the Unix platforms supporting it, allowing multiple sockets to be
+    /// bound to the same port, with the inbound connections distributed among them.
+    ///
+    /// note: `SO_REUSEADDR` is always set on Unix platforms, like it is by [`TcpListener::bind`]. Elsewhere the flag
+    /// has no effect; in particular, `SO_REUSEADDR` is never set on Windows, where it would allow other processes
+    /// to bind the same port and take over the inbound connections.
+    pub reuse_address: bool,
+    /// Expect every inbound connection to start with a PROXY protocol (version 2) header, as sent by load balancers
+    /// like HAProxy; the original source address it conveys is then used as the peer's address.
+    ///
+    /// note: Connections with a malformed header are rejected.
+    pub expect_proxy_protocol: bool,
     /// The list of IO errors considered fatal and causing the connection to be dropped.
     ///
     /// note: Tcp needs to implement the [`Reading`] and/or [`Writing`] protocol in order for it to have any effect.
     pub fatal_io_errors: Vec<io::ErrorKind>,
+    /// The maximum number of bytes per second that can be sent to a single peer.
+    ///
+    /// note: Tcp needs to implement the [`Writing`] protocol in order for it to have any effect.
+    pub max_send_bytes_per_sec: Option<u64>,
+    /// The maximum number of outbound messages buffered for a single connection that is still being set up (e.g.
+    /// during the [`Handshake`]); they are sent in order once it's ready. If more messages are sent in the meantime,
+    /// the connection is dropped once it's ready.
+    ///
+    /// note: Tcp needs to implement the [`Writing`] protocol in order for it to have any effect. If set to `None`,
+    /// the messages sent to connections that are not yet ready fail with [`io::ErrorKind::NotConnected`].
+    pub warmup_buffer_size: Option<usize>,
+    /// The number of messages a peer can send before it has to wait for the node to process them; the credits are
+    /// granted back to the peer (in batches of half the window) as the messages are processed, so that a slow
+    /// consumer throttles the sender instead of causing unbounded buffering. The node is subject to the window
+    /// granted by the peer in turn: the [`Writing`] protocol waits for its credits once they are exhausted.
+    ///
+    /// note: Tcp needs to implement both the [`Reading`] and [`Writing`] protocols in order for it to have any
+    /// effect. Since the frame format changes, it needs to be set (not necessarily to the same value) consistently
+    /// across peers. If set to `None`, the messages are not subject to flow control.
+    pub flow_control_window: Option<u32>,
+    /// The maximum number of bytes per second that can be received from a single peer; reading from peers exceeding
+    /// it is postponed, applying backpressure to their connections.
+    ///
+    /// note: Tcp needs to implement the [`Reading`] protocol in order for it to have any effect.
+    pub max_recv_bytes_per_sec: Option<u64>,
+    /// The time a peer is allowed to continuously exceed [`Config::max_recv_bytes_per_sec`] for before it is banned
+    /// for [`Config::recv_limit_ban_duration`].
+    pub recv_limit_grace_period: Duration,
+    /// The duration of a ban caused by exceeding [`Config::recv_limit_grace_period`].
+    ///
+    /// note: If set to `None`, peers exceeding the limit are only slowed down, and never banned.
+    pub recv_limit_ban_duration: Option<Duration>,
+    /// The maximum number of control messages (e.g. pings) per second that can be received from a single peer;
+    /// peers exceeding it are registered as failing, and are meant to be disconnected.
+    ///
+    /// note: Tcp doesn't recognize the control messages itself; they need to be registered via
+    /// [`Tcp::register_control_message`]. If set to `None`, they are not rate-limited.
+    pub max_control_messages_per_sec: Option<u32>,
+    /// The duration of a ban caused by exceeding [`Config::max_control_messages_per_sec`].
+    ///
+    /// note: If set to `None`, the flooding peers are not banned; disconnecting them is left to the caller.
+    pub control_flood_ban_duration: Option<Duration>,
+    /// The duration of a quarantine imposed via [`Tcp::quarantine_peer`]; it is lifted automatically afterwards.
+    pub quarantine_duration: Duration,
+    /// The maximum time a connection can remain idle, i.e. not send or receive any data, before it is dropped.
+    ///
+    /// note: If set to `None`, idle connections are retained. Otherwise, Tcp needs to implement the [`Reading`]
+    /// and/or [`Writing`] protocol in order for the activity to be registered.
+    pub idle_timeout: Option<Duration>,
+    /// The maximum time an address can remain registered as a pending connection before it is dropped from the
+    /// pending ones; it is a safeguard against the addresses leaked by the connection attempts that failed to clean
+    /// up after themselves, which would otherwise count against [`Config::max_connections`] indefinitely.
+    ///
+    /// note: If set to `None`, the pending connections are not swept. Otherwise, it should comfortably exceed
+    /// [`Config::connect_timeout`] combined with the duration of the handshake, as the connection attempts that are
+    /// still in progress are no longer accounted for, and fail once they would otherwise be established.
+    pub max_connecting_age: Option<Duration>,
+    /// The maximum time a read or a write can remain blocked without making any progress before the connection is
+    /// considered stuck and dropped; unlike [`Config::idle_timeout`], it applies to the I/O that is in progress, i.e.
+    /// a partially received inbound message, or an outbound message that can't be written (e.g. due to a full send
+    /// window). A large message that keeps being transferred, however slowly, is not affected.
+    ///
+    /// note: Tcp needs to implement the [`Reading`] and/or [`Writing`] protocol in order for it to have any effect.
+    pub io_timeout: Option<Duration>,
+    /// The maximum time a disconnect waits for the messages still queued for the peer to be sent, and for the write
+    /// side of the stream to be closed, so that the peer receives the final bytes (e.g. the reason for the disconnect).
+    ///
+    /// note: If set to `None`, the queued messages are dropped. It doesn't apply to the connections that are already
+    /// broken, e.g. due to an I/O error, and Tcp needs to implement the [`Writing`] protocol for it to have any effect.
+    pub disconnect_flush_timeout: Option<Duration>,
+    /// The maximum time a connection can remain established before it is dropped, forcing a reconnection (and
+    /// a new handshake); the connections with the trusted peers (see [`Tcp::is_trusted`]) are exempt.
+    ///
+    /// note: If set to `None`, the connections are not rotated.
+    pub max_connection_lifetime: Option<Duration>,
+    /// Re-dials the peers whose connections were rotated due to [`Config::max_connection_lifetime`], as long as
+    /// the connections were initiated by Tcp; the new connections retain the labels of the previous ones.
+    pub redial_rotated_connections: bool,
+    /// The IP addresses of the peers that are always admitted, even if [`Config::max_connections`] or
+    /// [`Config::max_connections_per_ip`] is reached, and are never evicted, reaped due to [`Config::idle_timeout`],
+    /// or rotated due to [`Config::max_connection_lifetime`] (see [`Tcp::is_trusted`]).
+    pub trusted_ips: Vec<IpAddr>,
+    /// Allows the lowest-scoring untrusted peer to be disconnected from in order to admit an inbound connection from
+    /// a trusted peer when [`Config::max_connections`] is reached.
+    pub allow_eviction: bool,
+    /// The addresses of the trusted peers that can also be reconnected to (see [`Config::reconnect_trusted_peers`]);
+    /// they are trusted just like the [`Config::trusted_ips`], and the list can be changed at runtime via
+    /// [`Tcp::add_trusted_peer`] and [`Tcp::remove_trusted_peer`].
+    ///
+    /// note: As the ports of the inbound connections are ephemeral, the peers are matched by their IP addresses.
+    pub trusted_peers: Vec<SocketAddr>,
+    /// Re-dials the [`Config::trusted_peers`] whose connections initiated by Tcp were dropped, with the delays
+    /// between the attempts determined by the given policy; the attempts continue beyond
+    /// [`BackoffPolicy::max_attempts`] (at [`BackoffPolicy::max_delay`] intervals) until one of them succeeds, or
+    /// the peer is either no longer trusted or gets banned.
+    ///
+    /// note: If set to `None`, the trusted peers are not reconnected to automatically.
+    pub reconnect_trusted_peers: Option<BackoffPolicy>,
+    /// The range the scores of known peers are clamped to.
+    pub peer_score_range: RangeInclusive<i32>,
+    /// The maximum estimated clock offset of a peer before it's considered skewed (see [`Tcp::is_clock_skewed`]).
+    ///
+    /// note: If set to `None`, no peers are considered skewed.
+    pub max_clock_offset: Option<Duration>,
+    /// The minimum duration of a connection for it to be considered long-lived, and to raise the peer's score.
+    pub long_lived_connection: Duration,
+    /// The maximum number of peers that [`Tcp::disconnect_all`] disconnects from concurrently.
+    ///
+    /// note: A value of `0` is treated as `1`.
+    pub disconnect_concurrency: usize,
+    /// The minimum delay between two subsequent connection attempts to the addresses queued via
+    /// [`Tcp::enqueue_dial`].
+    pub dial_interval: Duration,
     /// The maximum number of active connections Tcp can maintain at any given time.
     ///
     /// note: This number can very briefly be breached by 1 in case of inbound connection attempts. It can never be
     /// breached by outbound connection attempts, though.
     pub max_connections: u16,
-    /// The maximum time (in milliseconds) allowed to establish a raw (before the [`Handshake`] protocol) TCP connection.
-    pub connection_timeout_ms: u16,
+    /// The minimum number of connections initiated by Tcp that the node should maintain, so that it can't be
+    /// eclipsed by the peers connecting to it; Tcp doesn't enforce it on its own, but it reports the shortfall
+    /// via [`Tcp::num_missing_outbound`], which a higher-level supervisor can act on.
+    pub min_outbound_connections: u16,
+    /// The number of connections below which the node should look for more peers; see [`Tcp::peer_demand`].
+    pub target_connections_low: u16,
+    /// The number of connections which, once reached, stops the search for more peers; above it, the node has
+    /// a surplus of peers.
+    ///
+    /// note: It should not be lower than [`Config::target_connections_low`].
+    pub target_connections_high: u16,
+    /// The maximum number of active and pending connections Tcp can maintain with a single IP address.
+    ///
+    /// note: If set to `None`, only [`Config::max_connections`] applies.
+    pub max_connections_per_ip: Option<u16>,
+    /// The maximum number of new inbound connections accepted per second, smoothing out connection storms; the
+    /// excess connections are dropped immediately, before any other checks or the [`Handshake`].
+    ///
+    /// note: If set to `None`, the inbound connections are not rate-limited. Up to a second's worth of them can be
+    /// accepted in a burst.
+    pub max_inbound_connections_per_sec: Option<u32>,
+    /// The maximum number of outbound connections with peers sharing an IP prefix, i.e. a `/16` for IPv4 and
+    /// a `/32` for IPv6; it keeps the connections initiated by Tcp diverse, making it harder for an attacker
+    /// controlling a single network range to eclipse the node. The trusted peers (see [`Tcp::is_trusted`]) are exempt.
+    ///
+    /// note: If set to `None`, the outbound connections are not restricted by prefix. Only the established
+    /// connections are considered, so the limit can briefly be breached by concurrent connection attempts.
+    pub max_outbound_per_prefix: Option<u16>,
+    /// The maximum number of active connections with client nodes.
+    ///
+    /// note: The node type of a connection is only known once it's provided via [`Tcp::set_node_type`];
+    /// until then, only [`Config::max_connections`] applies. If set to `None`, there is no dedicated limit.
+    pub max_client_connections: Option<u16>,
+    /// The maximum number of active connections with prover nodes.
+    ///
+    /// note: The same remarks as for [`Config::max_client_connections`] apply.
+    pub max_prover_connections: Option<u16>,
+    /// The maximum number of active connections with validator nodes.
+    ///
+    /// note: The same remarks as for [`Config::max_client_connections`] apply.
+    pub max_validator_connections: Option<u16>,
+    /// Exempts loopback addresses from [`Config::max_connections_per_ip`]; useful for local testing.
+    pub exempt_loopback_from_ip_limit: bool,
+    /// The IP ranges Tcp is allowed to connect with, both inbound and outbound.
+    ///
+    /// note: If set to `None`, all the IP ranges that are not in [`Config::denied_ips`] are allowed.
+    pub allowed_ips: Option<Vec<IpNetwork>>,
+    /// The IP ranges Tcp refuses to connect with, both inbound and outbound; it takes precedence over
+    /// [`Config::allowed_ips`], and can be modified at runtime via [`Tcp::add_denied_ip`] and [`Tcp::remove_denied_ip`].
+    pub denied_ips: Vec<IpNetwork>,
+    /// Restricts Tcp to loopback addresses: [`Tcp::enable_listener`] refuses to bind any other address (including
+    /// the [`Config::additional_listeners`]), and the outbound connections to other addresses are refused.
+    ///
+    /// note: It is meant as a safety rail for tests and single-host deployments.
+    pub loopback_only: bool,
+    /// The address of a SOCKS5 proxy (e.g. Tor) all the outbound connections should be relayed through.
+    ///
+    /// note: Only proxies that don't require authentication are supported.
+    pub socks5_proxy: Option<SocketAddr>,
+    /// Secures the connections with TLS: the handshake is performed right after a connection is established, with
+    /// Tcp acting as the client on the connections it initiates and as the server on the ones it accepts.
+    ///
+    /// note: Both sides need to have it set; it doesn't apply to the connections over Unix domain sockets.
+    pub tls: Option<TlsConfig>,
+    /// The local port outbound connections should originate from, e.g. so that NAT port-forwarding rules apply to
+    /// them. If the port is unavailable, a random one is used instead.
+    ///
+    /// note: If set to `None`, outbound connections originate from random ports.
+    pub connect_source_port: Option<u16>,
+    /// The name of the network interface outbound connections should egress via (`SO_BINDTODEVICE`), e.g. to
+    /// keep the peering traffic off the management interface on multi-homed hosts.
+    ///
+    /// note: Only supported on Linux, where it requires the `CAP_NET_RAW` capability; on other platforms the
+    /// outbound connections fail if it is set. If a SOCKS5 proxy is used, it applies to the connection to the proxy.
+    pub bind_device: Option<String>,
+    /// The IP family preferred when connecting to a host resolving to multiple addresses.
+    ///
+    /// note: If set to `None`, the addresses are attempted in the order they were resolved in.
+    pub preferred_ip_family: Option<IpFamily>,
+    /// Makes [`Tcp::connect_to_host`] race the connection attempts to all the resolved addresses, as per RFC 8305
+    /// ("Happy Eyeballs"), instead of attempting them one by one.
+    pub happy_eyeballs: bool,
+    /// The delay between the subsequent connection attempts made if [`Config::happy_eyeballs`] is set.
+    ///
+    /// note: A failed attempt is followed by the next one right away, without waiting for the delay to elapse.
+    pub happy_eyeballs_delay: Duration,
+    /// The maximum time allowed to establish a raw (before the [`Handshake`] protocol) TCP connection.
+    ///
+    /// note: If set to `None`, connection attempts are only bounded by the OS-level TCP timeouts.
+    pub connect_timeout: Option<Duration>,
+    /// The idle time after which TCP keepalive probes are sent, so that half-open connections (e.g. with crashed
+    /// peers) are eventually detected.
+    ///
+    /// note: If set to `None`, TCP keepalive is not enabled. It doesn't apply to Unix domain socket connections.
+    pub tcp_keepalive: Option<Duration>,
+    /// Disables Nagle's algorithm (by setting `TCP_NODELAY`), so that small messages are sent without delay.
+    ///
+    /// note: It doesn't apply to Unix domain socket connections.
+    pub tcp_nodelay: bool,
+    /// The requested size (in bytes) of the send buffer of every TCP connection (`SO_SNDBUF`); the kernel may
+    /// grant a different one (e.g. Linux doubles it, and caps it at `net.core.wmem_max`), which is logged.
+    ///
+    /// note: If set to `None`, the OS defaults apply. It doesn't apply to Unix domain socket connections.
+    pub so_sndbuf: Option<usize>,
+    /// The requested size (in bytes) of the receive buffer of every TCP connection (`SO_RCVBUF`); the remarks
+    /// for [`Config::so_sndbuf`] apply, with the cap being `net.core.rmem_max`.
+    ///
+    /// note: As it's applied once the connection is established, it doesn't affect the TCP window scale
+    /// negotiated during the TCP handshake, which can limit the effective window.
+    pub so_rcvbuf: Option<usize>,
+    /// The order in which the [`Reading`] and [`Writing`] protocols are enabled, following the [`Handshake`].
+    ///
+    /// note: The default is handshake → reading → writing; see [`Reading::await_read_readiness`] in order to delay
+    /// the reads until a custom condition is met.
+    pub protocol_order: ProtocolOrder,
+    /// The maximum time allowed for a freshly established connection to conclude the [`Handshake`] protocol,
+    /// including the preceding TLS handshake and network magic exchange, if any; they share a single deadline.
+    ///
+    /// note: This is enforced by the Tcp itself, in addition to [`Handshake::TIMEOUT_MS`]; if set to `None`,
+    /// only the latter applies.
+    pub handshake_timeout: Option<Duration>,
+    /// Rejects the peers that don't prove their identity during the [`Handshake`], i.e. the connections whose
+    /// identity isn't registered via [`Connection::set_identity`] by the time it concludes.
+    ///
+    /// note: Tcp needs to implement the [`Handshake`] protocol in order for it to have any effect.
+    pub require_peer_identity: bool,
+    /// The magic bytes identifying the network, exchanged at the very start of every connection (after the TLS
+    /// handshake, if [`Config::tls`] is set); the connections that start with different bytes, e.g. the ones from
+    /// unrelated services or nodes of another network, are dropped before the [`Handshake`].
+    ///
+    /// note: Both sides need to have it set; the exchange is bounded by [`Config::handshake_timeout`].
+    pub network_magic: Option<[u8; 4]>,
+    /// The lowest protocol version advertised by peers that is supported.
+    ///
+    /// note: Tcp doesn't interpret the version itself; it is meant to be checked by the higher-level protocols
+    /// (e.g. [`Handshake`]) via [`Config::is_version_supported`]. If set to `None`, there is no lower bound.
+    pub min_supported_version: Option<u32>,
+    /// The highest protocol version advertised by peers that is supported.
+    ///
+    /// note: If set to `None`, there is no upper bound.
+    pub max_supported_version: Option<u32>,
+    /// Appends a CRC32 checksum of the payload to every message frame, and verifies it for the inbound ones.
+    ///
+    /// note: Tcp doesn't interpret it itself; it is meant to be applied by the framing codecs of the [`Reading`]
+    /// and [`Writing`] protocols. Since the frame format changes, it needs to be set consistently across peers.
+    pub verify_checksums: bool,
+    /// The zstd compression level applied to the message payloads larger than [`Config::compression_threshold`].
+    ///
+    /// note: Tcp doesn't interpret it itself; it is meant to be applied by the framing codecs of the [`Reading`]
+    /// and [`Writing`] protocols. If set to `None`, the payloads are not compressed. Since the frame format changes,
+    /// it needs to be set consistently across peers.
+    pub message_compression: Option<CompressionLevel>,
+    /// The size (in bytes) above which the message payloads are compressed.
+    ///
+    /// note: It only applies if [`Config::message_compression`] is set.
+    pub compression_threshold: usize,
+    /// The maximum size (in bytes) of a single inbound message.
+    ///
+    /// note: Tcp doesn't interpret it itself; it is meant to be enforced by the framing codec of the [`Reading`]
+    /// protocol before the read buffer is allocated. An oversized message results in an
+    /// [`io::ErrorKind::InvalidData`] error, which is registered as a failure and causes a disconnect.
+    pub max_message_size: usize,
+    /// The initial capacity (in bytes) of the per-connection buffer used by the [`Reading`] protocol. Can be set to
+    /// the maximum expected size of an inbound message in order to only allocate it once.
+    ///
+    /// note: The buffer is allocated for every connection, so the memory it takes up is roughly this value times the
+    /// number of connected peers; a value of `0` defers the allocation to the first read. If set to `None`, the
+    /// deprecated [`Reading::INITIAL_BUFFER_SIZE`] is used instead.
+    pub read_buffer_capacity: Option<usize>,
+    /// Includes the per-peer gauges (labeled by address) in the Prometheus metrics rendered by the Tcp.
+    ///
+    /// note: The `prometheus` feature needs to be enabled in order for it to have any effect.
+    pub per_peer_metrics: bool,
+}
+
+/// A zstd compression level; higher levels trade speed for a better compression ratio.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CompressionLevel(pub i32);
+
+impl Default for CompressionLevel {
+    fn default() -> Self {
+        // The default level of zstd.
+        Self(3)
+    }
+}
+
+/// The order in which the [`Reading`] and [`Writing`] protocols are enabled for a new connection, following the
+/// [`Handshake`]; the reads only commence once the connection is fully ready, regardless of the order.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ProtocolOrder {
+    /// [`Reading`] is enabled before [`Writing`].
+    #[default]
+    ReadingFirst,
+    /// [`Writing`] is enabled before [`Reading`], e.g. for protocols in which the node speaks first.
+    WritingFirst,
+}
+
+/// A family of IP addresses.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IpFamily {
+    /// IPv4 addresses.
+    V4,
+    /// IPv6 addresses.
+    V6,
+}
+
+impl IpFamily {
+    /// Returns the family the given IP address belongs to.
+    pub fn of(ip: IpAddr) -> Self {
+        match ip {
+            IpAddr::V4(_) => Self::V4,
+            IpAddr::V6(_) => Self::V6,
+        }
+    }
 }
 
 impl Config {
@@ -67,6 +425,22 @@ impl Config {
             ..Default::default()
         }
     }
+
+    /// Returns the maximum number of active connections with nodes of the given type, if limited.
+    pub fn max_connections_of_type(&self, node_type: NodeType) -> Option<u16> {
+        match node_type {
+            NodeType::Client => self.max_client_connections,
+            NodeType::Prover => self.max_prover_connections,
+            NodeType::Validator => self.max_validator_connections,
+        }
+    }
+
+    /// Checks whether the given protocol version is within the range delimited by [`Config::min_supported_version`]
+    /// and [`Config::max_supported_version`].
+    pub fn is_version_supported(&self, version: u32) -> bool {
+        self.min_supported_version.map_or(true, |min| version >= min)
+            && self.max_supported_version.map_or(true, |max| version <= max)
+    }
 }
 
 impl Default for Config {
@@ -86,10 +460,94 @@ impl Default for Config {
             name: None,
             listener_ip: default_ip(),
             desired_listening_port: None,
+            additional_listeners: Vec::new(),
             allow_random_port: true,
+            listen_backlog: None,
+            reuse_address: false,
+            expect_proxy_protocol: false,
             fatal_io_errors: vec![ConnectionReset, ConnectionAborted, BrokenPipe, InvalidData, UnexpectedEof],
+            max_send_bytes_per_sec: None,
+            warmup_buffer_size: None,
+            flow_control_window: None,
+            max_recv_bytes_per_sec: None,
+            recv_limit_grace_period: Duration::from_secs(10),
+            recv_limit_ban_duration: None,
+            max_control_messages_per_sec: None,
+            control_flood_ban_duration: None,
+            quarantine_duration: Duration::from_secs(10 * 60),
+            idle_timeout: None,
+            max_connecting_age: None,
+            io_timeout: None,
+            disconnect_flush_timeout: Some(Duration::from_secs(1)),
+            max_connection_lifetime: None,
+            redial_rotated_connections: false,
+            trusted_ips: Vec::new(),
+            allow_eviction: false,
+            trusted_peers: Vec::new(),
+            reconnect_trusted_peers: None,
+            peer_score_range: KnownPeers::DEFAULT_SCORE_RANGE,
+            max_clock_offset: Some(Duration::from_secs(10)),
+            long_lived_connection: Duration::from_secs(10 * 60),
+            disconnect_concurrency: 64,
+            dial_interval: Duration::from_millis(100),
             max_connections: 100,
-            connection_timeout_ms: 1_000,
+            min_outbound_connections: 0,
+            target_connections_low: 0,
+            target_connections_high: 100,
+            max_connections_per_ip: None,
+            max_inbound_connections_per_sec: None,
+            max_outbound_per_prefix: None,
+            max_client_connections: None,
+            max_prover_connections: None,
+            max_validator_connections: None,
+            exempt_loopback_from_ip_limit: false,
+            allowed_ips: None,
+            denied_ips: Vec::new(),
+            loopback_only: false,
+            socks5_proxy: None,
+            tls: None,
+            connect_source_port: None,
+            bind_device: None,
+            preferred_ip_family: None,
+            happy_eyeballs: false,
+            happy_eyeballs_delay: Duration::from_millis(250),
+            connect_timeout: Some(Duration::from_millis(1_000)),
+            tcp_keepalive: None,
+            tcp_nodelay: false,
+            so_sndbuf: None,
+            so_rcvbuf: None,
+            protocol_order: ProtocolOrder::ReadingFirst,
+            handshake_timeout: None,
+            require_peer_identity: false,
+            network_magic: None,
+            min_supported_version: None,
+            max_supported_version: None,
+            verify_checksums: false,
+            message_compression: None,
+            compression_threshold: 16 * 1024,
+            max_message_size: 128 * 1024 * 1024,
+            read_buffer_capacity: None,
+            per_peer_metrics: false,
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_version_supported() {
+        // Ensure all versions are supported by default.
+        let config = Config::default();
+        assert!(config.is_version_supported(0));
+        assert!(config.is_version_supported(u32::MAX));
+
+        // Ensure the bounds are inclusive.
+        let config = Config { min_supported_version: Some(15), max_supported_version: Some(17), ..Default::default() };
+        assert!(!config.is_version_supported(14));
+        assert!(config.is_version_supported(15));
+        assert!(config.is_version_supported(17));
+        assert!(!config.is_version_supported(18));
+    }
+}
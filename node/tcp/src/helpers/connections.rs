@@ -14,18 +14,42 @@
 
 //! Objects associated with connection handling.
 
-use std::{collections::HashMap, net::SocketAddr, ops::Not};
+use std::{
+    any::Any,
+    collections::HashMap,
+    io,
+    net::{IpAddr, SocketAddr},
+    ops::Not,
+    path::PathBuf,
+    pin::Pin,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    task::{Context, Poll},
+    time::{Duration, Instant},
+};
 
 use parking_lot::RwLock;
+use serde::{Deserialize, Serialize};
+#[cfg(unix)]
+use tokio::net::UnixStream;
 use tokio::{
-    io::{AsyncRead, AsyncWrite},
+    io::{AsyncRead, AsyncWrite, ReadBuf},
     net::TcpStream,
-    sync::oneshot,
-    task::JoinHandle,
+    sync::{oneshot, watch},
+    time::timeout,
 };
+use tokio_rustls::TlsStream;
+use tracing::Span;
 
 #[cfg(doc)]
-use crate::protocols::{Handshake, Reading, Writing};
+use crate::{
+    protocols::{Handshake, Reading, Writing},
+    Config,
+    Tcp,
+};
+use crate::{Capabilities, ConnectionStats, FlowControl, NamedTask, PeerInfo, RateCounter, RateLimiter};
 
 /// A map of all currently connected addresses to their associated connection.
 #[derive(Default)]
@@ -52,6 +76,158 @@ impl Connections {
         self.0.read().len()
     }
 
+    /// Returns the number of connections where the peer is on the given side.
+    pub(crate) fn num_connected_by_side(&self, side: ConnectionSide) -> usize {
+        self.0.read().values().filter(|conn| conn.side == side).count()
+    }
+
+    /// Returns the number of connected addresses with the given IP.
+    pub(crate) fn num_connected_ip(&self, ip: IpAddr) -> usize {
+        self.0.read().keys().filter(|addr| addr.ip() == ip).count()
+    }
+
+    /// Returns the stats of the connection associated with the given address.
+    pub(crate) fn stats(&self, addr: SocketAddr) -> Option<Arc<ConnectionStats>> {
+        self.0.read().get(&addr).map(|conn| Arc::clone(&conn.stats))
+    }
+
+    /// Returns the information on the connection associated with the given address; the history of the peer
+    /// (e.g. its score) is left to be filled in by the caller, so that the lock isn't held for longer than necessary.
+    pub(crate) fn peer_info(&self, addr: SocketAddr) -> Option<PeerInfo> {
+        self.0.read().get(&addr).map(PeerInfo::from)
+    }
+
+    /// Returns the information on all the connections, like [`Connections::peer_info`].
+    pub(crate) fn all_peer_info(&self) -> Vec<PeerInfo> {
+        self.0.read().values().map(PeerInfo::from).collect()
+    }
+
+    /// Returns the stats of all the connections.
+    pub(crate) fn all_stats(&self) -> Vec<(SocketAddr, Arc<ConnectionStats>)> {
+        self.0.read().iter().map(|(addr, conn)| (*addr, Arc::clone(&conn.stats))).collect()
+    }
+
+    /// Returns the list of addresses whose connections have been idle for longer than the given timeout.
+    pub(crate) fn idle_addrs(&self, idle_timeout: Duration) -> Vec<SocketAddr> {
+        self.0
+            .read()
+            .iter()
+            .filter(|(_, conn)| conn.stats.last_activity().elapsed() > idle_timeout)
+            .map(|(addr, _)| *addr)
+            .collect()
+    }
+
+    /// Returns the addresses, sides, and labels of the connections that have been established for longer than the
+    /// given lifetime.
+    pub(crate) fn expired(&self, max_lifetime: Duration) -> Vec<(SocketAddr, ConnectionSide, String)> {
+        self.0
+            .read()
+            .values()
+            .filter(|conn| conn.stats.connected_since().elapsed() > max_lifetime)
+            .map(|conn| (conn.addr, conn.side, conn.label.clone()))
+            .collect()
+    }
+
+    /// Returns `true` if the connection associated with the given address is fully ready.
+    pub(crate) fn is_ready(&self, addr: SocketAddr) -> bool {
+        self.0.read().get(&addr).map(|conn| conn.is_ready()).unwrap_or(false)
+    }
+
+    /// Returns the addresses of the fully ready connections that are not quarantined, along with the moments they
+    /// were established in.
+    pub(crate) fn ready_addrs(&self) -> Vec<(SocketAddr, Instant)> {
+        self.0
+            .read()
+            .values()
+            .filter(|conn| conn.is_ready() && !conn.is_quarantined())
+            .map(|conn| (conn.addr, conn.stats.connected_since()))
+            .collect()
+    }
+
+    /// Sets the moment the quarantine of the connection associated with the given address expires (or lifts it);
+    /// returns whether it was quarantined before, or `None` if the address is not connected.
+    pub(crate) fn set_quarantine(&self, addr: SocketAddr, until: Option<Instant>) -> Option<bool> {
+        self.0.write().get_mut(&addr).map(|conn| {
+            let was_quarantined = conn.is_quarantined();
+            conn.quarantined_until = until;
+            was_quarantined
+        })
+    }
+
+    /// Returns `true` if the connection associated with the given address is quarantined.
+    pub(crate) fn is_quarantined(&self, addr: SocketAddr) -> bool {
+        self.0.read().get(&addr).map(|conn| conn.is_quarantined()).unwrap_or(false)
+    }
+
+    /// Returns the addresses of the quarantined connections.
+    pub(crate) fn quarantined_addrs(&self) -> Vec<SocketAddr> {
+        self.0.read().values().filter(|conn| conn.is_quarantined()).map(|conn| conn.addr).collect()
+    }
+
+    /// Returns the label of the connection associated with the given address.
+    pub(crate) fn label(&self, addr: SocketAddr) -> Option<String> {
+        self.0.read().get(&addr).map(|conn| conn.label.clone())
+    }
+
+    /// Returns the list of addresses whose connections have the given label.
+    pub(crate) fn addrs_with_label(&self, label: &str) -> Vec<SocketAddr> {
+        self.0.read().values().filter(|conn| conn.label == label).map(|conn| conn.addr).collect()
+    }
+
+    /// Returns the list of addresses whose connections have the peer on the given side.
+    pub(crate) fn addrs_by_side(&self, side: ConnectionSide) -> Vec<SocketAddr> {
+        self.0.read().values().filter(|conn| conn.side == side).map(|conn| conn.addr).collect()
+    }
+
+    /// Returns the list of addresses whose connections are known to be with nodes of the given type.
+    pub(crate) fn addrs_of_type(&self, node_type: NodeType) -> Vec<SocketAddr> {
+        self.0.read().values().filter(|conn| conn.node_type == Some(node_type)).map(|conn| conn.addr).collect()
+    }
+
+    /// Sets the node type of the connection associated with the given address, unless the number of connections
+    /// with nodes of that type would exceed the given limit; returns `false` in such a case.
+    pub(crate) fn set_node_type(&self, addr: SocketAddr, node_type: NodeType, limit: Option<u16>) -> bool {
+        let mut conns = self.0.write();
+        let num_of_type = conns.values().filter(|conn| conn.addr != addr && conn.node_type == Some(node_type)).count();
+
+        match conns.get_mut(&addr) {
+            Some(conn) => {
+                if limit.is_some_and(|limit| num_of_type >= limit as usize) {
+                    return false;
+                }
+                conn.node_type = Some(node_type);
+                true
+            }
+            None => true,
+        }
+    }
+
+    /// Returns the capabilities of the connection associated with the given address, if they are known.
+    pub(crate) fn capabilities(&self, addr: SocketAddr) -> Option<Capabilities> {
+        self.0.read().get(&addr).and_then(|conn| conn.capabilities)
+    }
+
+    /// Returns the identity proven by the connection associated with the given address, if it is of the given type.
+    pub(crate) fn identity<T: Any + Clone>(&self, addr: SocketAddr) -> Option<T> {
+        self.0.read().get(&addr).and_then(|conn| conn.identity())
+    }
+
+    /// Registers a control message received via the connection associated with the given address, and returns the
+    /// number of such messages received within the last second.
+    pub(crate) fn register_control_message(&self, addr: SocketAddr) -> Option<usize> {
+        self.0.read().get(&addr).map(|conn| conn.control_messages.register())
+    }
+
+    /// Returns a receiver whose sender is dropped along with the connection associated with the given address.
+    pub(crate) fn closed_receiver(&self, addr: SocketAddr) -> Option<watch::Receiver<()>> {
+        self.0.read().get(&addr).map(|conn| conn.closed_notifier.subscribe())
+    }
+
+    /// Returns the names of the tasks spawned for all the connections.
+    pub(crate) fn task_names(&self) -> Vec<String> {
+        self.0.read().values().flat_map(|conn| conn.tasks.iter().map(|task| task.name().to_owned())).collect()
+    }
+
     /// Returns the list of connected addresses.
     pub(crate) fn addrs(&self) -> Vec<SocketAddr> {
         self.0.read().keys().copied().collect()
@@ -73,29 +249,72 @@ pub struct Connection {
     addr: SocketAddr,
     /// The connection's side in relation to Tcp.
     side: ConnectionSide,
+    /// The connection's statistics.
+    stats: Arc<ConnectionStats>,
+    /// The connection's user-supplied label.
+    pub(crate) label: String,
+    /// The type of the connected node, once known.
+    pub(crate) node_type: Option<NodeType>,
+    /// The capabilities advertised by the connected node, once known.
+    pub(crate) capabilities: Option<Capabilities>,
+    /// The identity proven by the connected node, once verified.
+    pub(crate) identity: Option<Arc<dyn Any + Send + Sync>>,
+    /// The connection's tracing span.
+    pub(crate) span: Span,
     /// Available and used only in the [`Handshake`] protocol.
-    pub(crate) stream: Option<TcpStream>,
+    pub(crate) stream: Option<Stream>,
     /// Available and used only in the [`Reading`] protocol.
     pub(crate) reader: Option<Box<dyn AR>>,
     /// Available and used only in the [`Writing`] protocol.
     pub(crate) writer: Option<Box<dyn AW>>,
+    /// Used to pace the [`Writing`] protocol, if the sending rate is limited.
+    pub(crate) send_limiter: Option<RateLimiter>,
+    /// Used to pace the [`Reading`] protocol, if the receiving rate is limited.
+    pub(crate) recv_limiter: Option<RateLimiter>,
+    /// The flow control state shared by the [`Reading`] and [`Writing`] protocols, if it's enabled.
+    pub(crate) flow_control: Option<Arc<FlowControl>>,
+    /// The moment the quarantine of the connection expires, if it was imposed (see [`Tcp::quarantine_peer`]).
+    pub(crate) quarantined_until: Option<Instant>,
+    /// Counts the control messages received within the last second (see [`Tcp::register_control_message`]).
+    pub(crate) control_messages: RateCounter,
+    /// Used to ask the [`Writing`] task to flush the queued messages and close the write side of the stream; it
+    /// acknowledges via the provided sender once it's done.
+    pub(crate) writer_closer: Option<oneshot::Sender<oneshot::Sender<()>>>,
     /// Used to notify the [`Reading`] protocol that the connection is fully ready.
     pub(crate) readiness_notifier: Option<oneshot::Sender<()>>,
+    /// Set once all the enabled protocols are operational.
+    pub(crate) ready: Arc<AtomicBool>,
     /// Handles to tasks spawned for the connection.
-    pub(crate) tasks: Vec<JoinHandle<()>>,
+    pub(crate) tasks: Vec<NamedTask>,
+    /// Dropped along with the connection, which resolves the futures returned by [`Tcp::closed`].
+    closed_notifier: watch::Sender<()>,
 }
 
 impl Connection {
     /// Creates a [`Connection`] with placeholders for protocol-related objects.
-    pub(crate) fn new(addr: SocketAddr, stream: TcpStream, side: ConnectionSide) -> Self {
+    pub(crate) fn new(addr: SocketAddr, stream: Stream, side: ConnectionSide) -> Self {
         Self {
             addr,
             stream: Some(stream),
             reader: None,
             writer: None,
+            send_limiter: None,
+            recv_limiter: None,
+            flow_control: None,
+            quarantined_until: None,
+            control_messages: RateCounter::new(Duration::from_secs(1)),
+            writer_closer: None,
             readiness_notifier: None,
+            ready: Default::default(),
             side,
+            stats: Default::default(),
+            label: Default::default(),
+            node_type: None,
+            capabilities: None,
+            identity: None,
+            span: Span::none(),
             tasks: Default::default(),
+            closed_notifier: watch::channel(()).0,
         }
     }
 
@@ -109,10 +328,186 @@ impl Connection {
     pub fn side(&self) -> ConnectionSide {
         self.side
     }
+
+    /// Returns the label of the connection; it is empty if none was provided.
+    pub fn label(&self) -> &str {
+        &self.label
+    }
+
+    /// Returns the type of the connected node, if it is already known.
+    pub fn node_type(&self) -> Option<NodeType> {
+        self.node_type
+    }
+
+    /// Returns the capabilities advertised by the connected node, if they are already known.
+    pub fn capabilities(&self) -> Option<Capabilities> {
+        self.capabilities
+    }
+
+    /// Registers the capabilities advertised by the connected node; meant to be used during the [`Handshake`].
+    pub fn set_capabilities(&mut self, capabilities: Capabilities) {
+        self.capabilities = Some(capabilities);
+    }
+
+    /// Returns the identity proven by the connected node, if it is already verified and of the given type.
+    pub fn identity<T: Any + Clone>(&self) -> Option<T> {
+        self.identity.as_ref()?.downcast_ref::<T>().cloned()
+    }
+
+    /// Registers the identity proven by the connected node, e.g. the address whose key signed a challenge; meant
+    /// to be used during the [`Handshake`], once the proof is verified.
+    pub fn set_identity<T: Any + Send + Sync>(&mut self, identity: T) {
+        self.identity = Some(Arc::new(identity));
+    }
+
+    /// Returns the tracing span of the connection.
+    pub fn span(&self) -> &Span {
+        &self.span
+    }
+
+    /// Returns the statistics of the connection.
+    pub fn stats(&self) -> &Arc<ConnectionStats> {
+        &self.stats
+    }
+
+    /// Asks the [`Writing`] task to send the messages still queued for the connection and to close the write side of
+    /// the stream, so that the peer receives the final bytes; waits for it no longer than `flush_timeout`. Returns
+    /// `false` if it couldn't be done in time, or if the task is already gone.
+    pub(crate) async fn flush_and_close(&mut self, flush_timeout: Duration) -> bool {
+        // without the Writing protocol, there is nothing to flush
+        let Some(closer) = self.writer_closer.take() else {
+            return true;
+        };
+
+        let (ack_sender, ack_receiver) = oneshot::channel();
+        if closer.send(ack_sender).is_err() {
+            return false;
+        }
+        matches!(timeout(flush_timeout, ack_receiver).await, Ok(Ok(())))
+    }
+
+    /// Returns `true` if the connection is fully operational, i.e. all the enabled protocols (including the
+    /// [`Reading`] task) are ready to handle it; it is always `false` during the [`Handshake`].
+    pub fn is_ready(&self) -> bool {
+        self.ready.load(Ordering::Acquire)
+    }
+
+    /// Returns `true` if the connection is quarantined (see [`Tcp::quarantine_peer`]); the quarantine expires on its
+    /// own after [`Config::quarantine_duration`].
+    pub fn is_quarantined(&self) -> bool {
+        self.quarantined_until.map_or(false, |until| until > Instant::now())
+    }
+}
+
+/// The type of a connected node; Tcp doesn't determine it itself, but it can be provided by the higher-level
+/// protocols (see [`Tcp::set_node_type`]) in order to enforce the per-type connection limits.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum NodeType {
+    /// A client node.
+    Client,
+    /// A prover node.
+    Prover,
+    /// A validator node.
+    Validator,
+}
+
+/// An endpoint Tcp can listen on.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ListenEndpoint {
+    /// A TCP socket address.
+    Tcp(SocketAddr),
+    /// The path of a Unix domain socket.
+    Unix(PathBuf),
+}
+
+/// The stream underlying a [`Connection`].
+pub enum Stream {
+    /// A TCP stream.
+    Tcp(TcpStream),
+    /// A Unix domain socket stream.
+    #[cfg(unix)]
+    Unix(UnixStream),
+    /// A TCP stream secured with TLS (see [`Config::tls`]).
+    Tls(Box<TlsStream<TcpStream>>),
+}
+
+impl From<TcpStream> for Stream {
+    fn from(stream: TcpStream) -> Self {
+        Self::Tcp(stream)
+    }
+}
+
+#[cfg(unix)]
+impl From<UnixStream> for Stream {
+    fn from(stream: UnixStream) -> Self {
+        Self::Unix(stream)
+    }
+}
+
+impl AsyncRead for Stream {
+    fn poll_read(self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &mut ReadBuf<'_>) -> Poll<io::Result<()>> {
+        match self.get_mut() {
+            Self::Tcp(stream) => Pin::new(stream).poll_read(cx, buf),
+            #[cfg(unix)]
+            Self::Unix(stream) => Pin::new(stream).poll_read(cx, buf),
+            Self::Tls(stream) => Pin::new(stream).poll_read(cx, buf),
+        }
+    }
+}
+
+impl AsyncWrite for Stream {
+    fn poll_write(self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &[u8]) -> Poll<io::Result<usize>> {
+        match self.get_mut() {
+            Self::Tcp(stream) => Pin::new(stream).poll_write(cx, buf),
+            #[cfg(unix)]
+            Self::Unix(stream) => Pin::new(stream).poll_write(cx, buf),
+            Self::Tls(stream) => Pin::new(stream).poll_write(cx, buf),
+        }
+    }
+
+    fn poll_write_vectored(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        bufs: &[io::IoSlice<'_>],
+    ) -> Poll<io::Result<usize>> {
+        match self.get_mut() {
+            Self::Tcp(stream) => Pin::new(stream).poll_write_vectored(cx, bufs),
+            #[cfg(unix)]
+            Self::Unix(stream) => Pin::new(stream).poll_write_vectored(cx, bufs),
+            Self::Tls(stream) => Pin::new(stream).poll_write_vectored(cx, bufs),
+        }
+    }
+
+    fn is_write_vectored(&self) -> bool {
+        match self {
+            Self::Tcp(stream) => stream.is_write_vectored(),
+            #[cfg(unix)]
+            Self::Unix(stream) => stream.is_write_vectored(),
+            Self::Tls(stream) => stream.is_write_vectored(),
+        }
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        match self.get_mut() {
+            Self::Tcp(stream) => Pin::new(stream).poll_flush(cx),
+            #[cfg(unix)]
+            Self::Unix(stream) => Pin::new(stream).poll_flush(cx),
+            Self::Tls(stream) => Pin::new(stream).poll_flush(cx),
+        }
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        match self.get_mut() {
+            Self::Tcp(stream) => Pin::new(stream).poll_shutdown(cx),
+            #[cfg(unix)]
+            Self::Unix(stream) => Pin::new(stream).poll_shutdown(cx),
+            Self::Tls(stream) => Pin::new(stream).poll_shutdown(cx),
+        }
+    }
 }
 
 /// Indicates who was the initiator and who was the responder when the connection was established.
-#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
 pub enum ConnectionSide {
     /// The side that initiated the connection.
     Initiator,
@@ -0,0 +1,110 @@
+// Copyright (C) 2019-2023 Aleo Systems Inc.
+// This file is part of the snarkOS library.
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at:
+// http://www.apache.org/licenses/LICENSE-2.0
+
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::{
+    cmp::Reverse,
+    collections::{BinaryHeap, HashMap},
+    net::SocketAddr,
+    sync::atomic::{AtomicBool, Ordering},
+};
+
+use parking_lot::Mutex;
+use tokio::sync::Notify;
+
+#[cfg(doc)]
+use crate::Tcp;
+
+/// A queue of the addresses pending a connection attempt via [`Tcp::enqueue_dial`]; the addresses with a higher
+/// priority are dialed first, and the ones with the same priority are dialed in the order they were queued in.
+#[derive(Default)]
+pub(crate) struct DialQueue {
+    /// The queued addresses.
+    entries: Mutex<DialEntries>,
+    /// Used to wake the dialing task once an address is queued.
+    notify: Notify,
+    /// Indicates whether the dialing task has been spawned.
+    started: AtomicBool,
+}
+
+#[derive(Default)]
+struct DialEntries {
+    /// The queued addresses, along with their priorities and sequence numbers; it may contain stale entries,
+    /// i.e. the ones whose priority has been raised since, which are skipped.
+    heap: BinaryHeap<(u8, Reverse<u64>, SocketAddr)>,
+    /// The current priorities of the queued addresses.
+    priorities: HashMap<SocketAddr, u8>,
+    /// The sequence number of the next entry.
+    next_seq: u64,
+}
+
+impl DialQueue {
+    /// Queues the given address with the given priority, or raises the priority of an already queued one;
+    /// returns `false` if the address was already queued with the same or a higher priority.
+    pub(crate) fn push(&self, addr: SocketAddr, priority: u8) -> bool {
+        let mut entries = self.entries.lock();
+        match entries.priorities.get(&addr) {
+            Some(&current) if current >= priority => return false,
+            _ => {}
+        }
+
+        entries.priorities.insert(addr, priority);
+        let seq = entries.next_seq;
+        entries.next_seq += 1;
+        entries.heap.push((priority, Reverse(seq), addr));
+        drop(entries);
+
+        self.notify.notify_one();
+        true
+    }
+
+    /// Returns the queued address with the highest priority, without removing it from the queue.
+    pub(crate) fn peek(&self) -> Option<SocketAddr> {
+        let mut entries = self.entries.lock();
+        while let Some(&(priority, _, addr)) = entries.heap.peek() {
+            if entries.priorities.get(&addr) == Some(&priority) {
+                return Some(addr);
+            }
+            // Discard the stale entry.
+            entries.heap.pop();
+        }
+        None
+    }
+
+    /// Removes the given address from the queue.
+    pub(crate) fn remove(&self, addr: SocketAddr) -> bool {
+        // The heap entry becomes stale, and is discarded later on.
+        self.entries.lock().priorities.remove(&addr).is_some()
+    }
+
+    /// Waits until the queue is not empty.
+    pub(crate) async fn wait(&self) {
+        loop {
+            let notified = self.notify.notified();
+            if self.peek().is_some() {
+                return;
+            }
+            notified.await;
+        }
+    }
+
+    /// Returns the number of queued addresses.
+    pub(crate) fn len(&self) -> usize {
+        self.entries.lock().priorities.len()
+    }
+
+    /// Returns `true` the first time it's called, indicating that the dialing task needs to be spawned.
+    pub(crate) fn start(&self) -> bool {
+        !self.started.swap(true, Ordering::AcqRel)
+    }
+}
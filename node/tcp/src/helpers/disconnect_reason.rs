@@ -0,0 +1,51 @@
+// Copyright (C) 2019-2023 Aleo Systems Inc.
+// This file is part of the snarkOS library.
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at:
+// http://www.apache.org/licenses/LICENSE-2.0
+
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+#[cfg(doc)]
+use crate::{Config, Tcp};
+
+/// The reason why a connection was torn down (see [`Tcp::subscribe_disconnects`]).
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum DisconnectReason {
+    /// The node decided to disconnect, e.g. via [`Tcp::disconnect`].
+    Local,
+    /// The peer closed the connection.
+    PeerClosed,
+    /// Reading from the connection failed or timed out.
+    ReadError,
+    /// Writing to the connection failed or timed out.
+    WriteError,
+    /// The node's policies required it, e.g. due to a ban, a connection limit, inactivity (see
+    /// [`Config::idle_timeout`]), or the age of the connection (see [`Config::max_connection_lifetime`]).
+    Policy,
+    /// The node is shutting down.
+    Shutdown,
+}
+
+impl fmt::Display for DisconnectReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let reason = match self {
+            Self::Local => "disconnected locally",
+            Self::PeerClosed => "the peer closed the connection",
+            Self::ReadError => "couldn't read from the connection",
+            Self::WriteError => "couldn't write to the connection",
+            Self::Policy => "dropped due to the node's policies",
+            Self::Shutdown => "the node is shutting down",
+        };
+        f.write_str(reason)
+    }
+}
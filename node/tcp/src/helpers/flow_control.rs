@@ -0,0 +1,245 @@
+// Copyright (C) 2019-2023 Aleo Systems Inc.
+// This file is part of the snarkOS library.
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at:
+// http://www.apache.org/licenses/LICENSE-2.0
+
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::{
+    io,
+    sync::{
+        atomic::{AtomicU32, Ordering::*},
+        Arc,
+    },
+};
+
+use bytes::{Buf, BufMut, BytesMut};
+use tokio::sync::{Notify, Semaphore};
+use tokio_util::codec::{Decoder, Encoder};
+
+#[cfg(doc)]
+use crate::{
+    protocols::{Reading, Writing},
+    Config,
+};
+
+/// The length of the header of a flow-controlled frame: its kind (1B) and the length of its payload (4B).
+const HEADER_LEN: usize = 5;
+/// The room left in an inbound frame for the header of the wrapped codec on top of [`Config::max_message_size`].
+const WRAPPED_HEADER_ALLOWANCE: usize = 64;
+/// The kind of a frame carrying a message.
+const DATA_FRAME: u8 = 0;
+/// The kind of a frame carrying a [`Frame::WindowUpdate`].
+const WINDOW_UPDATE_FRAME: u8 = 1;
+
+/// A frame sent over a connection subject to flow control (see [`Config::flow_control_window`]).
+pub(crate) enum Frame<M> {
+    /// A message encoded with the [`Writing::Codec`]; it consumes one of the credits granted by the peer.
+    Data(M),
+    /// Grants the peer the given number of credits, i.e. allows it to send that many more messages.
+    WindowUpdate(u32),
+}
+
+/// The flow control state of a single connection; the peer is granted a window of messages it can send, which is
+/// replenished as they are processed, and the node can only send as many messages as the peer granted it.
+pub(crate) struct FlowControl {
+    /// The number of messages the peer can send before it has to wait for more credits.
+    window: u32,
+    /// The credits granted by the peer, i.e. the number of messages that can still be sent to it.
+    send_credits: Semaphore,
+    /// The credits that are yet to be granted to the peer.
+    pending_grant: AtomicU32,
+    /// Used to wake the writer task once the credits are to be granted.
+    grant_notify: Notify,
+}
+
+impl FlowControl {
+    /// Creates the flow control state with the given window; the peer is granted all of it right away.
+    pub(crate) fn new(window: u32) -> Self {
+        let flow_control = Self {
+            window,
+            send_credits: Semaphore::new(0),
+            pending_grant: AtomicU32::new(window),
+            grant_notify: Default::default(),
+        };
+        flow_control.grant_notify.notify_one();
+
+        flow_control
+    }
+
+    /// Waits for a credit granted by the peer and consumes it.
+    pub(crate) async fn acquire_send_credit(&self) {
+        // the semaphore is never closed
+        if let Ok(permit) = self.send_credits.acquire().await {
+            permit.forget();
+        }
+    }
+
+    /// Registers the credits granted by the peer.
+    pub(crate) fn add_send_credits(&self, credits: u32) -> io::Result<()> {
+        if self.send_credits.available_permits().saturating_add(credits as usize) > Semaphore::MAX_PERMITS {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "the peer granted too many flow control credits"));
+        }
+        self.send_credits.add_permits(credits as usize);
+
+        Ok(())
+    }
+
+    /// Registers the processing of a message received from the peer; once half of the window has been processed,
+    /// the corresponding credits are granted back to the peer.
+    pub(crate) fn register_processed(&self) {
+        let pending = self.pending_grant.fetch_add(1, Relaxed) + 1;
+        if pending >= (self.window / 2).max(1) {
+            self.grant_notify.notify_one();
+        }
+    }
+
+    /// Waits until there are credits to be granted to the peer, and takes them.
+    pub(crate) async fn take_grant(&self) -> u32 {
+        loop {
+            self.grant_notify.notified().await;
+            let credits = self.pending_grant.swap(0, Relaxed);
+            if credits != 0 {
+                return credits;
+            }
+        }
+    }
+}
+
+/// A codec wrapping the ones of the [`Reading`] and [`Writing`] protocols in order to apply flow control; each
+/// message is sent in a frame of its own, interleaved with the [`Frame::WindowUpdate`]s. If flow control is not
+/// enabled, the messages are passed through as they are.
+pub(crate) struct FlowControlCodec<C> {
+    codec: C,
+    /// The flow control state of the connection, if it's enabled.
+    flow_control: Option<Arc<FlowControl>>,
+    /// The maximum size of the payload of an inbound frame; it prevents the peers from causing excessive allocations.
+    max_payload_len: usize,
+}
+
+impl<C> FlowControlCodec<C> {
+    /// Wraps the given codec; the frames are only used if the flow control state is provided.
+    pub(crate) fn new(codec: C, flow_control: Option<Arc<FlowControl>>, max_message_size: usize) -> Self {
+        Self { codec, flow_control, max_payload_len: max_message_size.saturating_add(WRAPPED_HEADER_ALLOWANCE) }
+    }
+}
+
+impl<D: Decoder<Error = io::Error>> Decoder for FlowControlCodec<D> {
+    type Error = io::Error;
+    type Item = D::Item;
+
+    fn decode(&mut self, src: &mut BytesMut) -> io::Result<Option<Self::Item>> {
+        let Some(flow_control) = &self.flow_control else {
+            return self.codec.decode(src);
+        };
+
+        loop {
+            if src.len() < HEADER_LEN {
+                return Ok(None);
+            }
+            let kind = src[0];
+            let payload_len = u32::from_be_bytes([src[1], src[2], src[3], src[4]]) as usize;
+
+            match kind {
+                WINDOW_UPDATE_FRAME if payload_len == 4 => {
+                    if src.len() < HEADER_LEN + payload_len {
+                        return Ok(None);
+                    }
+                    src.advance(HEADER_LEN);
+                    flow_control.add_send_credits(src.get_u32())?;
+                }
+                DATA_FRAME if payload_len <= self.max_payload_len => {
+                    if src.len() < HEADER_LEN + payload_len {
+                        src.reserve(HEADER_LEN + payload_len - src.len());
+                        return Ok(None);
+                    }
+                    src.advance(HEADER_LEN);
+                    let mut payload = src.split_to(payload_len);
+                    return match self.codec.decode(&mut payload)? {
+                        Some(message) if payload.is_empty() => Ok(Some(message)),
+                        _ => Err(io::Error::new(
+                            io::ErrorKind::InvalidData,
+                            "a data frame must contain a single message",
+                        )),
+                    };
+                }
+                _ => return Err(io::Error::new(io::ErrorKind::InvalidData, "invalid flow control frame")),
+            }
+        }
+    }
+}
+
+impl<M, E: Encoder<M, Error = io::Error>> Encoder<Frame<M>> for FlowControlCodec<E> {
+    type Error = io::Error;
+
+    fn encode(&mut self, frame: Frame<M>, dst: &mut BytesMut) -> io::Result<()> {
+        match frame {
+            Frame::Data(message) if self.flow_control.is_none() => self.codec.encode(message, dst),
+            Frame::Data(message) => {
+                let start = dst.len();
+                dst.put_u8(DATA_FRAME);
+                dst.put_u32(0);
+                self.codec.encode(message, dst)?;
+                let payload_len = u32::try_from(dst.len() - start - HEADER_LEN)
+                    .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "the message is too large"))?;
+                dst[start + 1..start + HEADER_LEN].copy_from_slice(&payload_len.to_be_bytes());
+                Ok(())
+            }
+            Frame::WindowUpdate(credits) => {
+                dst.put_u8(WINDOW_UPDATE_FRAME);
+                dst.put_u32(4);
+                dst.put_u32(credits);
+                Ok(())
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio_util::codec::LengthDelimitedCodec;
+
+    #[test]
+    fn frames_roundtrip() {
+        let flow_control = Arc::new(FlowControl::new(4));
+        let mut codec = FlowControlCodec::new(LengthDelimitedCodec::new(), Some(Arc::clone(&flow_control)), 1024);
+
+        let mut buf = BytesMut::new();
+        codec.encode(Frame::Data(bytes::Bytes::from_static(b"first")), &mut buf).unwrap();
+        codec.encode(Frame::WindowUpdate(3), &mut buf).unwrap();
+        codec.encode(Frame::Data(bytes::Bytes::from_static(b"second")), &mut buf).unwrap();
+
+        // Ensure the messages are decoded, and the credits are registered along the way.
+        assert_eq!(&codec.decode(&mut buf).unwrap().unwrap()[..], b"first");
+        assert_eq!(flow_control.send_credits.available_permits(), 0);
+        assert_eq!(&codec.decode(&mut buf).unwrap().unwrap()[..], b"second");
+        assert_eq!(flow_control.send_credits.available_permits(), 3);
+        assert!(buf.is_empty());
+
+        // Ensure oversized frames are rejected.
+        codec.encode(Frame::Data(bytes::Bytes::from(vec![0; 2048])), &mut buf).unwrap();
+        assert_eq!(codec.decode(&mut buf).unwrap_err().kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[tokio::test]
+    async fn credits_are_granted_in_batches() {
+        let flow_control = FlowControl::new(4);
+
+        // Ensure the whole window is granted initially.
+        assert_eq!(flow_control.take_grant().await, 4);
+
+        // Ensure the credits are granted back once half of the window is processed.
+        flow_control.register_processed();
+        assert_eq!(flow_control.pending_grant.load(Relaxed), 1);
+        flow_control.register_processed();
+        assert_eq!(flow_control.take_grant().await, 2);
+    }
+}
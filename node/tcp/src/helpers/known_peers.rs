@@ -12,55 +12,340 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use std::{collections::HashMap, net::SocketAddr, sync::Arc};
+use std::{
+    collections::HashMap,
+    net::{IpAddr, SocketAddr},
+    ops::RangeInclusive,
+    sync::Arc,
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
 
 use parking_lot::RwLock;
+use serde::{Deserialize, Serialize};
 
 use crate::Stats;
 
 /// Contains statistics related to Tcp's peers, currently connected or not.
-#[derive(Default)]
-pub struct KnownPeers(RwLock<HashMap<SocketAddr, Arc<Stats>>>);
+pub struct KnownPeers {
+    /// The stats of all the known peers.
+    peers: RwLock<HashMap<SocketAddr, Arc<Stats>>>,
+    /// The banned peers, along with the expiry of their bans.
+    bans: RwLock<HashMap<SocketAddr, Instant>>,
+    /// The range the peers' scores are clamped to.
+    score_range: RangeInclusive<i32>,
+}
+
+/// A serializable record of a known peer, used to persist the peers between runs (see [`KnownPeers::export`]).
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PeerRecord {
+    /// The address of the peer.
+    pub addr: SocketAddr,
+    /// The number of failures associated with the peer.
+    pub failures: u64,
+    /// The score reflecting the behavior of the peer.
+    pub score: i32,
+    /// The time the peer was last heard from, in seconds since the Unix epoch, if it was.
+    pub last_seen: Option<u64>,
+}
+
+impl Default for KnownPeers {
+    fn default() -> Self {
+        Self::new(Self::DEFAULT_SCORE_RANGE)
+    }
+}
 
 impl KnownPeers {
+    /// The default range the peers' scores are clamped to.
+    pub const DEFAULT_SCORE_RANGE: RangeInclusive<i32> = -100..=100;
+    /// The score penalty applied with every failure.
+    pub const FAILURE_PENALTY: i32 = -1;
+    /// The score reward applied with every long-lived connection.
+    pub const LONG_LIVED_CONNECTION_REWARD: i32 = 1;
+
+    /// Creates an empty collection of known peers, whose scores are clamped to the given range.
+    pub fn new(score_range: RangeInclusive<i32>) -> Self {
+        Self { peers: Default::default(), bans: Default::default(), score_range }
+    }
+
     /// Adds an address to the list of known peers.
     pub fn add(&self, addr: SocketAddr) {
-        self.0.write().entry(addr).or_default();
+        self.peers.write().entry(addr).or_default();
     }
 
     /// Returns the stats for the given peer.
     pub fn get(&self, addr: SocketAddr) -> Option<Arc<Stats>> {
-        self.0.read().get(&addr).map(Arc::clone)
+        self.peers.read().get(&addr).map(Arc::clone)
     }
 
     /// Removes an address from the list of known peers.
     pub fn remove(&self, addr: SocketAddr) -> Option<Arc<Stats>> {
-        self.0.write().remove(&addr)
+        self.peers.write().remove(&addr)
     }
 
     /// Returns the list of all known peers and their stats.
     pub fn snapshot(&self) -> HashMap<SocketAddr, Arc<Stats>> {
-        self.0.read().clone()
+        self.peers.read().clone()
+    }
+
+    /// Returns the records of all the known peers, sorted by address, e.g. in order to persist them between runs.
+    pub fn export(&self) -> Vec<PeerRecord> {
+        let mut records = self
+            .peers
+            .read()
+            .iter()
+            .map(|(addr, stats)| PeerRecord {
+                addr: *addr,
+                failures: stats.failures(),
+                score: stats.score(),
+                last_seen: stats.last_seen(),
+            })
+            .collect::<Vec<_>>();
+        records.sort_unstable_by_key(|record| record.addr);
+        records
+    }
+
+    /// Restores the given records of known peers, e.g. persisted in a previous run, and returns the number of the
+    /// imported ones. If `max_age` is provided, the peers that were last heard from longer ago (or never) are
+    /// skipped; the peers that are already known are left intact.
+    pub fn import(&self, records: Vec<PeerRecord>, max_age: Option<Duration>) -> usize {
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).map(|since| since.as_secs()).unwrap_or_default();
+        let mut peers = self.peers.write();
+        let mut num_imported = 0;
+
+        for record in records {
+            if let Some(max_age) = max_age {
+                match record.last_seen {
+                    Some(last_seen) if now.saturating_sub(last_seen) <= max_age.as_secs() => {}
+                    _ => continue,
+                }
+            }
+            if peers.contains_key(&record.addr) {
+                continue;
+            }
+
+            let stats = Stats::default();
+            let score = record.score.clamp(*self.score_range.start(), *self.score_range.end());
+            stats.restore(record.failures, score, record.last_seen);
+            peers.insert(record.addr, Arc::new(stats));
+            num_imported += 1;
+        }
+
+        num_imported
     }
 
     /// Registers a submission of a message to the given address.
     pub fn register_sent_message(&self, to: SocketAddr, size: usize) {
-        if let Some(stats) = self.0.read().get(&to) {
+        if let Some(stats) = self.peers.read().get(&to) {
             stats.register_sent_message(size);
         }
     }
 
     /// Registers a receipt of a message to the given address.
     pub fn register_received_message(&self, from: SocketAddr, size: usize) {
-        if let Some(stats) = self.0.read().get(&from) {
+        if let Some(stats) = self.peers.read().get(&from) {
             stats.register_received_message(size);
+            stats.register_seen();
         }
     }
 
+    /// Registers that the given address was heard from just now, e.g. upon the establishment of a connection.
+    pub fn register_seen(&self, addr: SocketAddr) {
+        if let Some(stats) = self.peers.read().get(&addr) {
+            stats.register_seen();
+        }
+    }
+
+    /// Returns `true` if the given address was heard from within the provided window.
+    pub fn seen_within(&self, addr: SocketAddr, window: Duration) -> bool {
+        self.peers
+            .read()
+            .get(&addr)
+            .and_then(|stats| stats.last_seen_at())
+            .map_or(false, |last_seen| last_seen.elapsed() <= window)
+    }
+
+    /// Returns the addresses, sorted, that weren't heard from for longer than the given duration, including the
+    /// ones that weren't heard from at all during this run; useful when pruning the peer cache.
+    pub fn stale_peers(&self, older_than: Duration) -> Vec<SocketAddr> {
+        let mut addrs = self
+            .peers
+            .read()
+            .iter()
+            .filter(|(_, stats)| stats.last_seen_at().map_or(true, |last_seen| last_seen.elapsed() > older_than))
+            .map(|(addr, _)| *addr)
+            .collect::<Vec<_>>();
+        addrs.sort_unstable();
+        addrs
+    }
+
     /// Registers a failure associated with the given address.
     pub fn register_failure(&self, addr: SocketAddr) {
-        if let Some(stats) = self.0.read().get(&addr) {
+        if let Some(stats) = self.peers.read().get(&addr) {
             stats.register_failure();
+            stats.adjust_score(Self::FAILURE_PENALTY, &self.score_range);
         }
     }
+
+    /// Adjusts the score of the given address by the provided delta, and returns the new score; the score
+    /// is clamped to the configured range. Returns `None` if the address is not known.
+    pub fn adjust_score(&self, addr: SocketAddr, delta: i32) -> Option<i32> {
+        self.peers.read().get(&addr).map(|stats| stats.adjust_score(delta, &self.score_range))
+    }
+
+    /// Returns the score of the given address, if it is known.
+    pub fn score(&self, addr: SocketAddr) -> Option<i32> {
+        self.peers.read().get(&addr).map(|stats| stats.score())
+    }
+
+    /// Registers a round-trip time measured with the given address.
+    pub fn register_latency(&self, addr: SocketAddr, latency: Duration) {
+        if let Some(stats) = self.peers.read().get(&addr) {
+            stats.register_latency(latency);
+        }
+    }
+
+    /// Returns the latest round-trip time measured with the given address, if there is one.
+    pub fn latency(&self, addr: SocketAddr) -> Option<Duration> {
+        self.peers.read().get(&addr).and_then(|stats| stats.latency())
+    }
+
+    /// Registers an estimate of the clock offset (in milliseconds) of the given address.
+    pub fn register_clock_offset(&self, addr: SocketAddr, offset_ms: i64) {
+        if let Some(stats) = self.peers.read().get(&addr) {
+            stats.register_clock_offset(offset_ms);
+        }
+    }
+
+    /// Returns the magnitude of the latest estimate of the clock offset of the given address, if there is one.
+    pub fn clock_offset(&self, addr: SocketAddr) -> Option<Duration> {
+        self.peers.read().get(&addr).and_then(|stats| stats.clock_offset())
+    }
+
+    /// Bans the given address until the provided expiry.
+    pub fn ban(&self, addr: SocketAddr, until: Instant) {
+        self.bans.write().insert(addr, until);
+    }
+
+    /// Lifts the ban on the given address; returns `true` if it was banned.
+    pub fn unban(&self, addr: SocketAddr) -> bool {
+        self.bans.write().remove(&addr).is_some()
+    }
+
+    /// Returns `true` if the given address is currently banned; an expired ban is removed.
+    pub fn is_banned(&self, addr: SocketAddr) -> bool {
+        let expiry = self.bans.read().get(&addr).copied();
+        match expiry {
+            Some(expiry) if expiry > Instant::now() => true,
+            Some(_) => {
+                self.bans.write().retain(|_, expiry| *expiry > Instant::now());
+                false
+            }
+            None => false,
+        }
+    }
+
+    /// Returns `true` if any address with the given IP is currently banned.
+    pub fn is_ip_banned(&self, ip: IpAddr) -> bool {
+        let now = Instant::now();
+        self.bans.read().iter().any(|(addr, expiry)| addr.ip() == ip && *expiry > now)
+    }
+
+    /// Returns the list of currently banned addresses and the expiry of their bans; expired bans are removed.
+    pub fn banned(&self) -> Vec<(SocketAddr, Instant)> {
+        let mut bans = self.bans.write();
+        let now = Instant::now();
+        bans.retain(|_, expiry| *expiry > now);
+        bans.iter().map(|(addr, expiry)| (*addr, *expiry)).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::net::Ipv4Addr;
+
+    #[test]
+    fn test_known_peer_latency() {
+        let known_peers = KnownPeers::default();
+        let addr = SocketAddr::from((Ipv4Addr::LOCALHOST, 1234));
+
+        // Ensure the latency is only available once measured.
+        assert_eq!(known_peers.latency(addr), None);
+        known_peers.add(addr);
+        assert_eq!(known_peers.latency(addr), None);
+
+        known_peers.register_latency(addr, Duration::from_millis(25));
+        assert_eq!(known_peers.latency(addr), Some(Duration::from_millis(25)));
+    }
+
+    #[test]
+    fn test_known_peers_export_import() {
+        let known_peers = KnownPeers::default();
+        let peer_addr: SocketAddr = "127.0.0.1:4130".parse().unwrap();
+        let silent_addr: SocketAddr = "127.0.0.1:4131".parse().unwrap();
+
+        known_peers.add(peer_addr);
+        known_peers.register_failure(peer_addr);
+        known_peers.adjust_score(peer_addr, 10);
+        known_peers.register_received_message(peer_addr, 100);
+        known_peers.add(silent_addr);
+
+        // Ensure the records survive a serialization round trip.
+        let records = known_peers.export();
+        assert_eq!(records.len(), 2);
+        let serialized = serde_json::to_string(&records).unwrap();
+        let deserialized: Vec<PeerRecord> = serde_json::from_str(&serialized).unwrap();
+        assert_eq!(deserialized, records);
+
+        // Ensure the stats are restored.
+        let restored_peers = KnownPeers::default();
+        assert_eq!(restored_peers.import(deserialized.clone(), None), 2);
+        assert_eq!(restored_peers.export(), records);
+        let stats = restored_peers.get(peer_addr).unwrap();
+        assert_eq!(stats.failures(), 1);
+        assert_eq!(stats.score(), 9);
+        assert!(stats.last_seen().is_some());
+
+        // Ensure the peers that are already known are left intact.
+        assert_eq!(restored_peers.import(deserialized.clone(), None), 0);
+
+        // Ensure the stale records are skipped, along with the ones of the peers that were never heard from.
+        let mut stale_record = records[0].clone();
+        stale_record.last_seen = stale_record.last_seen.map(|last_seen| last_seen - 3600);
+        let fresh_peers = KnownPeers::default();
+        assert_eq!(fresh_peers.import(vec![stale_record], Some(Duration::from_secs(60))), 0);
+        assert_eq!(fresh_peers.import(deserialized, Some(Duration::from_secs(60))), 1);
+        assert!(fresh_peers.get(peer_addr).is_some());
+        assert!(fresh_peers.get(silent_addr).is_none());
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_known_peers_staleness() {
+        let known_peers = KnownPeers::default();
+        let peer_addr: SocketAddr = "127.0.0.1:4130".parse().unwrap();
+        let silent_addr: SocketAddr = "127.0.0.1:4131".parse().unwrap();
+        let unknown_addr: SocketAddr = "127.0.0.1:4132".parse().unwrap();
+
+        known_peers.add(peer_addr);
+        known_peers.add(silent_addr);
+        known_peers.register_seen(peer_addr);
+
+        // Ensure the peers that were never heard from are stale.
+        assert!(known_peers.seen_within(peer_addr, Duration::from_secs(10)));
+        assert!(!known_peers.seen_within(silent_addr, Duration::from_secs(10)));
+        assert!(!known_peers.seen_within(unknown_addr, Duration::from_secs(10)));
+        assert_eq!(known_peers.stale_peers(Duration::from_secs(10)), vec![silent_addr]);
+
+        // Ensure the peers become stale once the window elapses.
+        tokio::time::advance(Duration::from_secs(11)).await;
+        assert!(!known_peers.seen_within(peer_addr, Duration::from_secs(10)));
+        assert!(known_peers.seen_within(peer_addr, Duration::from_secs(20)));
+        assert_eq!(known_peers.stale_peers(Duration::from_secs(10)), vec![peer_addr, silent_addr]);
+
+        // Ensure a received message refreshes the timestamp.
+        known_peers.register_received_message(peer_addr, 100);
+        assert!(known_peers.seen_within(peer_addr, Duration::from_secs(10)));
+        assert_eq!(known_peers.stale_peers(Duration::from_secs(10)), vec![silent_addr]);
+    }
 }
@@ -12,17 +12,66 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+mod backoff;
+pub use backoff::BackoffPolicy;
+
+mod capabilities;
+pub use capabilities::Capabilities;
+
 mod config;
-pub use config::Config;
+pub use config::{CompressionLevel, Config, IpFamily, ProtocolOrder};
+
+mod dial_queue;
+pub(crate) use dial_queue::DialQueue;
+
+mod disconnect_reason;
+pub use disconnect_reason::DisconnectReason;
 
 pub mod connections;
-pub use connections::{Connection, ConnectionSide};
+pub use connections::{Connection, ConnectionSide, ListenEndpoint, NodeType, Stream};
+
+mod flow_control;
+pub(crate) use flow_control::{FlowControl, FlowControlCodec, Frame};
 
 mod known_peers;
-pub use known_peers::KnownPeers;
+pub use known_peers::{KnownPeers, PeerRecord};
+
+mod observed_addrs;
+pub(crate) use observed_addrs::ObservedAddrs;
+
+mod peer_demand;
+pub use peer_demand::PeerDemand;
+
+#[cfg(feature = "prometheus")]
+mod prometheus;
+
+mod peer_selection;
+pub(crate) use peer_selection::lowest_n;
+pub use peer_selection::SelectionStrategy;
+
+pub(crate) mod proxy_protocol;
+
+mod rate_limiter;
+pub use rate_limiter::{RateCounter, RateLimiter};
+
+mod reject_reason;
+pub use reject_reason::RejectReason;
+
+mod snapshot;
+pub use snapshot::{ConnectionStatsSnapshot, PeerInfo, PeerSnapshot, StatsSnapshot, TcpSnapshot};
+
+pub(crate) mod socks5;
 
 mod stats;
-pub use stats::Stats;
+pub use stats::{ConnectionStats, LatencyPercentiles, MessageTypeStats, Stats};
+
+mod tasks;
+pub(crate) use tasks::{NamedTask, TaskRegistry};
+
+mod tls;
+pub use tls::TlsConfig;
+
+use std::net::SocketAddr;
 
 use tracing::{debug_span, error_span, info_span, trace_span, warn_span, Span};
 
@@ -43,3 +92,21 @@ pub fn create_span(tcp_name: &str) -> Span {
     }
     span
 }
+
+/// Creates the tracing span of a single connection, based on its address and label.
+pub(crate) fn create_connection_span(tcp_span: &Span, addr: SocketAddr, label: &str) -> Span {
+    let mut span = trace_span!(parent: tcp_span, "conn", %addr, label);
+    if span.is_disabled() {
+        span = debug_span!(parent: tcp_span, "conn", %addr, label);
+    }
+    if span.is_disabled() {
+        span = info_span!(parent: tcp_span, "conn", %addr, label);
+    }
+    if span.is_disabled() {
+        span = warn_span!(parent: tcp_span, "conn", %addr, label);
+    }
+    if span.is_disabled() {
+        span = error_span!(parent: tcp_span, "conn", %addr, label);
+    }
+    span
+}
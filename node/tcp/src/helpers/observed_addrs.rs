@@ -0,0 +1,59 @@
+// Copyright (C) 2019-2023 Aleo Systems Inc.
+// This file is part of the snarkOS library.
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at:
+// http://www.apache.org/licenses/LICENSE-2.0
+
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+use std::{
+    collections::HashMap,
+    net::{IpAddr, SocketAddr},
+};
+
+use parking_lot::RwLock;
+
+#[cfg(doc)]
+use crate::Tcp;
+
+/// Tallies the external addresses the peers report seeing the node connect from (see
+/// [`Tcp::register_observed_addr`]); every peer IP gets a single vote, so a peer can't sway the tally by
+/// reporting repeatedly or via multiple connections.
+#[derive(Default)]
+pub(crate) struct ObservedAddrs {
+    /// The latest address reported by each of the peer IPs.
+    reports: RwLock<HashMap<IpAddr, SocketAddr>>,
+}
+
+impl ObservedAddrs {
+    /// Registers the address the given peer reports seeing the node at, replacing its previous report.
+    pub(crate) fn register(&self, reporter: IpAddr, observed: SocketAddr) {
+        self.reports.write().insert(reporter, observed);
+    }
+
+    /// Returns the reported addresses along with the numbers of peers reporting them, most reported first.
+    pub(crate) fn tally(&self) -> Vec<(SocketAddr, usize)> {
+        let mut counts = HashMap::<SocketAddr, usize>::new();
+        for addr in self.reports.read().values() {
+            *counts.entry(*addr).or_default() += 1;
+        }
+
+        let mut tally = counts.into_iter().collect::<Vec<_>>();
+        tally.sort_unstable_by(|(addr1, count1), (addr2, count2)| count2.cmp(count1).then(addr1.cmp(addr2)));
+        tally
+    }
+
+    /// Returns the most reported address, unless there is a tie for it.
+    pub(crate) fn most_reported(&self) -> Option<SocketAddr> {
+        match self.tally()[..] {
+            [(addr, _)] => Some(addr),
+            [(addr, count1), (_, count2), ..] if count1 > count2 => Some(addr),
+            _ => None,
+        }
+    }
+}
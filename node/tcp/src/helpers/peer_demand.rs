@@ -0,0 +1,77 @@
+// Copyright (C) 2019-2023 Aleo Systems Inc.
+// This file is part of the snarkOS library.
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at:
+// http://www.apache.org/licenses/LICENSE-2.0
+
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+#[cfg(doc)]
+use crate::{Config, Tcp};
+
+/// Indicates whether the node should be looking for more peers, based on the number of its connections and the
+/// [`Config::target_connections_low`] and [`Config::target_connections_high`] watermarks (see
+/// [`Tcp::peer_demand`]).
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum PeerDemand {
+    /// The number of connections dropped below the low watermark, and hasn't reached the high one since.
+    NeedMore,
+    /// The number of connections is within the watermarks.
+    Satisfied,
+    /// The number of connections exceeds the high watermark.
+    Surplus,
+}
+
+impl PeerDemand {
+    /// Returns the demand following the current one once the number of connections changes to `num_connected`.
+    ///
+    /// note: Once more peers are needed, the demand is only satisfied upon reaching the high watermark, so that
+    /// hovering around the low one doesn't cause the peer discovery to be repeatedly started and stopped.
+    pub(crate) fn next(self, num_connected: usize, low: u16, high: u16) -> Self {
+        if num_connected > high as usize {
+            Self::Surplus
+        } else if num_connected < low as usize || (self == Self::NeedMore && num_connected < high as usize) {
+            Self::NeedMore
+        } else {
+            Self::Satisfied
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hysteresis() {
+        let (low, high) = (2, 4);
+
+        // Drop below the low watermark.
+        let mut demand = PeerDemand::Satisfied;
+        for (num_connected, expected) in [
+            (3, PeerDemand::Satisfied),
+            (2, PeerDemand::Satisfied),
+            (1, PeerDemand::NeedMore),
+            // Hovering around the low watermark doesn't satisfy the demand.
+            (2, PeerDemand::NeedMore),
+            (1, PeerDemand::NeedMore),
+            (3, PeerDemand::NeedMore),
+            // Reaching the high watermark does.
+            (4, PeerDemand::Satisfied),
+            (5, PeerDemand::Surplus),
+            (4, PeerDemand::Satisfied),
+            (2, PeerDemand::Satisfied),
+            (0, PeerDemand::NeedMore),
+            // Exceeding the high watermark right away is a surplus.
+            (5, PeerDemand::Surplus),
+        ] {
+            demand = demand.next(num_connected, low, high);
+            assert_eq!(demand, expected, "unexpected demand with {num_connected} connections");
+        }
+    }
+}
@@ -0,0 +1,63 @@
+// Copyright (C) 2019-2023 Aleo Systems Inc.
+// This file is part of the snarkOS library.
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at:
+// http://www.apache.org/licenses/LICENSE-2.0
+
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::net::SocketAddr;
+
+#[cfg(doc)]
+use crate::{KnownPeers, Tcp};
+
+/// The strategy used to pick the peers in [`Tcp::select_peers`], e.g. in order to favor stable, long-lived
+/// connections when gossiping.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum SelectionStrategy {
+    /// The peers are picked uniformly at random.
+    Random,
+    /// The peers connected for the longest time are picked first.
+    OldestFirst,
+    /// The peers with the lowest latency (see [`KnownPeers::latency`]) are picked first; the ones whose latency is
+    /// not known yet are picked last.
+    LowestLatency,
+    /// The peers with the highest score (see [`KnownPeers::score`]) are picked first.
+    HighestScore,
+}
+
+/// Returns up to `n` of the given addresses with the lowest keys, ordered by them; apart from the sorting of the
+/// selected addresses, it runs in linear time.
+pub(crate) fn lowest_n<K: Ord>(mut candidates: Vec<(K, SocketAddr)>, n: usize) -> Vec<SocketAddr> {
+    if n == 0 {
+        return vec![];
+    }
+    if candidates.len() > n {
+        candidates.select_nth_unstable(n - 1);
+        candidates.truncate(n);
+    }
+    candidates.sort_unstable();
+
+    candidates.into_iter().map(|(_, addr)| addr).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lowest_keys_are_selected_in_order() {
+        let addr = |port: u16| SocketAddr::from(([127, 0, 0, 1], port));
+        let candidates = vec![(5, addr(1)), (1, addr(2)), (4, addr(3)), (2, addr(4)), (3, addr(5))];
+
+        assert!(lowest_n(candidates.clone(), 0).is_empty());
+        assert_eq!(lowest_n(candidates.clone(), 3), vec![addr(2), addr(4), addr(5)]);
+        assert_eq!(lowest_n(candidates, 10), vec![addr(2), addr(4), addr(5), addr(3), addr(1)]);
+    }
+}
@@ -0,0 +1,125 @@
+// Copyright (C) 2019-2023 Aleo Systems Inc.
+// This file is part of the snarkOS library.
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at:
+// http://www.apache.org/licenses/LICENSE-2.0
+
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::fmt::Write;
+
+use crate::Tcp;
+
+const CONNECTED: &str = "snarkos_tcp_connected";
+const CONNECTING: &str = "snarkos_tcp_connecting";
+const FAILURES: &str = "snarkos_tcp_failures_total";
+const BYTES_SENT: &str = "snarkos_tcp_bytes_sent_total";
+const BYTES_RECEIVED: &str = "snarkos_tcp_bytes_received_total";
+const PEER_FAILURES: &str = "snarkos_tcp_peer_failures";
+const PEER_BYTES_SENT: &str = "snarkos_tcp_peer_bytes_sent";
+const PEER_BYTES_RECEIVED: &str = "snarkos_tcp_peer_bytes_received";
+
+/// Writes the `HELP` and `TYPE` lines of a metric.
+fn write_header(out: &mut String, name: &str, kind: &str, help: &str) {
+    // Writing to a `String` can't fail.
+    let _ = writeln!(out, "# HELP {name} {help}");
+    let _ = writeln!(out, "# TYPE {name} {kind}");
+}
+
+/// Writes a single metric along with its header.
+fn write_metric(out: &mut String, name: &str, kind: &str, help: &str, value: u64) {
+    write_header(out, name, kind, help);
+    let _ = writeln!(out, "{name} {value}");
+}
+
+impl Tcp {
+    /// Renders the Tcp's statistics in the Prometheus text exposition format. The per-peer
+    /// gauges are only included if [`Config::per_peer_metrics`](crate::Config::per_peer_metrics) is set.
+    pub fn render_prometheus_metrics(&self) -> String {
+        let mut out = String::with_capacity(1024);
+
+        let (_, bytes_sent) = self.stats().sent();
+        let (_, bytes_received) = self.stats().received();
+
+        write_metric(&mut out, CONNECTED, "gauge", "The number of active connections.", self.num_connected() as u64);
+        write_metric(&mut out, CONNECTING, "gauge", "The number of pending connections.", self.num_connecting() as u64);
+        write_metric(&mut out, FAILURES, "counter", "The number of failures.", self.stats().failures());
+        write_metric(&mut out, BYTES_SENT, "counter", "The number of bytes sent.", bytes_sent);
+        write_metric(&mut out, BYTES_RECEIVED, "counter", "The number of bytes received.", bytes_received);
+
+        if self.config().per_peer_metrics {
+            let mut peers = self.known_peers().snapshot().into_iter().collect::<Vec<_>>();
+            // Sort the peers, so that the output is stable.
+            peers.sort_unstable_by_key(|(addr, _)| *addr);
+
+            write_header(&mut out, PEER_FAILURES, "gauge", "The number of failures per peer.");
+            for (addr, stats) in &peers {
+                let _ = writeln!(out, "{PEER_FAILURES}{{addr=\"{addr}\"}} {}", stats.failures());
+            }
+            write_header(&mut out, PEER_BYTES_SENT, "gauge", "The number of bytes sent per peer.");
+            for (addr, stats) in &peers {
+                let _ = writeln!(out, "{PEER_BYTES_SENT}{{addr=\"{addr}\"}} {}", stats.sent().1);
+            }
+            write_header(&mut out, PEER_BYTES_RECEIVED, "gauge", "The number of bytes received per peer.");
+            for (addr, stats) in &peers {
+                let _ = writeln!(out, "{PEER_BYTES_RECEIVED}{{addr=\"{addr}\"}} {}", stats.received().1);
+            }
+        }
+
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Config, Tcp};
+
+    use std::net::SocketAddr;
+
+    #[test]
+    fn test_render_prometheus_metrics() {
+        let tcp = Tcp::new(Config { per_peer_metrics: true, ..Default::default() });
+
+        let addr: SocketAddr = "1.2.3.4:4130".parse().unwrap();
+        tcp.known_peers().add(addr);
+        tcp.known_peers().register_sent_message(addr, 10);
+        tcp.known_peers().register_received_message(addr, 20);
+        tcp.known_peers().register_failure(addr);
+        tcp.stats().register_sent_message(10);
+        tcp.stats().register_received_message(20);
+
+        let expected = "\
+# HELP snarkos_tcp_connected The number of active connections.
+# TYPE snarkos_tcp_connected gauge
+snarkos_tcp_connected 0
+# HELP snarkos_tcp_connecting The number of pending connections.
+# TYPE snarkos_tcp_connecting gauge
+snarkos_tcp_connecting 0
+# HELP snarkos_tcp_failures_total The number of failures.
+# TYPE snarkos_tcp_failures_total counter
+snarkos_tcp_failures_total 0
+# HELP snarkos_tcp_bytes_sent_total The number of bytes sent.
+# TYPE snarkos_tcp_bytes_sent_total counter
+snarkos_tcp_bytes_sent_total 10
+# HELP snarkos_tcp_bytes_received_total The number of bytes received.
+# TYPE snarkos_tcp_bytes_received_total counter
+snarkos_tcp_bytes_received_total 20
+# HELP snarkos_tcp_peer_failures The number of failures per peer.
+# TYPE snarkos_tcp_peer_failures gauge
+snarkos_tcp_peer_failures{addr=\"1.2.3.4:4130\"} 1
+# HELP snarkos_tcp_peer_bytes_sent The number of bytes sent per peer.
+# TYPE snarkos_tcp_peer_bytes_sent gauge
+snarkos_tcp_peer_bytes_sent{addr=\"1.2.3.4:4130\"} 10
+# HELP snarkos_tcp_peer_bytes_received The number of bytes received per peer.
+# TYPE snarkos_tcp_peer_bytes_received gauge
+snarkos_tcp_peer_bytes_received{addr=\"1.2.3.4:4130\"} 20
+";
+        assert_eq!(tcp.render_prometheus_metrics(), expected);
+    }
+}
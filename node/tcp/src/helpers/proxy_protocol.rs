@@ -0,0 +1,146 @@
+// Copyright (C) 2019-2023 Aleo Systems Inc.
+// This file is part of the snarkOS library.
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at:
+// http://www.apache.org/licenses/LICENSE-2.0
+
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! A parser for the headers of version 2 of the PROXY protocol, as specified in
+//! <https://www.haproxy.org/download/2.8/doc/proxy-protocol.txt>.
+
+use std::{
+    io,
+    net::{Ipv4Addr, Ipv6Addr, SocketAddr},
+    time::Duration,
+};
+
+use tokio::io::{AsyncRead, AsyncReadExt};
+
+/// The maximum time allowed for a PROXY protocol header to be received.
+pub(crate) const HEADER_TIMEOUT: Duration = Duration::from_secs(3);
+
+/// The signature every PROXY protocol v2 header starts with.
+const SIGNATURE: [u8; 12] = *b"\r\n\r\n\0\r\nQUIT\n";
+
+/// The length of the fixed part of the header.
+const FIXED_HEADER_LEN: usize = 16;
+
+/// Reads a PROXY protocol v2 header from the given stream, returning the original source address it
+/// conveys; `None` is returned for the `LOCAL` command and for unsupported address families.
+pub(crate) async fn read_proxy_header<R: AsyncRead + Unpin>(stream: &mut R) -> io::Result<Option<SocketAddr>> {
+    let mut header = [0u8; FIXED_HEADER_LEN];
+    stream.read_exact(&mut header).await?;
+
+    let len = u16::from_be_bytes([header[14], header[15]]) as usize;
+    let mut payload = vec![0u8; len];
+    stream.read_exact(&mut payload).await?;
+
+    parse_proxy_header(&header, &payload)
+}
+
+/// Parses a PROXY protocol v2 header consisting of its fixed part and the variable-length payload.
+fn parse_proxy_header(header: &[u8; FIXED_HEADER_LEN], payload: &[u8]) -> io::Result<Option<SocketAddr>> {
+    let invalid = |msg: &str| io::Error::new(io::ErrorKind::InvalidData, format!("invalid PROXY header: {msg}"));
+
+    if header[..12] != SIGNATURE {
+        return Err(invalid("bad signature"));
+    }
+    if header[12] >> 4 != 0x2 {
+        return Err(invalid("unsupported version"));
+    }
+
+    match header[12] & 0x0F {
+        // LOCAL: the connection was established by the proxy itself.
+        0x0 => return Ok(None),
+        // PROXY: the connection is relayed on behalf of another host.
+        0x1 => {}
+        _ => return Err(invalid("unknown command")),
+    }
+
+    match header[13] {
+        // TCP over IPv4.
+        0x11 => {
+            if payload.len() < 12 {
+                return Err(invalid("truncated IPv4 addresses"));
+            }
+            let ip = Ipv4Addr::new(payload[0], payload[1], payload[2], payload[3]);
+            let port = u16::from_be_bytes([payload[8], payload[9]]);
+            Ok(Some(SocketAddr::from((ip, port))))
+        }
+        // TCP over IPv6.
+        0x21 => {
+            if payload.len() < 36 {
+                return Err(invalid("truncated IPv6 addresses"));
+            }
+            let mut octets = [0u8; 16];
+            octets.copy_from_slice(&payload[..16]);
+            let port = u16::from_be_bytes([payload[32], payload[33]]);
+            Ok(Some(SocketAddr::from((Ipv6Addr::from(octets), port))))
+        }
+        // UNSPEC, UNIX sockets, or datagrams; the source address is not usable.
+        _ => Ok(None),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn header(command: u8, family: u8, len: u16) -> [u8; FIXED_HEADER_LEN] {
+        let mut header = [0u8; FIXED_HEADER_LEN];
+        header[..12].copy_from_slice(&SIGNATURE);
+        header[12] = 0x20 | command;
+        header[13] = family;
+        header[14..].copy_from_slice(&len.to_be_bytes());
+        header
+    }
+
+    #[tokio::test]
+    async fn test_read_proxy_header_ipv4() {
+        let mut bytes = header(0x1, 0x11, 12).to_vec();
+        bytes.extend_from_slice(&[1, 2, 3, 4, 5, 6, 7, 8, 0x10, 0x22, 0x00, 0x50]);
+        // The data following the header must not be consumed.
+        bytes.extend_from_slice(b"payload");
+
+        let mut stream = &bytes[..];
+        let addr = read_proxy_header(&mut stream).await.unwrap();
+        assert_eq!(addr, Some("1.2.3.4:4130".parse().unwrap()));
+        assert_eq!(stream, b"payload");
+    }
+
+    #[test]
+    fn test_parse_proxy_header_ipv6() {
+        let mut payload = vec![0u8; 36];
+        payload[15] = 1;
+        payload[32..34].copy_from_slice(&4130u16.to_be_bytes());
+
+        let addr = parse_proxy_header(&header(0x1, 0x21, 36), &payload).unwrap();
+        assert_eq!(addr, Some("[::1]:4130".parse().unwrap()));
+    }
+
+    #[test]
+    fn test_parse_proxy_header_local() {
+        assert_eq!(parse_proxy_header(&header(0x0, 0x00, 0), &[]).unwrap(), None);
+    }
+
+    #[test]
+    fn test_parse_proxy_header_malformed() {
+        let mut bad_signature = header(0x1, 0x11, 12);
+        bad_signature[0] = 0;
+        let err = parse_proxy_header(&bad_signature, &[0u8; 12]).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+
+        let err = parse_proxy_header(&header(0x1, 0x11, 4), &[0u8; 4]).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+
+        let err = parse_proxy_header(&header(0x7, 0x11, 12), &[0u8; 12]).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
+}
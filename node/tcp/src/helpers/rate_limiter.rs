@@ -0,0 +1,181 @@
+// Copyright (C) 2019-2023 Aleo Systems Inc.
+// This file is part of the snarkOS library.
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at:
+// http://www.apache.org/licenses/LICENSE-2.0
+
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::{collections::VecDeque, time::Duration};
+
+use parking_lot::Mutex;
+use tokio::time::{sleep, Instant};
+
+/// A token bucket used to pace the traffic of a single connection; it holds up to a second's worth of bytes.
+pub struct RateLimiter {
+    /// The number of bytes replenished every second.
+    bytes_per_sec: u64,
+    /// The number of available bytes (negative if in debt), and the moment it was last updated.
+    state: Mutex<(f64, Instant)>,
+}
+
+impl RateLimiter {
+    /// Creates a full [`RateLimiter`] allowing the given number of bytes per second.
+    pub fn new(bytes_per_sec: u64) -> Self {
+        Self { bytes_per_sec, state: Mutex::new((bytes_per_sec as f64, Instant::now())) }
+    }
+
+    /// Returns the number of bytes replenished every second.
+    pub fn bytes_per_sec(&self) -> u64 {
+        self.bytes_per_sec
+    }
+
+    /// Consumes the given number of bytes, and returns the time needed for the bucket to get out of debt.
+    pub fn consume(&self, num_bytes: usize) -> Duration {
+        let rate = self.bytes_per_sec as f64;
+
+        let mut state = self.state.lock();
+        let available = self.replenish(&mut state);
+        *available -= num_bytes as f64;
+
+        if *available >= 0.0 || rate == 0.0 {
+            Duration::ZERO
+        } else {
+            Duration::from_secs_f64(-*available / rate)
+        }
+    }
+
+    /// Returns the number of bytes available right away.
+    pub fn available(&self) -> usize {
+        let mut state = self.state.lock();
+        self.replenish(&mut state).max(0.0) as usize
+    }
+
+    /// Returns the time needed for the given number of bytes (up to a second's worth) to become available.
+    pub fn time_until_available(&self, num_bytes: usize) -> Duration {
+        let rate = self.bytes_per_sec as f64;
+
+        let mut state = self.state.lock();
+        let missing = (num_bytes as f64).min(rate) - *self.replenish(&mut state);
+
+        if missing <= 0.0 || rate == 0.0 {
+            Duration::ZERO
+        } else {
+            Duration::from_secs_f64(missing / rate)
+        }
+    }
+
+    /// Consumes the given number of bytes only if they are available right away, without getting into debt;
+    /// returns `false` otherwise, leaving the bucket intact.
+    pub fn try_consume(&self, num_bytes: usize) -> bool {
+        let mut state = self.state.lock();
+        let available = self.replenish(&mut state);
+        if *available < num_bytes as f64 {
+            return false;
+        }
+        *available -= num_bytes as f64;
+        true
+    }
+
+    /// Replenishes the bucket, up to its capacity, and returns the number of available bytes.
+    fn replenish<'a>(&self, state: &'a mut (f64, Instant)) -> &'a mut f64 {
+        let now = Instant::now();
+        let (available, last_update) = state;
+        *available = (*available + now.duration_since(*last_update).as_secs_f64() * self.bytes_per_sec as f64)
+            .min(self.bytes_per_sec as f64);
+        *last_update = now;
+        available
+    }
+
+    /// Consumes the given number of bytes, waiting for the bucket to get out of debt if need be.
+    pub async fn acquire(&self, num_bytes: usize) {
+        let delay = self.consume(num_bytes);
+        if !delay.is_zero() {
+            sleep(delay).await;
+        }
+    }
+}
+
+/// Counts the events (e.g. control messages from a single peer) registered within a sliding window.
+pub struct RateCounter {
+    /// The duration of the window.
+    window: Duration,
+    /// The moments of the events registered within the window.
+    events: Mutex<VecDeque<Instant>>,
+}
+
+impl RateCounter {
+    /// Creates an empty [`RateCounter`] with the given window.
+    pub fn new(window: Duration) -> Self {
+        Self { window, events: Default::default() }
+    }
+
+    /// Registers an event, and returns the number of events registered within the window, including it.
+    pub fn register(&self) -> usize {
+        let now = Instant::now();
+        let mut events = self.events.lock();
+        while events.front().is_some_and(|moment| now.duration_since(*moment) >= self.window) {
+            events.pop_front();
+        }
+        events.push_back(now);
+        events.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test(start_paused = true)]
+    async fn test_rate_limiter() {
+        let limiter = RateLimiter::new(1_000);
+
+        // The first second's worth of bytes is available immediately.
+        let start = Instant::now();
+        limiter.acquire(1_000).await;
+        assert_eq!(start.elapsed(), Duration::ZERO);
+
+        // Further bytes are paced.
+        for _ in 0..4 {
+            limiter.acquire(500).await;
+        }
+        assert_eq!(start.elapsed().as_millis(), 2_000);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_rate_limiter_try_consume() {
+        let limiter = RateLimiter::new(2);
+
+        // The first second's worth is available immediately, but no more.
+        assert!(limiter.try_consume(1));
+        assert!(limiter.try_consume(1));
+        assert!(!limiter.try_consume(1));
+
+        // The failed attempts don't get the bucket into debt.
+        sleep(Duration::from_millis(500)).await;
+        assert!(limiter.try_consume(1));
+        assert!(!limiter.try_consume(1));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_rate_counter() {
+        let counter = RateCounter::new(Duration::from_secs(1));
+
+        // The events within the window are counted together.
+        assert_eq!(counter.register(), 1);
+        sleep(Duration::from_millis(600)).await;
+        assert_eq!(counter.register(), 2);
+
+        // The older events slide out of the window.
+        sleep(Duration::from_millis(600)).await;
+        assert_eq!(counter.register(), 2);
+        sleep(Duration::from_secs(1)).await;
+        assert_eq!(counter.register(), 1);
+    }
+}
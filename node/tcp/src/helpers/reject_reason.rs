@@ -0,0 +1,75 @@
+// Copyright (C) 2019-2023 Aleo Systems Inc.
+// This file is part of the snarkOS library.
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at:
+// http://www.apache.org/licenses/LICENSE-2.0
+
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+use std::{fmt, io};
+
+#[cfg(doc)]
+use crate::{Config, Tcp};
+
+/// The reason why [`Tcp`] would refuse to connect to an address (see [`Tcp::would_accept`]).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RejectReason {
+    /// The address belongs to the node itself.
+    SelfConnect,
+    /// The address is denied, or not allowed, by the node's configuration or at runtime.
+    Denied,
+    /// The address is not a loopback one, while [`Config::loopback_only`] is set.
+    NotLoopback,
+    /// The address is banned.
+    Banned,
+    /// The global or the per-IP connection limit is reached.
+    ConnectionLimit,
+    /// The limit of outbound connections within the address' IP prefix is reached.
+    PrefixLimit,
+    /// The address is already connected.
+    AlreadyConnected,
+    /// A connection with the address is already being set up.
+    AlreadyConnecting,
+    /// The peer didn't prove its identity during the handshake, while [`Config::require_peer_identity`] is set.
+    Unauthenticated,
+}
+
+impl RejectReason {
+    /// Returns the kind of the error returned by the connection attempts refused for this reason.
+    pub fn kind(&self) -> io::ErrorKind {
+        match self {
+            Self::SelfConnect => io::ErrorKind::AddrInUse,
+            Self::Denied | Self::NotLoopback | Self::Banned | Self::Unauthenticated => io::ErrorKind::PermissionDenied,
+            Self::ConnectionLimit | Self::PrefixLimit => io::ErrorKind::ConnectionRefused,
+            Self::AlreadyConnected | Self::AlreadyConnecting => io::ErrorKind::AlreadyExists,
+        }
+    }
+}
+
+impl fmt::Display for RejectReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let reason = match self {
+            Self::SelfConnect => "it's a self-connect",
+            Self::Denied => "the address is denied",
+            Self::NotLoopback => "the address is not a loopback one",
+            Self::Banned => "the address is banned",
+            Self::ConnectionLimit => "too many connections",
+            Self::PrefixLimit => "too many outbound connections within the IP prefix",
+            Self::AlreadyConnected => "already connected",
+            Self::AlreadyConnecting => "already connecting",
+            Self::Unauthenticated => "the peer didn't prove its identity",
+        };
+        f.write_str(reason)
+    }
+}
+
+impl From<RejectReason> for io::Error {
+    fn from(reason: RejectReason) -> Self {
+        io::Error::new(reason.kind(), reason.to_string())
+    }
+}
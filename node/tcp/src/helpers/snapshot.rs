@@ -0,0 +1,164 @@
+// Copyright (C) 2019-2023 Aleo Systems Inc.
+// This file is part of the snarkOS library.
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at:
+// http://www.apache.org/licenses/LICENSE-2.0
+
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::{collections::BTreeMap, net::SocketAddr};
+
+use serde::{Deserialize, Serialize};
+
+#[cfg(doc)]
+use crate::Tcp;
+use crate::{Connection, ConnectionSide, ConnectionStats, MessageTypeStats, NodeType, Stats};
+
+/// A serializable point-in-time view of Tcp's connections and statistics.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TcpSnapshot {
+    /// The name of the Tcp.
+    pub name: String,
+    /// The number of active connections.
+    pub num_connected: usize,
+    /// The number of pending connections.
+    pub num_connecting: usize,
+    /// The addresses of the active connections.
+    pub connected_addrs: Vec<SocketAddr>,
+    /// The addresses of the pending connections.
+    pub connecting_addrs: Vec<SocketAddr>,
+    /// The statistics of the Tcp as a whole.
+    pub stats: StatsSnapshot,
+    /// The statistics of the known peers, currently connected or not.
+    pub known_peers: Vec<PeerSnapshot>,
+}
+
+/// A serializable point-in-time view of [`Stats`].
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StatsSnapshot {
+    /// The number of all messages sent.
+    pub msgs_sent: u64,
+    /// The number of all bytes sent.
+    pub bytes_sent: u64,
+    /// The number of all messages received.
+    pub msgs_received: u64,
+    /// The number of all bytes received.
+    pub bytes_received: u64,
+    /// The number of failures.
+    pub failures: u64,
+    /// The score reflecting the behavior of a peer.
+    pub score: i32,
+    /// The latest round-trip time to a peer in milliseconds, if it was measured.
+    pub latency_ms: Option<u64>,
+    /// The latest estimate of a peer's clock offset in milliseconds, if there is one; positive if it's ahead.
+    pub clock_offset_ms: Option<i64>,
+    /// The sizes of the sent messages, per message type.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub sent_by_message_type: BTreeMap<String, MessageTypeStats>,
+    /// The sizes of the received messages, per message type.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub received_by_message_type: BTreeMap<String, MessageTypeStats>,
+}
+
+impl From<&Stats> for StatsSnapshot {
+    fn from(stats: &Stats) -> Self {
+        let (msgs_sent, bytes_sent) = stats.sent();
+        let (msgs_received, bytes_received) = stats.received();
+
+        Self {
+            msgs_sent,
+            bytes_sent,
+            msgs_received,
+            bytes_received,
+            failures: stats.failures(),
+            score: stats.score(),
+            latency_ms: stats.latency().map(|latency| latency.as_millis() as u64),
+            clock_offset_ms: stats.clock_offset_ms(),
+            sent_by_message_type: stats.sent_by_message_type().into_iter().collect(),
+            received_by_message_type: stats.received_by_message_type().into_iter().collect(),
+        }
+    }
+}
+
+/// The statistics of a single known peer.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PeerSnapshot {
+    /// The address of the peer.
+    pub addr: SocketAddr,
+    /// The statistics of the peer.
+    #[serde(flatten)]
+    pub stats: StatsSnapshot,
+}
+
+/// A serializable point-in-time view of a single connection, along with the history of the peer (see
+/// [`Tcp::peer_info`]).
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PeerInfo {
+    /// The address of the peer.
+    pub addr: SocketAddr,
+    /// The side of the peer.
+    pub side: ConnectionSide,
+    /// The type of the peer, if it is already known.
+    pub node_type: Option<NodeType>,
+    /// The label of the connection; it is empty if none was provided.
+    pub label: String,
+    /// Indicates whether the connection is fully operational.
+    pub is_ready: bool,
+    /// Indicates whether the peer is quarantined.
+    pub is_quarantined: bool,
+    /// The statistics of the connection.
+    pub connection: ConnectionStatsSnapshot,
+    /// The latest round-trip time to the peer in milliseconds, if it was measured.
+    pub latency_ms: Option<u64>,
+    /// The score reflecting the behavior of the peer.
+    pub score: i32,
+}
+
+impl From<&Connection> for PeerInfo {
+    fn from(conn: &Connection) -> Self {
+        Self {
+            addr: conn.addr(),
+            side: conn.side(),
+            node_type: conn.node_type(),
+            label: conn.label().to_owned(),
+            is_ready: conn.is_ready(),
+            is_quarantined: conn.is_quarantined(),
+            connection: conn.stats().as_ref().into(),
+            latency_ms: None,
+            score: 0,
+        }
+    }
+}
+
+/// A serializable point-in-time view of [`ConnectionStats`].
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ConnectionStatsSnapshot {
+    /// The time elapsed since the connection was established, in milliseconds.
+    pub uptime_ms: u64,
+    /// The time elapsed since the latest activity, in milliseconds.
+    pub idle_ms: u64,
+    /// The number of all bytes sent.
+    pub bytes_sent: u64,
+    /// The number of all bytes received.
+    pub bytes_received: u64,
+    /// The recent receiving rate, in bytes per second.
+    pub recv_rate: u64,
+}
+
+impl From<&ConnectionStats> for ConnectionStatsSnapshot {
+    fn from(stats: &ConnectionStats) -> Self {
+        Self {
+            uptime_ms: stats.uptime().as_millis() as u64,
+            idle_ms: stats.last_activity().elapsed().as_millis() as u64,
+            bytes_sent: stats.bytes_sent(),
+            bytes_received: stats.bytes_received(),
+            recv_rate: stats.recv_rate(),
+        }
+    }
+}
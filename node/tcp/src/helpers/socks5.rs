@@ -0,0 +1,139 @@
+// Copyright (C) 2019-2023 Aleo Systems Inc.
+// This file is part of the snarkOS library.
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at:
+// http://www.apache.org/licenses/LICENSE-2.0
+
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! The client side of the SOCKS5 protocol (RFC 1928), limited to the unauthenticated `CONNECT` command.
+
+use std::{io, net::SocketAddr};
+
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+
+const VERSION: u8 = 0x05;
+const NO_AUTH: u8 = 0x00;
+const NO_ACCEPTABLE_METHODS: u8 = 0xFF;
+const CMD_CONNECT: u8 = 0x01;
+const ATYP_IPV4: u8 = 0x01;
+const ATYP_DOMAIN: u8 = 0x03;
+const ATYP_IPV6: u8 = 0x04;
+
+/// Instructs the SOCKS5 proxy on the other end of the stream to connect to the target address; once
+/// this succeeds, the stream is relayed to the target.
+pub(crate) async fn connect<S: AsyncRead + AsyncWrite + Unpin>(stream: &mut S, target: SocketAddr) -> io::Result<()> {
+    // Negotiate the authentication method.
+    stream.write_all(&[VERSION, 1, NO_AUTH]).await?;
+    let mut reply = [0u8; 2];
+    stream.read_exact(&mut reply).await?;
+    if reply[0] != VERSION {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, "the proxy doesn't speak SOCKS5"));
+    }
+    match reply[1] {
+        NO_AUTH => {}
+        NO_ACCEPTABLE_METHODS => {
+            return Err(io::Error::new(io::ErrorKind::PermissionDenied, "the proxy requires authentication"));
+        }
+        _ => return Err(io::Error::new(io::ErrorKind::InvalidData, "the proxy chose an unsupported method")),
+    }
+
+    // Request a connection with the target address.
+    let mut request = vec![VERSION, CMD_CONNECT, 0];
+    match target {
+        SocketAddr::V4(addr) => {
+            request.push(ATYP_IPV4);
+            request.extend_from_slice(&addr.ip().octets());
+        }
+        SocketAddr::V6(addr) => {
+            request.push(ATYP_IPV6);
+            request.extend_from_slice(&addr.ip().octets());
+        }
+    }
+    request.extend_from_slice(&target.port().to_be_bytes());
+    stream.write_all(&request).await?;
+
+    // Read the reply, including the address the proxy bound to.
+    let mut reply = [0u8; 4];
+    stream.read_exact(&mut reply).await?;
+    if reply[0] != VERSION {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, "the proxy doesn't speak SOCKS5"));
+    }
+    if let Some(e) = reply_error(reply[1]) {
+        return Err(e);
+    }
+    let addr_len = match reply[3] {
+        ATYP_IPV4 => 4,
+        ATYP_IPV6 => 16,
+        ATYP_DOMAIN => stream.read_u8().await? as usize,
+        _ => return Err(io::Error::new(io::ErrorKind::InvalidData, "the proxy replied with an unknown address type")),
+    };
+    let mut bound_addr = vec![0u8; addr_len + 2];
+    stream.read_exact(&mut bound_addr).await?;
+
+    Ok(())
+}
+
+/// Maps a SOCKS5 reply code to the corresponding error, if it indicates one.
+fn reply_error(code: u8) -> Option<io::Error> {
+    let (kind, msg) = match code {
+        0x00 => return None,
+        0x01 => (io::ErrorKind::Other, "general SOCKS server failure"),
+        0x02 => (io::ErrorKind::PermissionDenied, "connection not allowed by ruleset"),
+        0x03 => (io::ErrorKind::Other, "network unreachable"),
+        0x04 => (io::ErrorKind::Other, "host unreachable"),
+        0x05 => (io::ErrorKind::ConnectionRefused, "connection refused"),
+        0x06 => (io::ErrorKind::TimedOut, "TTL expired"),
+        0x07 => (io::ErrorKind::Unsupported, "command not supported"),
+        0x08 => (io::ErrorKind::Unsupported, "address type not supported"),
+        _ => (io::ErrorKind::InvalidData, "unknown reply code"),
+    };
+
+    Some(io::Error::new(kind, format!("the SOCKS5 proxy failed to connect: {msg}")))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use tokio::io::duplex;
+
+    /// Acts as a SOCKS5 proxy replying with the given code; returns the requested target address bytes.
+    async fn mock_proxy<S: AsyncRead + AsyncWrite + Unpin>(mut stream: S, code: u8) -> Vec<u8> {
+        let mut greeting = [0u8; 3];
+        stream.read_exact(&mut greeting).await.unwrap();
+        assert_eq!(greeting, [VERSION, 1, NO_AUTH]);
+        stream.write_all(&[VERSION, NO_AUTH]).await.unwrap();
+
+        let mut request = [0u8; 10];
+        stream.read_exact(&mut request).await.unwrap();
+        stream.write_all(&[VERSION, code, 0, ATYP_IPV4, 127, 0, 0, 1, 0x10, 0x22]).await.unwrap();
+
+        request[3..].to_vec()
+    }
+
+    #[tokio::test]
+    async fn test_connect() {
+        let (mut client, proxy) = duplex(64);
+        let target: SocketAddr = "1.2.3.4:4130".parse().unwrap();
+
+        let (result, request) = tokio::join!(connect(&mut client, target), mock_proxy(proxy, 0x00));
+        result.unwrap();
+        assert_eq!(request, vec![ATYP_IPV4, 1, 2, 3, 4, 0x10, 0x22]);
+    }
+
+    #[tokio::test]
+    async fn test_connect_refused() {
+        let (mut client, proxy) = duplex(64);
+        let target: SocketAddr = "1.2.3.4:4130".parse().unwrap();
+
+        let (result, _) = tokio::join!(connect(&mut client, target), mock_proxy(proxy, 0x05));
+        assert_eq!(result.unwrap_err().kind(), io::ErrorKind::ConnectionRefused);
+    }
+}
@@ -12,9 +12,22 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use std::sync::atomic::{AtomicU64, Ordering::Relaxed};
+use std::{
+    borrow::Cow,
+    collections::HashMap,
+    ops::RangeInclusive,
+    sync::atomic::{AtomicI32, AtomicU64, Ordering::Relaxed},
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+
+use parking_lot::RwLock;
+use serde::{Deserialize, Serialize};
 
 /// Contains statistics related to Tcp.
+///
+/// The message, byte, and failure counts (including the ones per message type) and the handshake latency
+/// histogram are monotonic counters, which only ever grow until they are [`reset`](Self::reset); the score,
+/// the latency, the clock offset, and the last-seen time are gauges, which reflect the current state.
 #[derive(Default)]
 pub struct Stats {
     /// The number of all messages sent.
@@ -27,6 +40,25 @@ pub struct Stats {
     bytes_received: AtomicU64,
     /// The number of failures.
     failures: AtomicU64,
+    /// The score reflecting the behavior of a peer.
+    score: AtomicI32,
+    /// The latest round-trip time to a peer in nanoseconds; `0` if it wasn't measured.
+    latency_ns: AtomicU64,
+    /// The latest estimate of a peer's clock offset in milliseconds; positive if its clock is ahead.
+    clock_offset_ms: RwLock<Option<i64>>,
+    /// The time a peer was last heard from, in seconds since the Unix epoch; `0` if it wasn't.
+    last_seen_secs: AtomicU64,
+    /// The moment a peer was last heard from during this run, if it was.
+    last_seen_at: RwLock<Option<tokio::time::Instant>>,
+    /// The sizes of the sent messages, per message type; the map is only write-locked when a new
+    /// type is seen, while the counters are updated atomically under the read lock.
+    sent_message_types: RwLock<HashMap<Cow<'static, str>, MessageTypeCounters>>,
+    /// The sizes of the received messages, per message type; locked like `sent_message_types`.
+    received_message_types: RwLock<HashMap<Cow<'static, str>, MessageTypeCounters>>,
+    /// The durations of the successful connection establishments, including the handshakes.
+    handshake_latency: LatencyHistogram,
+    /// The number of failed connection establishments.
+    handshake_failures: AtomicU64,
 }
 
 impl Stats {
@@ -51,6 +83,78 @@ impl Stats {
         self.failures.load(Relaxed)
     }
 
+    /// Returns the score.
+    pub fn score(&self) -> i32 {
+        self.score.load(Relaxed)
+    }
+
+    /// Adjusts the score by the given delta, keeping it within the provided range.
+    pub fn adjust_score(&self, delta: i32, range: &RangeInclusive<i32>) -> i32 {
+        let clamp = |score: i32| score.saturating_add(delta).clamp(*range.start(), *range.end());
+        // The closure always returns `Some`, so this can't fail.
+        let previous = self.score.fetch_update(Relaxed, Relaxed, |score| Some(clamp(score))).unwrap_or_default();
+        clamp(previous)
+    }
+
+    /// Returns the latest measured round-trip time, if there is one.
+    pub fn latency(&self) -> Option<Duration> {
+        match self.latency_ns.load(Relaxed) {
+            0 => None,
+            nanos => Some(Duration::from_nanos(nanos)),
+        }
+    }
+
+    /// Registers a measured round-trip time.
+    pub fn register_latency(&self, latency: Duration) {
+        // The value is kept non-zero, so that it's distinguishable from the lack of a measurement.
+        let nanos = u64::try_from(latency.as_nanos()).unwrap_or(u64::MAX).max(1);
+        self.latency_ns.store(nanos, Relaxed);
+    }
+
+    /// Returns the latest estimate of the clock offset in milliseconds, if there is one; it is positive if the
+    /// peer's clock is ahead.
+    pub fn clock_offset_ms(&self) -> Option<i64> {
+        *self.clock_offset_ms.read()
+    }
+
+    /// Returns the magnitude of the latest estimate of the clock offset, if there is one.
+    pub fn clock_offset(&self) -> Option<Duration> {
+        self.clock_offset_ms().map(|offset_ms| Duration::from_millis(offset_ms.unsigned_abs()))
+    }
+
+    /// Registers an estimate of the clock offset in milliseconds.
+    pub fn register_clock_offset(&self, offset_ms: i64) {
+        *self.clock_offset_ms.write() = Some(offset_ms);
+    }
+
+    /// Returns the time a peer was last heard from, in seconds since the Unix epoch, if it was.
+    pub fn last_seen(&self) -> Option<u64> {
+        match self.last_seen_secs.load(Relaxed) {
+            0 => None,
+            secs => Some(secs),
+        }
+    }
+
+    /// Returns the moment a peer was last heard from during this run, if it was; unlike [`last_seen`](Self::last_seen),
+    /// it is monotonic and isn't restored from a previous run.
+    pub fn last_seen_at(&self) -> Option<tokio::time::Instant> {
+        *self.last_seen_at.read()
+    }
+
+    /// Registers that a peer was heard from just now.
+    pub fn register_seen(&self) {
+        let secs = SystemTime::now().duration_since(UNIX_EPOCH).map(|since| since.as_secs()).unwrap_or_default();
+        self.last_seen_secs.store(secs, Relaxed);
+        *self.last_seen_at.write() = Some(tokio::time::Instant::now());
+    }
+
+    /// Restores the failure count, the score, and the last-seen time, e.g. persisted in a previous run.
+    pub(crate) fn restore(&self, failures: u64, score: i32, last_seen: Option<u64>) {
+        self.failures.store(failures, Relaxed);
+        self.score.store(score, Relaxed);
+        self.last_seen_secs.store(last_seen.unwrap_or_default(), Relaxed);
+    }
+
     /// Registers a sent message of the provided `size` in bytes.
     pub fn register_sent_message(&self, size: usize) {
         self.msgs_sent.fetch_add(1, Relaxed);
@@ -67,4 +171,365 @@ impl Stats {
     pub fn register_failure(&self) {
         self.failures.fetch_add(1, Relaxed);
     }
+
+    /// Registers a sent message of the given type and `size` in bytes.
+    pub fn register_sent_message_type(&self, message_type: Cow<'static, str>, size: usize) {
+        register_message_type(&self.sent_message_types, message_type, size);
+    }
+
+    /// Registers a received message of the given type and `size` in bytes.
+    pub fn register_received_message_type(&self, message_type: Cow<'static, str>, size: usize) {
+        register_message_type(&self.received_message_types, message_type, size);
+    }
+
+    /// Returns the sizes of the sent messages, per message type.
+    pub fn sent_by_message_type(&self) -> HashMap<String, MessageTypeStats> {
+        load_message_types(&self.sent_message_types)
+    }
+
+    /// Returns the sizes of the received messages, per message type.
+    pub fn received_by_message_type(&self) -> HashMap<String, MessageTypeStats> {
+        load_message_types(&self.received_message_types)
+    }
+
+    /// Returns the sizes of the sent and received messages combined, per message type.
+    pub fn by_message_type(&self) -> HashMap<String, MessageTypeStats> {
+        let mut merged = self.sent_by_message_type();
+        for (name, received) in self.received_by_message_type() {
+            merged.entry(name).and_modify(|sent| *sent = sent.merge(&received)).or_insert(received);
+        }
+        merged
+    }
+
+    /// Registers the duration of a successful connection establishment, including the handshake.
+    pub fn register_handshake_latency(&self, latency: Duration) {
+        self.handshake_latency.record(latency);
+    }
+
+    /// Registers a failed connection establishment; it is not included in the handshake latencies.
+    pub fn register_handshake_failure(&self) {
+        self.handshake_failures.fetch_add(1, Relaxed);
+    }
+
+    /// Returns the number of failed connection establishments.
+    pub fn handshake_failures(&self) -> u64 {
+        self.handshake_failures.load(Relaxed)
+    }
+
+    /// Returns the percentiles of the durations of the successful connection establishments, or `None` if
+    /// there were none. As the durations are bucketed, each percentile is the upper bound of its bucket,
+    /// capped at the longest duration.
+    pub fn handshake_latency_percentiles(&self) -> Option<LatencyPercentiles> {
+        self.handshake_latency.percentiles()
+    }
+
+    /// Zeroes the monotonic counters, leaving the gauges intact. Each counter is reset atomically, so
+    /// no concurrent increment is lost; one that happens during the reset is either zeroed or kept whole.
+    pub fn reset(&self) {
+        self.msgs_sent.store(0, Relaxed);
+        self.msgs_received.store(0, Relaxed);
+        self.bytes_sent.store(0, Relaxed);
+        self.bytes_received.store(0, Relaxed);
+        self.failures.store(0, Relaxed);
+        self.sent_message_types.write().clear();
+        self.received_message_types.write().clear();
+        self.handshake_latency.reset();
+        self.handshake_failures.store(0, Relaxed);
+    }
+}
+
+/// The 50th, 90th, and 99th percentiles of a set of latencies.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct LatencyPercentiles {
+    /// The median latency.
+    pub p50: Duration,
+    /// The 90th percentile latency.
+    pub p90: Duration,
+    /// The 99th percentile latency.
+    pub p99: Duration,
+}
+
+/// A lock-free histogram of latencies, with exponentially growing buckets.
+#[derive(Default)]
+struct LatencyHistogram {
+    /// The number of samples per bucket; the last bucket holds the ones above the greatest bound.
+    buckets: [AtomicU64; LatencyHistogram::BUCKET_BOUNDS_MS.len() + 1],
+    /// The greatest sample in nanoseconds.
+    max_ns: AtomicU64,
+}
+
+impl LatencyHistogram {
+    /// The (inclusive) upper bounds of the buckets, in milliseconds.
+    const BUCKET_BOUNDS_MS: [u64; 13] = [1, 2, 5, 10, 20, 50, 100, 200, 500, 1_000, 2_000, 5_000, 10_000];
+
+    /// Records a single sample.
+    fn record(&self, latency: Duration) {
+        let index = Self::BUCKET_BOUNDS_MS
+            .iter()
+            .position(|bound_ms| latency <= Duration::from_millis(*bound_ms))
+            .unwrap_or(Self::BUCKET_BOUNDS_MS.len());
+        self.buckets[index].fetch_add(1, Relaxed);
+        self.max_ns.fetch_max(u64::try_from(latency.as_nanos()).unwrap_or(u64::MAX), Relaxed);
+    }
+
+    /// Returns the percentiles of the samples; they are capped at the greatest sample.
+    fn percentiles(&self) -> Option<LatencyPercentiles> {
+        let counts = self.buckets.iter().map(|bucket| bucket.load(Relaxed)).collect::<Vec<_>>();
+        let total = counts.iter().sum::<u64>();
+        if total == 0 {
+            return None;
+        }
+        let max = Duration::from_nanos(self.max_ns.load(Relaxed));
+
+        let percentile = |percent: u64| {
+            // The rank of the sample at the given percentile, starting at 1.
+            let rank = (total * percent).div_ceil(100).max(1);
+            let mut cumulative = 0;
+            for (index, count) in counts.iter().enumerate() {
+                cumulative += count;
+                if cumulative >= rank {
+                    return match Self::BUCKET_BOUNDS_MS.get(index) {
+                        Some(bound_ms) => Duration::from_millis(*bound_ms).min(max),
+                        None => max,
+                    };
+                }
+            }
+            max
+        };
+
+        Some(LatencyPercentiles { p50: percentile(50), p90: percentile(90), p99: percentile(99) })
+    }
+
+    /// Discards all the samples.
+    fn reset(&self) {
+        for bucket in &self.buckets {
+            bucket.store(0, Relaxed);
+        }
+        self.max_ns.store(0, Relaxed);
+    }
+}
+
+/// The sizes of the messages of a single type.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MessageTypeStats {
+    /// The number of messages.
+    pub count: u64,
+    /// The collective size of the messages in bytes.
+    pub total_bytes: u64,
+    /// The size of the smallest message in bytes.
+    pub min_size: u64,
+    /// The size of the largest message in bytes.
+    pub max_size: u64,
+}
+
+/// Registers a message of the given type and `size` in bytes in the given per-type counters.
+fn register_message_type(
+    message_types: &RwLock<HashMap<Cow<'static, str>, MessageTypeCounters>>,
+    message_type: Cow<'static, str>,
+    size: usize,
+) {
+    if let Some(counters) = message_types.read().get(&message_type) {
+        counters.register(size);
+        return;
+    }
+    message_types.write().entry(message_type).or_default().register(size);
+}
+
+/// Returns the current values of the given per-type counters.
+fn load_message_types(
+    message_types: &RwLock<HashMap<Cow<'static, str>, MessageTypeCounters>>,
+) -> HashMap<String, MessageTypeStats> {
+    message_types.read().iter().map(|(name, counters)| (name.to_string(), counters.load())).collect()
+}
+
+impl MessageTypeStats {
+    /// Combines the sizes of two sets of messages of the same type.
+    fn merge(&self, other: &Self) -> Self {
+        Self {
+            count: self.count + other.count,
+            total_bytes: self.total_bytes + other.total_bytes,
+            min_size: self.min_size.min(other.min_size),
+            max_size: self.max_size.max(other.max_size),
+        }
+    }
+}
+
+/// The atomic counters backing [`MessageTypeStats`].
+struct MessageTypeCounters {
+    count: AtomicU64,
+    total_bytes: AtomicU64,
+    min_size: AtomicU64,
+    max_size: AtomicU64,
+}
+
+impl Default for MessageTypeCounters {
+    fn default() -> Self {
+        Self {
+            count: Default::default(),
+            total_bytes: Default::default(),
+            min_size: AtomicU64::new(u64::MAX),
+            max_size: Default::default(),
+        }
+    }
+}
+
+impl MessageTypeCounters {
+    /// Registers a message of the given `size` in bytes.
+    fn register(&self, size: usize) {
+        let size = size as u64;
+        self.count.fetch_add(1, Relaxed);
+        self.total_bytes.fetch_add(size, Relaxed);
+        self.min_size.fetch_min(size, Relaxed);
+        self.max_size.fetch_max(size, Relaxed);
+    }
+
+    /// Returns the current values of the counters.
+    fn load(&self) -> MessageTypeStats {
+        let count = self.count.load(Relaxed);
+        MessageTypeStats {
+            count,
+            total_bytes: self.total_bytes.load(Relaxed),
+            min_size: if count == 0 { 0 } else { self.min_size.load(Relaxed) },
+            max_size: self.max_size.load(Relaxed),
+        }
+    }
+}
+
+/// Statistics related to a single connection.
+pub struct ConnectionStats {
+    /// The moment the connection was established.
+    connected_since: Instant,
+    /// The number of all bytes sent.
+    bytes_sent: AtomicU64,
+    /// The number of all bytes received.
+    bytes_received: AtomicU64,
+    /// The start of the current receiving rate window, in milliseconds since the connection was established.
+    recv_window_start_ms: AtomicU64,
+    /// The number of bytes received in the current receiving rate window.
+    recv_window_bytes: AtomicU64,
+    /// The receiving rate (in bytes per second) measured in the previous window.
+    recv_rate: AtomicU64,
+    /// The moment of the latest activity, in milliseconds since the connection was established.
+    last_activity_ms: AtomicU64,
+}
+
+impl Default for ConnectionStats {
+    fn default() -> Self {
+        Self {
+            connected_since: Instant::now(),
+            bytes_sent: Default::default(),
+            bytes_received: Default::default(),
+            recv_window_start_ms: Default::default(),
+            recv_window_bytes: Default::default(),
+            recv_rate: Default::default(),
+            last_activity_ms: Default::default(),
+        }
+    }
+}
+
+impl ConnectionStats {
+    /// The duration of the window the receiving rate is measured over, in milliseconds.
+    const RATE_WINDOW_MS: u64 = 1_000;
+
+    /// Returns the moment the connection was established.
+    pub fn connected_since(&self) -> Instant {
+        self.connected_since
+    }
+
+    /// Returns the time elapsed since the connection was established.
+    pub fn uptime(&self) -> Duration {
+        self.connected_since.elapsed()
+    }
+
+    /// Returns the moment the connection last sent or received any data (or was established, if it hasn't yet).
+    pub fn last_activity(&self) -> Instant {
+        self.connected_since + Duration::from_millis(self.last_activity_ms.load(Relaxed))
+    }
+
+    /// Returns the number of bytes sent via the connection.
+    pub fn bytes_sent(&self) -> u64 {
+        self.bytes_sent.load(Relaxed)
+    }
+
+    /// Returns the number of bytes received via the connection.
+    pub fn bytes_received(&self) -> u64 {
+        self.bytes_received.load(Relaxed)
+    }
+
+    /// Returns the recent receiving rate, in bytes per second.
+    pub fn recv_rate(&self) -> u64 {
+        // A window that has been stale for longer than its duration indicates a lack of traffic.
+        let now_ms = self.connected_since.elapsed().as_millis() as u64;
+        if now_ms.saturating_sub(self.recv_window_start_ms.load(Relaxed)) >= 2 * Self::RATE_WINDOW_MS {
+            0
+        } else {
+            self.recv_rate.load(Relaxed)
+        }
+    }
+
+    /// Registers the sending of `size` bytes.
+    pub fn register_sent_bytes(&self, size: usize) {
+        self.bytes_sent.fetch_add(size as u64, Relaxed);
+        self.register_activity();
+    }
+
+    /// Registers the receipt of `size` bytes.
+    pub fn register_received_bytes(&self, size: usize) {
+        self.bytes_received.fetch_add(size as u64, Relaxed);
+        self.register_activity();
+
+        // Conclude the current receiving rate window if it has elapsed.
+        let now_ms = self.connected_since.elapsed().as_millis() as u64;
+        let window_start_ms = self.recv_window_start_ms.load(Relaxed);
+        let window_ms = now_ms.saturating_sub(window_start_ms);
+        if window_ms >= Self::RATE_WINDOW_MS
+            && self.recv_window_start_ms.compare_exchange(window_start_ms, now_ms, Relaxed, Relaxed).is_ok()
+        {
+            let window_bytes = self.recv_window_bytes.swap(size as u64, Relaxed);
+            self.recv_rate.store(window_bytes * 1_000 / window_ms, Relaxed);
+        } else {
+            self.recv_window_bytes.fetch_add(size as u64, Relaxed);
+        }
+    }
+
+    /// Registers activity on the connection.
+    fn register_activity(&self) {
+        self.last_activity_ms.fetch_max(self.connected_since.elapsed().as_millis() as u64, Relaxed);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_handshake_latency_percentiles() {
+        let stats = Stats::default();
+        assert_eq!(stats.handshake_latency_percentiles(), None);
+
+        // Register 100 samples: 80 in the 5ms bucket, 15 in the 100ms bucket, and 5 above the greatest bound.
+        for _ in 0..80 {
+            stats.register_handshake_latency(Duration::from_millis(3));
+        }
+        for _ in 0..15 {
+            stats.register_handshake_latency(Duration::from_millis(70));
+        }
+        for _ in 0..5 {
+            stats.register_handshake_latency(Duration::from_secs(30));
+        }
+
+        let percentiles = stats.handshake_latency_percentiles().unwrap();
+        assert_eq!(percentiles.p50, Duration::from_millis(5));
+        assert_eq!(percentiles.p90, Duration::from_millis(100));
+        assert_eq!(percentiles.p99, Duration::from_secs(30));
+
+        // Ensure the percentiles are capped at the greatest sample.
+        let stats = Stats::default();
+        stats.register_handshake_latency(Duration::from_micros(1_500));
+        assert_eq!(stats.handshake_latency_percentiles().unwrap().p99, Duration::from_micros(1_500));
+
+        // Ensure the samples are discarded upon a reset.
+        stats.reset();
+        assert_eq!(stats.handshake_latency_percentiles(), None);
+    }
 }
@@ -0,0 +1,95 @@
+// Copyright (C) 2019-2023 Aleo Systems Inc.
+// This file is part of the snarkOS library.
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at:
+// http://www.apache.org/licenses/LICENSE-2.0
+
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+use std::borrow::Cow;
+
+use parking_lot::Mutex;
+use tokio::task::JoinHandle;
+
+#[cfg(doc)]
+use crate::Tcp;
+
+/// A handle to a task spawned by [`Tcp`], along with a descriptive name used for diagnostics.
+pub(crate) struct NamedTask {
+    /// The name of the task.
+    name: Cow<'static, str>,
+    /// The handle to the task.
+    handle: JoinHandle<()>,
+}
+
+impl NamedTask {
+    /// Attaches the given name to the handle of a task.
+    pub(crate) fn new(name: impl Into<Cow<'static, str>>, handle: JoinHandle<()>) -> Self {
+        Self { name: name.into(), handle }
+    }
+
+    /// Returns the name of the task.
+    pub(crate) fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Aborts the task.
+    pub(crate) fn abort(&self) {
+        self.handle.abort();
+    }
+}
+
+/// A collection of the long-running tasks spawned by [`Tcp`]; the finished ones are pruned whenever another one is
+/// registered, which keeps it bounded.
+#[derive(Default)]
+pub(crate) struct TaskRegistry(Mutex<Vec<NamedTask>>);
+
+impl TaskRegistry {
+    /// Registers the given task under the given name.
+    pub(crate) fn register(&self, name: impl Into<Cow<'static, str>>, handle: JoinHandle<()>) {
+        let task = NamedTask::new(name, handle);
+        let mut tasks = self.0.lock();
+        tasks.retain(|task| !task.handle.is_finished());
+        tasks.push(task);
+    }
+
+    /// Returns the names of the registered tasks.
+    pub(crate) fn names(&self) -> Vec<String> {
+        self.0.lock().iter().map(|task| task.name().to_owned()).collect()
+    }
+
+    /// Removes all the registered tasks and returns them.
+    pub(crate) fn take(&self) -> Vec<NamedTask> {
+        std::mem::take(&mut *self.0.lock())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_task_registry() {
+        let registry = TaskRegistry::default();
+
+        // Ensure the finished tasks are pruned once another one is registered.
+        let finished_task = tokio::spawn(async {});
+        while !finished_task.is_finished() {
+            tokio::task::yield_now().await;
+        }
+        registry.register("finished", finished_task);
+        registry.register("pending", tokio::spawn(std::future::pending()));
+        assert_eq!(registry.names(), vec!["pending"]);
+
+        // Ensure the tasks can be taken out in order to abort them.
+        for task in registry.take() {
+            task.abort();
+        }
+        assert!(registry.names().is_empty());
+    }
+}
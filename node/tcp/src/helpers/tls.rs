@@ -0,0 +1,128 @@
+// Copyright (C) 2019-2023 Aleo Systems Inc.
+// This file is part of the snarkOS library.
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at:
+// http://www.apache.org/licenses/LICENSE-2.0
+
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//! The optional TLS encryption of connections.
+
+use std::{
+    fmt,
+    io::{self, ErrorKind},
+    net::SocketAddr,
+    sync::Arc,
+};
+
+use tokio::net::TcpStream;
+use tokio_rustls::{
+    rustls::{
+        pki_types::{CertificateDer, PrivateKeyDer, ServerName},
+        server::WebPkiClientVerifier,
+        ClientConfig,
+        RootCertStore,
+        ServerConfig,
+    },
+    TlsAcceptor,
+    TlsConnector,
+    TlsStream,
+};
+
+use crate::ConnectionSide;
+
+/// The TLS setup of the connections: the certificate chain and the key presented to the peers, along with the
+/// certificate authorities trusted to sign the peers' certificates.
+#[derive(Clone)]
+pub struct TlsConfig {
+    /// The configuration applied when accepting connections, i.e. acting as the TLS server.
+    server: Arc<ServerConfig>,
+    /// The configuration applied when initiating connections, i.e. acting as the TLS client.
+    client: Arc<ClientConfig>,
+    /// The name the peers' certificates are verified against; if unset, their IP addresses are used.
+    server_name: Option<String>,
+}
+
+impl TlsConfig {
+    /// Creates a TLS setup presenting the given certificate chain (starting with the end-entity certificate) and the
+    /// matching key, and trusting the certificates signed by the given authorities. If `require_client_auth` is set,
+    /// the inbound connections are only accepted from the peers presenting a trusted certificate (mutual TLS).
+    pub fn new(
+        cert_chain: Vec<CertificateDer<'static>>,
+        key: PrivateKeyDer<'static>,
+        trusted_roots: Vec<CertificateDer<'static>>,
+        require_client_auth: bool,
+    ) -> io::Result<Self> {
+        let invalid_input = |e: &dyn fmt::Display| io::Error::new(ErrorKind::InvalidInput, e.to_string());
+
+        let mut roots = RootCertStore::empty();
+        for root in trusted_roots {
+            roots.add(root).map_err(|e| invalid_input(&e))?;
+        }
+        let roots = Arc::new(roots);
+
+        let server_builder = match require_client_auth {
+            true => {
+                let verifier =
+                    WebPkiClientVerifier::builder(Arc::clone(&roots)).build().map_err(|e| invalid_input(&e))?;
+                ServerConfig::builder().with_client_cert_verifier(verifier)
+            }
+            false => ServerConfig::builder().with_no_client_auth(),
+        };
+        let server =
+            server_builder.with_single_cert(cert_chain.clone(), key.clone_key()).map_err(|e| invalid_input(&e))?;
+
+        // The certificate is always presented by the client; it's ignored unless the server requests it.
+        let client = ClientConfig::builder()
+            .with_root_certificates(roots)
+            .with_client_auth_cert(cert_chain, key)
+            .map_err(|e| invalid_input(&e))?;
+
+        Ok(Self { server: Arc::new(server), client: Arc::new(client), server_name: None })
+    }
+
+    /// Verifies the peers' certificates against the given name instead of their IP addresses, e.g. if all the
+    /// nodes share a certificate issued for a common domain.
+    pub fn with_server_name(mut self, server_name: String) -> Self {
+        self.server_name = Some(server_name);
+        self
+    }
+
+    /// Performs the TLS handshake over the given stream; Tcp acts as the client on the connections it initiates,
+    /// and as the server on the ones it accepts.
+    pub(crate) async fn secure(
+        &self,
+        stream: TcpStream,
+        peer_addr: SocketAddr,
+        own_side: ConnectionSide,
+    ) -> io::Result<TlsStream<TcpStream>> {
+        match own_side {
+            ConnectionSide::Initiator => {
+                let server_name = match self.server_name {
+                    Some(ref name) => {
+                        ServerName::try_from(name.clone()).map_err(|e| io::Error::new(ErrorKind::InvalidInput, e))?
+                    }
+                    None => ServerName::from(peer_addr.ip()),
+                };
+                let stream = TlsConnector::from(Arc::clone(&self.client)).connect(server_name, stream).await?;
+                Ok(stream.into())
+            }
+            ConnectionSide::Responder => {
+                let stream = TlsAcceptor::from(Arc::clone(&self.server)).accept(stream).await?;
+                Ok(stream.into())
+            }
+        }
+    }
+}
+
+impl fmt::Debug for TlsConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // The configurations are elided, as they contain the private key.
+        f.debug_struct("TlsConfig").field("server_name", &self.server_name).finish_non_exhaustive()
+    }
+}
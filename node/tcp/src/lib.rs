@@ -20,10 +20,16 @@
 mod helpers;
 pub use helpers::*;
 
+pub use ipnetwork::IpNetwork;
+pub use tokio_rustls::rustls;
+
 pub mod protocols;
 
 mod tcp;
-pub use tcp::Tcp;
+pub use tcp::{accept_error_delay, Tcp};
+
+#[cfg(any(test, feature = "test"))]
+pub mod test_helpers;
 
 use std::net::IpAddr;
 
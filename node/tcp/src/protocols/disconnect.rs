@@ -56,7 +56,7 @@ where
             }
         });
         let _ = rx.await;
-        self.tcp().tasks.lock().push(disconnect_task);
+        self.tcp().tasks.register("disconnect handler", disconnect_task);
 
         // register the Disconnect handler with the Tcp
         let hdl = Box::new(ProtocolHandler(from_node_sender));
@@ -0,0 +1,75 @@
+// Copyright (C) 2019-2023 Aleo Systems Inc.
+// This file is part of the snarkOS library.
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at:
+// http://www.apache.org/licenses/LICENSE-2.0
+
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::{
+    any::{Any, TypeId},
+    borrow::Cow,
+    collections::HashMap,
+    io,
+    net::SocketAddr,
+    sync::Arc,
+};
+
+use async_trait::async_trait;
+use parking_lot::RwLock;
+
+#[cfg(doc)]
+use crate::{protocols::Reading, Tcp};
+
+/// A handler of the inbound messages of a specific type, registered via [`Tcp::register_message_handler`];
+/// the messages are routed to it by the default implementation of [`Reading::process_message`].
+#[async_trait]
+pub trait MessageHandler<M>: Send + Sync + 'static {
+    /// Handles a message received from the given peer.
+    async fn handle(&self, peer: SocketAddr, message: M) -> io::Result<()>;
+}
+
+/// The handlers of the messages of a single type `M`.
+pub(crate) struct MessageHandlers<M> {
+    /// The handlers registered for the given message types.
+    pub(crate) by_type: HashMap<Cow<'static, str>, Arc<dyn MessageHandler<M>>>,
+    /// The handler of the messages of the types no handler is registered for.
+    pub(crate) fallback: Option<Arc<dyn MessageHandler<M>>>,
+}
+
+impl<M> Default for MessageHandlers<M> {
+    fn default() -> Self {
+        Self { by_type: Default::default(), fallback: None }
+    }
+}
+
+/// The message handlers registered with the [`Tcp`], for all the message types `M` it's used with.
+#[derive(Default)]
+pub(crate) struct MessageRegistry(RwLock<HashMap<TypeId, Box<dyn Any + Send + Sync>>>);
+
+impl MessageRegistry {
+    /// Applies the given function to the handlers of the messages of type `M`.
+    pub(crate) fn update<M: 'static, T>(&self, f: impl FnOnce(&mut MessageHandlers<M>) -> T) -> T {
+        let mut registry = self.0.write();
+        let handlers = registry
+            .entry(TypeId::of::<M>())
+            .or_insert_with(|| Box::<MessageHandlers<M>>::default())
+            .downcast_mut::<MessageHandlers<M>>()
+            .unwrap(); // safe; the entries are keyed by their type
+        f(handlers)
+    }
+
+    /// Returns the handler for the messages of type `M` and the given message type, falling back to the
+    /// generic one; returns `None` if neither is registered.
+    pub(crate) fn handler<M: 'static>(&self, message_type: Option<&str>) -> Option<Arc<dyn MessageHandler<M>>> {
+        let registry = self.0.read();
+        let handlers = registry.get(&TypeId::of::<M>())?.downcast_ref::<MessageHandlers<M>>()?;
+        message_type.and_then(|message_type| handlers.by_type.get(message_type)).or(handlers.fallback.as_ref()).cloned()
+    }
+}
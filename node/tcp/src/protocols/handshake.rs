@@ -12,11 +12,11 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use std::{io, time::Duration};
+use std::{io, pin::pin, time::Duration};
 
+use futures_util::future::{select, Either};
 use tokio::{
     io::{split, AsyncRead, AsyncWrite},
-    net::TcpStream,
     sync::{mpsc, oneshot},
     time::timeout,
 };
@@ -25,6 +25,7 @@ use tracing::*;
 use crate::{
     protocols::{ProtocolHandler, ReturnableConnection},
     Connection,
+    Stream,
     P2P,
 };
 
@@ -54,13 +55,26 @@ where
             trace!(parent: self_clone.tcp().span(), "spawned the Handshake handler task");
             tx.send(()).unwrap(); // safe; the channel was just opened
 
-            while let Some((conn, result_sender)) = from_node_receiver.recv().await {
+            while let Some((conn, mut result_sender)) = from_node_receiver.recv().await {
                 let addr = conn.addr();
 
                 let node = self_clone.clone();
                 tokio::spawn(async move {
                     debug!(parent: node.tcp().span(), "shaking hands with {} as the {:?}", addr, !conn.side());
-                    let result = timeout(Duration::from_millis(Self::TIMEOUT_MS), node.perform_handshake(conn)).await;
+                    // if the Tcp stops awaiting the result (e.g. due to Config::handshake_timeout), drop the connection
+                    let result = {
+                        let handshake =
+                            pin!(timeout(Duration::from_millis(Self::TIMEOUT_MS), node.perform_handshake(conn)));
+                        let tcp_gave_up = pin!(result_sender.closed());
+
+                        match select(handshake, tcp_gave_up).await {
+                            Either::Left((result, _)) => result,
+                            Either::Right(_) => {
+                                error!(parent: node.tcp().span(), "handshake with {} was abandoned", addr);
+                                return;
+                            }
+                        }
+                    };
 
                     let ret = match result {
                         Ok(Ok(conn)) => {
@@ -77,15 +91,16 @@ where
                         }
                     };
 
-                    // return the Connection to the Tcp, resuming Tcp::adapt_stream
+                    // return the Connection to the Tcp, resuming Tcp::adapt_stream; if the Tcp has
+                    // stopped waiting in the meantime, the connection is simply dropped
                     if result_sender.send(ret).is_err() {
-                        unreachable!("couldn't return a Connection to the Tcp");
+                        debug!(parent: node.tcp().span(), "the Tcp is no longer awaiting the handshake with {}", addr);
                     }
                 });
             }
         });
         let _ = rx.await;
-        self.tcp().tasks.lock().push(handshake_task);
+        self.tcp().tasks.register("handshake handler", handshake_task);
 
         // register the Handshake handler with the Tcp
         let hdl = Box::new(ProtocolHandler(from_node_sender));
@@ -97,13 +112,13 @@ where
     async fn perform_handshake(&self, conn: Connection) -> io::Result<Connection>;
 
     /// Borrows the full connection stream to be used in the implementation of [`Handshake::perform_handshake`].
-    fn borrow_stream<'a>(&self, conn: &'a mut Connection) -> &'a mut TcpStream {
+    fn borrow_stream<'a>(&self, conn: &'a mut Connection) -> &'a mut Stream {
         conn.stream.as_mut().unwrap()
     }
 
     /// Assumes full control of a connection's stream in the implementation of [`Handshake::perform_handshake`], by
     /// the end of which it *must* be followed by [`Handshake::return_stream`].
-    fn take_stream(&self, conn: &mut Connection) -> TcpStream {
+    fn take_stream(&self, conn: &mut Connection) -> Stream {
         conn.stream.take().unwrap()
     }
 
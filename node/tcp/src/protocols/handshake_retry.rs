@@ -0,0 +1,113 @@
+// Copyright (C) 2019-2023 Aleo Systems Inc.
+// This file is part of the snarkOS library.
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at:
+// http://www.apache.org/licenses/LICENSE-2.0
+
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::{io, time::Duration};
+
+use tokio::time::{sleep, timeout};
+use tracing::*;
+
+use crate::{BackoffPolicy, Connection};
+
+#[cfg(doc)]
+use crate::{protocols::Handshake, Config, Tcp};
+
+/// A single attempt at a handshake, which - unlike [`Handshake::perform_handshake`] - only borrows the
+/// [`Connection`], so that it can be retried on the same stream by a [`RetryingHandshake`].
+#[async_trait::async_trait]
+pub trait HandshakeAttempt: Send + Sync {
+    /// Attempts to perform the handshake on the given connection.
+    async fn attempt(&self, conn: &mut Connection) -> io::Result<()>;
+
+    /// Returns `true` if the handshake may be attempted again after the given error.
+    ///
+    /// By default, all the errors other than the ones indicating that the stream is no longer usable are retried.
+    fn is_retryable(&self, error: &io::Error) -> bool {
+        !matches!(
+            error.kind(),
+            io::ErrorKind::UnexpectedEof
+                | io::ErrorKind::ConnectionReset
+                | io::ErrorKind::ConnectionAborted
+                | io::ErrorKind::BrokenPipe
+                | io::ErrorKind::NotConnected
+        )
+    }
+}
+
+/// An adapter retrying a failed [`HandshakeAttempt`] on the same stream, with delays determined by a
+/// [`BackoffPolicy`]; it is meant to be used in the implementation of [`Handshake::perform_handshake`].
+///
+/// note: the connection remains in the [`Tcp`]'s `connecting` set for as long as the retries are in progress,
+/// and it is only removed from it once the handshake ultimately fails (or the connection is established), so
+/// the same address can't be connected to concurrently in the meantime. On the other hand, all the attempts
+/// and the delays in between are subject to [`Handshake::TIMEOUT_MS`] and [`Config::handshake_timeout`],
+/// which should be set accordingly.
+#[derive(Clone, Debug)]
+pub struct RetryingHandshake<H> {
+    inner: H,
+    policy: BackoffPolicy,
+    attempt_timeout: Option<Duration>,
+}
+
+impl<H: HandshakeAttempt> RetryingHandshake<H> {
+    /// Creates a new adapter, making up to [`BackoffPolicy::max_attempts`] attempts at the given handshake.
+    pub fn new(inner: H, policy: BackoffPolicy) -> Self {
+        Self { inner, policy, attempt_timeout: None }
+    }
+
+    /// Sets the maximum time allowed for a single attempt; an attempt that times out is retried like a failed one.
+    ///
+    /// note: as a timed out attempt is interrupted at an arbitrary point, the implementation of
+    /// [`HandshakeAttempt::attempt`] needs to be able to resume (or restart) the handshake on the same stream.
+    pub fn with_attempt_timeout(mut self, attempt_timeout: Duration) -> Self {
+        self.attempt_timeout = Some(attempt_timeout);
+        self
+    }
+
+    /// Returns the wrapped handshake.
+    pub fn inner(&self) -> &H {
+        &self.inner
+    }
+
+    /// Performs the handshake, retrying it as long as the failures are retryable and the attempts are not exhausted.
+    pub async fn perform(&self, mut conn: Connection) -> io::Result<Connection> {
+        let mut delays = self.policy.delays();
+
+        loop {
+            let result = match self.attempt_timeout {
+                Some(attempt_timeout) => match timeout(attempt_timeout, self.inner.attempt(&mut conn)).await {
+                    Ok(result) => result,
+                    Err(_) => Err(io::ErrorKind::TimedOut.into()),
+                },
+                None => self.inner.attempt(&mut conn).await,
+            };
+
+            let err = match result {
+                Ok(()) => return Ok(conn),
+                Err(e) => e,
+            };
+
+            if !self.inner.is_retryable(&err) {
+                return Err(err);
+            }
+
+            match delays.next() {
+                Some(delay) => {
+                    debug!(parent: conn.span(), "Retrying the handshake in {}ms: {err}", delay.as_millis());
+                    sleep(delay).await;
+                }
+                None => return Err(err),
+            }
+        }
+    }
+}
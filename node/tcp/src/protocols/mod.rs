@@ -24,16 +24,21 @@ use tokio::sync::{mpsc, oneshot};
 use crate::connections::Connection;
 
 mod disconnect;
+mod dispatch;
 mod handshake;
+mod handshake_retry;
 mod on_connect;
 mod reading;
 mod writing;
 
 pub use disconnect::Disconnect;
+pub use dispatch::MessageHandler;
+pub(crate) use dispatch::MessageRegistry;
 pub use handshake::Handshake;
+pub use handshake_retry::{HandshakeAttempt, RetryingHandshake};
 pub use on_connect::OnConnect;
 pub use reading::Reading;
-pub use writing::Writing;
+pub use writing::{Priority, Writing};
 
 #[derive(Default)]
 pub(crate) struct Protocols {
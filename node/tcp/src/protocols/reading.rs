@@ -13,10 +13,19 @@
 // limitations under the License.
 
 #[cfg(doc)]
-use crate::{protocols::Handshake, Config};
+use crate::{
+    protocols::{Handshake, MessageHandler},
+    Config,
+};
 use crate::{
     protocols::{ProtocolHandler, ReturnableConnection},
     ConnectionSide,
+    ConnectionStats,
+    DisconnectReason,
+    FlowControl,
+    FlowControlCodec,
+    NamedTask,
+    RateLimiter,
     Tcp,
     P2P,
 };
@@ -24,10 +33,20 @@ use crate::{
 use async_trait::async_trait;
 use bytes::BytesMut;
 use futures_util::StreamExt;
-use std::{io, net::SocketAddr};
+use std::{
+    borrow::Cow,
+    future::Future,
+    io,
+    net::SocketAddr,
+    pin::Pin,
+    sync::Arc,
+    task::{ready, Context, Poll},
+    time::Duration,
+};
 use tokio::{
-    io::AsyncRead,
+    io::{AsyncRead, ReadBuf},
     sync::{mpsc, oneshot},
+    time::{sleep, timeout_at, Instant, Sleep},
 };
 use tokio_util::codec::{Decoder, FramedRead};
 use tracing::*;
@@ -55,6 +74,7 @@ where
     /// of the inbound message in order to only allocate it once.
     ///
     /// The default value is 1024KiB.
+    #[deprecated(note = "use `Config::read_buffer_capacity` instead; this value only applies if it's not set")]
     const INITIAL_BUFFER_SIZE: usize = 1024 * 1024;
 
     /// The final (deserialized) type of inbound messages.
@@ -82,7 +102,7 @@ where
             }
         });
         let _ = rx_reading.await;
-        self.tcp().tasks.lock().push(reading_task);
+        self.tcp().tasks.register("reading handler", reading_task);
 
         // register the Reading handler with the Tcp
         let hdl = Box::new(ProtocolHandler(conn_sender));
@@ -93,8 +113,28 @@ where
     /// The `side` param indicates the connection side **from the node's perspective**.
     fn codec(&self, addr: SocketAddr, side: ConnectionSide) -> Self::Codec;
 
+    /// Returns the type of the given message, which is used to gather the statistics per message type (see
+    /// [`Stats::received_by_message_type`](crate::Stats::received_by_message_type)); the default implementation
+    /// returns `None`, which leaves the message out of them.
+    fn message_type(_message: &Self::Message) -> Option<Cow<'static, str>> {
+        None
+    }
+
+    /// Waits until the reads from the given connection can commence; it's called once the connection is fully
+    /// ready, so it can be used to delay the reads until a custom condition is met (e.g. the node having spoken
+    /// first). The inbound messages are buffered by the OS in the meantime.
+    ///
+    /// The default implementation returns immediately.
+    async fn await_read_readiness(&self, _addr: SocketAddr) {}
+
     /// Processes an inbound message. Can be used to update state, send replies etc.
-    async fn process_message(&self, source: SocketAddr, message: Self::Message) -> io::Result<()>;
+    ///
+    /// The default implementation routes the message to the [`MessageHandler`] registered for its
+    /// [`Reading::message_type`] (see [`Tcp::register_message_handler`]).
+    async fn process_message(&self, source: SocketAddr, message: Self::Message) -> io::Result<()> {
+        let message_type = Self::message_type(&message);
+        self.tcp().dispatch_message(source, message_type, message).await
+    }
 }
 
 /// This trait is used to restrict access to methods that would otherwise be public in [`Reading`].
@@ -103,12 +143,15 @@ trait ReadingInternal: Reading {
     /// Applies the [`Reading`] protocol to a single connection.
     async fn handle_new_connection(&self, (conn, conn_returner): ReturnableConnection);
 
-    /// Wraps the user-supplied [`Decoder`] ([`Reading::Codec`]) in another one used for message accounting.
+    /// Wraps the user-supplied [`Decoder`] ([`Reading::Codec`]) in the ones used for flow control (see
+    /// [`Config::flow_control_window`]) and message accounting.
     fn map_codec<T: AsyncRead>(
         &self,
         framed: FramedRead<T, Self::Codec>,
         addr: SocketAddr,
-    ) -> FramedRead<T, CountingCodec<Self::Codec>>;
+        conn_stats: Arc<ConnectionStats>,
+        flow_control: Option<Arc<FlowControl>>,
+    ) -> FramedRead<T, CountingCodec<FlowControlCodec<Self::Codec>>>;
 }
 
 #[async_trait]
@@ -117,15 +160,23 @@ impl<R: Reading> ReadingInternal for R {
         let addr = conn.addr();
         let codec = self.codec(addr, !conn.side());
         let reader = conn.reader.take().expect("missing connection reader!");
+        // the peers continuously exceeding the receiving rate limit are only banned if the ban duration is set
+        let config = self.tcp().config();
+        let grace_period = config.recv_limit_ban_duration.map(|_| config.recv_limit_grace_period);
+        let reader = PacedReader::new(reader, conn.recv_limiter.take(), grace_period);
         let framed = FramedRead::new(reader, codec);
-        let mut framed = self.map_codec(framed, addr);
+        let flow_control = conn.flow_control.clone();
+        let mut framed = self.map_codec(framed, addr, Arc::clone(conn.stats()), flow_control.clone());
+        let conn_span = conn.span().clone();
 
         // the connection will notify the reading task once it's fully ready
         let (tx_conn_ready, rx_conn_ready) = oneshot::channel();
         conn.readiness_notifier = Some(tx_conn_ready);
 
-        if Self::INITIAL_BUFFER_SIZE != 0 {
-            framed.read_buffer_mut().reserve(Self::INITIAL_BUFFER_SIZE);
+        #[allow(deprecated)]
+        let buffer_capacity = self.tcp().config().read_buffer_capacity.unwrap_or(Self::INITIAL_BUFFER_SIZE);
+        if buffer_capacity != 0 {
+            framed.read_buffer_mut().reserve(buffer_capacity);
         }
 
         let (inbound_message_sender, mut inbound_message_receiver) = mpsc::channel(Self::MESSAGE_QUEUE_DEPTH);
@@ -135,61 +186,104 @@ impl<R: Reading> ReadingInternal for R {
 
         // the task for processing parsed messages
         let self_clone = self.clone();
+        let processing_span = conn_span.clone();
         let inbound_processing_task = tokio::spawn(async move {
             let node = self_clone.tcp();
-            trace!(parent: node.span(), "spawned a task for processing messages from {addr}");
+            trace!(parent: &processing_span, "spawned a task for processing messages from {addr}");
             tx_processing.send(()).unwrap(); // safe; the channel was just opened
 
             while let Some(msg) = inbound_message_receiver.recv().await {
                 if let Err(e) = self_clone.process_message(addr, msg).await {
-                    error!(parent: node.span(), "can't process a message from {addr}: {e}");
+                    error!(parent: &processing_span, "can't process a message from {addr}: {e}");
                     node.known_peers().register_failure(addr);
                 }
+                // the processed messages free up room in the window granted to the peer
+                if let Some(ref flow_control) = flow_control {
+                    flow_control.register_processed();
+                }
                 #[cfg(feature = "metrics")]
                 metrics::decrement_gauge(metrics::tcp::TCP_TASKS, 1f64);
             }
         });
         let _ = rx_processing.await;
-        conn.tasks.push(inbound_processing_task);
+        conn.tasks.push(NamedTask::new(format!("message processor for {addr}"), inbound_processing_task));
 
         // use a channel to know when the reader task is ready
         let (tx_reader, rx_reader) = oneshot::channel::<()>();
 
         // the task for reading messages from a stream
+        let self_clone = self.clone();
         let node = self.tcp().clone();
         let reader_task = tokio::spawn(async move {
-            trace!(parent: node.span(), "spawned a task for reading messages from {addr}");
+            trace!(parent: &conn_span, "spawned a task for reading messages from {addr}");
             tx_reader.send(()).unwrap(); // safe; the channel was just opened
 
             // postpone reads until the connection is fully established; if the process fails,
             // this task gets aborted, so there is no need for a dedicated timeout
             let _ = rx_conn_ready.await;
+            self_clone.await_read_readiness(addr).await;
+
+            let reason = loop {
+                let bytes = match node.config().io_timeout {
+                    Some(io_timeout) => {
+                        // a partially received message needs to keep making progress, while an idle connection
+                        // may wait for the next one for as long as it likes (see Config::idle_timeout)
+                        let deadline = match framed.read_buffer().is_empty() {
+                            true => Instant::now() + io_timeout,
+                            false => framed.get_ref().last_read() + io_timeout,
+                        };
+                        match timeout_at(deadline, framed.next()).await {
+                            Ok(bytes) => bytes,
+                            // no data is pending, so the connection is merely idle
+                            Err(_) if framed.read_buffer().is_empty() => continue,
+                            // some data was read in the meantime, so the connection is merely slow
+                            Err(_) if framed.get_ref().last_read() + io_timeout > Instant::now() => continue,
+                            Err(_) => {
+                                error!(parent: &conn_span, "reading from {addr} stalled mid-message; it's stuck");
+                                node.known_peers().register_failure(addr);
+                                break DisconnectReason::ReadError;
+                            }
+                        }
+                    }
+                    None => framed.next().await,
+                };
+                let Some(bytes) = bytes else {
+                    break DisconnectReason::PeerClosed;
+                };
 
-            while let Some(bytes) = framed.next().await {
                 match bytes {
                     Ok(msg) => {
                         // send the message for further processing
                         if let Err(e) = inbound_message_sender.try_send(msg) {
-                            error!(parent: node.span(), "can't process a message from {addr}: {e}");
+                            error!(parent: &conn_span, "can't process a message from {addr}: {e}");
                             node.stats().register_failure();
                         }
                         #[cfg(feature = "metrics")]
                         metrics::increment_gauge(metrics::tcp::TCP_TASKS, 1f64);
                     }
+                    Err(_) if framed.get_ref().exceeded_grace_period() => {
+                        warn!(parent: &conn_span, "{addr} keeps exceeding the receiving rate limit");
+                        if let Some(ban_duration) = node.config().recv_limit_ban_duration {
+                            // the ban also results in a disconnect
+                            node.ban_peer(addr, ban_duration).await;
+                        }
+                        return;
+                    }
                     Err(e) => {
-                        error!(parent: node.span(), "can't read from {addr}: {e}");
+                        error!(parent: &conn_span, "can't read from {addr}: {e}");
                         node.known_peers().register_failure(addr);
                         if node.config().fatal_io_errors.contains(&e.kind()) {
-                            break;
+                            break DisconnectReason::ReadError;
                         }
                     }
                 }
-            }
+            };
 
-            let _ = node.disconnect(addr).await;
+            // the connection is broken, so there is no point in flushing anything
+            let _ = node.abort_connection(addr, reason).await;
         });
         let _ = rx_reader.await;
-        conn.tasks.push(reader_task);
+        conn.tasks.push(NamedTask::new(format!("reader for {addr}"), reader_task));
 
         // return the Connection to the Tcp, resuming Tcp::adapt_stream
         if conn_returner.send(Ok(conn)).is_err() {
@@ -201,8 +295,18 @@ impl<R: Reading> ReadingInternal for R {
         &self,
         framed: FramedRead<T, Self::Codec>,
         addr: SocketAddr,
-    ) -> FramedRead<T, CountingCodec<Self::Codec>> {
-        framed.map_decoder(|codec| CountingCodec { codec, node: self.tcp().clone(), addr, acc: 0 })
+        conn_stats: Arc<ConnectionStats>,
+        flow_control: Option<Arc<FlowControl>>,
+    ) -> FramedRead<T, CountingCodec<FlowControlCodec<Self::Codec>>> {
+        let max_message_size = self.tcp().config().max_message_size;
+        framed.map_decoder(|codec| CountingCodec {
+            codec: FlowControlCodec::new(codec, flow_control, max_message_size),
+            node: self.tcp().clone(),
+            addr,
+            conn_stats,
+            message_type: Self::message_type,
+            acc: 0,
+        })
     }
 }
 
@@ -211,6 +315,9 @@ struct CountingCodec<D: Decoder> {
     codec: D,
     node: Tcp,
     addr: SocketAddr,
+    conn_stats: Arc<ConnectionStats>,
+    /// Returns the type of a decoded message.
+    message_type: fn(&D::Item) -> Option<Cow<'static, str>>,
     acc: usize,
 }
 
@@ -231,6 +338,10 @@ impl<D: Decoder> Decoder for CountingCodec<D> {
                 self.acc = 0;
                 self.node.known_peers().register_received_message(self.addr, read_len);
                 self.node.stats().register_received_message(read_len);
+                self.conn_stats.register_received_bytes(read_len);
+                if let Some(message_type) = ret.as_ref().and_then(self.message_type) {
+                    self.node.stats().register_received_message_type(message_type, read_len);
+                }
             } else {
                 self.acc = read_len;
             }
@@ -239,3 +350,99 @@ impl<D: Decoder> Decoder for CountingCodec<D> {
         Ok(ret)
     }
 }
+
+/// Paces the reads from a connection according to its receiving rate limit (see [`Config::max_recv_bytes_per_sec`]);
+/// each read is bounded by the number of bytes the limiter allows at the moment, so that the peer's excess traffic
+/// is left in the socket, applying backpressure to the connection. It also keeps track of the moment of the last
+/// successful read, so that stuck connections can be told apart from slow ones (see [`Config::io_timeout`]).
+struct PacedReader<R> {
+    inner: R,
+    /// The moment any bytes were last read.
+    last_read: Instant,
+    limiter: Option<RateLimiter>,
+    /// The time the peer is allowed to continuously exceed the limit for, if it's enforced.
+    grace_period: Option<Duration>,
+    /// Resolves once the limiter allows more bytes to be read.
+    delay: Option<Pin<Box<Sleep>>>,
+    /// The moment since which the peer has been continuously exceeding the limit.
+    saturated_since: Option<Instant>,
+}
+
+impl<R> PacedReader<R> {
+    fn new(inner: R, limiter: Option<RateLimiter>, grace_period: Option<Duration>) -> Self {
+        // a limit of 0 bytes per second is treated as no limit at all, just like in the rate limiter itself
+        let limiter = limiter.filter(|limiter| limiter.bytes_per_sec() != 0);
+        Self { inner, last_read: Instant::now(), limiter, grace_period, delay: None, saturated_since: None }
+    }
+
+    /// Returns the moment any bytes were last read.
+    fn last_read(&self) -> Instant {
+        self.last_read
+    }
+
+    /// Returns `true` if the peer has been exceeding the limit for longer than the grace period.
+    fn exceeded_grace_period(&self) -> bool {
+        match (self.saturated_since, self.grace_period) {
+            (Some(since), Some(grace_period)) => since.elapsed() > grace_period,
+            _ => false,
+        }
+    }
+}
+
+impl<R: AsyncRead + Unpin> AsyncRead for PacedReader<R> {
+    fn poll_read(self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &mut ReadBuf<'_>) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        let Some(limiter) = &this.limiter else {
+            let num_filled = buf.filled().len();
+            ready!(Pin::new(&mut this.inner).poll_read(cx, buf))?;
+            if buf.filled().len() > num_filled {
+                this.last_read = Instant::now();
+            }
+            return Poll::Ready(Ok(()));
+        };
+
+        loop {
+            if let Some(delay) = this.delay.as_mut() {
+                ready!(delay.as_mut().poll(cx));
+                this.delay = None;
+            }
+            if this.exceeded_grace_period() {
+                return Poll::Ready(Err(io::Error::new(io::ErrorKind::Other, "the receiving rate limit was exceeded")));
+            }
+
+            let available = limiter.available();
+            if available == 0 {
+                // the peer has more to send than the limit allows; wait for a tenth of a second's worth of bytes,
+                // so that the reads are neither too small nor too bursty
+                this.saturated_since.get_or_insert_with(Instant::now);
+                let chunk = (limiter.bytes_per_sec() / 10).max(1) as usize;
+                this.delay = Some(Box::pin(sleep(limiter.time_until_available(buf.remaining().min(chunk)))));
+                continue;
+            }
+
+            let limit = available.min(buf.remaining());
+            let mut limited = ReadBuf::new(buf.initialize_unfilled_to(limit));
+            let result = Pin::new(&mut this.inner).poll_read(cx, &mut limited);
+            let num_read = limited.filled().len();
+            match result {
+                Poll::Ready(Ok(())) => {
+                    buf.advance(num_read);
+                    limiter.consume(num_read);
+                    if num_read != 0 {
+                        this.last_read = Instant::now();
+                    }
+                    // the socket was drained, so the peer doesn't exceed the limit (anymore)
+                    if num_read < limit {
+                        this.saturated_since = None;
+                    }
+                    return Poll::Ready(Ok(()));
+                }
+                Poll::Ready(Err(e)) => return Poll::Ready(Err(e)),
+                Poll::Pending => {
+                    this.saturated_since = None;
+                    return Poll::Pending;
+                }
+            }
+        }
+    }
+}
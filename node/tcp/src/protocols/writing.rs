@@ -12,14 +12,26 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use std::{any::Any, collections::HashMap, io, net::SocketAddr, sync::Arc};
+use std::{
+    any::Any,
+    borrow::Cow,
+    collections::HashMap,
+    future::Future,
+    io,
+    net::SocketAddr,
+    pin::Pin,
+    sync::Arc,
+    task::{Context, Poll},
+    time::Duration,
+};
 
 use async_trait::async_trait;
-use futures_util::sink::SinkExt;
-use parking_lot::RwLock;
+use futures_util::{future, sink::SinkExt, FutureExt};
+use parking_lot::{Mutex, RwLock};
 use tokio::{
-    io::AsyncWrite,
+    io::{AsyncWrite, AsyncWriteExt},
     sync::{mpsc, oneshot},
+    time::{sleep, timeout, Sleep},
 };
 use tokio_util::codec::{Encoder, FramedWrite};
 use tracing::*;
@@ -30,10 +42,26 @@ use crate::{
     protocols::{Protocol, ProtocolHandler, ReturnableConnection},
     Connection,
     ConnectionSide,
+    DisconnectReason,
+    FlowControl,
+    FlowControlCodec,
+    Frame,
+    NamedTask,
     P2P,
 };
 
-type WritingSenders = Arc<RwLock<HashMap<SocketAddr, mpsc::Sender<WrappedMessage>>>>;
+type WritingSenders = Arc<RwLock<HashMap<SocketAddr, QueueSenders>>>;
+
+/// The priority of an outbound message; high-priority messages are sent ahead of the low-priority ones
+/// queued for the same connection.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum Priority {
+    /// Messages that mustn't be delayed by bulk traffic, e.g. the ones related to consensus.
+    High,
+    /// All the other messages, e.g. gossip.
+    #[default]
+    Low,
+}
 
 /// Can be used to specify and enable writing, i.e. sending outbound messages. If the [`Handshake`]
 /// protocol is enabled too, it goes into force only after the handshake has been concluded.
@@ -49,6 +77,12 @@ where
     /// The default value is 1024.
     const MESSAGE_QUEUE_DEPTH: usize = 1024;
 
+    /// The maximum number of high-priority messages sent in a row while there are low-priority messages queued
+    /// for the same connection; it ensures that the low-priority traffic can't be starved indefinitely.
+    ///
+    /// The default value is 16.
+    const HIGH_PRIORITY_BURST: usize = 16;
+
     /// The type of the outbound messages; unless their serialization is expensive and the message
     /// is broadcasted (in which case it would get serialized multiple times), serialization should
     /// be done in the implementation of [`Self::Codec`].
@@ -81,10 +115,11 @@ where
             }
         });
         let _ = rx_writing.await;
-        self.tcp().tasks.lock().push(writing_task);
+        self.tcp().tasks.register("writing handler", writing_task);
 
         // register the WritingHandler with the Tcp
-        let hdl = Box::new(WritingHandler { handler: ProtocolHandler(conn_sender), senders });
+        let hdl =
+            Box::new(WritingHandler { handler: ProtocolHandler(conn_sender), senders, warmup: Default::default() });
         assert!(self.tcp().protocols.writing.set(hdl).is_ok(), "the Writing protocol was enabled more than once!");
     }
 
@@ -92,40 +127,119 @@ where
     /// The `side` param indicates the connection side **from the node's perspective**.
     fn codec(&self, addr: SocketAddr, side: ConnectionSide) -> Self::Codec;
 
-    /// Sends the provided message to the specified [`SocketAddr`]. Returns as soon as the message is queued to
-    /// be sent, without waiting for the actual delivery; instead, the caller is provided with a [`oneshot::Receiver`]
-    /// which can be used to determine when and whether the message has been delivered.
+    /// Returns the type of the given message, which is used to gather the statistics per message type (see
+    /// [`Stats::sent_by_message_type`](crate::Stats::sent_by_message_type)); the default implementation
+    /// returns `None`, which leaves the message out of them.
+    fn message_type(_message: &Self::Message) -> Option<Cow<'static, str>> {
+        None
+    }
+
+    /// Sends the provided message to the specified [`SocketAddr`] with [`Priority::Low`]. Returns as soon as the
+    /// message is queued to be sent, without waiting for the actual delivery; instead, the caller is provided with
+    /// a [`oneshot::Receiver`] which can be used to determine when and whether the message has been delivered.
     ///
     /// # Errors
     ///
     /// The following errors can be returned:
     /// - [`io::ErrorKind::NotConnected`] if the node is not connected to the provided address
-    /// - [`io::ErrorKind::Other`] if the outbound message queue for this address is full
+    /// - [`io::ErrorKind::WouldBlock`] if the outbound message queue for this address is full; in order to wait
+    ///   for it to free up instead, use [`Writing::unicast_with_backpressure`]. It is also returned if the warmup
+    ///   buffer of a connection that is still being set up is full (see [`Config::warmup_buffer_size`])
     /// - [`io::ErrorKind::Unsupported`] if [`Writing::enable_writing`] hadn't been called yet
     fn unicast(&self, addr: SocketAddr, message: Self::Message) -> io::Result<oneshot::Receiver<io::Result<()>>> {
-        // access the protocol handler
-        if let Some(handler) = self.tcp().protocols.writing.get() {
-            // find the message sender for the given address
-            if let Some(sender) = handler.senders.read().get(&addr).cloned() {
-                let (msg, delivery) = WrappedMessage::new(Box::new(message));
-                sender
-                    .try_send(msg)
-                    .map_err(|e| {
-                        error!(parent: self.tcp().span(), "can't send a message to {}: {}", addr, e);
-                        self.tcp().stats().register_failure();
-                        io::ErrorKind::Other.into()
-                    })
-                    .map(|_| delivery)
-            } else {
-                Err(io::ErrorKind::NotConnected.into())
-            }
-        } else {
-            Err(io::ErrorKind::Unsupported.into())
+        self.unicast_with_priority(addr, message, Priority::Low)
+    }
+
+    /// Sends the provided message to the specified [`SocketAddr`] using the queue of the given [`Priority`].
+    /// Other than that, it works just like [`Writing::unicast`].
+    fn unicast_with_priority(
+        &self,
+        addr: SocketAddr,
+        message: Self::Message,
+        priority: Priority,
+    ) -> io::Result<oneshot::Receiver<io::Result<()>>> {
+        let (msg, delivery) = WrappedMessage::new(Box::new(message));
+        // the messages to connections that are still being set up may be buffered until they're ready
+        let Some(msg) = self.buffer_warmup_message(addr, priority, msg)? else {
+            return Ok(delivery);
+        };
+        // find the message sender for the given address
+        let sender = self.writing_sender(addr, priority)?;
+        sender
+            .try_send(msg)
+            .map_err(|e| {
+                error!(parent: self.tcp().span(), "can't send a message to {}: {}", addr, e);
+                self.tcp().stats().register_failure();
+                match e {
+                    mpsc::error::TrySendError::Full(_) => io::ErrorKind::WouldBlock.into(),
+                    mpsc::error::TrySendError::Closed(_) => io::ErrorKind::NotConnected.into(),
+                }
+            })
+            .map(|_| delivery)
+    }
+
+    /// Sends the provided message to the specified [`SocketAddr`] with [`Priority::Low`], waiting for the outbound
+    /// message queue for this address to free up if it's full. Other than that, it works just like
+    /// [`Writing::unicast`].
+    ///
+    /// # Errors
+    ///
+    /// The following errors can be returned:
+    /// - [`io::ErrorKind::NotConnected`] if the node is not (or no longer) connected to the provided address
+    /// - [`io::ErrorKind::Unsupported`] if [`Writing::enable_writing`] hadn't been called yet
+    async fn unicast_with_backpressure(
+        &self,
+        addr: SocketAddr,
+        message: Self::Message,
+    ) -> io::Result<oneshot::Receiver<io::Result<()>>> {
+        self.unicast_with_backpressure_and_priority(addr, message, Priority::Low).await
+    }
+
+    /// Sends the provided message to the specified [`SocketAddr`] using the queue of the given [`Priority`], waiting
+    /// for it to free up if it's full. Other than that, it works just like [`Writing::unicast_with_backpressure`].
+    async fn unicast_with_backpressure_and_priority(
+        &self,
+        addr: SocketAddr,
+        message: Self::Message,
+        priority: Priority,
+    ) -> io::Result<oneshot::Receiver<io::Result<()>>> {
+        let (msg, delivery) = WrappedMessage::new(Box::new(message));
+        // the messages to connections that are still being set up may be buffered until they're ready
+        let Some(msg) = self.buffer_warmup_message(addr, priority, msg)? else {
+            return Ok(delivery);
+        };
+        // find the message sender for the given address
+        let sender = self.writing_sender(addr, priority)?;
+        sender.send(msg).await.map_err(|_| io::Error::from(io::ErrorKind::NotConnected))?;
+
+        Ok(delivery)
+    }
+
+    /// Sends the provided message to the specified [`SocketAddr`] with [`Priority::High`], waits for it to be
+    /// flushed to the stream (but no longer than `flush_timeout`), and then disconnects from it. Returns `true`
+    /// if the node was connected to the provided address.
+    ///
+    /// This is meant to be used to let the peer know why it's being disconnected; a wedged peer can't delay
+    /// the teardown by more than `flush_timeout`.
+    async fn disconnect_with_message(&self, addr: SocketAddr, message: Self::Message, flush_timeout: Duration) -> bool {
+        match self.unicast_with_priority(addr, message, Priority::High) {
+            Ok(delivery) => match timeout(flush_timeout, delivery).await {
+                Ok(Ok(Ok(()))) => {}
+                Ok(Ok(Err(e))) => {
+                    debug!(parent: self.tcp().span(), "couldn't flush the final message to {}: {}", addr, e)
+                }
+                Ok(Err(_)) => debug!(parent: self.tcp().span(), "the final message to {} was dropped", addr),
+                Err(_) => debug!(parent: self.tcp().span(), "timed out flushing the final message to {}", addr),
+            },
+            Err(e) => debug!(parent: self.tcp().span(), "couldn't send the final message to {}: {}", addr, e),
         }
+
+        self.tcp().disconnect(addr).await
     }
 
-    /// Broadcasts the provided message to all connected peers. Returns as soon as the message is queued to
-    /// be sent to all the peers, without waiting for the actual delivery. This method doesn't provide the
+    /// Broadcasts the provided message to all connected peers with [`Priority::Low`], save for the quarantined ones
+    /// (see [`Tcp::quarantine_peer`]). Returns as soon as the message is queued to be sent to all the peers, without
+    /// waiting for the actual delivery. This method doesn't provide the
     /// means to check when and if the messages actually get delivered; you can achieve that by calling
     /// [`Writing::unicast`] for each address returned by [`Tcp::connected_addrs`].
     ///
@@ -133,15 +247,25 @@ where
     ///
     /// Returns [`io::ErrorKind::Unsupported`] if [`Writing::enable_writing`] hadn't been called yet.
     fn broadcast(&self, message: Self::Message) -> io::Result<()>
+    where
+        Self::Message: Clone,
+    {
+        self.broadcast_with_priority(message, Priority::Low)
+    }
+
+    /// Broadcasts the provided message to all connected peers using the queues of the given [`Priority`]. Other than
+    /// that, it works just like [`Writing::broadcast`].
+    fn broadcast_with_priority(&self, message: Self::Message, priority: Priority) -> io::Result<()>
     where
         Self::Message: Clone,
     {
         // access the protocol handler
         if let Some(handler) = self.tcp().protocols.writing.get() {
             let senders = handler.senders.read().clone();
-            for (addr, message_sender) in senders {
+            let quarantined = self.tcp().quarantined_peers();
+            for (addr, message_senders) in senders.into_iter().filter(|(addr, _)| !quarantined.contains(addr)) {
                 let (msg, _delivery) = WrappedMessage::new(Box::new(message.clone()));
-                let _ = message_sender.try_send(msg).map_err(|e| {
+                let _ = message_senders.get(priority).try_send(msg).map_err(|e| {
                     error!(parent: self.tcp().span(), "can't send a message to {}: {}", addr, e);
                     self.tcp().stats().register_failure();
                 });
@@ -157,12 +281,32 @@ where
 /// This trait is used to restrict access to methods that would otherwise be public in [`Writing`].
 #[async_trait]
 trait WritingInternal: Writing {
+    /// Returns the sender of the outbound message queue of the given priority for the given address.
+    fn writing_sender(&self, addr: SocketAddr, priority: Priority) -> io::Result<mpsc::Sender<WrappedMessage>>;
+
+    /// Buffers the given message if [`Config::warmup_buffer_size`] is set and the connection with the given address
+    /// is still being set up (or already has buffered messages); returns the message back otherwise.
+    fn buffer_warmup_message(
+        &self,
+        addr: SocketAddr,
+        priority: Priority,
+        msg: WrappedMessage,
+    ) -> io::Result<Option<WrappedMessage>>;
+
     /// Writes the given message to the network stream and returns the number of written bytes.
     async fn write_to_stream<W: AsyncWrite + Unpin + Send>(
         &self,
         message: Self::Message,
-        writer: &mut FramedWrite<W, Self::Codec>,
-    ) -> Result<usize, <Self::Codec as Encoder<Self::Message>>::Error>;
+        writer: &mut FramedWrite<W, FlowControlCodec<Self::Codec>>,
+    ) -> io::Result<usize>;
+
+    /// Waits for a credit granted by the peer (see [`Config::flow_control_window`]); the credits due to the peer are
+    /// still granted in the meantime, so that both sides can't end up waiting for each other.
+    async fn acquire_send_credit<W: AsyncWrite + Unpin + Send>(
+        &self,
+        flow_control: &FlowControl,
+        writer: &mut FramedWrite<W, FlowControlCodec<Self::Codec>>,
+    ) -> io::Result<()>;
 
     /// Applies the [`Writing`] protocol to a single connection.
     async fn handle_new_connection(&self, (conn, conn_returner): ReturnableConnection, conn_senders: &WritingSenders);
@@ -170,18 +314,71 @@ trait WritingInternal: Writing {
 
 #[async_trait]
 impl<W: Writing> WritingInternal for W {
+    fn writing_sender(&self, addr: SocketAddr, priority: Priority) -> io::Result<mpsc::Sender<WrappedMessage>> {
+        // access the protocol handler
+        let handler = self.tcp().protocols.writing.get().ok_or(io::ErrorKind::Unsupported)?;
+        let sender = handler.senders.read().get(&addr).map(|senders| senders.get(priority).clone());
+        sender.ok_or_else(|| io::ErrorKind::NotConnected.into())
+    }
+
+    fn buffer_warmup_message(
+        &self,
+        addr: SocketAddr,
+        priority: Priority,
+        msg: WrappedMessage,
+    ) -> io::Result<Option<WrappedMessage>> {
+        // access the protocol handler
+        let handler = self.tcp().protocols.writing.get().ok_or(io::ErrorKind::Unsupported)?;
+        let Some(capacity) = self.tcp().config().warmup_buffer_size else {
+            return Ok(Some(msg));
+        };
+
+        let mut warmup = handler.warmup.lock();
+        // once the messages are buffered for a connection, the following ones are too, so that the order is retained
+        if !warmup.contains_key(&addr) && (self.tcp().is_connected(addr) || !self.tcp().is_connecting(addr)) {
+            return Ok(Some(msg));
+        }
+        let buffer = warmup.entry(addr).or_default();
+        if buffer.overflowed || buffer.messages.len() >= capacity {
+            if !buffer.overflowed {
+                warn!(parent: self.tcp().span(), "the warmup buffer for {addr} overflowed");
+                buffer.overflowed = true;
+                // dropping the buffered messages fails their delivery
+                buffer.messages.clear();
+            }
+            return Err(io::ErrorKind::WouldBlock.into());
+        }
+        buffer.messages.push((priority, msg));
+
+        Ok(None)
+    }
+
     async fn write_to_stream<A: AsyncWrite + Unpin + Send>(
         &self,
         message: Self::Message,
-        writer: &mut FramedWrite<A, Self::Codec>,
-    ) -> Result<usize, <Self::Codec as Encoder<Self::Message>>::Error> {
-        writer.feed(message).await?;
+        writer: &mut FramedWrite<A, FlowControlCodec<Self::Codec>>,
+    ) -> io::Result<usize> {
+        writer.feed(Frame::Data(message)).await?;
         let len = writer.write_buffer().len();
         writer.flush().await?;
 
         Ok(len)
     }
 
+    async fn acquire_send_credit<A: AsyncWrite + Unpin + Send>(
+        &self,
+        flow_control: &FlowControl,
+        writer: &mut FramedWrite<A, FlowControlCodec<Self::Codec>>,
+    ) -> io::Result<()> {
+        loop {
+            tokio::select! {
+                biased;
+                credits = flow_control.take_grant() => writer.send(Frame::WindowUpdate(credits)).await?,
+                _ = flow_control.acquire_send_credit() => return Ok(()),
+            }
+        }
+    }
+
     async fn handle_new_connection(
         &self,
         (mut conn, conn_returner): ReturnableConnection,
@@ -189,13 +386,25 @@ impl<W: Writing> WritingInternal for W {
     ) {
         let addr = conn.addr();
         let codec = self.codec(addr, !conn.side());
-        let writer = conn.writer.take().expect("missing connection writer!");
-        let mut framed = FramedWrite::new(writer, codec);
-
-        let (outbound_message_sender, mut outbound_message_receiver) = mpsc::channel(Self::MESSAGE_QUEUE_DEPTH);
-
-        // register the connection's message sender with the Writing protocol handler
-        conn_senders.write().insert(addr, outbound_message_sender);
+        // the writes that stop making progress are failed, so that a stuck connection doesn't linger
+        let writer =
+            StallGuard::new(conn.writer.take().expect("missing connection writer!"), self.tcp().config().io_timeout);
+        let flow_control = conn.flow_control.clone();
+        let max_message_size = self.tcp().config().max_message_size;
+        let mut framed = FramedWrite::new(writer, FlowControlCodec::new(codec, flow_control.clone(), max_message_size));
+        let conn_stats = Arc::clone(conn.stats());
+        let send_limiter = conn.send_limiter.take();
+        let conn_span = conn.span().clone();
+
+        let (high_priority_sender, high_priority_receiver) = mpsc::channel(Self::MESSAGE_QUEUE_DEPTH);
+        let (low_priority_sender, low_priority_receiver) = mpsc::channel(Self::MESSAGE_QUEUE_DEPTH);
+
+        // used to flush the queued messages and close the write side of the stream upon a clean disconnect
+        let (closer, close_receiver) = oneshot::channel::<oneshot::Sender<()>>();
+        conn.writer_closer = Some(closer);
+
+        // register the connection's message senders with the Writing protocol handler
+        conn_senders.write().insert(addr, QueueSenders { high: high_priority_sender, low: low_priority_sender });
 
         // this will automatically drop the sender upon a disconnect
         let auto_cleanup = SenderCleanup { addr, senders: Arc::clone(conn_senders) };
@@ -207,38 +416,110 @@ impl<W: Writing> WritingInternal for W {
         let self_clone = self.clone();
         let writer_task = tokio::spawn(async move {
             let node = self_clone.tcp();
-            trace!(parent: node.span(), "spawned a task for writing messages to {}", addr);
+            trace!(parent: &conn_span, "spawned a task for writing messages to {}", addr);
             tx_writer.send(()).unwrap(); // safe; the channel was just opened
 
             // move the cleanup into the task that gets aborted on disconnect
             let _auto_cleanup = auto_cleanup;
-
-            while let Some(wrapped_msg) = outbound_message_receiver.recv().await {
-                let msg = wrapped_msg.msg.downcast().unwrap();
+            // the messages still queued once the task ends are failed
+            let mut high_priority_receiver = QueueDrain(high_priority_receiver);
+            let mut low_priority_receiver = QueueDrain(low_priority_receiver);
+
+            // the number of high-priority messages sent in a row
+            let mut num_high_priority = 0;
+
+            // the close request is only handled once both queues are drained
+            let mut close_receiver = close_receiver.fuse();
+
+            let reason = loop {
+                let wrapped_msg = if num_high_priority >= Self::HIGH_PRIORITY_BURST {
+                    // give the low-priority queue a turn
+                    num_high_priority = 0;
+                    match low_priority_receiver.0.try_recv() {
+                        Ok(wrapped_msg) => wrapped_msg,
+                        Err(_) => continue,
+                    }
+                } else {
+                    tokio::select! {
+                        biased;
+                        credits = take_grant(flow_control.as_deref()) => {
+                            // grant the credits for the messages processed in the meantime to the peer
+                            if let Err(e) = framed.send(Frame::WindowUpdate(credits)).await {
+                                error!(parent: &conn_span, "couldn't grant flow control credits to {addr}: {e}");
+                                break DisconnectReason::WriteError;
+                            }
+                            continue;
+                        }
+                        Some(wrapped_msg) = high_priority_receiver.0.recv() => {
+                            num_high_priority += 1;
+                            wrapped_msg
+                        }
+                        Some(wrapped_msg) = low_priority_receiver.0.recv() => {
+                            num_high_priority = 0;
+                            wrapped_msg
+                        }
+                        Ok(ack) = &mut close_receiver => {
+                            // the connection is being dropped cleanly, so the disconnect is already in progress
+                            if let Err(e) = framed.get_mut().shutdown().await {
+                                debug!(parent: &conn_span, "couldn't half-close the stream to {addr}: {e}");
+                            }
+                            let _ = ack.send(());
+                            return;
+                        }
+                        else => break DisconnectReason::Local,
+                    }
+                };
+                let msg: Box<Self::Message> = wrapped_msg.msg.downcast().unwrap();
+                let message_type = Self::message_type(&msg);
+
+                // if the peer is subject to flow control, wait until it allows more messages
+                if let Some(ref flow_control) = flow_control {
+                    if let Err(e) = self_clone.acquire_send_credit(flow_control, &mut framed).await {
+                        error!(parent: &conn_span, "couldn't grant flow control credits to {addr}: {e}");
+                        let _ = wrapped_msg.delivery_notification.send(Err(e));
+                        break DisconnectReason::WriteError;
+                    }
+                }
 
                 match self_clone.write_to_stream(*msg, &mut framed).await {
                     Ok(len) => {
                         let _ = wrapped_msg.delivery_notification.send(Ok(()));
                         node.known_peers().register_sent_message(addr, len);
                         node.stats().register_sent_message(len);
-                        trace!(parent: node.span(), "sent {}B to {}", len, addr);
+                        conn_stats.register_sent_bytes(len);
+                        if let Some(message_type) = message_type {
+                            node.stats().register_sent_message_type(message_type, len);
+                        }
+                        trace!(parent: &conn_span, "sent {}B to {}", len, addr);
+
+                        // if the sending rate is limited, postpone the next write accordingly
+                        if let Some(ref limiter) = send_limiter {
+                            limiter.acquire(len).await;
+                        }
+                    }
+                    Err(e) if e.kind() == io::ErrorKind::TimedOut && node.config().io_timeout.is_some() => {
+                        node.known_peers().register_failure(addr);
+                        error!(parent: &conn_span, "writing to {addr} stalled; the connection is stuck");
+                        let _ = wrapped_msg.delivery_notification.send(Err(e));
+                        break DisconnectReason::WriteError;
                     }
                     Err(e) => {
                         node.known_peers().register_failure(addr);
-                        error!(parent: node.span(), "couldn't send a message to {}: {}", addr, e);
+                        error!(parent: &conn_span, "couldn't send a message to {}: {}", addr, e);
                         let is_fatal = node.config().fatal_io_errors.contains(&e.kind());
                         let _ = wrapped_msg.delivery_notification.send(Err(e));
                         if is_fatal {
-                            break;
+                            break DisconnectReason::WriteError;
                         }
                     }
                 }
-            }
+            };
 
-            node.disconnect(addr).await;
+            // the connection is broken, so there is no point in flushing anything
+            node.abort_connection(addr, reason).await;
         });
         let _ = rx_writer.await;
-        conn.tasks.push(writer_task);
+        conn.tasks.push(NamedTask::new(format!("writer for {addr}"), writer_task));
 
         // return the Connection to the Tcp, resuming Tcp::adapt_stream
         if conn_returner.send(Ok(conn)).is_err() {
@@ -247,6 +528,14 @@ impl<W: Writing> WritingInternal for W {
     }
 }
 
+/// Waits until there are flow control credits to be granted to the peer, if it's subject to flow control.
+async fn take_grant(flow_control: Option<&FlowControl>) -> u32 {
+    match flow_control {
+        Some(flow_control) => flow_control.take_grant().await,
+        None => future::pending().await,
+    }
+}
+
 /// Used to queue messages for delivery.
 struct WrappedMessage {
     msg: Box<dyn Any + Send>,
@@ -262,10 +551,60 @@ impl WrappedMessage {
     }
 }
 
+/// The outbound messages buffered for a connection that is still being set up (see [`Config::warmup_buffer_size`]).
+#[derive(Default)]
+struct WarmupBuffer {
+    /// The buffered messages, in the order they were sent in.
+    messages: Vec<(Priority, WrappedMessage)>,
+    /// Set if more messages were sent than the buffer can hold.
+    overflowed: bool,
+}
+
 /// The handler object dedicated to the [`Writing`] protocol.
 pub(crate) struct WritingHandler {
     handler: ProtocolHandler<Connection, io::Result<Connection>>,
     senders: WritingSenders,
+    warmup: Mutex<HashMap<SocketAddr, WarmupBuffer>>,
+}
+
+impl WritingHandler {
+    /// Moves the messages buffered while the connection with the given address was being set up to its outbound
+    /// message queues, in order; returns `false` if the buffer overflowed in the meantime.
+    pub(crate) fn flush_warmup_buffer(&self, addr: SocketAddr) -> bool {
+        // the lock is held until the messages are queued, so that the ones sent in the meantime can't overtake them
+        let mut warmup = self.warmup.lock();
+        let Some(buffer) = warmup.remove(&addr) else {
+            return true;
+        };
+        if buffer.overflowed {
+            return false;
+        }
+
+        if let Some(senders) = self.senders.read().get(&addr) {
+            for (priority, msg) in buffer.messages {
+                if let Err(e) = senders.get(priority).try_send(msg) {
+                    let _ = e.into_inner().delivery_notification.send(Err(io::ErrorKind::WouldBlock.into()));
+                }
+            }
+        }
+
+        true
+    }
+
+    /// Discards the messages buffered for the given address, e.g. once its connection couldn't be set up.
+    pub(crate) fn discard_warmup_buffer(&self, addr: SocketAddr) {
+        self.warmup.lock().remove(&addr);
+    }
+
+    /// Returns the number of outbound messages that are queued, but not yet picked up by the writer tasks.
+    pub(crate) fn num_queued_messages(&self) -> usize {
+        self.senders.read().values().map(QueueSenders::len).sum()
+    }
+
+    /// Returns the number of outbound messages that are queued for the given address, if it's connected.
+    pub(crate) fn queue_depth(&self, addr: SocketAddr) -> Option<usize> {
+        self.senders.read().get(&addr).map(QueueSenders::len)
+    }
 }
 
 impl Protocol<Connection, io::Result<Connection>> for WritingHandler {
@@ -274,6 +613,28 @@ impl Protocol<Connection, io::Result<Connection>> for WritingHandler {
     }
 }
 
+/// The senders of the outbound message queues of a single connection.
+#[derive(Clone)]
+struct QueueSenders {
+    high: mpsc::Sender<WrappedMessage>,
+    low: mpsc::Sender<WrappedMessage>,
+}
+
+impl QueueSenders {
+    /// Returns the sender of the queue of the given priority.
+    fn get(&self, priority: Priority) -> &mpsc::Sender<WrappedMessage> {
+        match priority {
+            Priority::High => &self.high,
+            Priority::Low => &self.low,
+        }
+    }
+
+    /// Returns the total number of queued messages.
+    fn len(&self) -> usize {
+        [&self.high, &self.low].iter().map(|sender| sender.max_capacity() - sender.capacity()).sum()
+    }
+}
+
 struct SenderCleanup {
     addr: SocketAddr,
     senders: WritingSenders,
@@ -284,3 +645,118 @@ impl Drop for SenderCleanup {
         self.senders.write().remove(&self.addr);
     }
 }
+
+/// Fails the messages that remain queued for a connection once its writer task ends (e.g. due to a disconnect),
+/// so that their senders are notified.
+struct QueueDrain(mpsc::Receiver<WrappedMessage>);
+
+impl Drop for QueueDrain {
+    fn drop(&mut self) {
+        self.0.close();
+        while let Ok(wrapped_msg) = self.0.try_recv() {
+            let _ = wrapped_msg.delivery_notification.send(Err(io::ErrorKind::NotConnected.into()));
+        }
+    }
+}
+
+/// Fails the writes to a connection that remain blocked for longer than [`Config::io_timeout`] without making any
+/// progress, e.g. due to a full send window; a message that is merely written slowly is not affected.
+struct StallGuard<W> {
+    inner: W,
+    io_timeout: Option<Duration>,
+    /// Resolves once the pending write has been blocked for too long.
+    deadline: Option<Pin<Box<Sleep>>>,
+}
+
+impl<W> StallGuard<W> {
+    fn new(inner: W, io_timeout: Option<Duration>) -> Self {
+        Self { inner, io_timeout, deadline: None }
+    }
+
+    /// Arms the deadline if the write is blocked, and resets it if the write makes progress.
+    fn check_progress<T>(&mut self, cx: &mut Context<'_>, result: Poll<io::Result<T>>) -> Poll<io::Result<T>> {
+        if result.is_ready() {
+            self.deadline = None;
+            return result;
+        }
+
+        if let Some(io_timeout) = self.io_timeout {
+            let deadline = self.deadline.get_or_insert_with(|| Box::pin(sleep(io_timeout)));
+            if deadline.as_mut().poll(cx).is_ready() {
+                self.deadline = None;
+                return Poll::Ready(Err(io::ErrorKind::TimedOut.into()));
+            }
+        }
+
+        Poll::Pending
+    }
+}
+
+impl<W: AsyncWrite + Unpin> AsyncWrite for StallGuard<W> {
+    fn poll_write(self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &[u8]) -> Poll<io::Result<usize>> {
+        let this = self.get_mut();
+        let result = Pin::new(&mut this.inner).poll_write(cx, buf);
+        this.check_progress(cx, result)
+    }
+
+    fn poll_write_vectored(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        bufs: &[io::IoSlice<'_>],
+    ) -> Poll<io::Result<usize>> {
+        let this = self.get_mut();
+        let result = Pin::new(&mut this.inner).poll_write_vectored(cx, bufs);
+        this.check_progress(cx, result)
+    }
+
+    fn is_write_vectored(&self) -> bool {
+        self.inner.is_write_vectored()
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        let result = Pin::new(&mut this.inner).poll_flush(cx);
+        this.check_progress(cx, result)
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        let result = Pin::new(&mut this.inner).poll_shutdown(cx);
+        this.check_progress(cx, result)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::io::{duplex, AsyncReadExt};
+
+    #[tokio::test(start_paused = true)]
+    async fn stall_guard_resets_on_progress() {
+        let (writer, mut reader) = duplex(64);
+        let mut writer = StallGuard::new(writer, Some(Duration::from_millis(100)));
+
+        // Read slowly, but steadily.
+        let slow_reader = tokio::spawn(async move {
+            let mut buf = [0u8; 16];
+            let mut num_read = 0;
+            while num_read != 1024 {
+                sleep(Duration::from_millis(50)).await;
+                num_read += reader.read(&mut buf).await.unwrap();
+            }
+            reader
+        });
+
+        // Ensure a write that takes longer than the timeout, but keeps making progress, succeeds.
+        let start = tokio::time::Instant::now();
+        writer.write_all(&[0u8; 1024]).await.unwrap();
+        assert!(start.elapsed() > Duration::from_secs(1));
+
+        // Ensure a write that makes no progress fails once the timeout expires.
+        let _reader = slow_reader.await.unwrap();
+        let start = tokio::time::Instant::now();
+        let err = writer.write_all(&[0u8; 1024]).await.unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::TimedOut);
+        assert_eq!(start.elapsed(), Duration::from_millis(100));
+    }
+}
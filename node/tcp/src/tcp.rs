@@ -13,40 +13,90 @@
 // limitations under the License.
 
 use std::{
-    collections::HashSet,
+    any::Any,
+    borrow::Cow,
+    cmp::Reverse,
+    collections::{HashMap, HashSet, VecDeque},
     fmt,
+    future::Future,
     io,
-    net::{IpAddr, SocketAddr},
+    net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr},
     ops::Deref,
+    path::PathBuf,
     sync::{
-        atomic::{AtomicUsize, Ordering::*},
+        atomic::{AtomicBool, AtomicUsize, Ordering::*},
         Arc,
     },
-    time::Duration,
+    time::{Duration, Instant},
 };
+#[cfg(unix)]
+use std::{path::Path, sync::atomic::AtomicU64};
 
-use once_cell::sync::OnceCell;
-use parking_lot::Mutex;
+use futures_util::{future::join_all, stream::FuturesUnordered, StreamExt};
+use ipnetwork::IpNetwork;
+use parking_lot::{Mutex, RwLock};
+use rand::seq::SliceRandom;
+#[cfg(unix)]
+use tokio::net::{UnixListener, UnixStream};
 use tokio::{
-    io::split,
-    net::{TcpListener, TcpStream},
-    sync::oneshot,
-    task::JoinHandle,
-    time::timeout,
+    io::{split, AsyncReadExt, AsyncWriteExt},
+    net::{lookup_host, TcpListener, TcpSocket, TcpStream},
+    sync::{broadcast, mpsc, oneshot, watch, Semaphore},
+    task::{JoinHandle, JoinSet},
+    time::{sleep, timeout, timeout_at},
 };
 use tracing::*;
 
 use crate::{
     connections::{Connection, ConnectionSide, Connections},
-    protocols::{Protocol, Protocols},
+    create_connection_span,
+    lowest_n,
+    protocols::{MessageHandler, MessageRegistry, Protocol, Protocols},
+    proxy_protocol,
+    socks5,
+    BackoffPolicy,
+    Capabilities,
     Config,
+    ConnectionStats,
+    DialQueue,
+    DisconnectReason,
+    FlowControl,
+    IpFamily,
     KnownPeers,
+    ListenEndpoint,
+    NamedTask,
+    NodeType,
+    ObservedAddrs,
+    PeerDemand,
+    PeerInfo,
+    PeerSnapshot,
+    ProtocolOrder,
+    RateLimiter,
+    RejectReason,
+    SelectionStrategy,
     Stats,
+    Stream,
+    TaskRegistry,
+    TcpSnapshot,
 };
 
+// The default maximum number of inbound connections queued by a TCP listener.
+const DEFAULT_LISTEN_BACKLOG: u32 = 1024;
+
+// The delay following a failed accept; it doubles with every consecutive failure, up to the maximum one.
+const MIN_ACCEPT_ERROR_DELAY: Duration = Duration::from_millis(10);
+const MAX_ACCEPT_ERROR_DELAY: Duration = Duration::from_secs(1);
+
+// The number of disconnect events retained for the subscribers that fall behind.
+const DISCONNECT_EVENTS_CAPACITY: usize = 1024;
+
 // A sequential numeric identifier assigned to `Tcp`s that were not provided with a name.
 static SEQUENTIAL_NODE_ID: AtomicUsize = AtomicUsize::new(0);
 
+// A sequential numeric identifier used to assign placeholder addresses to Unix domain socket connections.
+#[cfg(unix)]
+static SEQUENTIAL_UNIX_CONN_ID: AtomicU64 = AtomicU64::new(0);
+
 /// The central object responsible for handling connections.
 #[derive(Clone)]
 pub struct Tcp(Arc<InnerTcp>);
@@ -65,20 +115,49 @@ pub struct InnerTcp {
     span: Span,
     /// The node's configuration.
     config: Config,
-    /// The node's listening address.
-    listening_addr: OnceCell<SocketAddr>,
+    /// A random nonce identifying the node; it is used to detect self-connects that the address checks can't catch.
+    nonce: u64,
+    /// The node's listening addresses; the first one is the primary one.
+    listening_addrs: RwLock<Vec<SocketAddr>>,
+    /// The paths of the Unix domain sockets the node is listening on.
+    unix_listening_paths: Mutex<Vec<PathBuf>>,
     /// Contains objects used by the protocols implemented by the node.
     pub(crate) protocols: Protocols,
-    /// A set of connections that have not been finalized yet.
-    connecting: Mutex<HashSet<SocketAddr>>,
+    /// The connections that have not been finalized yet, along with the times they were registered at.
+    connecting: Mutex<HashMap<SocketAddr, Instant>>,
+    /// The IP ranges the node refuses to connect with.
+    denied_ips: RwLock<Vec<IpNetwork>>,
+    /// The addresses of the peers that bypass the connection limits.
+    trusted_peers: RwLock<Vec<SocketAddr>>,
+    /// Notifies the trusted peer supervisor about the dropped connections, if it's enabled.
+    disconnect_events: Option<mpsc::UnboundedSender<(SocketAddr, ConnectionSide)>>,
+    /// Notifies the subscribers about the dropped connections, along with the reasons.
+    disconnects: broadcast::Sender<(SocketAddr, DisconnectReason)>,
+    /// Paces the new inbound connections, if their rate is limited.
+    inbound_limiter: Option<RateLimiter>,
+    /// Set while the listeners are paused, i.e. the new inbound connections are refused.
+    listener_paused: AtomicBool,
+    /// The current demand for peers; its subscribers are notified whenever it changes.
+    peer_demand: watch::Sender<PeerDemand>,
     /// Contains objects related to the node's active connections.
     connections: Connections,
     /// Collects statistics related to the node's peers.
     known_peers: KnownPeers,
     /// Collects statistics related to the node itself.
     stats: Stats,
+    /// The addresses queued to be connected to.
+    dial_queue: DialQueue,
+    /// The node's external addresses, as reported by its peers.
+    observed_addrs: ObservedAddrs,
+    /// The handlers of the inbound messages.
+    message_handlers: MessageRegistry,
+    /// The task of the node's primary listener, kept apart so that it can be replaced (see
+    /// [`Tcp::rebind_listener`]).
+    primary_listening_task: Mutex<Option<NamedTask>>,
+    /// The node's other listening tasks.
+    listening_tasks: TaskRegistry,
     /// The node's tasks.
-    pub(crate) tasks: Mutex<Vec<JoinHandle<()>>>,
+    pub(crate) tasks: TaskRegistry,
 }
 
 impl Tcp {
@@ -92,19 +171,73 @@ impl Tcp {
         // Create a tracing span containing the node's name.
         let span = crate::helpers::create_span(config.name.as_deref().unwrap());
 
+        // Initialize the list of denied IP ranges.
+        let denied_ips = RwLock::new(config.denied_ips.clone());
+        // Initialize the list of trusted peers.
+        let trusted_peers = RwLock::new(config.trusted_peers.clone());
+        // If enabled, set up the channel used by the trusted peer supervisor.
+        let (disconnect_events, disconnect_receiver) = match config.reconnect_trusted_peers {
+            Some(_) => {
+                let (sender, receiver) = mpsc::unbounded_channel();
+                (Some(sender), Some(receiver))
+            }
+            None => (None, None),
+        };
+        // If enabled, initialize the limiter of the inbound connections.
+        let inbound_limiter = config.max_inbound_connections_per_sec.map(|limit| RateLimiter::new(limit.into()));
+        // Initialize the demand for peers, based on the lack of connections.
+        let (low, high) = (config.target_connections_low, config.target_connections_high);
+        let (peer_demand, _) = watch::channel(PeerDemand::Satisfied.next(0, low, high));
+        // Initialize the collection of known peers.
+        let known_peers = KnownPeers::new(config.peer_score_range.clone());
+
         // Initialize the Tcp stack.
         let tcp = Tcp(Arc::new(InnerTcp {
             span,
             config,
-            listening_addr: Default::default(),
+            nonce: rand::random(),
+            listening_addrs: Default::default(),
+            unix_listening_paths: Default::default(),
             protocols: Default::default(),
             connecting: Default::default(),
+            denied_ips,
+            trusted_peers,
+            disconnect_events,
+            disconnects: broadcast::channel(DISCONNECT_EVENTS_CAPACITY).0,
+            inbound_limiter,
+            listener_paused: Default::default(),
+            peer_demand,
             connections: Default::default(),
-            known_peers: Default::default(),
+            known_peers,
             stats: Default::default(),
+            dial_queue: Default::default(),
+            observed_addrs: Default::default(),
+            message_handlers: Default::default(),
+            primary_listening_task: Default::default(),
+            listening_tasks: Default::default(),
             tasks: Default::default(),
         }));
 
+        // If enabled, spawn the task dropping the idle connections.
+        if let Some(idle_timeout) = tcp.config.idle_timeout {
+            tcp.spawn_idle_connection_reaper(idle_timeout);
+        }
+
+        // If enabled, spawn the task dropping the stale pending connections.
+        if let Some(max_age) = tcp.config.max_connecting_age {
+            tcp.spawn_pending_connection_sweeper(max_age);
+        }
+
+        // If enabled, spawn the task rotating the long-lived connections.
+        if let Some(max_lifetime) = tcp.config.max_connection_lifetime {
+            tcp.spawn_connection_rotator(max_lifetime);
+        }
+
+        // If enabled, spawn the task reconnecting to the trusted peers.
+        if let (Some(policy), Some(receiver)) = (tcp.config.reconnect_trusted_peers, disconnect_receiver) {
+            tcp.spawn_trusted_peer_supervisor(policy, receiver);
+        }
+
         debug!(parent: tcp.span(), "The node is ready");
 
         tcp
@@ -123,10 +256,50 @@ impl Tcp {
         &self.config
     }
 
+    /// Returns the random nonce identifying the node.
+    #[inline]
+    pub fn nonce(&self) -> u64 {
+        self.nonce
+    }
+
     /// Returns the listening address; returns an error if Tcp was not configured
-    /// to listen for inbound connections.
+    /// to listen for inbound connections, including if it only listens on Unix domain sockets.
     pub fn listening_addr(&self) -> io::Result<SocketAddr> {
-        self.listening_addr.get().copied().ok_or_else(|| io::ErrorKind::AddrNotAvailable.into())
+        self.listening_addrs.read().first().copied().ok_or_else(|| io::ErrorKind::AddrNotAvailable.into())
+    }
+
+    /// Returns all the listening addresses, starting with the primary one; returns an empty list if Tcp was
+    /// not configured to listen for inbound connections.
+    pub fn listening_addrs(&self) -> Vec<SocketAddr> {
+        self.listening_addrs.read().clone()
+    }
+
+    /// Returns all the endpoints the node is listening on, starting with the TCP ones.
+    pub fn listen_endpoints(&self) -> Vec<ListenEndpoint> {
+        let mut endpoints: Vec<_> = self.listening_addrs().into_iter().map(ListenEndpoint::Tcp).collect();
+        endpoints.extend(self.unix_listening_paths.lock().iter().cloned().map(ListenEndpoint::Unix));
+        endpoints
+    }
+
+    /// Stops accepting inbound connections, e.g. for the duration of a maintenance; the listeners remain bound, and
+    /// the connections arriving in the meantime are closed right away. The existing connections, as well as the
+    /// outbound ones, are unaffected.
+    pub fn pause_listener(&self) {
+        if !self.listener_paused.swap(true, Relaxed) {
+            info!(parent: self.span(), "Paused accepting inbound connections");
+        }
+    }
+
+    /// Resumes accepting inbound connections after [`Tcp::pause_listener`].
+    pub fn resume_listener(&self) {
+        if self.listener_paused.swap(false, Relaxed) {
+            info!(parent: self.span(), "Resumed accepting inbound connections");
+        }
+    }
+
+    /// Returns `true` if the listeners are paused (see [`Tcp::pause_listener`]).
+    pub fn is_listening_paused(&self) -> bool {
+        self.listener_paused.load(Relaxed)
     }
 
     /// Checks whether the provided address is connected.
@@ -134,9 +307,15 @@ impl Tcp {
         self.connections.is_connected(addr)
     }
 
+    /// Checks if the connection with the provided address is fully operational, i.e. it is connected and all the
+    /// enabled protocols are ready to handle it; until then, the peer might not be reading the messages yet.
+    pub fn is_connection_ready(&self, addr: SocketAddr) -> bool {
+        self.connections.is_ready(addr)
+    }
+
     /// Checks if Tcp is currently setting up a connection with the provided address.
     pub fn is_connecting(&self, addr: SocketAddr) -> bool {
-        self.connecting.lock().contains(&addr)
+        self.connecting.lock().contains_key(&addr)
     }
 
     /// Returns the number of active connections.
@@ -154,9 +333,293 @@ impl Tcp {
         self.connections.addrs()
     }
 
+    /// Returns up to `n` addresses of the fully ready connections (see [`Tcp::is_connection_ready`]), picked
+    /// according to the provided strategy; the peers that are still being set up or are quarantined (see
+    /// [`Tcp::quarantine_peer`]) are never selected.
+    pub fn select_peers(&self, n: usize, strategy: SelectionStrategy) -> Vec<SocketAddr> {
+        let mut candidates = self.connections.ready_addrs();
+        match strategy {
+            SelectionStrategy::Random => {
+                let (selected, _) = candidates.partial_shuffle(&mut rand::thread_rng(), n);
+                selected.iter().map(|(addr, _)| *addr).collect()
+            }
+            SelectionStrategy::OldestFirst => {
+                lowest_n(candidates.into_iter().map(|(addr, connected_since)| (connected_since, addr)).collect(), n)
+            }
+            SelectionStrategy::LowestLatency => {
+                let latency = |addr| self.known_peers.latency(addr).unwrap_or(Duration::MAX);
+                lowest_n(candidates.into_iter().map(|(addr, _)| (latency(addr), addr)).collect(), n)
+            }
+            SelectionStrategy::HighestScore => {
+                let score = |addr| Reverse(self.known_peers.score(addr).unwrap_or_default());
+                lowest_n(candidates.into_iter().map(|(addr, _)| (score(addr), addr)).collect(), n)
+            }
+        }
+    }
+
+    /// Returns the label of the connection with the provided address.
+    pub fn connection_label(&self, addr: SocketAddr) -> Option<String> {
+        self.connections.label(addr)
+    }
+
+    /// Returns a list containing the addresses of the active connections where the peer is on the provided side,
+    /// i.e. [`ConnectionSide::Responder`] for the outbound connections, and [`ConnectionSide::Initiator`] for the
+    /// inbound ones (see [`Connection::side`]).
+    pub fn connected_addrs_by_side(&self, side: ConnectionSide) -> Vec<SocketAddr> {
+        self.connections.addrs_by_side(side)
+    }
+
+    /// Returns the number of active connections where the peer is on the provided side (see
+    /// [`Tcp::connected_addrs_by_side`]).
+    pub fn num_connected_by_side(&self, side: ConnectionSide) -> usize {
+        self.connections.num_connected_by_side(side)
+    }
+
+    /// Returns the number of outbound connections missing in order to reach [`Config::min_outbound_connections`].
+    pub fn num_missing_outbound(&self) -> usize {
+        let num_outbound = self.num_connected_by_side(ConnectionSide::Responder);
+        (self.config.min_outbound_connections as usize).saturating_sub(num_outbound)
+    }
+
+    /// Returns a future that resolves once the connection with the provided address is closed, or right away if
+    /// there is no such connection; it can be used to stop awaiting a response from a peer that is gone.
+    pub fn closed(&self, addr: SocketAddr) -> impl Future<Output = ()> {
+        let receiver = self.connections.closed_receiver(addr);
+        async move {
+            if let Some(mut receiver) = receiver {
+                // nothing is ever sent, so this only ends once the sender is dropped along with the connection
+                while receiver.changed().await.is_ok() {}
+            }
+        }
+    }
+
+    /// Returns the current demand for peers, based on the number of connections and the
+    /// [`Config::target_connections_low`] and [`Config::target_connections_high`] watermarks.
+    pub fn peer_demand(&self) -> PeerDemand {
+        *self.peer_demand.borrow()
+    }
+
+    /// Returns a receiver notified whenever the demand for peers changes, e.g. in order to start the peer discovery
+    /// once more peers are needed, and to stop it once the demand is satisfied.
+    pub fn subscribe_peer_demand(&self) -> watch::Receiver<PeerDemand> {
+        self.peer_demand.subscribe()
+    }
+
+    /// Updates the demand for peers following a change in the number of connections.
+    fn update_peer_demand(&self) {
+        let num_connected = self.num_connected();
+        let (low, high) = (self.config.target_connections_low, self.config.target_connections_high);
+        self.peer_demand.send_if_modified(|demand| {
+            let next = demand.next(num_connected, low, high);
+            if next == *demand {
+                return false;
+            }
+            debug!(parent: self.span(), "The demand for peers is now {next:?} ({num_connected} connections)");
+            *demand = next;
+            true
+        });
+    }
+
+    /// Returns a list containing the addresses of the active connections with the provided label.
+    pub fn connections_with_label(&self, label: &str) -> Vec<SocketAddr> {
+        self.connections.addrs_with_label(label)
+    }
+
+    /// Returns a list containing the addresses of the active connections with nodes of the provided type; the
+    /// connections whose node type is not known yet are not included.
+    pub fn connections_of_type(&self, node_type: NodeType) -> Vec<SocketAddr> {
+        self.connections.addrs_of_type(node_type)
+    }
+
+    /// Returns the capabilities advertised by the peer with the provided address during the handshake, if it is
+    /// connected and they are known; the features the peer doesn't support should not be used with it.
+    pub fn peer_capabilities(&self, addr: SocketAddr) -> Option<Capabilities> {
+        self.connections.capabilities(addr)
+    }
+
+    /// Returns the identity proven by the peer with the provided address during the handshake, if it is connected,
+    /// and the identity was verified and is of the given type, e.g. the `Address` used to sign a challenge.
+    pub fn peer_identity<T: Any + Clone>(&self, addr: SocketAddr) -> Option<T> {
+        self.connections.identity(addr)
+    }
+
+    /// Records the node type of the connection with the provided address, as learned by the higher-level protocols.
+    /// If it would exceed the limit of connections with nodes of that type, the connection is dropped instead, and
+    /// `false` is returned.
+    pub fn set_node_type(&self, addr: SocketAddr, node_type: NodeType) -> bool {
+        let limit = self.config.max_connections_of_type(node_type);
+        if self.connections.set_node_type(addr, node_type, limit) {
+            return true;
+        }
+
+        warn!(parent: self.span(), "Maximum number of {node_type:?} connections reached; disconnecting from {addr}");
+        let tcp = self.clone();
+        tokio::spawn(async move {
+            tcp.disconnect_for(addr, DisconnectReason::Policy).await;
+        });
+        false
+    }
+
+    /// Returns the statistics of the connection with the provided address.
+    pub fn connection_stats(&self, addr: SocketAddr) -> Option<Arc<ConnectionStats>> {
+        self.connections.stats(addr)
+    }
+
+    /// Returns a list containing the statistics of all the active connections.
+    pub fn all_connection_stats(&self) -> Vec<(SocketAddr, Arc<ConnectionStats>)> {
+        self.connections.all_stats()
+    }
+
+    /// Checks whether the estimated clock offset of the given peer exceeds [`Config::max_clock_offset`]; such
+    /// peers can be deprioritized by the time-sensitive logic.
+    pub fn is_clock_skewed(&self, addr: SocketAddr) -> bool {
+        match (self.config.max_clock_offset, self.known_peers.clock_offset(addr)) {
+            (Some(max_offset), Some(offset)) => offset > max_offset,
+            _ => false,
+        }
+    }
+
+    /// Returns a list containing up to `n` addresses of active connections, starting with the lowest-scoring ones.
+    pub fn lowest_scoring_peers(&self, n: usize) -> Vec<SocketAddr> {
+        let mut peers = self
+            .connected_addrs()
+            .into_iter()
+            .map(|addr| (self.known_peers.score(addr).unwrap_or_default(), addr))
+            .collect::<Vec<_>>();
+        peers.sort_unstable();
+        peers.into_iter().take(n).map(|(_, addr)| addr).collect()
+    }
+
     /// Returns a list containing addresses of pending connections.
     pub fn connecting_addrs(&self) -> Vec<SocketAddr> {
-        self.connecting.lock().iter().copied().collect()
+        self.connecting.lock().keys().copied().collect()
+    }
+
+    /// Returns the list of IP ranges the node refuses to connect with.
+    pub fn denied_ips(&self) -> Vec<IpNetwork> {
+        self.denied_ips.read().clone()
+    }
+
+    /// Adds the given IP range to the list of denied ones; returns `false` if it was already present.
+    pub fn add_denied_ip(&self, network: IpNetwork) -> bool {
+        let mut denied_ips = self.denied_ips.write();
+        if denied_ips.contains(&network) {
+            false
+        } else {
+            denied_ips.push(network);
+            true
+        }
+    }
+
+    /// Removes the given IP range from the list of denied ones; returns `false` if it wasn't present.
+    pub fn remove_denied_ip(&self, network: IpNetwork) -> bool {
+        let mut denied_ips = self.denied_ips.write();
+        let num_denied = denied_ips.len();
+        denied_ips.retain(|denied| *denied != network);
+        denied_ips.len() != num_denied
+    }
+
+    /// Returns the list of the trusted peers' addresses.
+    pub fn trusted_peers(&self) -> Vec<SocketAddr> {
+        self.trusted_peers.read().clone()
+    }
+
+    /// Adds the given address to the list of trusted peers; returns `false` if it was already present.
+    pub fn add_trusted_peer(&self, addr: SocketAddr) -> bool {
+        let mut trusted_peers = self.trusted_peers.write();
+        if trusted_peers.contains(&addr) {
+            false
+        } else {
+            trusted_peers.push(addr);
+            true
+        }
+    }
+
+    /// Removes the given address from the list of trusted peers; returns `false` if it wasn't present.
+    pub fn remove_trusted_peer(&self, addr: SocketAddr) -> bool {
+        let mut trusted_peers = self.trusted_peers.write();
+        let num_trusted = trusted_peers.len();
+        trusted_peers.retain(|trusted| *trusted != addr);
+        trusted_peers.len() != num_trusted
+    }
+
+    /// Bans the given address for the provided duration, disconnecting from it if it is currently connected.
+    pub async fn ban_peer(&self, addr: SocketAddr, duration: Duration) {
+        warn!(parent: self.span(), "Banning {addr} for {}s", duration.as_secs());
+        self.known_peers.ban(addr, Instant::now() + duration);
+
+        if self.is_connected(addr) {
+            self.disconnect_for(addr, DisconnectReason::Policy).await;
+        }
+    }
+
+    /// Quarantines the peer with the provided address for [`Config::quarantine_duration`], e.g. pending further
+    /// evidence of its misbehavior: the connection is kept, but the peer is no longer picked by [`Tcp::select_peers`]
+    /// or sent the [`Writing::broadcast`](crate::protocols::Writing::broadcast) messages, while the messages sent
+    /// to it directly (e.g. the control ones) are still delivered. Returns `false` if it is not connected.
+    pub fn quarantine_peer(&self, addr: SocketAddr) -> bool {
+        let duration = self.config.quarantine_duration;
+        if self.connections.set_quarantine(addr, Some(Instant::now() + duration)).is_none() {
+            return false;
+        }
+
+        warn!(parent: self.span(), "Quarantining {addr} for {}s", duration.as_secs());
+        true
+    }
+
+    /// Lifts the quarantine of the peer with the provided address ahead of time; returns `false` if it wasn't
+    /// quarantined.
+    pub fn unquarantine_peer(&self, addr: SocketAddr) -> bool {
+        if self.connections.set_quarantine(addr, None) != Some(true) {
+            return false;
+        }
+
+        info!(parent: self.span(), "Lifted the quarantine of {addr}");
+        true
+    }
+
+    /// Returns `true` if the peer with the provided address is quarantined (see [`Tcp::quarantine_peer`]).
+    pub fn is_quarantined(&self, addr: SocketAddr) -> bool {
+        self.connections.is_quarantined(addr)
+    }
+
+    /// Returns the addresses of the quarantined peers (see [`Tcp::quarantine_peer`]).
+    pub fn quarantined_peers(&self) -> Vec<SocketAddr> {
+        self.connections.quarantined_addrs()
+    }
+
+    /// Registers a control message (e.g. a ping) received from the given address, and returns `false` if the peer
+    /// exceeds [`Config::max_control_messages_per_sec`]. In such a case, a failure is registered for the peer, and it
+    /// is banned for [`Config::control_flood_ban_duration`] if set; otherwise, the caller should disconnect from it.
+    pub async fn register_control_message(&self, addr: SocketAddr) -> bool {
+        let Some(limit) = self.config.max_control_messages_per_sec else {
+            return true;
+        };
+        let Some(num_messages) = self.connections.register_control_message(addr) else {
+            return true;
+        };
+        if num_messages <= limit as usize {
+            return true;
+        }
+
+        warn!(parent: self.span(), "{addr} is flooding the control messages ({num_messages} in the last second)");
+        self.known_peers.register_failure(addr);
+        if let Some(ban_duration) = self.config.control_flood_ban_duration {
+            // the ban also results in a disconnect
+            self.ban_peer(addr, ban_duration).await;
+        }
+
+        false
+    }
+
+    /// Checks whether the provided address is currently banned.
+    pub fn is_banned(&self, addr: SocketAddr) -> bool {
+        self.known_peers.is_banned(addr)
+    }
+
+    /// Returns a list containing the banned addresses and the expiry of their bans.
+    pub fn banned_peers(&self) -> Vec<(SocketAddr, Instant)> {
+        self.known_peers.banned()
     }
 
     /// Returns a reference to the collection of statistics of known peers.
@@ -165,36 +628,200 @@ impl Tcp {
         &self.known_peers
     }
 
+    /// Registers the address the given peer reports seeing the node connect from; as a peer accepting a connection
+    /// sees the initiator's external address (i.e. [`Connection::addr`] on the responder side), it can be shared
+    /// with the initiator by the protocols (e.g. the handshake), and tallied via this method in order to discover
+    /// the node's external address (e.g. if it's behind a NAT).
+    ///
+    /// note: As the ports of the outbound connections are usually ephemeral, only the IP addresses are reliable,
+    /// unless the peers report them along with the listening port.
+    pub fn register_observed_addr(&self, reporter: SocketAddr, observed: SocketAddr) {
+        trace!(parent: self.span(), "{reporter} reports seeing the node at {observed}");
+        self.observed_addrs.register(reporter.ip(), observed);
+    }
+
+    /// Returns the external addresses reported via [`Tcp::register_observed_addr`], along with the numbers of
+    /// peers (distinct IPs) reporting them; the most reported ones come first.
+    pub fn observed_external_addrs(&self) -> Vec<(SocketAddr, usize)> {
+        self.observed_addrs.tally()
+    }
+
+    /// Returns the address most often reported via [`Tcp::register_observed_addr`], which is likely the node's
+    /// external address; returns `None` if there are no reports, or if there is a tie.
+    pub fn likely_external_addr(&self) -> Option<SocketAddr> {
+        self.observed_addrs.most_reported()
+    }
+
     /// Returns a reference to the statistics.
     #[inline]
     pub fn stats(&self) -> &Stats {
         &self.stats
     }
 
+    /// Zeroes the monotonic counters of the statistics, both the global ones and the ones of the known peers.
+    pub fn reset_stats(&self) {
+        self.stats.reset();
+        for stats in self.known_peers.snapshot().values() {
+            stats.reset();
+        }
+    }
+
+    /// Returns a serializable snapshot of the connections and the statistics; the addresses are sorted.
+    pub fn snapshot(&self) -> TcpSnapshot {
+        let mut connected_addrs = self.connected_addrs();
+        connected_addrs.sort_unstable();
+        let mut connecting_addrs = self.connecting_addrs();
+        connecting_addrs.sort_unstable();
+
+        let mut known_peers = self
+            .known_peers()
+            .snapshot()
+            .into_iter()
+            .map(|(addr, stats)| PeerSnapshot { addr, stats: stats.as_ref().into() })
+            .collect::<Vec<_>>();
+        known_peers.sort_unstable_by_key(|peer| peer.addr);
+
+        TcpSnapshot {
+            name: self.name().to_owned(),
+            num_connected: connected_addrs.len(),
+            num_connecting: connecting_addrs.len(),
+            connected_addrs,
+            connecting_addrs,
+            stats: self.stats().into(),
+            known_peers,
+        }
+    }
+
+    /// Returns everything known about the connection with the given address and its peer, if it is connected.
+    pub fn peer_info(&self, addr: SocketAddr) -> Option<PeerInfo> {
+        self.connections.peer_info(addr).map(|info| self.with_peer_history(info))
+    }
+
+    /// Returns everything known about all the connections and their peers, sorted by address.
+    pub fn all_peer_info(&self) -> Vec<PeerInfo> {
+        let mut infos =
+            self.connections.all_peer_info().into_iter().map(|info| self.with_peer_history(info)).collect::<Vec<_>>();
+        infos.sort_unstable_by_key(|info| info.addr);
+        infos
+    }
+
+    /// Fills in the latency and the score of the given peer, as registered among the known peers.
+    fn with_peer_history(&self, mut info: PeerInfo) -> PeerInfo {
+        if let Some(stats) = self.known_peers.get(info.addr) {
+            info.latency_ms = stats.latency().map(|latency| latency.as_millis() as u64);
+            info.score = stats.score();
+        }
+        info
+    }
+
+    /// Returns the names of the tasks spawned by the node, including the listening ones and the ones dedicated to
+    /// its connections; useful for diagnostics.
+    pub fn task_names(&self) -> Vec<String> {
+        let mut names =
+            self.primary_listening_task.lock().iter().map(|task| task.name().to_owned()).collect::<Vec<_>>();
+        names.extend(self.listening_tasks.names());
+        names.extend(self.tasks.names());
+        names.extend(self.connections.task_names());
+        names
+    }
+
     /// Returns the tracing [`Span`] associated with Tcp.
     #[inline]
     pub fn span(&self) -> &Span {
         &self.span
     }
 
+    /// Returns the number of outbound messages that are queued to be sent; it is always `0` if the
+    /// [`Writing`](crate::protocols::Writing) protocol is not enabled.
+    pub fn num_queued_messages(&self) -> usize {
+        self.protocols.writing.get().map(|handler| handler.num_queued_messages()).unwrap_or(0)
+    }
+
+    /// Returns the number of outbound messages that are queued to be sent to the provided address, or `None` if
+    /// it's not connected or the [`Writing`](crate::protocols::Writing) protocol is not enabled.
+    pub fn queue_depth(&self, addr: SocketAddr) -> Option<usize> {
+        self.protocols.writing.get().and_then(|handler| handler.queue_depth(addr))
+    }
+
+    /// Registers the handler of the inbound messages of type `M` with the given [`Reading::message_type`]; returns
+    /// `false` if it replaced a previously registered one.
+    ///
+    /// [`Reading::message_type`]: crate::protocols::Reading::message_type
+    pub fn register_message_handler<M: Send + 'static>(
+        &self,
+        message_type: impl Into<Cow<'static, str>>,
+        handler: impl MessageHandler<M>,
+    ) -> bool {
+        let message_type = message_type.into();
+        self.message_handlers.update(|handlers| handlers.by_type.insert(message_type, Arc::new(handler)).is_none())
+    }
+
+    /// Registers the handler of the inbound messages of type `M` whose message types have no dedicated handler.
+    pub fn set_fallback_message_handler<M: Send + 'static>(&self, handler: impl MessageHandler<M>) {
+        self.message_handlers.update(|handlers| handlers.fallback = Some(Arc::new(handler)));
+    }
+
+    /// Routes an inbound message to the handler registered for its type; if there is none, the message is logged
+    /// and dropped.
+    pub async fn dispatch_message<M: Send + 'static>(
+        &self,
+        source: SocketAddr,
+        message_type: Option<Cow<'static, str>>,
+        message: M,
+    ) -> io::Result<()> {
+        match self.message_handlers.handler::<M>(message_type.as_deref()) {
+            Some(handler) => handler.handle(source, message).await,
+            None => {
+                let message_type = message_type.as_deref().unwrap_or("unknown");
+                warn!(parent: self.span(), "Ignoring an unhandled message ({message_type}) from {source}");
+                Ok(())
+            }
+        }
+    }
+
     /// Gracefully shuts down the stack.
     pub async fn shut_down(&self) {
-        debug!(parent: self.span(), "Shutting down the TCP stack");
+        self.shut_down_with(Duration::ZERO).await
+    }
 
-        // Retrieve all tasks.
-        let mut tasks = std::mem::take(&mut *self.tasks.lock()).into_iter();
+    /// Gracefully shuts down the stack, allowing up to `drain` for the queued outbound messages to be sent
+    /// after no new inbound connections are accepted, before disconnecting from all the peers.
+    pub async fn shut_down_with(&self, drain: Duration) {
+        debug!(parent: self.span(), "Shutting down the TCP stack");
 
-        // Abort the listening task first.
-        if let Some(listening_task) = tasks.next() {
-            listening_task.abort(); // abort the listening task first
+        // Abort the listening tasks first.
+        let primary_listening_task = self.primary_listening_task.lock().take();
+        for listening_task in primary_listening_task.into_iter().chain(self.listening_tasks.take()) {
+            debug!(parent: self.span(), "Aborting the {} task", listening_task.name());
+            listening_task.abort();
         }
-        // Disconnect from all connected peers.
-        for addr in self.connected_addrs() {
-            self.disconnect(addr).await;
+        // Unix domain socket files outlive their listeners, and would prevent them from being bound again.
+        for path in std::mem::take(&mut *self.unix_listening_paths.lock()) {
+            let _ = std::fs::remove_file(path);
+        }
+
+        // Allow the outbound messages to be flushed.
+        if !drain.is_zero() {
+            let flushed = async {
+                while self.num_queued_messages() != 0 {
+                    sleep(Duration::from_millis(10)).await;
+                }
+            };
+            if timeout(drain, flushed).await.is_err() {
+                warn!(parent: self.span(), "Shutting down with {} queued messages", self.num_queued_messages());
+            }
         }
+
+        // Retrieve all the remaining tasks.
+        let tasks = self.tasks.take();
+
+        // Disconnect from all connected peers; this must precede aborting the tasks, as the disconnect
+        // handler is one of them.
+        self.disconnect_all_for(DisconnectReason::Shutdown).await;
         // Abort all remaining tasks.
-        for handle in tasks {
-            handle.abort();
+        for task in tasks {
+            debug!(parent: self.span(), "Aborting the {} task", task.name());
+            task.abort();
         }
     }
 }
@@ -202,130 +829,754 @@ impl Tcp {
 impl Tcp {
     /// Connects to the provided `SocketAddr`.
     pub async fn connect(&self, addr: SocketAddr) -> io::Result<()> {
-        if let Ok(listening_addr) = self.listening_addr() {
-            // TODO(nkls): maybe this first check can be dropped; though it might be best to keep just in case.
-            if addr == listening_addr || self.is_self_connect(addr) {
-                error!(parent: self.span(), "Attempted to self-connect ({addr})");
-                return Err(io::ErrorKind::AddrInUse.into());
+        self.connect_labeled(addr, String::new()).await
+    }
+
+    /// Connects to the provided `SocketAddr`, attaching the given label to the connection; it is visible in
+    /// the connection's logs, and can be used to group connections via [`Tcp::connections_with_label`].
+    pub async fn connect_labeled(&self, addr: SocketAddr, label: String) -> io::Result<()> {
+        self.prepare_connect(addr)?;
+        let stream = self.open_stream_within_timeout(addr).await;
+        self.finalize_connect(addr, stream, label).await
+    }
+
+    /// Connects to the provided `SocketAddr`, provided the stream can be opened before the given deadline; the
+    /// remaining time is applied as the connect timeout (alongside [`Config::connect_timeout`], if it's shorter).
+    /// If the deadline has already passed, no attempt is made and an [`io::ErrorKind::TimedOut`] error is returned.
+    pub async fn connect_before(&self, addr: SocketAddr, deadline: Instant) -> io::Result<()> {
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        if remaining.is_zero() {
+            warn!(parent: self.span(), "Not connecting to {addr}; the deadline has passed");
+            return Err(io::ErrorKind::TimedOut.into());
+        }
+
+        self.prepare_connect(addr)?;
+        let stream = match timeout(remaining, self.open_stream_within_timeout(addr)).await {
+            Ok(result) => result,
+            Err(_) => Err(io::ErrorKind::TimedOut.into()),
+        };
+        // note: the pending connection is cleaned up here in case of failure, including the deadline expiry.
+        self.finalize_connect(addr, stream, String::new()).await
+    }
+
+    /// Performs the checks preceding an outbound connection and registers it as pending.
+    fn prepare_connect(&self, addr: SocketAddr) -> io::Result<()> {
+        if let Err(reason) = self.would_accept(addr) {
+            match reason {
+                RejectReason::AlreadyConnected | RejectReason::AlreadyConnecting => {
+                    warn!(parent: self.span(), "Not connecting to {addr}: {reason}")
+                }
+                _ => error!(parent: self.span(), "Refusing to connect to {addr}: {reason}"),
             }
+            return Err(reason.into());
         }
 
-        if !self.can_add_connection() {
-            error!(parent: self.span(), "Too many connections; refusing to connect to {addr}");
-            return Err(io::ErrorKind::ConnectionRefused.into());
+        // The pending connections are checked again, as another attempt could have been registered in the meantime.
+        let mut connecting = self.connecting.lock();
+        if connecting.contains_key(&addr) {
+            warn!(parent: self.span(), "Not connecting to {addr}: {}", RejectReason::AlreadyConnecting);
+            return Err(RejectReason::AlreadyConnecting.into());
         }
+        connecting.insert(addr, Instant::now());
+
+        Ok(())
+    }
 
+    /// Checks whether a connection with the provided `SocketAddr` would currently be permitted by the node's
+    /// policies (the self-connect guard, the allowed and denied IPs, the bans, and the connection limits), without
+    /// dialing it or changing any state; returns the reason it would be refused otherwise.
+    pub fn would_accept(&self, addr: SocketAddr) -> Result<(), RejectReason> {
+        // TODO(nkls): maybe this first check can be dropped; though it might be best to keep just in case.
+        if self.listening_addrs().contains(&addr) || self.is_self_connect(addr) {
+            return Err(RejectReason::SelfConnect);
+        }
+        if !self.is_ip_permitted(addr.ip()) {
+            return Err(RejectReason::Denied);
+        }
+        if self.config.loopback_only && !addr.ip().is_loopback() {
+            return Err(RejectReason::NotLoopback);
+        }
+        if self.is_banned(addr) {
+            return Err(RejectReason::Banned);
+        }
+        if !self.can_add_connection_from(addr.ip()) {
+            return Err(RejectReason::ConnectionLimit);
+        }
+        if !self.can_add_outbound_connection_to(addr.ip()) {
+            return Err(RejectReason::PrefixLimit);
+        }
         if self.is_connected(addr) {
-            warn!(parent: self.span(), "Already connected to {addr}");
-            return Err(io::ErrorKind::AlreadyExists.into());
+            return Err(RejectReason::AlreadyConnected);
         }
-
-        if !self.connecting.lock().insert(addr) {
-            warn!(parent: self.span(), "Already connecting to {addr}");
-            return Err(io::ErrorKind::AlreadyExists.into());
+        if self.is_connecting(addr) {
+            return Err(RejectReason::AlreadyConnecting);
         }
 
-        let stream =
-            match timeout(Duration::from_millis(self.config().connection_timeout_ms.into()), TcpStream::connect(addr))
-                .await
-            {
-                Ok(Ok(stream)) => Ok(stream),
-                Ok(err) => {
-                    self.connecting.lock().remove(&addr);
-                    err
-                }
-                Err(err) => {
-                    self.connecting.lock().remove(&addr);
-                    error!("connection timeout error: {}", err);
-                    Err(io::ErrorKind::TimedOut.into())
-                }
-            }?;
+        Ok(())
+    }
+
+    /// Opens a raw TCP stream with the provided `SocketAddr`, observing [`Config::connect_timeout`].
+    async fn open_stream_within_timeout(&self, addr: SocketAddr) -> io::Result<TcpStream> {
+        match self.config().connect_timeout {
+            Some(connect_timeout) => match timeout(connect_timeout, self.open_stream(addr)).await {
+                Ok(result) => result,
+                Err(_) => Err(io::ErrorKind::TimedOut.into()),
+            },
+            None => self.open_stream(addr).await,
+        }
+    }
 
-        let ret = self.adapt_stream(stream, addr, ConnectionSide::Initiator).await;
+    /// Finalizes a pending outbound connection, cleaning it up in case of failure.
+    async fn finalize_connect<S: Into<Stream>>(
+        &self,
+        addr: SocketAddr,
+        stream: io::Result<S>,
+        label: String,
+    ) -> io::Result<()> {
+        // The connection is finalized by `Tcp::adapt_stream`; all the failures up to and including it share the cleanup.
+        let ret = match stream {
+            Ok(stream) => self.adapt_stream(stream.into(), addr, ConnectionSide::Initiator, label).await,
+            Err(e) => Err(e),
+        };
 
         if let Err(ref e) = ret {
             self.connecting.lock().remove(&addr);
+            self.discard_warmup_buffer(addr);
+            // The peer may not be known yet if the stream couldn't be opened (e.g. due to a timeout).
+            self.known_peers().add(addr);
             self.known_peers().register_failure(addr);
-            error!(parent: self.span(), "Unable to initiate a connection with {addr}: {e}");
+            if e.kind() == io::ErrorKind::TimedOut {
+                error!(parent: self.span(), "Timed out while connecting to {addr}");
+            } else {
+                error!(parent: self.span(), "Unable to initiate a connection with {addr}: {e}");
+            }
         }
 
         ret
     }
 
-    /// Disconnects from the provided `SocketAddr`.
-    pub async fn disconnect(&self, addr: SocketAddr) -> bool {
-        if let Some(handler) = self.protocols.disconnect.get() {
-            if self.is_connected(addr) {
-                let (sender, receiver) = oneshot::channel();
-                handler.trigger((addr, sender));
-                let _ = receiver.await; // can't really fail
-            }
+    /// Connects to the Unix domain socket bound to the given path. As such a peer has no IP address, the connection
+    /// is assigned a unique placeholder address, which is returned, and identifies the peer from then on.
+    #[cfg(unix)]
+    pub async fn connect_unix(&self, path: impl AsRef<Path>) -> io::Result<SocketAddr> {
+        let path = path.as_ref();
+
+        if !self.can_add_connection() {
+            error!(parent: self.span(), "Too many connections; refusing to connect to {}", path.display());
+            return Err(io::ErrorKind::ConnectionRefused.into());
         }
 
-        let conn = self.connections.remove(addr);
+        let addr = unix_peer_addr();
+        self.connecting.lock().insert(addr, Instant::now());
+        debug!(parent: self.span(), "Connecting to {} as {addr}", path.display());
 
-        if let Some(ref conn) = conn {
-            debug!(parent: self.span(), "Disconnecting from {}", conn.addr());
+        let stream = match self.config().connect_timeout {
+            Some(connect_timeout) => match timeout(connect_timeout, UnixStream::connect(path)).await {
+                Ok(result) => result,
+                Err(_) => Err(io::ErrorKind::TimedOut.into()),
+            },
+            None => UnixStream::connect(path).await,
+        };
+        self.finalize_connect(addr, stream, String::new()).await?;
 
-            // Shut down the associated tasks of the peer.
-            for task in conn.tasks.iter().rev() {
-                task.abort();
-            }
+        Ok(addr)
+    }
 
-            // If the (owning) Tcp was not the initiator of the connection, it doesn't know the listening address
-            // of the associated peer, so the related stats are unreliable; the next connection initiated by the
-            // peer could be bound to an entirely different port number
-            if conn.side() == ConnectionSide::Initiator {
-                self.known_peers().remove(conn.addr());
-            }
+    /// Connects to the provided host, attempting all the addresses it resolves to (starting with the ones belonging
+    /// to [`Config::preferred_ip_family`]) until a connection is established. Returns the last error if all of
+    /// them fail, or [`io::ErrorKind::NotFound`] if the host can't be resolved.
+    ///
+    /// If [`Config::happy_eyeballs`] is set, the attempts alternate between the IP families and are made
+    /// concurrently, [`Config::happy_eyeballs_delay`] apart unless the previous one fails sooner (as per RFC 8305).
+    pub async fn connect_to_host(&self, host: &str, port: u16) -> io::Result<()> {
+        let addrs = self.resolve_host(host, port).await?;
 
-            debug!(parent: self.span(), "Disconnected from {}", conn.addr());
-        } else {
-            warn!(parent: self.span(), "Failed to disconnect, was not connected to {addr}");
+        if self.config.happy_eyeballs {
+            return self.connect_racing(interleave_ip_families(addrs)).await;
         }
 
-        conn.is_some()
-    }
-}
+        let mut last_err = None;
+        for addr in addrs {
+            match self.connect(addr).await {
+                Ok(()) => return Ok(()),
+                Err(e) => last_err = Some(e),
+            }
+        }
 
-impl Tcp {
-    /// Spawns a task that listens for incoming connections.
-    pub async fn enable_listener(&self) -> io::Result<SocketAddr> {
-        // Retrieve the listening IP address, which must be set.
-        let listener_ip =
-            self.config().listener_ip.expect("Tcp::enable_listener was called, but Config::listener_ip is not set");
+        Err(last_err.unwrap_or_else(|| io::ErrorKind::NotFound.into()))
+    }
 
-        // Initialize the TCP listener.
-        let listener = self.create_listener(listener_ip).await?;
+    /// Races staggered connection attempts to the provided addresses (as per RFC 8305): the next attempt is started
+    /// as soon as the previous one fails, or once [`Config::happy_eyeballs_delay`] elapses without it completing. The
+    /// first raw TCP stream to be established is finalized, while the remaining attempts are cancelled; both the
+    /// failed and the losing attempts are registered as failures of their addresses.
+    async fn connect_racing(&self, addrs: Vec<SocketAddr>) -> io::Result<()> {
+        let delay = self.config.happy_eyeballs_delay;
 
-        // Discover the port, if it was unspecified.
-        let port = listener.local_addr()?.port();
+        let attempt = |addr: SocketAddr| async move {
+            let result = async {
+                self.prepare_connect(addr)?;
+                // If the attempt fails or gets cancelled, the address is no longer pending.
+                let guard = ConnectingGuard { tcp: self, addr };
+                match self.open_stream_within_timeout(addr).await {
+                    Ok(stream) => Ok((guard, stream)),
+                    Err(e) => {
+                        self.known_peers().register_failure(addr);
+                        Err(e)
+                    }
+                }
+            };
+            (addr, result.await)
+        };
 
-        // Set the listening IP address.
-        let listening_addr = (listener_ip, port).into();
-        self.listening_addr.set(listening_addr).expect("The node's listener was started more than once");
+        let mut addrs = addrs.into_iter();
+        let mut attempts = FuturesUnordered::new();
+        let mut in_flight = HashSet::new();
+        let mut last_err = None;
+        loop {
+            if let Some(addr) = addrs.next() {
+                in_flight.insert(addr);
+                attempts.push(attempt(addr));
+            }
+            if attempts.is_empty() {
+                break;
+            }
+
+            // The next attempt is due once the delay elapses, unless there are no more addresses to attempt.
+            let next_attempt_due = async {
+                match addrs.len() {
+                    0 => std::future::pending().await,
+                    _ => sleep(delay).await,
+                }
+            };
+
+            tokio::select! {
+                Some((addr, result)) = attempts.next() => {
+                    in_flight.remove(&addr);
+                    match result {
+                        Ok((guard, stream)) => {
+                            // Cancel the other attempts; they lost the race.
+                            drop(attempts);
+                            for addr in in_flight {
+                                self.known_peers().register_failure(addr);
+                            }
+                            let addr = guard.defuse();
+                            return self.finalize_connect(addr, Ok(stream), String::new()).await;
+                        }
+                        // A failed attempt is followed by the next one right away.
+                        Err(e) => last_err = Some(e),
+                    }
+                }
+                _ = next_attempt_due => {}
+            }
+        }
+
+        Err(last_err.unwrap_or_else(|| io::ErrorKind::NotFound.into()))
+    }
+
+    /// Resolves the provided host into a list of addresses, ordered in line with [`Config::preferred_ip_family`].
+    async fn resolve_host(&self, host: &str, port: u16) -> io::Result<Vec<SocketAddr>> {
+        let mut addrs = match lookup_host((host, port)).await {
+            Ok(addrs) => addrs.collect::<Vec<_>>(),
+            Err(e) => {
+                error!(parent: self.span(), "Unable to resolve {host}: {e}");
+                return Err(io::Error::new(io::ErrorKind::NotFound, e));
+            }
+        };
+
+        if addrs.is_empty() {
+            error!(parent: self.span(), "{host} doesn't resolve to any address");
+            return Err(io::ErrorKind::NotFound.into());
+        }
+
+        // The sort is stable, so the resolution order is otherwise retained.
+        if let Some(family) = self.config.preferred_ip_family {
+            addrs.sort_by_key(|addr| IpFamily::of(addr.ip()) != family);
+        }
+
+        Ok(addrs)
+    }
+
+    /// Opens a raw TCP stream with the provided `SocketAddr`, relayed via the configured SOCKS5 proxy, if any.
+    async fn open_stream(&self, addr: SocketAddr) -> io::Result<TcpStream> {
+        let Some(proxy_addr) = self.config.socks5_proxy else {
+            return self.open_direct_stream(addr).await;
+        };
+
+        let mut stream = self.open_direct_stream(proxy_addr).await?;
+        if let Err(e) = socks5::connect(&mut stream, addr).await {
+            error!(parent: self.span(), "Unable to connect to {addr} via the proxy at {proxy_addr}: {e}");
+            return Err(e);
+        }
+
+        Ok(stream)
+    }
+
+    /// Opens a raw TCP stream with the provided `SocketAddr`, originating from the configured source port, if any.
+    async fn open_direct_stream(&self, addr: SocketAddr) -> io::Result<TcpStream> {
+        if self.config.connect_source_port.is_none() && self.config.bind_device.is_none() {
+            return TcpStream::connect(addr).await;
+        }
+
+        // Prepare a socket of the same IP family as the target address.
+        let new_socket = || -> io::Result<TcpSocket> {
+            let socket = if addr.is_ipv4() { TcpSocket::new_v4()? } else { TcpSocket::new_v6()? };
+            socket.set_reuseaddr(true)?;
+            if let Some(device) = &self.config.bind_device {
+                Self::bind_to_device(&socket, device)?;
+            }
+            Ok(socket)
+        };
+        let unspecified_ip = match addr {
+            SocketAddr::V4(_) => IpAddr::V4(Ipv4Addr::UNSPECIFIED),
+            SocketAddr::V6(_) => IpAddr::V6(Ipv6Addr::UNSPECIFIED),
+        };
+
+        let mut socket = new_socket()?;
+        if let Some(port) = self.config.connect_source_port {
+            if let Err(e) = socket.bind(SocketAddr::new(unspecified_ip, port)) {
+                if e.kind() != io::ErrorKind::AddrInUse {
+                    return Err(e);
+                }
+                warn!(parent: self.span(), "Source port {port} is unavailable, connecting to {addr} from a random one");
+                socket = new_socket()?;
+            }
+        }
+
+        socket.connect(addr).await
+    }
+
+    /// Makes the given socket egress via the network interface with the given name (`SO_BINDTODEVICE`).
+    #[cfg(any(target_os = "android", target_os = "fuchsia", target_os = "linux"))]
+    fn bind_to_device(socket: &TcpSocket, device: &str) -> io::Result<()> {
+        socket.bind_device(Some(device.as_bytes())).map_err(|e| {
+            let msg = if e.kind() == io::ErrorKind::PermissionDenied {
+                format!("couldn't bind to device {device}: {e} (CAP_NET_RAW is required)")
+            } else {
+                format!("couldn't bind to device {device}: {e}")
+            };
+            io::Error::new(e.kind(), msg)
+        })
+    }
+
+    /// Binding sockets to network interfaces by name is not supported on this platform.
+    #[cfg(not(any(target_os = "android", target_os = "fuchsia", target_os = "linux")))]
+    fn bind_to_device(_socket: &TcpSocket, device: &str) -> io::Result<()> {
+        Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            format!("binding to device {device} is not supported on this platform (Config::bind_device is Linux-only)"),
+        ))
+    }
+
+    /// Connects to the provided `SocketAddr`, retrying in accordance with the given [`BackoffPolicy`]. The
+    /// retries are aborted early if the address gets connected in the meantime, or if it gets banned.
+    pub async fn connect_with_retry(&self, addr: SocketAddr, policy: BackoffPolicy) -> io::Result<()> {
+        let mut delays = policy.delays();
+
+        loop {
+            let err = match self.connect(addr).await {
+                Ok(()) => return Ok(()),
+                Err(e) => e,
+            };
+
+            if self.is_connected(addr) || self.is_banned(addr) {
+                return Err(err);
+            }
+
+            match delays.next() {
+                Some(delay) => {
+                    debug!(parent: self.span(), "Retrying to connect to {addr} in {}ms", delay.as_millis());
+                    sleep(delay).await;
+                }
+                None => return Err(err),
+            }
+        }
+    }
+
+    /// Connects to a single logical peer reachable via any of the provided addresses (e.g. its IPv4 and IPv6 ones),
+    /// trying them one at a time, in the order they were provided in, until one succeeds; returns the connected
+    /// address. If one of them is already connected, it is returned right away, without any attempts. Unlike with
+    /// [`Tcp::connect_many`], at most one connection is established; every attempt is subject to the same checks
+    /// and the [`Config::connect_timeout`] as [`Tcp::connect`], and if none succeeds, the last error is returned.
+    pub async fn connect_any(&self, addrs: &[SocketAddr]) -> io::Result<SocketAddr> {
+        if let Some(addr) = addrs.iter().find(|addr| self.is_connected(**addr)) {
+            return Ok(*addr);
+        }
+
+        let mut seen = HashSet::new();
+        let mut last_err = None;
+        for addr in addrs.iter().copied().filter(|addr| seen.insert(*addr)) {
+            match self.connect(addr).await {
+                Ok(()) => return Ok(addr),
+                Err(e) => {
+                    debug!(parent: self.span(), "Couldn't connect to {addr}, trying the next address: {e}");
+                    last_err = Some(e);
+                }
+            }
+        }
+
+        Err(last_err.unwrap_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "no addresses were provided")))
+    }
+
+    /// Connects to the provided addresses, with up to `max_concurrent` connection attempts in progress at a time;
+    /// each attempt is subject to the same checks as [`Tcp::connect`] (e.g. the connection limits and the bans) at
+    /// the moment it starts. The addresses that are already connected or connecting are skipped; the results of
+    /// the remaining ones are returned in the order they were provided in.
+    pub async fn connect_many(
+        &self,
+        addrs: Vec<SocketAddr>,
+        max_concurrent: usize,
+    ) -> Vec<(SocketAddr, io::Result<()>)> {
+        self.connect_many_inner(addrs, max_concurrent, None).await
+    }
+
+    /// Works like [`Tcp::connect_many`], except that every attempt is made via [`Tcp::connect_before`] with the
+    /// given deadline, which makes it a cutoff for the whole batch; the attempts that would only start after the
+    /// deadline fail with an [`io::ErrorKind::TimedOut`] error.
+    pub async fn connect_many_before(
+        &self,
+        addrs: Vec<SocketAddr>,
+        max_concurrent: usize,
+        deadline: Instant,
+    ) -> Vec<(SocketAddr, io::Result<()>)> {
+        self.connect_many_inner(addrs, max_concurrent, Some(deadline)).await
+    }
+
+    /// The implementation of [`Tcp::connect_many`] and [`Tcp::connect_many_before`].
+    async fn connect_many_inner(
+        &self,
+        addrs: Vec<SocketAddr>,
+        max_concurrent: usize,
+        deadline: Option<Instant>,
+    ) -> Vec<(SocketAddr, io::Result<()>)> {
+        let semaphore = Semaphore::new(max_concurrent.max(1));
+
+        // Skip the duplicates, and the addresses that are already connected or connecting.
+        let mut seen = HashSet::new();
+        let addrs = addrs
+            .into_iter()
+            .filter(|addr| seen.insert(*addr) && !self.is_connected(*addr) && !self.is_connecting(*addr))
+            .collect::<Vec<_>>();
+
+        let attempts = addrs.into_iter().map(|addr| {
+            let semaphore = &semaphore;
+            async move {
+                let _permit = semaphore.acquire().await.unwrap(); // can't fail; the semaphore is never closed
+                let result = match deadline {
+                    Some(deadline) => self.connect_before(addr, deadline).await,
+                    None => self.connect(addr).await,
+                };
+                (addr, result)
+            }
+        });
+        join_all(attempts).await
+    }
+
+    /// Queues a connection attempt to the provided `SocketAddr`; the queued addresses are dialed in the order of
+    /// their priorities (the higher, the sooner), no more often than [`Config::dial_interval`], and only once
+    /// there is room for another connection. Returns `false` if the address is already connected, connecting,
+    /// or queued with the same or a higher priority; queueing it with a higher one makes it jump ahead.
+    pub fn enqueue_dial(&self, addr: SocketAddr, priority: u8) -> bool {
+        if self.is_connected(addr) || self.is_connecting(addr) {
+            return false;
+        }
+
+        if self.dial_queue.start() {
+            self.spawn_dialer();
+        }
+        self.dial_queue.push(addr, priority)
+    }
+
+    /// Returns the number of addresses queued via [`Tcp::enqueue_dial`] that haven't been dialed yet.
+    pub fn num_queued_dials(&self) -> usize {
+        self.dial_queue.len()
+    }
+
+    /// Disconnects from the provided `SocketAddr`; the messages still queued for it are flushed first, as long as
+    /// [`Config::disconnect_flush_timeout`] is set.
+    pub async fn disconnect(&self, addr: SocketAddr) -> bool {
+        self.disconnect_for(addr, DisconnectReason::Local).await
+    }
+
+    /// Works like [`Tcp::disconnect`], but reports the given reason to the subscribers of the disconnects.
+    pub(crate) async fn disconnect_for(&self, addr: SocketAddr, reason: DisconnectReason) -> bool {
+        self.disconnect_inner(addr, true, reason).await
+    }
+
+    /// Disconnects from the provided `SocketAddr` without flushing the queued messages; meant to be used once the
+    /// connection is known to be broken (e.g. due to an I/O error).
+    pub(crate) async fn abort_connection(&self, addr: SocketAddr, reason: DisconnectReason) -> bool {
+        self.disconnect_inner(addr, false, reason).await
+    }
+
+    /// Returns a receiver notified whenever a connection is torn down, with the address of the peer and the reason;
+    /// the subscribers that fall behind by more than 1024 events miss the oldest ones.
+    pub fn subscribe_disconnects(&self) -> broadcast::Receiver<(SocketAddr, DisconnectReason)> {
+        self.disconnects.subscribe()
+    }
+
+    /// Disconnects from the provided `SocketAddr`, optionally flushing the messages still queued for it.
+    async fn disconnect_inner(&self, addr: SocketAddr, flush: bool, reason: DisconnectReason) -> bool {
+        if let Some(handler) = self.protocols.disconnect.get() {
+            if self.is_connected(addr) {
+                let (sender, receiver) = oneshot::channel();
+                handler.trigger((addr, sender));
+                let _ = receiver.await; // can't really fail
+            }
+        }
+
+        let mut conn = self.connections.remove(addr);
+
+        if let Some(ref mut conn) = conn {
+            debug!(parent: self.span(), "Disconnecting from {}", conn.addr());
+
+            // Let the peer receive the final bytes, unless the connection is already broken.
+            if let (true, Some(flush_timeout)) = (flush, self.config.disconnect_flush_timeout) {
+                if !conn.flush_and_close(flush_timeout).await {
+                    debug!(parent: self.span(), "Couldn't flush the outbound messages to {}", conn.addr());
+                }
+            }
+
+            // Shut down the associated tasks of the peer.
+            for task in conn.tasks.iter().rev() {
+                task.abort();
+            }
+
+            // Reward the peer for maintaining a long-lived connection.
+            if conn.stats().uptime() >= self.config.long_lived_connection {
+                self.known_peers().adjust_score(conn.addr(), KnownPeers::LONG_LIVED_CONNECTION_REWARD);
+            }
+
+            // If the (owning) Tcp was not the initiator of the connection, it doesn't know the listening address
+            // of the associated peer, so the related stats are unreliable; the next connection initiated by the
+            // peer could be bound to an entirely different port number
+            if conn.side() == ConnectionSide::Initiator {
+                self.known_peers().remove(conn.addr());
+            }
+
+            debug!(parent: self.span(), "Disconnected from {} ({reason})", conn.addr());
+
+            self.update_peer_demand();
+
+            if let Some(ref disconnect_events) = self.disconnect_events {
+                let _ = disconnect_events.send((conn.addr(), conn.side()));
+            }
+            // note: the sending only fails if there are no subscribers
+            let _ = self.disconnects.send((conn.addr(), reason));
+        } else {
+            warn!(parent: self.span(), "Failed to disconnect, was not connected to {addr}");
+        }
+
+        conn.is_some()
+    }
 
+    /// Disconnects from all the connected peers, with up to [`Config::disconnect_concurrency`] disconnects
+    /// in progress at a time. Returns once all of them are concluded, with the number of peers disconnected from.
+    pub async fn disconnect_all(&self) -> usize {
+        self.disconnect_all_for(DisconnectReason::Local).await
+    }
+
+    /// The implementation of [`Tcp::disconnect_all`], reporting the given reason to the subscribers of the disconnects.
+    async fn disconnect_all_for(&self, reason: DisconnectReason) -> usize {
+        let concurrency = self.config.disconnect_concurrency.max(1);
+        let mut addrs = self.connected_addrs().into_iter();
+        let mut disconnects = JoinSet::new();
+        let mut num_disconnected = 0;
+
+        loop {
+            // Keep the number of concurrent disconnects within the limit.
+            while disconnects.len() < concurrency {
+                let Some(addr) = addrs.next() else { break };
+                let tcp = self.clone();
+                disconnects.spawn(async move { tcp.disconnect_for(addr, reason).await });
+            }
+            // Note: All the disconnects are awaited, so that none of them is aborted along with the `JoinSet`.
+            match disconnects.join_next().await {
+                Some(Ok(true)) => num_disconnected += 1,
+                Some(Ok(false)) => {}
+                Some(Err(e)) => error!(parent: self.span(), "A disconnect task failed: {e}"),
+                None => break,
+            }
+        }
+
+        num_disconnected
+    }
+}
+
+impl Tcp {
+    /// Spawns a task that listens for incoming connections.
+    pub async fn enable_listener(&self) -> io::Result<SocketAddr> {
+        // Retrieve the listening IP address, which must be set.
+        let listener_ip =
+            self.config().listener_ip.expect("Tcp::enable_listener was called, but Config::listener_ip is not set");
+
+        // Ensure no other addresses are bound if the node is restricted to the loopback ones.
+        if self.config().loopback_only {
+            let addrs = std::iter::once(listener_ip).chain(self.config().additional_listeners.iter().map(|a| a.ip()));
+            for ip in addrs {
+                if !ip.is_loopback() {
+                    error!(parent: self.span(), "Refusing to listen on {ip}; the node is restricted to loopback");
+                    return Err(io::Error::new(io::ErrorKind::InvalidInput, format!("{ip} is not a loopback address")));
+                }
+            }
+        }
+
+        // Initialize the TCP listener.
+        let listener = self.create_listener(listener_ip).await?;
+
+        // Discover the port, if it was unspecified.
+        let port = listener.local_addr()?.port();
+
+        // Determine the primary listening address.
+        let listening_addr = SocketAddr::new(listener_ip, port);
+        let mut listeners = vec![(listener, listening_addr)];
+
+        // Initialize the additional TCP listeners.
+        for &addr in &self.config().additional_listeners {
+            let listener = self.bind_listener(addr)?;
+            let addr = listener.local_addr()?;
+            listeners.push((listener, addr));
+        }
+
+        // Set the listening addresses.
+        {
+            let mut listening_addrs = self.listening_addrs.write();
+            assert!(listening_addrs.is_empty(), "The node's listener was started more than once");
+            *listening_addrs = listeners.iter().map(|(_, addr)| *addr).collect();
+        }
+
+        for (i, (listener, addr)) in listeners.into_iter().enumerate() {
+            let listening_task = self.spawn_listening_task(listener, addr).await;
+            if i == 0 {
+                *self.primary_listening_task.lock() =
+                    Some(NamedTask::new(format!("listener on {addr}"), listening_task));
+            } else {
+                self.listening_tasks.register(format!("listener on {addr}"), listening_task);
+            }
+        }
+
+        Ok(listening_addr)
+    }
+
+    /// Moves the primary listener to the provided address (e.g. once the node's public IP changes), without affecting
+    /// the existing connections; if the port is `0`, any available one is picked. If the new address can't be bound,
+    /// the old listener remains in place, and the error is returned. Returns the new primary listening address.
+    pub async fn rebind_listener(&self, new_addr: SocketAddr) -> io::Result<SocketAddr> {
+        let old_addr = self.listening_addr()?;
+
+        // Ensure no other addresses are bound if the node is restricted to the loopback ones.
+        if self.config().loopback_only && !new_addr.ip().is_loopback() {
+            error!(parent: self.span(), "Refusing to listen on {new_addr}; the node is restricted to loopback");
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, format!("{new_addr} is not a loopback address")));
+        }
+
+        // Bind the new listener before the old one is dropped.
+        let listener = self.bind_listener(new_addr).map_err(|e| {
+            error!(parent: self.span(), "Couldn't rebind the listener to {new_addr}: {e}");
+            e
+        })?;
+        let new_addr = SocketAddr::new(new_addr.ip(), listener.local_addr()?.port());
+
+        self.listening_addrs.write()[0] = new_addr;
+        let listening_task = self.spawn_listening_task(listener, new_addr).await;
+        let listening_task = NamedTask::new(format!("listener on {new_addr}"), listening_task);
+        if let Some(old_task) = self.primary_listening_task.lock().replace(listening_task) {
+            old_task.abort();
+        }
+        info!(parent: self.span(), "Moved the listener from {old_addr} to {new_addr}");
+
+        Ok(new_addr)
+    }
+
+    /// Spawns a task that listens for incoming connections on a Unix domain socket bound to the given path, which
+    /// must not exist yet; it can be used alongside, or instead of, [`Tcp::enable_listener`]. The socket file is
+    /// removed when the node is shut down.
+    #[cfg(unix)]
+    pub async fn enable_unix_listener(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        let path = path.as_ref().to_path_buf();
+        let listener = UnixListener::bind(&path)?;
+
+        // Use a channel to know when the listening task is ready.
+        let (tx, rx) = oneshot::channel();
+
+        let tcp = self.clone();
+        let listening_path = path.clone();
+        let listening_task = tokio::spawn(async move {
+            trace!(parent: tcp.span(), "Spawned the listening task for {}", listening_path.display());
+            tx.send(()).unwrap(); // safe; the channel was just opened
+
+            let mut consecutive_errors = 0;
+            loop {
+                // Await for a new connection.
+                match listener.accept().await {
+                    Ok((stream, _)) => {
+                        consecutive_errors = 0;
+                        if tcp.is_listening_paused() {
+                            debug!(parent: tcp.span(), "Refusing a Unix connection (the listener is paused)");
+                            continue;
+                        }
+                        tcp.accept_unix_connection(stream);
+                    }
+                    Err(e) => tcp.handle_accept_error(e, &mut consecutive_errors).await,
+                }
+            }
+        });
+        self.listening_tasks.register(format!("listener on {}", path.display()), listening_task);
+        let _ = rx.await;
+        debug!(parent: self.span(), "Listening on {}", path.display());
+        self.unix_listening_paths.lock().push(path);
+
+        Ok(())
+    }
+
+    /// Spawns a task that accepts the connections arriving at the given listener, and returns it once it's ready.
+    async fn spawn_listening_task(&self, listener: TcpListener, listening_addr: SocketAddr) -> JoinHandle<()> {
         // Use a channel to know when the listening task is ready.
         let (tx, rx) = oneshot::channel();
 
         let tcp = self.clone();
         let listening_task = tokio::spawn(async move {
-            trace!(parent: tcp.span(), "Spawned the listening task");
+            trace!(parent: tcp.span(), "Spawned the listening task for {listening_addr}");
             tx.send(()).unwrap(); // safe; the channel was just opened
 
+            let mut consecutive_errors = 0;
             loop {
                 // Await for a new connection.
                 match listener.accept().await {
-                    Ok((stream, addr)) => tcp.handle_connection(stream, addr),
-                    Err(e) => error!(parent: tcp.span(), "Failed to accept a connection: {e}"),
+                    Ok((stream, addr)) => {
+                        consecutive_errors = 0;
+                        if tcp.is_listening_paused() {
+                            debug!(parent: tcp.span(), "Refusing the connection from {addr} (the listener is paused)");
+                            continue;
+                        }
+                        tcp.handle_connection(stream, addr);
+                    }
+                    Err(e) => tcp.handle_accept_error(e, &mut consecutive_errors).await,
                 }
             }
         });
-        self.tasks.lock().push(listening_task);
         let _ = rx.await;
         debug!(parent: self.span(), "Listening on {listening_addr}");
 
-        Ok(listening_addr)
+        listening_task
+    }
+
+    /// Handles an error returned by a listener, backing off before the next accept, so that a persistent error
+    /// (e.g. running out of file descriptors) doesn't result in a busy loop.
+    async fn handle_accept_error(&self, e: io::Error, consecutive_errors: &mut u32) {
+        if is_fd_limit_error(&e) {
+            error!(
+                parent: self.span(),
+                "Out of file descriptors, can't accept a connection ({e}); consider raising the limit (`ulimit -n`)"
+            );
+        } else {
+            error!(parent: self.span(), "Failed to accept a connection: {e}");
+        }
+
+        // The delay is a plain sleep, so the listening task remains abortable.
+        let delay = accept_error_delay(*consecutive_errors);
+        *consecutive_errors = consecutive_errors.saturating_add(1);
+        sleep(delay).await;
     }
 
     /// Creates an instance of `TcpListener` based on the node's configuration.
@@ -335,7 +1586,7 @@ impl Tcp {
             // Construct the desired listening IP address.
             let desired_listening_addr = SocketAddr::new(listener_ip, port);
             // If a desired listening port is set, try to bind to it.
-            match TcpListener::bind(desired_listening_addr).await {
+            match self.bind_listener(desired_listening_addr) {
                 Ok(listener) => listener,
                 Err(e) => {
                     if self.config().allow_random_port {
@@ -344,7 +1595,7 @@ impl Tcp {
                             "Trying any listening port, as the desired port is unavailable: {e}"
                         );
                         let random_available_addr = SocketAddr::new(listener_ip, 0);
-                        TcpListener::bind(random_available_addr).await?
+                        self.bind_listener(random_available_addr)?
                     } else {
                         error!(parent: self.span(), "The desired listening port is unavailable: {e}");
                         return Err(e);
@@ -353,7 +1604,7 @@ impl Tcp {
             }
         } else if self.config().allow_random_port {
             let random_available_addr = SocketAddr::new(listener_ip, 0);
-            TcpListener::bind(random_available_addr).await?
+            self.bind_listener(random_available_addr)?
         } else {
             panic!("As 'listener_ip' is set, either 'desired_listening_port' or 'allow_random_port' must be set");
         };
@@ -361,91 +1612,555 @@ impl Tcp {
         Ok(listener)
     }
 
+    /// Binds a TCP listener to the given address, applying the socket options from the node's configuration.
+    fn bind_listener(&self, addr: SocketAddr) -> io::Result<TcpListener> {
+        let socket = if addr.is_ipv4() { TcpSocket::new_v4()? } else { TcpSocket::new_v6()? };
+
+        // Like `TcpListener::bind`, allow the port to be rebound while its old connections are in TIME_WAIT.
+        #[cfg(unix)]
+        socket.set_reuseaddr(true)?;
+        #[cfg(all(unix, not(target_os = "solaris"), not(target_os = "illumos")))]
+        if self.config.reuse_address {
+            socket.set_reuseport(true)?;
+        }
+
+        socket.bind(addr)?;
+        socket.listen(self.config.listen_backlog.unwrap_or(DEFAULT_LISTEN_BACKLOG))
+    }
+
     /// Handles a new inbound connection.
     fn handle_connection(&self, stream: TcpStream, addr: SocketAddr) {
         debug!(parent: self.span(), "Received a connection from {addr}");
 
-        if !self.can_add_connection() || self.is_self_connect(addr) {
-            debug!(parent: self.span(), "Rejecting the connection from {addr}");
+        if self.inbound_limiter.as_ref().is_some_and(|limiter| !limiter.try_consume(1)) {
+            debug!(parent: self.span(), "Shedding the connection from {addr} (inbound rate limit exceeded)");
             return;
         }
 
-        self.connecting.lock().insert(addr);
+        if !self.config.expect_proxy_protocol {
+            self.accept_connection(stream, addr);
+            return;
+        }
+
+        // The original source address needs to be read from the stream first; in the meantime, the connection counts
+        // against the connection limits as a pending one, registered under the address of the proxy.
+        if !self.can_add_connection() {
+            debug!(parent: self.span(), "Rejecting the connection from {addr}");
+            return;
+        }
+        self.connecting.lock().insert(addr, Instant::now());
 
         let tcp = self.clone();
         tokio::spawn(async move {
-            if let Err(e) = tcp.adapt_stream(stream, addr, ConnectionSide::Responder).await {
-                tcp.connecting.lock().remove(&addr);
-                tcp.known_peers().register_failure(addr);
-                error!(parent: tcp.span(), "Failed to connect with {addr}: {e}");
+            let mut stream = stream;
+            let header = timeout(proxy_protocol::HEADER_TIMEOUT, proxy_protocol::read_proxy_header(&mut stream)).await;
+            tcp.connecting.lock().remove(&addr);
+
+            match header {
+                Ok(Ok(source_addr)) => {
+                    let source_addr = source_addr.unwrap_or(addr);
+                    debug!(parent: tcp.span(), "The connection from {addr} was proxied on behalf of {source_addr}");
+                    tcp.accept_connection(stream, source_addr);
+                }
+                Ok(Err(e)) => error!(parent: tcp.span(), "Rejecting the connection from {addr}: {e}"),
+                Err(_) => error!(parent: tcp.span(), "Rejecting the connection from {addr}: no PROXY header"),
             }
         });
     }
 
-    /// Checks if the given IP address is the same as the listening address of this `Tcp`.
-    fn is_self_connect(&self, addr: SocketAddr) -> bool {
-        // SAFETY: if we're opening connections, this should never fail.
-        let listening_addr = self.listening_addr().unwrap();
-
-        match listening_addr.ip().is_loopback() {
-            // If localhost, check the ports, this only works on outbound connections, since we
-            // don't know the ephemeral port a peer might be using if they initiate the connection.
-            true => listening_addr.port() == addr.port(),
-            // If it's not localhost, matching IPs indicate a self-connect in both directions.
-            false => listening_addr.ip() == addr.ip(),
+    /// Accepts a new inbound connection from the given address, unless it should be rejected.
+    fn accept_connection(&self, stream: TcpStream, addr: SocketAddr) {
+        if !self.is_ip_permitted(addr.ip()) {
+            debug!(parent: self.span(), "Rejecting the connection from a denied address ({addr})");
+            return;
         }
-    }
-
-    /// Checks whether the `Tcp` can handle an additional connection.
-    fn can_add_connection(&self) -> bool {
-        // Retrieve the number of connected peers.
-        let num_connected = self.num_connected();
-        // Retrieve the maximum number of connected peers.
-        let limit = self.config.max_connections as usize;
 
-        if num_connected >= limit {
-            warn!(parent: self.span(), "Maximum number of active connections ({limit}) reached");
-            false
-        } else if num_connected + self.num_connecting() >= limit {
-            warn!(parent: self.span(), "Maximum number of active & pending connections ({limit}) reached");
-            false
-        } else {
-            true
+        // The port of an inbound connection is ephemeral, so any ban on the IP applies.
+        if self.known_peers.is_ip_banned(addr.ip()) {
+            debug!(parent: self.span(), "Rejecting the connection from a banned address ({addr})");
+            return;
         }
-    }
 
-    /// Prepares the freshly acquired connection to handle the protocols the Tcp implements.
-    async fn adapt_stream(&self, stream: TcpStream, peer_addr: SocketAddr, own_side: ConnectionSide) -> io::Result<()> {
-        self.known_peers.add(peer_addr);
+        if self.is_self_connect(addr) {
+            debug!(parent: self.span(), "Rejecting the connection from {addr}");
+            return;
+        }
 
-        // Register the port seen by the peer.
-        if own_side == ConnectionSide::Initiator {
-            if let Ok(addr) = stream.local_addr() {
-                debug!(
-                    parent: self.span(), "establishing connection with {}; the peer is connected on port {}",
-                    peer_addr, addr.port()
-                );
+        if !self.can_add_connection_from(addr.ip()) {
+            // If possible, make room for a trusted peer by evicting another one.
+            if let Some(evicted_addr) = self.eviction_candidate(addr.ip()) {
+                let tcp = self.clone();
+                tokio::spawn(async move {
+                    info!(parent: tcp.span(), "Evicting {evicted_addr} to admit the trusted peer {addr}");
+                    tcp.disconnect_for(evicted_addr, DisconnectReason::Policy).await;
+                    if tcp.can_add_connection_from(addr.ip()) {
+                        tcp.admit_connection(stream.into(), addr);
+                    } else {
+                        debug!(parent: tcp.span(), "Rejecting the connection from {addr}");
+                    }
+                });
             } else {
-                warn!(parent: self.span(), "couldn't determine the peer's port");
+                debug!(parent: self.span(), "Rejecting the connection from {addr}");
             }
+            return;
         }
 
-        let connection = Connection::new(peer_addr, stream, !own_side);
+        self.admit_connection(stream.into(), addr);
+    }
 
-        // Enact the enabled protocols.
-        let mut connection = self.enable_protocols(connection).await?;
+    /// Accepts a new inbound Unix domain socket connection, unless it should be rejected.
+    #[cfg(unix)]
+    fn accept_unix_connection(&self, stream: UnixStream) {
+        // Such peers have no IP address, so only the global connection limit applies to them.
+        let addr = unix_peer_addr();
+        debug!(parent: self.span(), "Received a Unix domain socket connection, assigned to {addr}");
+
+        if !self.can_add_connection() {
+            debug!(parent: self.span(), "Rejecting the connection from {addr}");
+            return;
+        }
+
+        self.admit_connection(stream.into(), addr);
+    }
+
+    /// Registers the inbound connection from the given address as pending, and finalizes it.
+    fn admit_connection(&self, stream: Stream, addr: SocketAddr) {
+        self.connecting.lock().insert(addr, Instant::now());
+
+        let tcp = self.clone();
+        tokio::spawn(async move {
+            if let Err(e) = tcp.adapt_stream(stream, addr, ConnectionSide::Responder, String::new()).await {
+                tcp.connecting.lock().remove(&addr);
+                tcp.discard_warmup_buffer(addr);
+                tcp.known_peers().register_failure(addr);
+                error!(parent: tcp.span(), "Failed to connect with {addr}: {e}");
+            }
+        });
+    }
+
+    /// Discards the outbound messages buffered for the given address while its connection was being set up.
+    fn discard_warmup_buffer(&self, addr: SocketAddr) {
+        if let Some(handler) = self.protocols.writing.get() {
+            handler.discard_warmup_buffer(addr);
+        }
+    }
+
+    /// Spawns a task that connects to the addresses from the dial queue.
+    fn spawn_dialer(&self) {
+        let tcp = self.clone();
+        let dialer_task = tokio::spawn(async move {
+            trace!(parent: tcp.span(), "Spawned the dialer task");
+
+            loop {
+                tcp.dial_queue.wait().await;
+
+                // The queued addresses wait until there is room for another connection.
+                if tcp.num_connected() + tcp.num_connecting() >= tcp.config.max_connections as usize {
+                    sleep(tcp.config.dial_interval.max(Duration::from_millis(10))).await;
+                    continue;
+                }
+
+                let Some(addr) = tcp.dial_queue.peek() else {
+                    continue;
+                };
+                tcp.dial_queue.remove(addr);
+
+                // The address might have been connected to since it was queued.
+                if tcp.is_connected(addr) || tcp.is_connecting(addr) {
+                    continue;
+                }
+
+                // The address is registered as pending right away, so that it's accounted for in the limit.
+                debug!(parent: tcp.span(), "Dialing {addr} from the queue");
+                if tcp.prepare_connect(addr).is_err() {
+                    continue;
+                }
+                let tcp_clone = tcp.clone();
+                tokio::spawn(async move {
+                    // The failures are logged and registered by the connect itself.
+                    let stream = tcp_clone.open_stream_within_timeout(addr).await;
+                    let _ = tcp_clone.finalize_connect(addr, stream, String::new()).await;
+                });
+                sleep(tcp.config.dial_interval).await;
+            }
+        });
+        self.tasks.register("dialer", dialer_task);
+    }
+
+    /// Spawns a task that periodically disconnects from the untrusted peers whose connections have exceeded the
+    /// maximum lifetime, and re-dials them if configured to.
+    fn spawn_connection_rotator(&self, max_lifetime: Duration) {
+        let tcp = self.clone();
+        let rotator_task = tokio::spawn(async move {
+            trace!(parent: tcp.span(), "Spawned the connection rotator task");
+
+            // Scan at a finer granularity than the lifetime itself, but not excessively often.
+            let scan_interval = (max_lifetime / 4).max(Duration::from_millis(10));
+            loop {
+                sleep(scan_interval).await;
+
+                // The connections are collected first, so that no lock is held during the disconnects; the ones
+                // that are still being set up are not included.
+                for (addr, side, label) in tcp.connections.expired(max_lifetime) {
+                    if tcp.is_trusted(addr.ip()) {
+                        continue;
+                    }
+
+                    debug!(parent: tcp.span(), "Rotating the connection with {addr} due to its age");
+                    tcp.disconnect_for(addr, DisconnectReason::Policy).await;
+
+                    // The connections initiated by the Tcp are the ones where the peer is the responder.
+                    if tcp.config.redial_rotated_connections && side == ConnectionSide::Responder {
+                        let tcp = tcp.clone();
+                        tokio::spawn(async move {
+                            let _ = tcp.connect_labeled(addr, label).await;
+                        });
+                    }
+                }
+            }
+        });
+        self.tasks.register("connection rotator", rotator_task);
+    }
+
+    /// Spawns a task that re-dials the trusted peers whose connections initiated by the Tcp were dropped.
+    fn spawn_trusted_peer_supervisor(
+        &self,
+        policy: BackoffPolicy,
+        mut receiver: mpsc::UnboundedReceiver<(SocketAddr, ConnectionSide)>,
+    ) {
+        let tcp = self.clone();
+        let supervisor_task = tokio::spawn(async move {
+            trace!(parent: tcp.span(), "Spawned the trusted peer supervisor task");
+
+            // The reconnects are owned by the supervisor, so that they're aborted along with it.
+            let mut reconnects = JoinSet::new();
+            let mut reconnecting = HashSet::new();
+            loop {
+                tokio::select! {
+                    event = receiver.recv() => {
+                        let Some((addr, side)) = event else { break };
+                        // The connections initiated by the Tcp are the ones where the peer is the responder.
+                        if side != ConnectionSide::Responder || !tcp.trusted_peers.read().contains(&addr) {
+                            continue;
+                        }
+                        // Avoid duplicate reconnects.
+                        if reconnecting.insert(addr) {
+                            reconnects.spawn(tcp.clone().reconnect_trusted_peer(addr, policy));
+                        }
+                    }
+                    Some(result) = reconnects.join_next(), if !reconnects.is_empty() => {
+                        if let Ok(addr) = result {
+                            reconnecting.remove(&addr);
+                        }
+                    }
+                }
+            }
+        });
+        self.tasks.register("trusted peer supervisor", supervisor_task);
+    }
+
+    /// Re-dials the given trusted peer in accordance with the given [`BackoffPolicy`], until the connection is
+    /// re-established or the peer is no longer trusted or gets banned; returns the peer's address.
+    async fn reconnect_trusted_peer(self, addr: SocketAddr, policy: BackoffPolicy) -> SocketAddr {
+        for delay in policy.delays().chain(std::iter::repeat(policy.max_delay)) {
+            sleep(delay).await;
+
+            if !self.trusted_peers.read().contains(&addr) || self.is_banned(addr) {
+                debug!(parent: self.span(), "No longer reconnecting to {addr}");
+                break;
+            }
+            // The peer might have been connected to in the meantime.
+            if self.is_connected(addr) || self.is_connecting(addr) {
+                break;
+            }
+
+            debug!(parent: self.span(), "Reconnecting to the trusted peer {addr}");
+            if self.connect(addr).await.is_ok() {
+                break;
+            }
+        }
+
+        addr
+    }
+
+    /// Spawns a task that periodically drops the pending connections that have been registered for too long.
+    fn spawn_pending_connection_sweeper(&self, max_age: Duration) {
+        let tcp = self.clone();
+        let sweeper_task = tokio::spawn(async move {
+            trace!(parent: tcp.span(), "Spawned the pending connection sweeper task");
+
+            // Scan at a finer granularity than the maximum age itself, but not excessively often.
+            let scan_interval = (max_age / 4).max(Duration::from_millis(10));
+            loop {
+                sleep(scan_interval).await;
+
+                tcp.connecting.lock().retain(|addr, registered_at| {
+                    let is_stale = registered_at.elapsed() > max_age;
+                    if is_stale {
+                        warn!(parent: tcp.span(), "Dropping a stale pending connection with {addr}");
+                    }
+                    !is_stale
+                });
+            }
+        });
+        self.tasks.register("pending connection sweeper", sweeper_task);
+    }
+
+    /// Spawns a task that periodically disconnects from the peers whose connections have been idle for too long.
+    fn spawn_idle_connection_reaper(&self, idle_timeout: Duration) {
+        let tcp = self.clone();
+        let reaper_task = tokio::spawn(async move {
+            trace!(parent: tcp.span(), "Spawned the idle connection reaper task");
+
+            // Scan at a finer granularity than the timeout itself, but not excessively often.
+            let scan_interval = (idle_timeout / 4).max(Duration::from_millis(10));
+            loop {
+                sleep(scan_interval).await;
+
+                // The connections are collected first, so that no lock is held during the disconnects.
+                for addr in tcp.connections.idle_addrs(idle_timeout) {
+                    if tcp.is_trusted(addr.ip()) {
+                        continue;
+                    }
+
+                    debug!(parent: tcp.span(), "Disconnecting from {addr} due to inactivity");
+                    tcp.disconnect_for(addr, DisconnectReason::Policy).await;
+                }
+            }
+        });
+        self.tasks.register("idle connection reaper", reaper_task);
+    }
+
+    /// Checks if the given IP address is the same as any of the listening addresses of this `Tcp`.
+    fn is_self_connect(&self, addr: SocketAddr) -> bool {
+        self.listening_addrs().into_iter().any(|listening_addr| match listening_addr.ip().is_loopback() {
+            // If localhost, check the ports, this only works on outbound connections, since we
+            // don't know the ephemeral port a peer might be using if they initiate the connection.
+            true => listening_addr.port() == addr.port(),
+            // If it's not localhost, matching IPs indicate a self-connect in both directions; however, as
+            // IPv6 link-local addresses are only unique within a link, they must also share the scope.
+            false => listening_addr.ip() == addr.ip() && have_matching_scopes(listening_addr, addr),
+        })
+    }
+
+    /// Checks whether the given IP address is allowed and not denied; IPv4-mapped IPv6 addresses are checked as the
+    /// IPv4 addresses they represent.
+    fn is_ip_permitted(&self, ip: IpAddr) -> bool {
+        let ip = ip.to_canonical();
+        if self.denied_ips.read().iter().any(|network| network.contains(ip)) {
+            return false;
+        }
+
+        match self.config.allowed_ips {
+            Some(ref allowed_ips) => allowed_ips.iter().any(|network| network.contains(ip)),
+            None => true,
+        }
+    }
+
+    /// Checks whether the given IP address belongs to a trusted peer, i.e. one of the [`Config::trusted_ips`] or
+    /// the [`Config::trusted_peers`]; such peers bypass the connection limits, and are never evicted, reaped or
+    /// rotated. As the ports of the inbound connections are ephemeral, the peers are matched by their IP addresses.
+    pub fn is_trusted(&self, ip: IpAddr) -> bool {
+        self.config.trusted_ips.contains(&ip) || self.trusted_peers.read().iter().any(|addr| addr.ip() == ip)
+    }
+
+    /// Returns the address of the peer that should be evicted in order to admit a connection from the given IP
+    /// address, if [`Config::allow_eviction`] is set, the IP is trusted, and the connection limit is reached.
+    fn eviction_candidate(&self, ip: IpAddr) -> Option<SocketAddr> {
+        // Evicting a peer only makes room if the global limit is the obstacle.
+        if !self.config.allow_eviction || !self.is_trusted(ip) || self.can_add_connection() {
+            return None;
+        }
+
+        // Only the fully established connections are considered, so no peer is evicted mid-handshake.
+        self.lowest_scoring_peers(self.num_connected())
+            .into_iter()
+            .find(|addr| !self.is_trusted(addr.ip()) && !self.is_connecting(*addr))
+    }
+
+    /// Checks whether the `Tcp` can handle an additional connection.
+    fn can_add_connection(&self) -> bool {
+        // Retrieve the number of connected peers.
+        let num_connected = self.num_connected();
+        // Retrieve the maximum number of connected peers.
+        let limit = self.config.max_connections as usize;
+
+        if num_connected >= limit {
+            warn!(parent: self.span(), "Maximum number of active connections ({limit}) reached");
+            false
+        } else if num_connected + self.num_connecting() >= limit {
+            warn!(parent: self.span(), "Maximum number of active & pending connections ({limit}) reached");
+            false
+        } else {
+            true
+        }
+    }
+
+    /// Checks whether the `Tcp` can handle an additional connection with the given IP address.
+    fn can_add_connection_from(&self, ip: IpAddr) -> bool {
+        // The trusted peers are always admitted.
+        if self.is_trusted(ip) {
+            return true;
+        }
+
+        if !self.can_add_connection() {
+            return false;
+        }
+
+        // Retrieve the per-IP limit, if there is one.
+        let Some(limit) = self.config.max_connections_per_ip else {
+            return true;
+        };
+        if ip.is_loopback() && self.config.exempt_loopback_from_ip_limit {
+            return true;
+        }
+
+        // Count both the active and the pending connections with the given IP.
+        let num_connected = self.connections.num_connected_ip(ip);
+        let num_connecting = self.connecting.lock().keys().filter(|addr| addr.ip() == ip).count();
+
+        if num_connected + num_connecting >= limit as usize {
+            warn!(parent: self.span(), "Maximum number of connections with {ip} ({limit}) reached");
+            false
+        } else {
+            true
+        }
+    }
+
+    /// Checks whether the `Tcp` can initiate an additional connection with the given IP address without exceeding
+    /// [`Config::max_outbound_per_prefix`].
+    fn can_add_outbound_connection_to(&self, ip: IpAddr) -> bool {
+        let Some(limit) = self.config.max_outbound_per_prefix else {
+            return true;
+        };
+        if self.is_trusted(ip) {
+            return true;
+        }
+
+        // The connections initiated by the Tcp are the ones where the peer is the responder.
+        let num_within_prefix = self
+            .connected_addrs_by_side(ConnectionSide::Responder)
+            .into_iter()
+            .filter(|addr| have_matching_prefixes(addr.ip(), ip))
+            .count();
+
+        num_within_prefix < limit as usize
+    }
+
+    /// Prepares the freshly acquired connection to handle the protocols the Tcp implements.
+    async fn adapt_stream(
+        &self,
+        stream: Stream,
+        peer_addr: SocketAddr,
+        own_side: ConnectionSide,
+        label: String,
+    ) -> io::Result<()> {
+        let start = Instant::now();
+        // The TLS handshake, the network magic exchange, and the Handshake protocol share a single deadline.
+        let deadline = self.config.handshake_timeout.map(|handshake_timeout| start + handshake_timeout);
+        self.known_peers.add(peer_addr);
+
+        // Register the port seen by the peer.
+        if own_side == ConnectionSide::Initiator {
+            match stream {
+                Stream::Tcp(ref stream) => {
+                    if let Ok(addr) = stream.local_addr() {
+                        debug!(
+                            parent: self.span(), "establishing connection with {}; the peer is connected on port {}",
+                            peer_addr, addr.port()
+                        );
+                    } else {
+                        warn!(parent: self.span(), "couldn't determine the peer's port");
+                    }
+                }
+                #[cfg(unix)]
+                Stream::Unix(_) => {
+                    debug!(parent: self.span(), "establishing connection with {peer_addr} over a Unix domain socket");
+                }
+                Stream::Tls(_) => {}
+            }
+        }
+
+        // Apply the configured socket options.
+        match stream {
+            Stream::Tcp(ref stream) => self.set_socket_options(stream, peer_addr),
+            #[cfg(unix)]
+            Stream::Unix(_) => {}
+            Stream::Tls(_) => {}
+        }
+
+        // Secure the stream with TLS, if it's enabled; it precedes the Handshake protocol.
+        let mut stream = match (&self.config.tls, stream) {
+            (Some(tls), Stream::Tcp(stream)) => {
+                match within_deadline(deadline, tls.secure(stream, peer_addr, own_side)).await {
+                    Ok(stream) => Stream::Tls(Box::new(stream)),
+                    Err(e) => {
+                        self.stats.register_handshake_failure();
+                        return Err(io::Error::new(e.kind(), format!("TLS handshake failed: {e}")));
+                    }
+                }
+            }
+            (_, stream) => stream,
+        };
+
+        // Exchange the network magic, if it's configured, so that the unrelated connections are dropped right away.
+        if let Some(magic) = self.config.network_magic {
+            if let Err(e) = within_deadline(deadline, exchange_network_magic(&mut stream, magic)).await {
+                self.stats.register_handshake_failure();
+                return Err(e);
+            }
+        }
+
+        let mut connection = Connection::new(peer_addr, stream, !own_side);
+        connection.span = create_connection_span(self.span(), peer_addr, &label);
+        connection.label = label;
+        connection.send_limiter = self.config.max_send_bytes_per_sec.map(RateLimiter::new);
+        connection.recv_limiter = self.config.max_recv_bytes_per_sec.map(RateLimiter::new);
+        connection.flow_control = self.config.flow_control_window.map(|window| Arc::new(FlowControl::new(window)));
+
+        // Enact the enabled protocols.
+        let mut connection = match self.enable_protocols(connection, deadline).await {
+            Ok(connection) => connection,
+            Err(e) => {
+                self.stats.register_handshake_failure();
+                return Err(e);
+            }
+        };
 
         // if Reading is enabled, we'll notify the related task when the connection is fully ready.
         let conn_ready_tx = connection.readiness_notifier.take();
+        let conn_ready = Arc::clone(&connection.ready);
+
+        // The pending connection could have been swept in the meantime (see Config::max_connecting_age); it's no
+        // longer accounted for, so it mustn't be established.
+        if !self.is_connecting(peer_addr) {
+            for task in &connection.tasks {
+                task.abort();
+            }
+            return Err(io::Error::new(io::ErrorKind::TimedOut, "the pending connection was dropped as stale"));
+        }
 
         self.connections.add(connection);
         self.connecting.lock().remove(&peer_addr);
+        self.update_peer_demand();
+        self.known_peers.register_seen(peer_addr);
+        self.stats.register_handshake_latency(start.elapsed());
 
-        // Send the aforementioned notification so that reading from the socket can commence.
+        // Mark the connection as ready and send the aforementioned notification so that reading from the socket
+        // can commence.
+        conn_ready.store(true, Release);
         if let Some(tx) = conn_ready_tx {
             let _ = tx.send(());
         }
 
+        // Send the messages buffered while the connection was being set up, unless there were too many of them.
+        if let Some(handler) = self.protocols.writing.get() {
+            if !handler.flush_warmup_buffer(peer_addr) {
+                self.disconnect_for(peer_addr, DisconnectReason::Policy).await;
+                return Err(io::Error::new(io::ErrorKind::ConnectionAborted, "the warmup buffer overflowed"));
+            }
+        }
+
         // If enabled, enact OnConnect.
         if let Some(handler) = self.protocols.on_connect.get() {
             let (sender, receiver) = oneshot::channel();
@@ -456,17 +2171,66 @@ impl Tcp {
         Ok(())
     }
 
-    /// Enacts the enabled protocols on the provided connection.
-    async fn enable_protocols(&self, conn: Connection) -> io::Result<Connection> {
+    /// Applies the TCP socket options from the node's configuration to the given stream.
+    fn set_socket_options(&self, stream: &TcpStream, peer_addr: SocketAddr) {
+        let socket = socket2::SockRef::from(stream);
+
+        if let Some(keepalive) = self.config.tcp_keepalive {
+            if let Err(e) = socket.set_tcp_keepalive(&socket2::TcpKeepalive::new().with_time(keepalive)) {
+                warn!(parent: self.span(), "Couldn't enable TCP keepalive for {peer_addr}: {e}");
+            }
+        }
+
+        if self.config.tcp_nodelay {
+            if let Err(e) = socket.set_nodelay(true) {
+                warn!(parent: self.span(), "Couldn't enable TCP_NODELAY for {peer_addr}: {e}");
+            }
+        }
+
+        if let Some(size) = self.config.so_sndbuf {
+            match socket.set_send_buffer_size(size).and_then(|_| socket.send_buffer_size()) {
+                Ok(granted) => {
+                    debug!(parent: self.span(), "The send buffer for {peer_addr} is {granted}B (requested: {size}B)")
+                }
+                Err(e) => warn!(parent: self.span(), "Couldn't set the send buffer size for {peer_addr}: {e}"),
+            }
+        }
+
+        if let Some(size) = self.config.so_rcvbuf {
+            match socket.set_recv_buffer_size(size).and_then(|_| socket.recv_buffer_size()) {
+                Ok(granted) => {
+                    debug!(parent: self.span(), "The receive buffer for {peer_addr} is {granted}B (requested: {size}B)")
+                }
+                Err(e) => warn!(parent: self.span(), "Couldn't set the receive buffer size for {peer_addr}: {e}"),
+            }
+        }
+    }
+
+    /// Enacts the enabled protocols on the provided connection; the handshake is bounded by the given deadline.
+    async fn enable_protocols(&self, conn: Connection, deadline: Option<Instant>) -> io::Result<Connection> {
         /// A helper macro to enable a protocol on a connection.
         macro_rules! enable_protocol {
             ($handler_type: ident, $node:expr, $conn: expr) => {
                 if let Some(handler) = $node.protocols.$handler_type.get() {
+                    let span = debug_span!(
+                        parent: $node.span(),
+                        "enable_protocol",
+                        protocol = stringify!($handler_type),
+                        addr = %$conn.addr(),
+                        elapsed_ms = field::Empty,
+                    );
+                    // the stage is only timed if its span is enabled
+                    let start = (!span.is_disabled()).then(Instant::now);
                     let (conn_returner, conn_retriever) = oneshot::channel();
 
                     handler.trigger(($conn, conn_returner));
 
-                    match conn_retriever.await {
+                    let result = conn_retriever.instrument(span.clone()).await;
+                    if let Some(start) = start {
+                        span.record("elapsed_ms", start.elapsed().as_millis() as u64);
+                    }
+
+                    match result {
                         Ok(Ok(conn)) => conn,
                         Err(_) => return Err(io::ErrorKind::BrokenPipe.into()),
                         Ok(e) => return e,
@@ -477,7 +2241,15 @@ impl Tcp {
             };
         }
 
-        let mut conn = enable_protocol!(handshake, self, conn);
+        // The handshake is additionally bounded by the configured timeout, so that stalling peers are cleaned out.
+        let handshake = async { Ok::<_, io::Error>(enable_protocol!(handshake, self, conn)) };
+        let mut conn = within_deadline(deadline, handshake).await?;
+
+        // Reject the peers that didn't prove their identity, if it's required.
+        if self.config.require_peer_identity && conn.identity.is_none() {
+            warn!(parent: self.span(), "Rejecting {}: {}", conn.addr(), RejectReason::Unauthenticated);
+            return Err(RejectReason::Unauthenticated.into());
+        }
 
         // Split the stream after the handshake (if not done before).
         if let Some(stream) = conn.stream.take() {
@@ -486,13 +2258,134 @@ impl Tcp {
             conn.writer = Some(Box::new(writer));
         }
 
-        let conn = enable_protocol!(reading, self, conn);
-        let conn = enable_protocol!(writing, self, conn);
+        let conn = match self.config.protocol_order {
+            ProtocolOrder::ReadingFirst => {
+                let conn = enable_protocol!(reading, self, conn);
+                enable_protocol!(writing, self, conn)
+            }
+            ProtocolOrder::WritingFirst => {
+                let conn = enable_protocol!(writing, self, conn);
+                enable_protocol!(reading, self, conn)
+            }
+        };
 
         Ok(conn)
     }
 }
 
+/// Awaits the given step of setting up a connection, bounded by the given deadline, if there is one.
+async fn within_deadline<T, F: Future<Output = io::Result<T>>>(deadline: Option<Instant>, step: F) -> io::Result<T> {
+    match deadline {
+        Some(deadline) => {
+            timeout_at(deadline.into(), step).await.unwrap_or_else(|_| Err(io::ErrorKind::TimedOut.into()))
+        }
+        None => step.await,
+    }
+}
+
+/// Sends the given network magic via the stream, and ensures that the peer sends the same one.
+async fn exchange_network_magic(stream: &mut Stream, magic: [u8; 4]) -> io::Result<()> {
+    stream.write_all(&magic).await?;
+    let mut peer_magic = [0u8; 4];
+    stream.read_exact(&mut peer_magic).await?;
+
+    if peer_magic != magic {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, format!("mismatched network magic {peer_magic:02x?}")));
+    }
+    Ok(())
+}
+
+/// Returns the delay preceding the next accept after the given number of consecutive accept errors.
+pub fn accept_error_delay(consecutive_errors: u32) -> Duration {
+    MIN_ACCEPT_ERROR_DELAY.saturating_mul(1 << consecutive_errors.min(16)).min(MAX_ACCEPT_ERROR_DELAY)
+}
+
+/// Checks whether the given error indicates that the process (`EMFILE`) or the system (`ENFILE`) has run out of
+/// file descriptors.
+fn is_fd_limit_error(e: &io::Error) -> bool {
+    // The values of EMFILE and ENFILE are the same on all the supported Unix platforms.
+    #[cfg(unix)]
+    const FD_LIMIT_ERRORS: [i32; 2] = [24, 23];
+    // WSAEMFILE
+    #[cfg(windows)]
+    const FD_LIMIT_ERRORS: [i32; 1] = [10024];
+    #[cfg(not(any(unix, windows)))]
+    const FD_LIMIT_ERRORS: [i32; 0] = [];
+
+    e.raw_os_error().map(|code| FD_LIMIT_ERRORS.contains(&code)).unwrap_or(false)
+}
+
+/// Checks whether the given addresses may belong to the same link, i.e. unless they are IPv6 link-local addresses
+/// with different (non-zero) scope IDs; an unspecified scope is considered to match any other.
+fn have_matching_scopes(addr1: SocketAddr, addr2: SocketAddr) -> bool {
+    match (addr1, addr2) {
+        (SocketAddr::V6(addr1), SocketAddr::V6(addr2)) if is_unicast_link_local(addr1.ip()) => {
+            addr1.scope_id() == 0 || addr2.scope_id() == 0 || addr1.scope_id() == addr2.scope_id()
+        }
+        _ => true,
+    }
+}
+
+/// Checks whether the given IP addresses share the prefix relevant to [`Config::max_outbound_per_prefix`], i.e.
+/// the `/16` for IPv4 and the `/32` for IPv6; the IPv4-mapped IPv6 addresses are treated as IPv4 ones.
+fn have_matching_prefixes(ip1: IpAddr, ip2: IpAddr) -> bool {
+    match (ip1.to_canonical(), ip2.to_canonical()) {
+        (IpAddr::V4(ip1), IpAddr::V4(ip2)) => ip1.octets()[..2] == ip2.octets()[..2],
+        (IpAddr::V6(ip1), IpAddr::V6(ip2)) => ip1.segments()[..2] == ip2.segments()[..2],
+        _ => false,
+    }
+}
+
+/// Checks whether the given IPv6 address is a unicast link-local one (`fe80::/10`).
+fn is_unicast_link_local(ip: &Ipv6Addr) -> bool {
+    ip.segments()[0] & 0xffc0 == 0xfe80
+}
+
+/// Returns a unique placeholder address for a Unix domain socket connection; it belongs to the discard-only
+/// `100::/64` block (RFC 6666), so it can't clash with the address of an actual peer.
+#[cfg(unix)]
+fn unix_peer_addr() -> SocketAddr {
+    let id = SEQUENTIAL_UNIX_CONN_ID.fetch_add(1, Relaxed);
+    SocketAddr::new(IpAddr::V6(Ipv6Addr::from(0x0100_u128 << 112 | id as u128)), 0)
+}
+
+/// Removes an address from the set of pending connections when dropped, unless it is defused.
+struct ConnectingGuard<'a> {
+    tcp: &'a Tcp,
+    addr: SocketAddr,
+}
+
+impl ConnectingGuard<'_> {
+    /// Keeps the address in the set of pending connections, and returns it.
+    fn defuse(self) -> SocketAddr {
+        let addr = self.addr;
+        std::mem::forget(self);
+        addr
+    }
+}
+
+impl Drop for ConnectingGuard<'_> {
+    fn drop(&mut self) {
+        self.tcp.connecting.lock().remove(&self.addr);
+    }
+}
+
+/// Reorders the given addresses so that the IP families alternate, starting with the family of the first one.
+fn interleave_ip_families(addrs: Vec<SocketAddr>) -> Vec<SocketAddr> {
+    let Some(first_family) = addrs.first().map(|addr| IpFamily::of(addr.ip())) else {
+        return addrs;
+    };
+    let (mut primary, mut secondary): (VecDeque<_>, VecDeque<_>) =
+        addrs.into_iter().partition(|addr| IpFamily::of(addr.ip()) == first_family);
+
+    let mut interleaved = Vec::with_capacity(primary.len() + secondary.len());
+    while !primary.is_empty() || !secondary.is_empty() {
+        interleaved.extend(primary.pop_front());
+        interleaved.extend(secondary.pop_front());
+    }
+    interleaved
+}
+
 impl fmt::Debug for Tcp {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         write!(f, "The TCP stack config: {:?}", self.config)
@@ -502,52 +2395,2716 @@ impl fmt::Debug for Tcp {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::{
+        protocols::{Disconnect, Handshake, HandshakeAttempt, Priority, Reading, RetryingHandshake, Writing},
+        rustls,
+        MessageTypeStats,
+        TlsConfig,
+        P2P,
+    };
+
+    use std::{
+        borrow::Cow,
+        net::{IpAddr, Ipv4Addr, SocketAddrV6},
+        sync::atomic::AtomicU64,
+    };
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio_util::codec::{BytesCodec, Decoder, LengthDelimitedCodec};
+
+    /// A node that records the sizes of the socket buffers of its connections during the handshake.
+    #[derive(Clone)]
+    struct BufferNode(Tcp, Arc<Mutex<Vec<(usize, usize)>>>);
+
+    impl P2P for BufferNode {
+        fn tcp(&self) -> &Tcp {
+            &self.0
+        }
+    }
+
+    #[async_trait::async_trait]
+    impl Handshake for BufferNode {
+        async fn perform_handshake(&self, mut conn: Connection) -> io::Result<Connection> {
+            if let Stream::Tcp(stream) = self.borrow_stream(&mut conn) {
+                let socket = socket2::SockRef::from(&*stream);
+                self.1.lock().push((socket.send_buffer_size()?, socket.recv_buffer_size()?));
+            }
+            Ok(conn)
+        }
+    }
+
+    /// A node that exchanges its capabilities with its peers during the handshake.
+    #[derive(Clone)]
+    struct CapabilityNode(Tcp, Capabilities);
+
+    impl P2P for CapabilityNode {
+        fn tcp(&self) -> &Tcp {
+            &self.0
+        }
+    }
+
+    #[async_trait::async_trait]
+    impl Handshake for CapabilityNode {
+        async fn perform_handshake(&self, mut conn: Connection) -> io::Result<Connection> {
+            let stream = self.borrow_stream(&mut conn);
+            stream.write_u64_le(self.1.bits()).await?;
+            let capabilities = Capabilities::from_bits_retain(stream.read_u64_le().await?);
+            conn.set_capabilities(capabilities);
+            Ok(conn)
+        }
+    }
+
+    /// A node that exchanges its numeric identity with its peers during the handshake; `0` stands for none.
+    #[derive(Clone)]
+    struct IdentityNode(Tcp, u64);
+
+    impl P2P for IdentityNode {
+        fn tcp(&self) -> &Tcp {
+            &self.0
+        }
+    }
+
+    #[async_trait::async_trait]
+    impl Handshake for IdentityNode {
+        async fn perform_handshake(&self, mut conn: Connection) -> io::Result<Connection> {
+            let stream = self.borrow_stream(&mut conn);
+            stream.write_u64_le(self.1).await?;
+            let identity = stream.read_u64_le().await?;
+            if identity != 0 {
+                conn.set_identity(identity);
+            }
+            Ok(conn)
+        }
+    }
+
+    /// A node whose handshake never concludes in a timely manner.
+    #[derive(Clone)]
+    struct StallingNode(Tcp);
+
+    impl P2P for StallingNode {
+        fn tcp(&self) -> &Tcp {
+            &self.0
+        }
+    }
+
+    /// A node that counts the bytes it reads.
+    #[derive(Clone)]
+    struct ReadingNode(Tcp, Arc<AtomicU64>);
+
+    impl P2P for ReadingNode {
+        fn tcp(&self) -> &Tcp {
+            &self.0
+        }
+    }
+
+    #[async_trait::async_trait]
+    impl Reading for ReadingNode {
+        type Codec = BytesCodec;
+        type Message = bytes::BytesMut;
+
+        fn codec(&self, _addr: SocketAddr, _side: ConnectionSide) -> Self::Codec {
+            Default::default()
+        }
+
+        async fn process_message(&self, _source: SocketAddr, message: Self::Message) -> io::Result<()> {
+            self.1.fetch_add(message.len() as u64, Relaxed);
+            Ok(())
+        }
+    }
+
+    /// A node that records whether its connections are ready during the handshake and upon reading a message.
+    #[derive(Clone)]
+    struct ReadinessNode(Tcp, Arc<Mutex<Vec<bool>>>);
+
+    impl P2P for ReadinessNode {
+        fn tcp(&self) -> &Tcp {
+            &self.0
+        }
+    }
+
+    #[async_trait::async_trait]
+    impl Handshake for ReadinessNode {
+        async fn perform_handshake(&self, conn: Connection) -> io::Result<Connection> {
+            self.1.lock().push(conn.is_ready());
+            self.1.lock().push(self.tcp().is_connection_ready(conn.addr()));
+            Ok(conn)
+        }
+    }
+
+    #[async_trait::async_trait]
+    impl Reading for ReadinessNode {
+        type Codec = BytesCodec;
+        type Message = bytes::BytesMut;
+
+        fn codec(&self, _addr: SocketAddr, _side: ConnectionSide) -> Self::Codec {
+            Default::default()
+        }
+
+        async fn process_message(&self, source: SocketAddr, _message: Self::Message) -> io::Result<()> {
+            self.1.lock().push(self.tcp().is_connection_ready(source));
+            Ok(())
+        }
+    }
+
+    /// A node that records the length-delimited messages it reads, bounded by its maximum message size.
+    #[derive(Clone)]
+    struct FramingNode(Tcp, Arc<Mutex<Vec<bytes::BytesMut>>>);
+
+    impl P2P for FramingNode {
+        fn tcp(&self) -> &Tcp {
+            &self.0
+        }
+    }
+
+    #[async_trait::async_trait]
+    impl Reading for FramingNode {
+        type Codec = LengthDelimitedCodec;
+        type Message = bytes::BytesMut;
+
+        fn codec(&self, _addr: SocketAddr, _side: ConnectionSide) -> Self::Codec {
+            LengthDelimitedCodec::builder()
+                .max_frame_length(self.0.config().max_message_size)
+                .little_endian()
+                .new_codec()
+        }
+
+        async fn process_message(&self, _source: SocketAddr, message: Self::Message) -> io::Result<()> {
+            self.1.lock().push(message);
+            Ok(())
+        }
+    }
+
+    /// A node that counts the length-delimited messages it reads, and the growths of its read buffers.
+    #[derive(Clone)]
+    struct CapacityNode(Tcp, Arc<AtomicUsize>, Arc<AtomicUsize>);
+
+    impl P2P for CapacityNode {
+        fn tcp(&self) -> &Tcp {
+            &self.0
+        }
+    }
+
+    /// A length-delimited codec that registers every time the read buffer outgrows its peak capacity.
+    struct CapacityCodec {
+        inner: LengthDelimitedCodec,
+        peak: usize,
+        growths: Arc<AtomicUsize>,
+    }
+
+    impl CapacityCodec {
+        fn register_capacity(&mut self, capacity: usize) {
+            if capacity > self.peak {
+                // the first observed capacity is the initial one
+                if self.peak != 0 {
+                    self.growths.fetch_add(1, Relaxed);
+                }
+                self.peak = capacity;
+            }
+        }
+    }
+
+    impl Decoder for CapacityCodec {
+        type Error = io::Error;
+        type Item = bytes::BytesMut;
+
+        fn decode(&mut self, src: &mut bytes::BytesMut) -> io::Result<Option<Self::Item>> {
+            self.register_capacity(src.capacity());
+            let message = self.inner.decode(src)?;
+            self.register_capacity(src.capacity());
+            Ok(message)
+        }
+    }
+
+    #[async_trait::async_trait]
+    impl Reading for CapacityNode {
+        type Codec = CapacityCodec;
+        type Message = bytes::BytesMut;
+
+        fn codec(&self, _addr: SocketAddr, _side: ConnectionSide) -> Self::Codec {
+            CapacityCodec {
+                inner: LengthDelimitedCodec::builder().little_endian().new_codec(),
+                peak: 0,
+                growths: self.2.clone(),
+            }
+        }
+
+        async fn process_message(&self, _source: SocketAddr, _message: Self::Message) -> io::Result<()> {
+            self.1.fetch_add(1, Relaxed);
+            Ok(())
+        }
+    }
+
+    /// A node that treats every length-delimited message it reads as a control message, dropping the flooding peers.
+    #[derive(Clone)]
+    struct ControlNode(Tcp);
+
+    impl P2P for ControlNode {
+        fn tcp(&self) -> &Tcp {
+            &self.0
+        }
+    }
+
+    #[async_trait::async_trait]
+    impl Reading for ControlNode {
+        type Codec = LengthDelimitedCodec;
+        type Message = bytes::BytesMut;
+
+        fn codec(&self, _addr: SocketAddr, _side: ConnectionSide) -> Self::Codec {
+            LengthDelimitedCodec::builder().little_endian().new_codec()
+        }
+
+        async fn process_message(&self, source: SocketAddr, _message: Self::Message) -> io::Result<()> {
+            if !self.tcp().register_control_message(source).await {
+                self.tcp().disconnect(source).await;
+            }
+            Ok(())
+        }
+    }
+
+    /// A node that writes length-delimited messages, using short outbound message queues.
+    #[derive(Clone)]
+    struct WritingNode(Tcp);
+
+    impl P2P for WritingNode {
+        fn tcp(&self) -> &Tcp {
+            &self.0
+        }
+    }
+
+    impl Writing for WritingNode {
+        type Codec = LengthDelimitedCodec;
+        type Message = bytes::Bytes;
+
+        const HIGH_PRIORITY_BURST: usize = 2;
+        const MESSAGE_QUEUE_DEPTH: usize = 4;
+
+        fn codec(&self, _addr: SocketAddr, _side: ConnectionSide) -> Self::Codec {
+            LengthDelimitedCodec::builder().little_endian().new_codec()
+        }
+    }
+
+    /// A node that writes length-delimited messages, with a slow handshake.
+    #[derive(Clone)]
+    struct WarmupNode(Tcp);
+
+    impl P2P for WarmupNode {
+        fn tcp(&self) -> &Tcp {
+            &self.0
+        }
+    }
+
+    #[async_trait::async_trait]
+    impl Handshake for WarmupNode {
+        async fn perform_handshake(&self, conn: Connection) -> io::Result<Connection> {
+            sleep(Duration::from_millis(200)).await;
+            Ok(conn)
+        }
+    }
+
+    impl Writing for WarmupNode {
+        type Codec = LengthDelimitedCodec;
+        type Message = bytes::Bytes;
+
+        fn codec(&self, _addr: SocketAddr, _side: ConnectionSide) -> Self::Codec {
+            LengthDelimitedCodec::builder().little_endian().new_codec()
+        }
+    }
+
+    /// A node that reads and writes length-delimited messages, whose type depends on their length.
+    #[derive(Clone)]
+    struct TypedNode(Tcp);
+
+    impl TypedNode {
+        fn type_of(message: &[u8]) -> Option<Cow<'static, str>> {
+            Some(if message.len() < 8 { "short".into() } else { "long".into() })
+        }
+    }
+
+    impl P2P for TypedNode {
+        fn tcp(&self) -> &Tcp {
+            &self.0
+        }
+    }
+
+    impl Writing for TypedNode {
+        type Codec = LengthDelimitedCodec;
+        type Message = bytes::Bytes;
+
+        fn codec(&self, _addr: SocketAddr, _side: ConnectionSide) -> Self::Codec {
+            LengthDelimitedCodec::builder().little_endian().new_codec()
+        }
+
+        fn message_type(message: &Self::Message) -> Option<Cow<'static, str>> {
+            TypedNode::type_of(message)
+        }
+    }
+
+    #[async_trait::async_trait]
+    impl Reading for TypedNode {
+        type Codec = LengthDelimitedCodec;
+        type Message = bytes::BytesMut;
+
+        fn codec(&self, _addr: SocketAddr, _side: ConnectionSide) -> Self::Codec {
+            LengthDelimitedCodec::builder().little_endian().new_codec()
+        }
+
+        fn message_type(message: &Self::Message) -> Option<Cow<'static, str>> {
+            TypedNode::type_of(message)
+        }
+    }
+
+    /// A node that only reads from its connections once its gate is opened.
+    #[derive(Clone)]
+    struct GatedNode(Tcp, Arc<AtomicBool>, Arc<AtomicUsize>);
+
+    impl P2P for GatedNode {
+        fn tcp(&self) -> &Tcp {
+            &self.0
+        }
+    }
+
+    #[async_trait::async_trait]
+    impl Reading for GatedNode {
+        type Codec = LengthDelimitedCodec;
+        type Message = bytes::BytesMut;
+
+        fn codec(&self, _addr: SocketAddr, _side: ConnectionSide) -> Self::Codec {
+            LengthDelimitedCodec::builder().little_endian().new_codec()
+        }
+
+        async fn await_read_readiness(&self, _addr: SocketAddr) {
+            while !self.1.load(SeqCst) {
+                sleep(Duration::from_millis(10)).await;
+            }
+        }
+
+        async fn process_message(&self, _source: SocketAddr, _message: Self::Message) -> io::Result<()> {
+            self.2.fetch_add(1, SeqCst);
+            Ok(())
+        }
+    }
+
+    /// A node that reads and writes length-delimited messages, only processing the inbound ones once its gate is
+    /// opened.
+    #[derive(Clone)]
+    struct ThrottledNode(Tcp, Arc<AtomicBool>);
+
+    impl P2P for ThrottledNode {
+        fn tcp(&self) -> &Tcp {
+            &self.0
+        }
+    }
+
+    impl Writing for ThrottledNode {
+        type Codec = LengthDelimitedCodec;
+        type Message = bytes::Bytes;
+
+        fn codec(&self, _addr: SocketAddr, _side: ConnectionSide) -> Self::Codec {
+            LengthDelimitedCodec::builder().little_endian().new_codec()
+        }
+    }
+
+    #[async_trait::async_trait]
+    impl Reading for ThrottledNode {
+        type Codec = LengthDelimitedCodec;
+        type Message = bytes::BytesMut;
+
+        fn codec(&self, _addr: SocketAddr, _side: ConnectionSide) -> Self::Codec {
+            LengthDelimitedCodec::builder().little_endian().new_codec()
+        }
+
+        async fn process_message(&self, _source: SocketAddr, _message: Self::Message) -> io::Result<()> {
+            while !self.1.load(SeqCst) {
+                sleep(Duration::from_millis(10)).await;
+            }
+            Ok(())
+        }
+    }
+
+    /// A message handler that counts the messages it receives.
+    struct CountingHandler(Arc<AtomicUsize>);
+
+    #[async_trait::async_trait]
+    impl MessageHandler<bytes::BytesMut> for CountingHandler {
+        async fn handle(&self, _peer: SocketAddr, _message: bytes::BytesMut) -> io::Result<()> {
+            self.0.fetch_add(1, SeqCst);
+            Ok(())
+        }
+    }
+
+    /// A node whose disconnects take a while, and which tracks the greatest number of them in progress at once.
+    #[derive(Clone)]
+    struct DisconnectingNode(Tcp, Arc<AtomicUsize>, Arc<AtomicUsize>);
+
+    impl P2P for DisconnectingNode {
+        fn tcp(&self) -> &Tcp {
+            &self.0
+        }
+    }
+
+    #[async_trait::async_trait]
+    impl Disconnect for DisconnectingNode {
+        async fn handle_disconnect(&self, _peer_addr: SocketAddr) {
+            let in_progress = self.1.fetch_add(1, SeqCst) + 1;
+            self.2.fetch_max(in_progress, SeqCst);
+            sleep(Duration::from_millis(100)).await;
+            self.1.fetch_sub(1, SeqCst);
+        }
+    }
+
+    #[async_trait::async_trait]
+    impl Handshake for StallingNode {
+        async fn perform_handshake(&self, conn: Connection) -> io::Result<Connection> {
+            tokio::time::sleep(Duration::from_secs(1)).await;
+            Ok(conn)
+        }
+    }
+
+    /// A handshake failing a given number of times before succeeding; it also counts the attempts made while
+    /// the address was still considered to be connecting.
+    #[derive(Clone)]
+    struct FlakyHandshake {
+        tcp: Tcp,
+        failures_left: Arc<AtomicUsize>,
+        attempts_while_connecting: Arc<AtomicUsize>,
+        error_kind: io::ErrorKind,
+    }
+
+    #[async_trait::async_trait]
+    impl HandshakeAttempt for FlakyHandshake {
+        async fn attempt(&self, conn: &mut Connection) -> io::Result<()> {
+            if self.tcp.is_connecting(conn.addr()) {
+                self.attempts_while_connecting.fetch_add(1, SeqCst);
+            }
+
+            if self.failures_left.fetch_update(SeqCst, SeqCst, |n| n.checked_sub(1)).is_ok() {
+                Err(self.error_kind.into())
+            } else {
+                Ok(())
+            }
+        }
+    }
+
+    #[derive(Clone)]
+    struct FlakyNode(Tcp, RetryingHandshake<FlakyHandshake>);
+
+    impl FlakyNode {
+        fn new(failures: usize, error_kind: io::ErrorKind, policy: BackoffPolicy) -> Self {
+            let tcp = Tcp::new(Default::default());
+            let handshake = FlakyHandshake {
+                tcp: tcp.clone(),
+                failures_left: Arc::new(AtomicUsize::new(failures)),
+                attempts_while_connecting: Default::default(),
+                error_kind,
+            };
+            Self(tcp, RetryingHandshake::new(handshake, policy))
+        }
+    }
+
+    impl P2P for FlakyNode {
+        fn tcp(&self) -> &Tcp {
+            &self.0
+        }
+    }
+
+    #[async_trait::async_trait]
+    impl Handshake for FlakyNode {
+        async fn perform_handshake(&self, conn: Connection) -> io::Result<Connection> {
+            self.1.perform(conn).await
+        }
+    }
+
+    /// Returns the address of a local listener that never accepts and whose backlog is already full, so that any
+    /// further connection attempts to it stall; the returned listener and streams need to be kept alive meanwhile.
+    async fn unresponsive_listener() -> (SocketAddr, TcpListener, Vec<TcpStream>) {
+        let socket = TcpSocket::new_v4().unwrap();
+        socket.bind((Ipv4Addr::LOCALHOST, 0).into()).unwrap();
+        let listener = socket.listen(0).unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        // Fill the backlog until the connection attempts start to stall.
+        let mut streams = Vec::new();
+        while let Ok(Ok(stream)) = timeout(Duration::from_millis(100), TcpStream::connect(addr)).await {
+            streams.push(stream);
+            assert!(streams.len() < 16, "the backlog of the listener couldn't be filled");
+        }
+
+        (addr, listener, streams)
+    }
+
+    /// Starts a minimal SOCKS5 proxy relaying the connections to the requested IPv4 addresses; returns its address,
+    /// along with the number of the connections it has accepted.
+    async fn start_socks5_proxy() -> (SocketAddr, Arc<AtomicUsize>) {
+        let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, 0)).await.unwrap();
+        let proxy_addr = listener.local_addr().unwrap();
+        let num_accepted = Arc::new(AtomicUsize::default());
+
+        let counter = num_accepted.clone();
+        tokio::spawn(async move {
+            while let Ok((mut stream, _)) = listener.accept().await {
+                counter.fetch_add(1, Relaxed);
+                tokio::spawn(async move {
+                    // Accept the unauthenticated method, and read the request for an IPv4 address.
+                    let mut greeting = [0u8; 3];
+                    stream.read_exact(&mut greeting).await?;
+                    stream.write_all(&[5, 0]).await?;
+                    let mut request = [0u8; 10];
+                    stream.read_exact(&mut request).await?;
+                    let ip = Ipv4Addr::new(request[4], request[5], request[6], request[7]);
+                    let target = SocketAddr::from((ip, u16::from_be_bytes([request[8], request[9]])));
+
+                    // Connect to the target, and relay the stream.
+                    let mut target_stream = TcpStream::connect(target).await?;
+                    stream.write_all(&[5, 0, 0, 1, 0, 0, 0, 0, 0, 0]).await?;
+                    tokio::io::copy_bidirectional(&mut stream, &mut target_stream).await?;
+                    Ok::<_, io::Error>(())
+                });
+            }
+        });
+
+        (proxy_addr, num_accepted)
+    }
+
+    #[tokio::test]
+    async fn test_new() {
+        let tcp = Tcp::new(Config {
+            listener_ip: Some(IpAddr::V4(Ipv4Addr::LOCALHOST)),
+            max_connections: 200,
+            ..Default::default()
+        });
+
+        assert_eq!(tcp.config.max_connections, 200);
+        assert_eq!(tcp.config.listener_ip, Some(IpAddr::V4(Ipv4Addr::LOCALHOST)));
+        assert_eq!(tcp.enable_listener().await.unwrap().ip(), IpAddr::V4(Ipv4Addr::LOCALHOST));
+
+        assert_eq!(tcp.num_connected(), 0);
+        assert_eq!(tcp.num_connecting(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_additional_listeners() {
+        let tcp = Tcp::new(Config {
+            listener_ip: Some(IpAddr::V4(Ipv4Addr::LOCALHOST)),
+            desired_listening_port: Some(0),
+            additional_listeners: vec![SocketAddr::from((Ipv4Addr::LOCALHOST, 0))],
+            ..Default::default()
+        });
+        let node_ip = tcp.enable_listener().await.unwrap();
+
+        let listening_addrs = tcp.listening_addrs();
+        assert_eq!(listening_addrs.len(), 2);
+        assert_eq!(listening_addrs[0], node_ip);
+        assert_eq!(tcp.listening_addr().unwrap(), node_ip);
+
+        // Ensure self-connecting is not possible via any of the listeners.
+        for addr in listening_addrs {
+            let err = tcp.connect(addr).await.unwrap_err();
+            assert_eq!(err.kind(), io::ErrorKind::AddrInUse);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_listener_options() {
+        // Ensure the listener can be bound using the custom options.
+        let tcp = Tcp::new(Config {
+            listener_ip: Some(IpAddr::V4(Ipv4Addr::LOCALHOST)),
+            desired_listening_port: Some(0),
+            listen_backlog: Some(16),
+            reuse_address: true,
+            ..Default::default()
+        });
+        let node_ip = tcp.enable_listener().await.unwrap();
+        TcpStream::connect(node_ip).await.unwrap();
+
+        // Ensure another listener can share the port.
+        #[cfg(target_os = "linux")]
+        {
+            let sharer = Tcp::new(Config {
+                listener_ip: Some(IpAddr::V4(Ipv4Addr::LOCALHOST)),
+                desired_listening_port: Some(node_ip.port()),
+                allow_random_port: false,
+                reuse_address: true,
+                ..Default::default()
+            });
+            assert_eq!(sharer.enable_listener().await.unwrap(), node_ip);
+        }
+
+        // Occupy a port.
+        let occupier = std::net::TcpListener::bind((Ipv4Addr::LOCALHOST, 0)).unwrap();
+        let busy_port = occupier.local_addr().unwrap().port();
+
+        // Ensure a random port is picked if the desired one is busy.
+        let tcp = Tcp::new(Config {
+            listener_ip: Some(IpAddr::V4(Ipv4Addr::LOCALHOST)),
+            desired_listening_port: Some(busy_port),
+            ..Default::default()
+        });
+        let node_ip = tcp.enable_listener().await.unwrap();
+        assert_ne!(node_ip.port(), busy_port);
+        TcpStream::connect(node_ip).await.unwrap();
+
+        // Ensure binding fails if a random port is not allowed.
+        let tcp = Tcp::new(Config {
+            listener_ip: Some(IpAddr::V4(Ipv4Addr::LOCALHOST)),
+            desired_listening_port: Some(busy_port),
+            allow_random_port: false,
+            ..Default::default()
+        });
+        assert_eq!(tcp.enable_listener().await.unwrap_err().kind(), io::ErrorKind::AddrInUse);
+    }
+
+    #[tokio::test]
+    async fn test_connect() {
+        let tcp = Tcp::new(Config::default());
+        let node_ip = tcp.enable_listener().await.unwrap();
+
+        // Ensure self-connecting is not possible.
+        tcp.connect(node_ip).await.unwrap_err();
+        assert_eq!(tcp.num_connected(), 0);
+        assert_eq!(tcp.num_connecting(), 0);
+        assert!(!tcp.is_connected(node_ip));
+        assert!(!tcp.is_connecting(node_ip));
+
+        // Initialize the peer.
+        let peer = Tcp::new(Config {
+            listener_ip: Some(IpAddr::V4(Ipv4Addr::LOCALHOST)),
+            desired_listening_port: Some(0),
+            max_connections: 1,
+            ..Default::default()
+        });
+        let peer_ip = peer.enable_listener().await.unwrap();
+
+        // Connect to the peer.
+        tcp.connect(peer_ip).await.unwrap();
+        assert_eq!(tcp.num_connected(), 1);
+        assert_eq!(tcp.num_connecting(), 0);
+        assert!(tcp.is_connected(peer_ip));
+        assert!(!tcp.is_connecting(peer_ip));
+
+        // Ensure the connection establishment is registered.
+        assert!(tcp.stats().handshake_latency_percentiles().is_some());
+        assert_eq!(tcp.stats().handshake_failures(), 0);
+
+        // Ensure the connection stats are available.
+        let conn_stats = tcp.connection_stats(peer_ip).unwrap();
+        assert_eq!(conn_stats.bytes_sent(), 0);
+        assert_eq!(conn_stats.bytes_received(), 0);
+        assert_eq!(tcp.all_connection_stats().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_snapshot() {
+        let tcp = Tcp::new(Config { name: Some("snapshot".into()), ..Default::default() });
+        let _node_ip = tcp.enable_listener().await.unwrap();
+
+        // Initialize the peer.
+        let peer = Tcp::new(Config {
+            listener_ip: Some(IpAddr::V4(Ipv4Addr::LOCALHOST)),
+            desired_listening_port: Some(0),
+            max_connections: 1,
+            ..Default::default()
+        });
+        let peer_ip = peer.enable_listener().await.unwrap();
+
+        // Connect to the peer.
+        tcp.connect(peer_ip).await.unwrap();
+        tcp.known_peers().register_sent_message(peer_ip, 42);
+
+        let snapshot = tcp.snapshot();
+        assert_eq!(snapshot.name, "snapshot");
+        assert_eq!(snapshot.num_connected, 1);
+        assert_eq!(snapshot.num_connecting, 0);
+        assert_eq!(snapshot.connected_addrs, vec![peer_ip]);
+        assert!(snapshot.connecting_addrs.is_empty());
+        assert_eq!(snapshot.known_peers.len(), 1);
+        assert_eq!(snapshot.known_peers[0].addr, peer_ip);
+        assert_eq!(snapshot.known_peers[0].stats.msgs_sent, 1);
+        assert_eq!(snapshot.known_peers[0].stats.bytes_sent, 42);
+
+        // Ensure the snapshot can be serialized and deserialized.
+        let json = serde_json::to_string(&snapshot).unwrap();
+        assert_eq!(serde_json::from_str::<TcpSnapshot>(&json).unwrap(), snapshot);
+    }
+
+    #[tokio::test]
+    async fn test_peer_info() {
+        let tcp = Tcp::new(Config::default());
+        let peer = Tcp::new(Config { listener_ip: Some(IpAddr::V4(Ipv4Addr::LOCALHOST)), ..Default::default() });
+        let peer_ip = peer.enable_listener().await.unwrap();
+        assert!(tcp.peer_info(peer_ip).is_none());
+
+        // Connect to the peer, and register some of its history.
+        tcp.connect_labeled(peer_ip, "bootstrap".into()).await.unwrap();
+        assert!(tcp.set_node_type(peer_ip, NodeType::Validator));
+        tcp.known_peers().adjust_score(peer_ip, 5);
+        tcp.known_peers().register_latency(peer_ip, Duration::from_millis(15));
+
+        let info = tcp.peer_info(peer_ip).unwrap();
+        assert_eq!(info.addr, peer_ip);
+        assert_eq!(info.side, ConnectionSide::Responder);
+        assert_eq!(info.node_type, Some(NodeType::Validator));
+        assert_eq!(info.label, "bootstrap");
+        assert!(info.is_ready);
+        assert_eq!(info.latency_ms, Some(15));
+        assert_eq!(info.score, 5);
+        assert_eq!(tcp.all_peer_info().into_iter().map(|info| info.addr).collect::<Vec<_>>(), vec![peer_ip]);
+
+        // Ensure the information can be serialized and deserialized.
+        let json = serde_json::to_string(&info).unwrap();
+        assert_eq!(serde_json::from_str::<PeerInfo>(&json).unwrap(), info);
+    }
+
+    #[tokio::test]
+    async fn test_stats_by_message_type() {
+        let node = TypedNode(Tcp::new(Config::default()));
+        node.enable_reading().await;
+        node.enable_writing().await;
+
+        // Initialize the peer, and connect to it.
+        let peer = TypedNode(Tcp::new(Config {
+            listener_ip: Some(IpAddr::V4(Ipv4Addr::LOCALHOST)),
+            desired_listening_port: Some(0),
+            ..Default::default()
+        }));
+        peer.enable_reading().await;
+        peer.enable_writing().await;
+        let peer_ip = peer.tcp().enable_listener().await.unwrap();
+        node.tcp().connect(peer_ip).await.unwrap();
+
+        // Send a few messages; each of them is preceded by its 4B length.
+        for message in [&[0u8; 2][..], &[0u8; 4], &[0u8; 16]] {
+            node.unicast(peer_ip, bytes::Bytes::copy_from_slice(message)).unwrap().await.unwrap().unwrap();
+        }
+        let sent = node.tcp().stats().sent_by_message_type();
+        assert_eq!(sent.len(), 2);
+        assert_eq!(sent["short"], MessageTypeStats { count: 2, total_bytes: 14, min_size: 6, max_size: 8 });
+        assert_eq!(sent["long"], MessageTypeStats { count: 1, total_bytes: 20, min_size: 20, max_size: 20 });
+
+        // Ensure the received messages are registered in the same way, and separately from the sent ones.
+        while peer.tcp().stats().received_by_message_type() != sent {
+            sleep(Duration::from_millis(10)).await;
+        }
+        assert!(node.tcp().stats().received_by_message_type().is_empty());
+        assert!(peer.tcp().stats().sent_by_message_type().is_empty());
+
+        // Ensure both directions are combined per message type.
+        let node_addr = peer.tcp().connected_addrs()[0];
+        peer.unicast(node_addr, bytes::Bytes::from_static(&[0u8; 32])).unwrap().await.unwrap().unwrap();
+        let combined = peer.tcp().stats().by_message_type();
+        assert_eq!(combined["short"], sent["short"]);
+        assert_eq!(combined["long"], MessageTypeStats { count: 2, total_bytes: 56, min_size: 20, max_size: 36 });
+
+        // Ensure the statistics are reset along with the other counters.
+        node.tcp().reset_stats();
+        assert!(node.tcp().stats().sent_by_message_type().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_protocol_order() {
+        let config = || Config {
+            listener_ip: Some(IpAddr::V4(Ipv4Addr::LOCALHOST)),
+            desired_listening_port: Some(0),
+            protocol_order: ProtocolOrder::WritingFirst,
+            ..Default::default()
+        };
+        let node = TypedNode(Tcp::new(config()));
+        let peer = TypedNode(Tcp::new(config()));
+        for node in [&node, &peer] {
+            node.enable_writing().await;
+            node.enable_reading().await;
+        }
+        let peer_ip = peer.tcp().enable_listener().await.unwrap();
+        node.tcp().connect(peer_ip).await.unwrap();
+        let node_addr = timeout(Duration::from_secs(1), async {
+            loop {
+                if let Some(addr) = peer.tcp().connected_addrs().pop() {
+                    break addr;
+                }
+                sleep(Duration::from_millis(10)).await;
+            }
+        })
+        .await
+        .unwrap();
+
+        // Ensure the messages flow both ways with the protocols enabled in the reverse order.
+        node.unicast(peer_ip, bytes::Bytes::from_static(b"ping")).unwrap().await.unwrap().unwrap();
+        peer.unicast(node_addr, bytes::Bytes::from_static(b"pong")).unwrap().await.unwrap().unwrap();
+        timeout(Duration::from_secs(1), async {
+            while node.tcp().stats().received().0 != 1 || peer.tcp().stats().received().0 != 1 {
+                sleep(Duration::from_millis(10)).await;
+            }
+        })
+        .await
+        .unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_await_read_readiness() {
+        let node = WritingNode(Tcp::new(Config::default()));
+        node.enable_writing().await;
+
+        // Initialize the peer, and connect to it.
+        let peer = GatedNode(
+            Tcp::new(Config { listener_ip: Some(IpAddr::V4(Ipv4Addr::LOCALHOST)), ..Default::default() }),
+            Default::default(),
+            Default::default(),
+        );
+        peer.enable_reading().await;
+        let peer_ip = peer.tcp().enable_listener().await.unwrap();
+        node.tcp().connect(peer_ip).await.unwrap();
+
+        // Ensure the message is not read while the gate is closed.
+        node.unicast(peer_ip, bytes::Bytes::from_static(b"hello")).unwrap().await.unwrap().unwrap();
+        sleep(Duration::from_millis(100)).await;
+        assert_eq!(peer.2.load(SeqCst), 0);
+
+        // Ensure it's read once the gate opens.
+        peer.1.store(true, SeqCst);
+        timeout(Duration::from_secs(1), async {
+            while peer.2.load(SeqCst) != 1 {
+                sleep(Duration::from_millis(10)).await;
+            }
+        })
+        .await
+        .unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_message_dispatch() {
+        let node = TypedNode(Tcp::new(Config::default()));
+        node.enable_writing().await;
+
+        // Initialize the peer, and register its message handlers.
+        let peer = TypedNode(Tcp::new(Config {
+            listener_ip: Some(IpAddr::V4(Ipv4Addr::LOCALHOST)),
+            desired_listening_port: Some(0),
+            ..Default::default()
+        }));
+        let num_long = Arc::new(AtomicUsize::new(0));
+        let num_other = Arc::new(AtomicUsize::new(0));
+        assert!(peer.tcp().register_message_handler("long", CountingHandler(Arc::new(AtomicUsize::new(0)))));
+        assert!(!peer.tcp().register_message_handler("long", CountingHandler(num_long.clone())));
+        peer.tcp().set_fallback_message_handler(CountingHandler(num_other.clone()));
+        peer.enable_reading().await;
+        let peer_ip = peer.tcp().enable_listener().await.unwrap();
+        node.tcp().connect(peer_ip).await.unwrap();
+
+        // Send a few messages of different types.
+        for message in [&[0u8; 2][..], &[0u8; 16], &[0u8; 16]] {
+            node.unicast(peer_ip, bytes::Bytes::copy_from_slice(message)).unwrap().await.unwrap().unwrap();
+        }
+
+        // Ensure the messages are routed to the handlers registered for their types.
+        while num_long.load(SeqCst) + num_other.load(SeqCst) != 3 {
+            sleep(Duration::from_millis(10)).await;
+        }
+        assert_eq!(num_long.load(SeqCst), 2);
+        assert_eq!(num_other.load(SeqCst), 1);
+    }
+
+    #[test]
+    fn test_clock_skew() {
+        let tcp = Tcp::new(Config { max_clock_offset: Some(Duration::from_secs(1)), ..Default::default() });
+        let peer_addr = "127.0.0.1:4130".parse().unwrap();
+
+        // Ensure a peer without an estimate is not considered skewed.
+        tcp.known_peers().add(peer_addr);
+        assert_eq!(tcp.known_peers().clock_offset(peer_addr), None);
+        assert!(!tcp.is_clock_skewed(peer_addr));
+
+        // Ensure the offsets are compared by their magnitude.
+        tcp.known_peers().register_clock_offset(peer_addr, -500);
+        assert_eq!(tcp.known_peers().clock_offset(peer_addr), Some(Duration::from_millis(500)));
+        assert!(!tcp.is_clock_skewed(peer_addr));
+        tcp.known_peers().register_clock_offset(peer_addr, -1_500);
+        assert!(tcp.is_clock_skewed(peer_addr));
+        tcp.known_peers().register_clock_offset(peer_addr, 1_500);
+        assert!(tcp.is_clock_skewed(peer_addr));
+        assert_eq!(tcp.known_peers().get(peer_addr).unwrap().clock_offset_ms(), Some(1_500));
+    }
+
+    #[tokio::test]
+    async fn test_reset_stats() {
+        let tcp = Tcp::new(Config::default());
+        let peer_addr = "127.0.0.1:4130".parse().unwrap();
+
+        // Register some statistics.
+        tcp.known_peers().add(peer_addr);
+        tcp.known_peers().register_sent_message(peer_addr, 42);
+        tcp.known_peers().register_failure(peer_addr);
+        tcp.known_peers().register_latency(peer_addr, Duration::from_millis(10));
+        tcp.stats().register_received_message(64);
+        tcp.stats().register_failure();
+        let score = tcp.known_peers().score(peer_addr).unwrap();
+
+        // Reset the statistics.
+        tcp.reset_stats();
+        assert_eq!(tcp.stats().received(), (0, 0));
+        assert_eq!(tcp.stats().failures(), 0);
+        let peer_stats = tcp.known_peers().get(peer_addr).unwrap();
+        assert_eq!(peer_stats.sent(), (0, 0));
+        assert_eq!(peer_stats.failures(), 0);
+
+        // Ensure the gauges are intact.
+        assert_eq!(peer_stats.score(), score);
+        assert_eq!(peer_stats.latency(), Some(Duration::from_millis(10)));
+    }
+
+    #[tokio::test]
+    async fn test_connect_timeout() {
+        let tcp = Tcp::new(Config { connect_timeout: Some(Duration::from_millis(1)), ..Default::default() });
+        let _node_ip = tcp.enable_listener().await.unwrap();
+
+        // Attempt to connect to an address that doesn't respond.
+        let (addr, _listener, _streams) = unresponsive_listener().await;
+        assert_eq!(tcp.connect(addr).await.unwrap_err().kind(), io::ErrorKind::TimedOut);
+        assert_eq!(tcp.num_connected(), 0);
+        assert_eq!(tcp.num_connecting(), 0);
+        assert!(!tcp.is_connected(addr));
+        assert!(!tcp.is_connecting(addr));
+
+        // Ensure the timeout is registered as a failure of the peer.
+        assert_eq!(tcp.known_peers().get(addr).unwrap().failures(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_handshake_timeout() {
+        let node = StallingNode(Tcp::new(Config {
+            handshake_timeout: Some(Duration::from_millis(100)),
+            ..Default::default()
+        }));
+        node.enable_handshake().await;
+
+        // Initialize the peer.
+        let peer = Tcp::new(Config {
+            listener_ip: Some(IpAddr::V4(Ipv4Addr::LOCALHOST)),
+            desired_listening_port: Some(0),
+            max_connections: 1,
+            ..Default::default()
+        });
+        let peer_ip = peer.enable_listener().await.unwrap();
+
+        // Ensure the stalled handshake is cut short.
+        let err = node.tcp().connect(peer_ip).await.unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::TimedOut);
+        assert_eq!(node.tcp().num_connected(), 0);
+        assert_eq!(node.tcp().num_connecting(), 0);
+        assert!(!node.tcp().is_connected(peer_ip));
+        assert!(!node.tcp().is_connecting(peer_ip));
+
+        // Ensure the failed handshake is not registered as a latency sample.
+        assert_eq!(node.tcp().stats().handshake_failures(), 1);
+        assert_eq!(node.tcp().stats().handshake_latency_percentiles(), None);
+    }
+
+    #[tokio::test]
+    async fn test_handshake_timeout_spans_magic_exchange() {
+        let node = StallingNode(Tcp::new(Config {
+            listener_ip: Some(IpAddr::V4(Ipv4Addr::LOCALHOST)),
+            handshake_timeout: Some(Duration::from_millis(400)),
+            network_magic: Some(*b"TEST"),
+            ..Default::default()
+        }));
+        node.enable_handshake().await;
+        let node_ip = node.tcp().enable_listener().await.unwrap();
+
+        // Exchange the magic slowly, after which the handshake stalls.
+        let start = Instant::now();
+        let mut stream = TcpStream::connect(node_ip).await.unwrap();
+        let mut magic = [0u8; 4];
+        stream.read_exact(&mut magic).await.unwrap();
+        sleep(Duration::from_millis(300)).await;
+        stream.write_all(&magic).await.unwrap();
+
+        // Ensure the time spent on the magic exchange counts towards the handshake timeout.
+        let mut received = Vec::new();
+        let _ = timeout(Duration::from_secs(1), stream.read_to_end(&mut received)).await.unwrap();
+        let elapsed = start.elapsed();
+        assert!(elapsed >= Duration::from_millis(400) && elapsed < Duration::from_millis(650), "{elapsed:?}");
+        assert_eq!(node.tcp().num_connected(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_connection_readiness() {
+        let node = ReadinessNode(
+            Tcp::new(Config {
+                listener_ip: Some(IpAddr::V4(Ipv4Addr::LOCALHOST)),
+                desired_listening_port: Some(0),
+                ..Default::default()
+            }),
+            Default::default(),
+        );
+        node.enable_handshake().await;
+        node.enable_reading().await;
+        let node_ip = node.tcp().enable_listener().await.unwrap();
+
+        // Connect to the node and send it a message.
+        let mut stream = TcpStream::connect(node_ip).await.unwrap();
+        let peer_addr = stream.local_addr().unwrap();
+        stream.write_all(b"hello").await.unwrap();
+
+        // Wait for the message to be read.
+        while node.1.lock().len() < 3 {
+            sleep(Duration::from_millis(10)).await;
+        }
+
+        // Ensure the connection was not ready during the handshake, but it was once the message was read.
+        assert_eq!(*node.1.lock(), vec![false, false, true]);
+        assert!(node.tcp().is_connection_ready(peer_addr));
+
+        // Ensure a disconnected address is not considered ready.
+        node.tcp().disconnect(peer_addr).await;
+        assert!(!node.tcp().is_connection_ready(peer_addr));
+    }
+
+    #[tokio::test]
+    async fn test_retrying_handshake() {
+        let policy = BackoffPolicy {
+            initial_delay: Duration::from_millis(10),
+            multiplier: 2,
+            max_delay: Duration::from_millis(50),
+            max_attempts: 3,
+        };
+
+        // Initialize the peer.
+        let peer = Tcp::new(Config {
+            listener_ip: Some(IpAddr::V4(Ipv4Addr::LOCALHOST)),
+            desired_listening_port: Some(0),
+            ..Default::default()
+        });
+        let peer_ip = peer.enable_listener().await.unwrap();
+
+        // Ensure a handshake failing fewer times than the number of attempts eventually succeeds, with
+        // the address remaining in the connecting set throughout the retries.
+        let node = FlakyNode::new(2, io::ErrorKind::InvalidData, policy);
+        node.enable_handshake().await;
+        node.tcp().connect(peer_ip).await.unwrap();
+        assert!(node.tcp().is_connected(peer_ip));
+        assert_eq!(node.1.inner().attempts_while_connecting.load(SeqCst), 3);
+        assert_eq!(node.tcp().stats().handshake_failures(), 0);
+
+        // Ensure the attempts are exhausted eventually, and the address is only removed from the connecting
+        // set afterwards.
+        let node = FlakyNode::new(3, io::ErrorKind::InvalidData, policy);
+        node.enable_handshake().await;
+        let err = node.tcp().connect(peer_ip).await.unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        assert_eq!(node.1.inner().attempts_while_connecting.load(SeqCst), 3);
+        assert!(!node.tcp().is_connecting(peer_ip));
+        assert_eq!(node.tcp().num_connected(), 0);
+        assert_eq!(node.tcp().stats().handshake_failures(), 1);
+
+        // Ensure a non-retryable failure is not retried.
+        let node = FlakyNode::new(1, io::ErrorKind::UnexpectedEof, policy);
+        node.enable_handshake().await;
+        let err = node.tcp().connect(peer_ip).await.unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+        assert_eq!(node.1.inner().attempts_while_connecting.load(SeqCst), 1);
+        assert!(!node.tcp().is_connecting(peer_ip));
+    }
+
+    #[tokio::test]
+    async fn test_denied_ips() {
+        let tcp = Tcp::new(Config {
+            allowed_ips: Some(vec!["127.0.0.0/8".parse().unwrap(), "10.0.0.0/8".parse().unwrap()]),
+            denied_ips: vec!["10.1.0.0/16".parse().unwrap()],
+            ..Default::default()
+        });
+
+        assert!(tcp.is_ip_permitted(IpAddr::V4(Ipv4Addr::LOCALHOST)));
+        assert!(tcp.is_ip_permitted(IpAddr::V4(Ipv4Addr::new(10, 2, 3, 4))));
+        assert!(!tcp.is_ip_permitted(IpAddr::V4(Ipv4Addr::new(10, 1, 3, 4))));
+        assert!(!tcp.is_ip_permitted(IpAddr::V4(Ipv4Addr::new(1, 2, 3, 4))));
+
+        // Ensure the IPv4-mapped IPv6 addresses are subject to the IPv4 ranges.
+        assert!(tcp.is_ip_permitted(IpAddr::V6(Ipv4Addr::new(10, 2, 3, 4).to_ipv6_mapped())));
+        assert!(!tcp.is_ip_permitted(IpAddr::V6(Ipv4Addr::new(10, 1, 3, 4).to_ipv6_mapped())));
+        assert!(!tcp.is_ip_permitted(IpAddr::V6(Ipv4Addr::new(1, 2, 3, 4).to_ipv6_mapped())));
+
+        // Deny the loopback range at runtime.
+        let loopback: IpNetwork = "127.0.0.0/8".parse().unwrap();
+        assert!(tcp.add_denied_ip(loopback));
+        assert!(!tcp.add_denied_ip(loopback));
+        assert!(!tcp.is_ip_permitted(IpAddr::V4(Ipv4Addr::LOCALHOST)));
+
+        // Ensure a denied connection attempt fails.
+        let addr = SocketAddr::from((Ipv4Addr::LOCALHOST, 4130));
+        let err = tcp.connect(addr).await.unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::PermissionDenied);
+        assert!(!tcp.is_connecting(addr));
+
+        // Lift the restriction.
+        assert!(tcp.remove_denied_ip(loopback));
+        assert!(!tcp.remove_denied_ip(loopback));
+        assert!(tcp.is_ip_permitted(IpAddr::V4(Ipv4Addr::LOCALHOST)));
+    }
+
+    #[tokio::test]
+    async fn test_ban_peer() {
+        let tcp = Tcp::new(Config::default());
+        let _node_ip = tcp.enable_listener().await.unwrap();
+
+        // Initialize the peer.
+        let peer = Tcp::new(Config {
+            listener_ip: Some(IpAddr::V4(Ipv4Addr::LOCALHOST)),
+            desired_listening_port: Some(0),
+            max_connections: 1,
+            ..Default::default()
+        });
+        let peer_ip = peer.enable_listener().await.unwrap();
+
+        // Connect to the peer and ban it.
+        tcp.connect(peer_ip).await.unwrap();
+        tcp.ban_peer(peer_ip, Duration::from_millis(100)).await;
+        assert!(!tcp.is_connected(peer_ip));
+        assert!(tcp.is_banned(peer_ip));
+        assert_eq!(tcp.banned_peers().len(), 1);
+
+        // Ensure the ban is enforced.
+        let err = tcp.connect(peer_ip).await.unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::PermissionDenied);
+
+        // Ensure the ban expires.
+        tokio::time::sleep(Duration::from_millis(150)).await;
+        assert!(!tcp.is_banned(peer_ip));
+        assert!(tcp.banned_peers().is_empty());
+        tcp.connect(peer_ip).await.unwrap();
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_connect_with_retry() {
+        let tcp = Tcp::new(Config { connect_timeout: None, ..Default::default() });
+
+        // Obtain an address nobody is listening on.
+        let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, 0)).await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        drop(listener);
+
+        let policy = BackoffPolicy {
+            initial_delay: Duration::from_millis(100),
+            multiplier: 2,
+            max_delay: Duration::from_millis(250),
+            max_attempts: 4,
+        };
+
+        // Ensure all the attempts are made, with the expected delays in between.
+        let start = tokio::time::Instant::now();
+        tcp.connect_with_retry(addr, policy).await.unwrap_err();
+        assert_eq!(start.elapsed(), Duration::from_millis(100 + 200 + 250));
+        assert_eq!(tcp.num_connecting(), 0);
+
+        // Ensure a ban aborts the retries immediately.
+        tcp.known_peers().ban(addr, Instant::now() + Duration::from_secs(60));
+        let start = tokio::time::Instant::now();
+        tcp.connect_with_retry(addr, policy).await.unwrap_err();
+        assert_eq!(start.elapsed(), Duration::ZERO);
+    }
+
+    async fn check_connect_source_port(ip: IpAddr) {
+        // Obtain an available port.
+        let source_port = TcpListener::bind((ip, 0)).await.unwrap().local_addr().unwrap().port();
+        let tcp = Tcp::new(Config { connect_source_port: Some(source_port), ..Default::default() });
+
+        // Initialize the peer.
+        let listener = TcpListener::bind((ip, 0)).await.unwrap();
+        let peer_ip = listener.local_addr().unwrap();
+
+        // Ensure the connection originates from the configured port.
+        let (connect_result, accept_result) = tokio::join!(tcp.connect(peer_ip), listener.accept());
+        connect_result.unwrap();
+        let (_stream, addr) = accept_result.unwrap();
+        assert_eq!(addr.port(), source_port);
+    }
+
+    #[tokio::test]
+    async fn test_connect_many() {
+        let tcp = Tcp::new(Config { max_connections: 3, ..Default::default() });
+
+        // Initialize the peers.
+        let mut peers = vec![];
+        let mut peer_ips = vec![];
+        for _ in 0..4 {
+            let peer = Tcp::new(Config {
+                listener_ip: Some(IpAddr::V4(Ipv4Addr::LOCALHOST)),
+                desired_listening_port: Some(0),
+                ..Default::default()
+            });
+            peer_ips.push(peer.enable_listener().await.unwrap());
+            peers.push(peer);
+        }
+        // Prepare an address nobody listens on.
+        let dead_ip = std::net::TcpListener::bind((Ipv4Addr::LOCALHOST, 0)).unwrap().local_addr().unwrap();
+
+        // Connect to one of the peers in advance.
+        tcp.connect(peer_ips[0]).await.unwrap();
+
+        // Ensure the connected and duplicate addresses are skipped, and the connection limit is honored.
+        let addrs = vec![peer_ips[0], dead_ip, peer_ips[1], peer_ips[1], peer_ips[2], peer_ips[3]];
+        let results = tcp.connect_many(addrs, 1).await;
+        let results =
+            results.into_iter().map(|(addr, result)| (addr, result.map_err(|e| e.kind()))).collect::<Vec<_>>();
+        assert_eq!(results, vec![
+            (dead_ip, Err(io::ErrorKind::ConnectionRefused)),
+            (peer_ips[1], Ok(())),
+            (peer_ips[2], Ok(())),
+            (peer_ips[3], Err(io::ErrorKind::ConnectionRefused)),
+        ]);
+        assert_eq!(tcp.num_connected(), 3);
+        assert_eq!(tcp.num_connecting(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_flow_control() {
+        let config = || Config {
+            listener_ip: Some(IpAddr::V4(Ipv4Addr::LOCALHOST)),
+            flow_control_window: Some(4),
+            ..Default::default()
+        };
+        let sender = ThrottledNode(Tcp::new(config()), Arc::new(AtomicBool::new(true)));
+        let receiver = ThrottledNode(Tcp::new(config()), Arc::new(AtomicBool::new(false)));
+        for node in [&sender, &receiver] {
+            node.enable_reading().await;
+            node.enable_writing().await;
+        }
+        let receiver_ip = receiver.tcp().enable_listener().await.unwrap();
+        sender.tcp().connect(receiver_ip).await.unwrap();
+
+        // Send more messages than the window allows.
+        let delivered = Arc::new(AtomicUsize::new(0));
+        for _ in 0..10 {
+            let delivery = sender.unicast(receiver_ip, bytes::Bytes::from_static(b"flow")).unwrap();
+            let delivered = Arc::clone(&delivered);
+            tokio::spawn(async move {
+                if let Ok(Ok(())) = delivery.await {
+                    delivered.fetch_add(1, SeqCst);
+                }
+            });
+        }
+
+        // Ensure the stalled reader throttles the sender.
+        sleep(Duration::from_millis(200)).await;
+        assert_eq!(delivered.load(SeqCst), 4);
+        assert_eq!(sender.tcp().queue_depth(receiver_ip), Some(5));
+
+        // Ensure the sender resumes once the reader catches up.
+        receiver.1.store(true, SeqCst);
+        timeout(Duration::from_secs(1), async {
+            while delivered.load(SeqCst) != 10 {
+                sleep(Duration::from_millis(10)).await;
+            }
+        })
+        .await
+        .unwrap();
+        assert!(sender.tcp().is_connected(receiver_ip));
+    }
+
+    #[tokio::test]
+    async fn test_select_peers() {
+        let tcp = Tcp::new(Default::default());
+
+        // Connect to the peers one by one, so that their connections are of a different age.
+        let mut peers = vec![];
+        let mut peer_ips = vec![];
+        for _ in 0..3 {
+            let peer = Tcp::new(Config { listener_ip: Some(IpAddr::V4(Ipv4Addr::LOCALHOST)), ..Default::default() });
+            let peer_ip = peer.enable_listener().await.unwrap();
+            tcp.connect(peer_ip).await.unwrap();
+            peers.push(peer);
+            peer_ips.push(peer_ip);
+            sleep(Duration::from_millis(10)).await;
+        }
+
+        // Ensure the random selection picks distinct connected peers.
+        let selected = tcp.select_peers(2, SelectionStrategy::Random);
+        assert_eq!(selected.len(), 2);
+        assert_ne!(selected[0], selected[1]);
+        assert!(selected.iter().all(|addr| peer_ips.contains(addr)));
+        assert_eq!(tcp.select_peers(5, SelectionStrategy::Random).len(), 3);
+        assert!(tcp.select_peers(0, SelectionStrategy::Random).is_empty());
+
+        // Ensure the longest-lived connections are picked first.
+        assert_eq!(tcp.select_peers(2, SelectionStrategy::OldestFirst), vec![peer_ips[0], peer_ips[1]]);
+        assert_eq!(tcp.select_peers(5, SelectionStrategy::OldestFirst), peer_ips);
+
+        // Ensure the fastest peers are picked first, and the ones with an unknown latency last.
+        tcp.known_peers().register_latency(peer_ips[0], Duration::from_millis(30));
+        tcp.known_peers().register_latency(peer_ips[2], Duration::from_millis(10));
+        assert_eq!(tcp.select_peers(3, SelectionStrategy::LowestLatency), vec![peer_ips[2], peer_ips[0], peer_ips[1]]);
+
+        // Ensure the best-behaved peers are picked first.
+        tcp.known_peers().adjust_score(peer_ips[1], 10);
+        tcp.known_peers().adjust_score(peer_ips[2], -10);
+        assert_eq!(tcp.select_peers(2, SelectionStrategy::HighestScore), vec![peer_ips[1], peer_ips[0]]);
+
+        // Ensure the peers mid-handshake are never selected.
+        let node = StallingNode(Tcp::new(Default::default()));
+        node.enable_handshake().await;
+        let tcp = node.tcp().clone();
+        let first = peer_ips[0];
+        tokio::spawn(async move { tcp.connect(first).await });
+        while !node.tcp().is_connecting(first) {
+            sleep(Duration::from_millis(10)).await;
+        }
+        assert!(node.tcp().select_peers(1, SelectionStrategy::Random).is_empty());
+        assert!(node.tcp().select_peers(1, SelectionStrategy::OldestFirst).is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_quarantine() {
+        let node =
+            WritingNode(Tcp::new(Config { quarantine_duration: Duration::from_millis(200), ..Default::default() }));
+        node.enable_writing().await;
+
+        // Initialize the peers, and connect to them.
+        let mut peers = vec![];
+        let mut peer_ips = vec![];
+        for _ in 0..2 {
+            let peer = GatedNode(
+                Tcp::new(Config { listener_ip: Some(IpAddr::V4(Ipv4Addr::LOCALHOST)), ..Default::default() }),
+                Arc::new(AtomicBool::new(true)),
+                Default::default(),
+            );
+            peer.enable_reading().await;
+            let peer_ip = peer.tcp().enable_listener().await.unwrap();
+            node.tcp().connect(peer_ip).await.unwrap();
+            peers.push(peer);
+            peer_ips.push(peer_ip);
+        }
+
+        // Ensure only the connected peers can be quarantined.
+        let unknown_ip = SocketAddr::from((Ipv4Addr::LOCALHOST, 1));
+        assert!(!node.tcp().quarantine_peer(unknown_ip));
+        assert!(node.tcp().quarantine_peer(peer_ips[0]));
+        assert!(node.tcp().is_quarantined(peer_ips[0]));
+        assert!(node.tcp().peer_info(peer_ips[0]).unwrap().is_quarantined);
+        assert_eq!(node.tcp().quarantined_peers(), vec![peer_ips[0]]);
+
+        // Ensure the quarantined peer is not selected.
+        assert_eq!(node.tcp().select_peers(2, SelectionStrategy::Random), vec![peer_ips[1]]);
+
+        // Ensure the quarantined peer is left out of the broadcasts, but can still be messaged directly.
+        node.broadcast(bytes::Bytes::from_static(b"gossip")).unwrap();
+        node.unicast(peer_ips[0], bytes::Bytes::from_static(b"ping")).unwrap().await.unwrap().unwrap();
+        timeout(Duration::from_secs(1), async {
+            while peers.iter().any(|peer| peer.2.load(SeqCst) != 1) {
+                sleep(Duration::from_millis(10)).await;
+            }
+        })
+        .await
+        .unwrap();
+        sleep(Duration::from_millis(50)).await;
+        assert_eq!(peers[0].2.load(SeqCst), 1);
+
+        // Ensure the quarantine can be lifted ahead of time.
+        assert!(node.tcp().unquarantine_peer(peer_ips[0]));
+        assert!(!node.tcp().unquarantine_peer(peer_ips[0]));
+        assert_eq!(node.tcp().select_peers(2, SelectionStrategy::Random).len(), 2);
+
+        // Ensure the quarantine expires on its own.
+        assert!(node.tcp().quarantine_peer(peer_ips[1]));
+        assert_eq!(node.tcp().select_peers(2, SelectionStrategy::Random), vec![peer_ips[0]]);
+        sleep(Duration::from_millis(250)).await;
+        assert!(!node.tcp().is_quarantined(peer_ips[1]));
+        assert!(node.tcp().quarantined_peers().is_empty());
+        assert!(node.tcp().is_connected(peer_ips[1]));
+    }
+
+    #[tokio::test]
+    async fn test_connect_any() {
+        let tcp = Tcp::new(Config { connect_timeout: Some(Duration::from_millis(100)), ..Default::default() });
+        let peer = Tcp::new(Config { listener_ip: Some(IpAddr::V4(Ipv4Addr::LOCALHOST)), ..Default::default() });
+        let peer_ip = peer.enable_listener().await.unwrap();
+        // Prepare an address nobody listens on, and one that doesn't respond at all.
+        let dead_ip = std::net::TcpListener::bind((Ipv4Addr::LOCALHOST, 0)).unwrap().local_addr().unwrap();
+        let (unresponsive_ip, _listener, _streams) = unresponsive_listener().await;
+
+        // Ensure no addresses or only the failing ones result in an error.
+        assert_eq!(tcp.connect_any(&[]).await.unwrap_err().kind(), io::ErrorKind::InvalidInput);
+        assert!(tcp.connect_any(&[dead_ip, dead_ip]).await.is_err());
+
+        // Ensure the failing addresses are skipped, each within the connect timeout.
+        let start = Instant::now();
+        assert_eq!(tcp.connect_any(&[unresponsive_ip, dead_ip, peer_ip]).await.unwrap(), peer_ip);
+        assert!(start.elapsed() < Duration::from_secs(1));
+
+        // Ensure an already connected address is returned without any further attempts.
+        let other_peer = Tcp::new(Config { listener_ip: Some(IpAddr::V4(Ipv4Addr::LOCALHOST)), ..Default::default() });
+        let other_peer_ip = other_peer.enable_listener().await.unwrap();
+        assert_eq!(tcp.connect_any(&[other_peer_ip, peer_ip]).await.unwrap(), peer_ip);
+        assert_eq!(tcp.connected_addrs(), vec![peer_ip]);
+        assert_eq!(tcp.num_connecting(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_connect_before() {
+        let tcp = Tcp::new(Config { connect_timeout: None, ..Default::default() });
+        let (addr, _listener, _streams) = unresponsive_listener().await;
+        let (other_addr, _other_listener, _other_streams) = unresponsive_listener().await;
+
+        // Ensure no attempt is made once the deadline has passed.
+        let err = tcp.connect_before(addr, Instant::now()).await.unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::TimedOut);
+        assert!(tcp.known_peers().get(addr).is_none());
+
+        // Ensure the pending connection is cleaned up once the deadline expires.
+        let start = Instant::now();
+        let err = tcp.connect_before(addr, start + Duration::from_millis(100)).await.unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::TimedOut);
+        assert!(start.elapsed() < Duration::from_secs(1));
+        assert_eq!(tcp.num_connected(), 0);
+        assert_eq!(tcp.num_connecting(), 0);
+        assert!(!tcp.is_connecting(addr));
+
+        // Ensure the deadline applies to the whole batch.
+        let addrs = vec![addr, other_addr];
+        let start = Instant::now();
+        let results = tcp.connect_many_before(addrs.clone(), 1, start + Duration::from_millis(100)).await;
+        assert!(start.elapsed() < Duration::from_secs(1));
+        assert!(results.iter().all(|(_, result)| result.is_err()));
+        assert_eq!(tcp.num_connecting(), 0);
+
+        let results = tcp.connect_many_before(addrs, 1, Instant::now()).await;
+        assert!(results.iter().all(|(_, result)| result.as_ref().unwrap_err().kind() == io::ErrorKind::TimedOut));
+    }
+
+    #[test]
+    fn test_link_local_scopes() {
+        let tcp = Tcp::new(Default::default());
+        let link_local = "fe80::1".parse().unwrap();
+        let listening_addr = SocketAddr::V6(SocketAddrV6::new(link_local, 4130, 0, 2));
+        *tcp.listening_addrs.write() = vec![listening_addr];
+
+        // Ensure the same link-local address is only considered to be the node's own within the same scope.
+        assert!(tcp.is_self_connect(SocketAddr::V6(SocketAddrV6::new(link_local, 4131, 0, 2))));
+        assert!(tcp.is_self_connect(SocketAddr::V6(SocketAddrV6::new(link_local, 4131, 0, 0))));
+        assert!(!tcp.is_self_connect(SocketAddr::V6(SocketAddrV6::new(link_local, 4131, 0, 3))));
+
+        // Ensure a peer with the same address on another link can be connected to.
+        let peer_addr = SocketAddr::V6(SocketAddrV6::new(link_local, 4130, 0, 3));
+        tcp.prepare_connect(peer_addr).unwrap();
+        assert!(tcp.is_connecting(peer_addr));
+        assert!(!tcp.is_connecting(listening_addr));
+
+        // Ensure the known peers with different scopes are distinct.
+        tcp.known_peers().add(listening_addr);
+        tcp.known_peers().add(peer_addr);
+        tcp.known_peers().register_failure(peer_addr);
+        assert_eq!(tcp.known_peers().snapshot().len(), 2);
+        assert_eq!(tcp.known_peers().get(listening_addr).unwrap().failures(), 0);
+        assert_eq!(tcp.known_peers().get(peer_addr).unwrap().failures(), 1);
+    }
+
+    #[test]
+    fn test_accept_errors() {
+        // Ensure the delay grows with consecutive errors, and is capped.
+        let delays = (0..10).map(|n| accept_error_delay(n).as_millis()).collect::<Vec<_>>();
+        assert_eq!(delays, vec![10, 20, 40, 80, 160, 320, 640, 1_000, 1_000, 1_000]);
+        assert_eq!(accept_error_delay(u32::MAX), MAX_ACCEPT_ERROR_DELAY);
+
+        // Ensure running out of file descriptors is recognized.
+        #[cfg(unix)]
+        {
+            assert!(is_fd_limit_error(&io::Error::from_raw_os_error(24)));
+            assert!(is_fd_limit_error(&io::Error::from_raw_os_error(23)));
+        }
+        assert!(!is_fd_limit_error(&io::ErrorKind::ConnectionAborted.into()));
+    }
+
+    #[tokio::test]
+    async fn test_max_connection_lifetime() {
+        // The trusted peer is the one listening on the IPv6 loopback address.
+        let trusted_ip = IpAddr::V6(Ipv6Addr::LOCALHOST);
+        let tcp = Tcp::new(Config {
+            max_connection_lifetime: Some(Duration::from_millis(200)),
+            redial_rotated_connections: true,
+            trusted_ips: vec![trusted_ip],
+            ..Default::default()
+        });
+
+        // Initialize the peers.
+        let peer = Tcp::new(Config {
+            listener_ip: Some(IpAddr::V4(Ipv4Addr::LOCALHOST)),
+            desired_listening_port: Some(0),
+            ..Default::default()
+        });
+        let peer_ip = peer.enable_listener().await.unwrap();
+        let trusted_peer =
+            Tcp::new(Config { listener_ip: Some(trusted_ip), desired_listening_port: Some(0), ..Default::default() });
+        let trusted_peer_ip = trusted_peer.enable_listener().await.unwrap();
+
+        tcp.connect_labeled(peer_ip, "rotated".into()).await.unwrap();
+        tcp.connect(trusted_peer_ip).await.unwrap();
+        let connected_since = tcp.connections.stats(peer_ip).unwrap().connected_since();
+
+        // Ensure the untrusted connection is rotated and re-established with the same label.
+        timeout(Duration::from_secs(2), async {
+            loop {
+                if let Some(stats) = tcp.connections.stats(peer_ip) {
+                    if stats.connected_since() != connected_since {
+                        break;
+                    }
+                }
+                sleep(Duration::from_millis(10)).await;
+            }
+        })
+        .await
+        .unwrap();
+        assert_eq!(tcp.connection_label(peer_ip), Some("rotated".into()));
+
+        // Ensure the trusted connection is retained.
+        let trusted_stats = tcp.connections.stats(trusted_peer_ip).unwrap();
+        assert!(trusted_stats.connected_since().elapsed() > Duration::from_millis(200));
+    }
+
+    #[tokio::test]
+    async fn test_trusted_peers() {
+        let tcp = Tcp::new(Config {
+            listener_ip: Some(IpAddr::V4(Ipv4Addr::LOCALHOST)),
+            desired_listening_port: Some(0),
+            max_connections: 1,
+            max_connections_per_ip: Some(1),
+            idle_timeout: Some(Duration::from_millis(100)),
+            ..Default::default()
+        });
+        let tcp_ip = tcp.enable_listener().await.unwrap();
+
+        // The untrusted peer is the one listening on the IPv6 loopback address, and it fills the only slot.
+        let peer = Tcp::new(Config {
+            listener_ip: Some(IpAddr::V6(Ipv6Addr::LOCALHOST)),
+            desired_listening_port: Some(0),
+            ..Default::default()
+        });
+        let peer_ip = peer.enable_listener().await.unwrap();
+        tcp.connect(peer_ip).await.unwrap();
+        assert!(!tcp.can_add_connection_from(tcp_ip.ip()));
+
+        // Trust the peers connecting from the IPv4 loopback address at runtime.
+        let trusted_addr = SocketAddr::new(tcp_ip.ip(), 1);
+        assert!(tcp.add_trusted_peer(trusted_addr));
+        assert!(!tcp.add_trusted_peer(trusted_addr));
+        assert_eq!(tcp.trusted_peers(), vec![trusted_addr]);
+
+        // Ensure the trusted peers bypass both the global and the per-IP limits.
+        let trusted_peers = [Tcp::new(Config::default()), Tcp::new(Config::default())];
+        for trusted_peer in &trusted_peers {
+            trusted_peer.connect(tcp_ip).await.unwrap();
+        }
+        timeout(Duration::from_secs(1), async {
+            while tcp.num_connected() != 3 {
+                sleep(Duration::from_millis(10)).await;
+            }
+        })
+        .await
+        .unwrap();
+
+        // Ensure only the untrusted connection is reaped due to inactivity.
+        sleep(Duration::from_millis(300)).await;
+        assert!(!tcp.is_connected(peer_ip));
+        assert_eq!(tcp.num_connected(), 2);
+
+        // Ensure the trusted peers are reaped once they're no longer trusted.
+        assert!(tcp.remove_trusted_peer(trusted_addr));
+        assert!(!tcp.remove_trusted_peer(trusted_addr));
+        sleep(Duration::from_millis(300)).await;
+        assert_eq!(tcp.num_connected(), 0);
+
+        // Ensure the trusted IPs are trusted in the very same way.
+        let tcp = Tcp::new(Config { max_connections: 0, trusted_ips: vec![tcp_ip.ip()], ..Default::default() });
+        assert!(tcp.is_trusted(tcp_ip.ip()));
+        assert!(tcp.can_add_connection_from(tcp_ip.ip()));
+        assert!(!tcp.can_add_connection_from(peer_ip.ip()));
+    }
+
+    #[tokio::test]
+    async fn test_trusted_peer_reconnects() {
+        let policy = BackoffPolicy {
+            initial_delay: Duration::from_millis(10),
+            multiplier: 2,
+            max_delay: Duration::from_millis(50),
+            max_attempts: 2,
+        };
+        let tcp = Tcp::new(Config { reconnect_trusted_peers: Some(policy), ..Default::default() });
+
+        // Initialize the peers; the trusted one listens on the IPv6 loopback address.
+        let peer = Tcp::new(Config {
+            listener_ip: Some(IpAddr::V4(Ipv4Addr::LOCALHOST)),
+            desired_listening_port: Some(0),
+            ..Default::default()
+        });
+        let peer_ip = peer.enable_listener().await.unwrap();
+        let trusted_peer = Tcp::new(Config {
+            listener_ip: Some(IpAddr::V6(Ipv6Addr::LOCALHOST)),
+            desired_listening_port: Some(0),
+            ..Default::default()
+        });
+        let trusted_peer_ip = trusted_peer.enable_listener().await.unwrap();
+        assert!(tcp.add_trusted_peer(trusted_peer_ip));
+
+        tcp.connect(peer_ip).await.unwrap();
+        tcp.connect(trusted_peer_ip).await.unwrap();
+
+        // Ensure only the trusted peer is reconnected to once the connections are dropped.
+        assert!(tcp.disconnect(peer_ip).await);
+        assert!(tcp.disconnect(trusted_peer_ip).await);
+        timeout(Duration::from_secs(1), async {
+            while !tcp.is_connected(trusted_peer_ip) {
+                sleep(Duration::from_millis(10)).await;
+            }
+        })
+        .await
+        .unwrap();
+        assert!(!tcp.is_connected(peer_ip));
+
+        // Ensure the trusted peer is not reconnected to once it's banned.
+        tcp.ban_peer(trusted_peer_ip, Duration::from_secs(60)).await;
+        sleep(Duration::from_millis(200)).await;
+        assert!(!tcp.is_connected(trusted_peer_ip));
+    }
+
+    #[test]
+    fn test_observed_external_addrs() {
+        let tcp = Tcp::new(Config::default());
+        let reporter = |i: u8| SocketAddr::from(([10, 0, 0, i], 4130));
+        let external_addr = SocketAddr::from(([1, 2, 3, 4], 4130));
+        let other_addr = SocketAddr::from(([5, 6, 7, 8], 4130));
+
+        assert!(tcp.observed_external_addrs().is_empty());
+        assert_eq!(tcp.likely_external_addr(), None);
+
+        // Ensure a tie isn't resolved arbitrarily.
+        tcp.register_observed_addr(reporter(1), external_addr);
+        tcp.register_observed_addr(reporter(2), other_addr);
+        assert_eq!(tcp.likely_external_addr(), None);
+
+        // Ensure every peer IP gets a single vote, regardless of its port.
+        tcp.register_observed_addr(SocketAddr::new(reporter(2).ip(), 1234), other_addr);
+        assert_eq!(tcp.likely_external_addr(), None);
+
+        // Ensure the most reported address is picked.
+        tcp.register_observed_addr(reporter(3), external_addr);
+        assert_eq!(tcp.observed_external_addrs(), vec![(external_addr, 2), (other_addr, 1)]);
+        assert_eq!(tcp.likely_external_addr(), Some(external_addr));
+
+        // Ensure the reports can be revised.
+        tcp.register_observed_addr(reporter(2), external_addr);
+        assert_eq!(tcp.observed_external_addrs(), vec![(external_addr, 3)]);
+    }
+
+    #[tokio::test]
+    async fn test_loopback_only() {
+        // Ensure a non-loopback listening address is rejected.
+        let tcp = Tcp::new(Config {
+            listener_ip: Some(IpAddr::V4(Ipv4Addr::UNSPECIFIED)),
+            loopback_only: true,
+            ..Default::default()
+        });
+        assert_eq!(tcp.enable_listener().await.unwrap_err().kind(), io::ErrorKind::InvalidInput);
+        assert!(tcp.listening_addr().is_err());
+
+        // Ensure the additional listeners are checked too.
+        let tcp = Tcp::new(Config {
+            listener_ip: Some(IpAddr::V4(Ipv4Addr::LOCALHOST)),
+            additional_listeners: vec![SocketAddr::from((Ipv4Addr::UNSPECIFIED, 0))],
+            loopback_only: true,
+            ..Default::default()
+        });
+        assert_eq!(tcp.enable_listener().await.unwrap_err().kind(), io::ErrorKind::InvalidInput);
+
+        // Ensure a loopback listening address is accepted.
+        let tcp = Tcp::new(Config {
+            listener_ip: Some(IpAddr::V4(Ipv4Addr::LOCALHOST)),
+            loopback_only: true,
+            ..Default::default()
+        });
+        tcp.enable_listener().await.unwrap();
+
+        // Ensure connecting to a non-loopback address is refused.
+        let addr = SocketAddr::from(([10, 255, 255, 1], 4130));
+        assert_eq!(tcp.connect(addr).await.unwrap_err().kind(), io::ErrorKind::PermissionDenied);
+        assert_eq!(tcp.num_connecting(), 0);
+
+        // Ensure connecting to a loopback address is still possible.
+        let peer = Tcp::new(Config {
+            listener_ip: Some(IpAddr::V4(Ipv4Addr::LOCALHOST)),
+            desired_listening_port: Some(0),
+            ..Default::default()
+        });
+        let peer_ip = peer.enable_listener().await.unwrap();
+        tcp.connect(peer_ip).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_connected_pair() {
+        let (a, b) = crate::test_helpers::connected_pair(Default::default(), Config::default()).await;
+
+        // Ensure the connection is ready on both sides.
+        let b_addr = SocketAddr::from((Ipv4Addr::LOCALHOST, b.listening_addr().unwrap().port()));
+        assert!(a.is_connection_ready(b_addr));
+        let a_addr = b.connected_addrs()[0];
+        assert!(b.is_connection_ready(a_addr));
+        assert_eq!(a.num_connected(), 1);
+        assert_eq!(b.num_connected(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_dial_queue() {
+        let tcp =
+            Tcp::new(Config { max_connections: 2, dial_interval: Duration::from_millis(10), ..Default::default() });
+
+        // Initialize the peers.
+        let mut peers = vec![];
+        let mut peer_ips = vec![];
+        for _ in 0..4 {
+            let peer = Tcp::new(Config {
+                listener_ip: Some(IpAddr::V4(Ipv4Addr::LOCALHOST)),
+                desired_listening_port: Some(0),
+                ..Default::default()
+            });
+            peer_ips.push(peer.enable_listener().await.unwrap());
+            peers.push(peer);
+        }
+
+        // Connect to one of the peers in advance.
+        tcp.connect(peer_ips[0]).await.unwrap();
+
+        // Ensure the connected and duplicate addresses are not queued.
+        assert!(!tcp.enqueue_dial(peer_ips[0], 0));
+        assert!(tcp.enqueue_dial(peer_ips[1], 0));
+        assert!(tcp.enqueue_dial(peer_ips[2], 0));
+        assert!(!tcp.enqueue_dial(peer_ips[1], 0));
+        // Ensure a higher priority makes an address jump ahead.
+        assert!(tcp.enqueue_dial(peer_ips[3], 1));
+        assert!(tcp.enqueue_dial(peer_ips[2], 2));
+        assert_eq!(tcp.num_queued_dials(), 3);
+
+        // Ensure the highest-priority address is dialed, and the rest wait for room for another connection.
+        while tcp.num_connected() != 2 {
+            sleep(Duration::from_millis(10)).await;
+        }
+        sleep(Duration::from_millis(100)).await;
+        assert!(tcp.is_connected(peer_ips[2]));
+        assert_eq!(tcp.num_connected(), 2);
+        assert_eq!(tcp.num_queued_dials(), 2);
+
+        // Ensure the queue keeps being drained as connections are dropped, in the order of the priorities.
+        tcp.disconnect(peer_ips[0]).await;
+        while !tcp.is_connected(peer_ips[3]) {
+            sleep(Duration::from_millis(10)).await;
+        }
+        assert!(!tcp.is_connected(peer_ips[1]));
+        assert_eq!(tcp.num_queued_dials(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_connect_source_port() {
+        check_connect_source_port(IpAddr::V4(Ipv4Addr::LOCALHOST)).await;
+        check_connect_source_port(IpAddr::V6(Ipv6Addr::LOCALHOST)).await;
+    }
+
+    // note: SO_BINDTODEVICE requires CAP_NET_RAW; run with `cargo test -- --ignored` in a privileged environment.
+    #[cfg(target_os = "linux")]
+    #[ignore = "requires CAP_NET_RAW"]
+    #[tokio::test]
+    async fn test_bind_device() {
+        let tcp = Tcp::new(Config { bind_device: Some("lo".into()), ..Default::default() });
+
+        // Initialize the peer.
+        let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, 0)).await.unwrap();
+        let peer_ip = listener.local_addr().unwrap();
+
+        // Connections egressing via the loopback interface succeed.
+        let (connect_result, accept_result) = tokio::join!(tcp.connect(peer_ip), listener.accept());
+        connect_result.unwrap();
+        accept_result.unwrap();
+
+        // Binding to an interface that doesn't exist fails.
+        let tcp = Tcp::new(Config { bind_device: Some("snarkos-none".into()), ..Default::default() });
+        assert!(tcp.connect(peer_ip).await.is_err());
+    }
+
+    #[cfg(not(any(target_os = "android", target_os = "fuchsia", target_os = "linux")))]
+    #[tokio::test]
+    async fn test_bind_device_unsupported() {
+        let tcp = Tcp::new(Config { bind_device: Some("lo0".into()), ..Default::default() });
+        let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, 0)).await.unwrap();
+
+        let err = tcp.connect(listener.local_addr().unwrap()).await.unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::Unsupported);
+    }
+
+    #[tokio::test]
+    async fn test_connect_via_socks5_proxy() {
+        let (proxy_addr, num_proxied) = start_socks5_proxy().await;
+        let tcp = Tcp::new(Config {
+            listener_ip: Some(IpAddr::V4(Ipv4Addr::LOCALHOST)),
+            socks5_proxy: Some(proxy_addr),
+            ..Default::default()
+        });
+        let node_ip = tcp.enable_listener().await.unwrap();
+
+        // Initialize the peer.
+        let peer = Tcp::new(Config { listener_ip: Some(IpAddr::V4(Ipv4Addr::LOCALHOST)), ..Default::default() });
+        let peer_ip = peer.enable_listener().await.unwrap();
+
+        // Ensure the connection is relayed via the proxy, and registered under the address of the peer.
+        tcp.connect(peer_ip).await.unwrap();
+        assert!(tcp.is_connected(peer_ip));
+        assert!(!tcp.is_connected(proxy_addr));
+        assert_eq!(num_proxied.load(Relaxed), 1);
+        while peer.num_connected() != 1 {
+            sleep(Duration::from_millis(10)).await;
+        }
+
+        // Ensure the self-connect guard applies to the address requested from the proxy.
+        assert_eq!(tcp.connect(node_ip).await.unwrap_err().kind(), io::ErrorKind::AddrInUse);
+        assert_eq!(num_proxied.load(Relaxed), 1);
+        assert_eq!(tcp.num_connecting(), 0);
+    }
+
+    /// Encodes a PROXY protocol v2 header conveying the given IPv4 source address.
+    fn proxy_v2_header(source: SocketAddr) -> Vec<u8> {
+        let SocketAddr::V4(source) = source else { unreachable!() };
+        let mut header = b"\r\n\r\n\0\r\nQUIT\n".to_vec();
+        header.extend_from_slice(&[0x21, 0x11, 0, 12]);
+        header.extend_from_slice(&source.ip().octets());
+        header.extend_from_slice(&Ipv4Addr::LOCALHOST.octets());
+        header.extend_from_slice(&source.port().to_be_bytes());
+        header.extend_from_slice(&4130u16.to_be_bytes());
+        header
+    }
+
+    #[tokio::test]
+    async fn test_proxied_source_addr() {
+        let tcp = Tcp::new(Config {
+            listener_ip: Some(IpAddr::V4(Ipv4Addr::LOCALHOST)),
+            expect_proxy_protocol: true,
+            allowed_ips: Some(vec!["1.2.3.4/32".parse().unwrap()]),
+            ..Default::default()
+        });
+        let node_ip = tcp.enable_listener().await.unwrap();
+
+        // Ensure the connection is registered under the source address conveyed by the header.
+        let source_addr: SocketAddr = "1.2.3.4:5555".parse().unwrap();
+        let mut stream = TcpStream::connect(node_ip).await.unwrap();
+        stream.write_all(&proxy_v2_header(source_addr)).await.unwrap();
+        while !tcp.is_connected(source_addr) {
+            sleep(Duration::from_millis(10)).await;
+        }
+        assert!(tcp.known_peers().get(source_addr).is_some());
+
+        // Ensure the allowlist applies to the source address rather than the address of the proxy.
+        let mut stream = TcpStream::connect(node_ip).await.unwrap();
+        stream.write_all(&proxy_v2_header("5.6.7.8:5555".parse().unwrap())).await.unwrap();
+        let mut buf = [0u8; 1];
+        assert!(!matches!(timeout(Duration::from_secs(1), stream.read(&mut buf)).await.unwrap(), Ok(n) if n > 0));
+        assert_eq!(tcp.num_connected(), 1);
+        assert_eq!(tcp.num_connecting(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_proxied_connection_limits() {
+        let tcp = Tcp::new(Config {
+            listener_ip: Some(IpAddr::V4(Ipv4Addr::LOCALHOST)),
+            expect_proxy_protocol: true,
+            max_connections: 1,
+            ..Default::default()
+        });
+        let node_ip = tcp.enable_listener().await.unwrap();
+
+        // Ensure a connection awaiting its header counts as a pending one.
+        let _pending = TcpStream::connect(node_ip).await.unwrap();
+        while tcp.num_connecting() != 1 {
+            sleep(Duration::from_millis(10)).await;
+        }
+
+        // Ensure it blocks other connections before their headers are even read.
+        let mut stream = TcpStream::connect(node_ip).await.unwrap();
+        stream.write_all(&proxy_v2_header("1.2.3.4:5555".parse().unwrap())).await.unwrap();
+        let mut buf = [0u8; 1];
+        // The stream is closed, possibly with a reset, as the header isn't read.
+        assert!(!matches!(timeout(Duration::from_secs(1), stream.read(&mut buf)).await.unwrap(), Ok(n) if n > 0));
+        assert_eq!(tcp.num_connected(), 0);
+        assert_eq!(tcp.num_connecting(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_connect_to_host() {
+        let tcp = Tcp::new(Config { preferred_ip_family: Some(IpFamily::V4), ..Default::default() });
+
+        // Initialize the peer.
+        let peer = Tcp::new(Config {
+            listener_ip: Some(IpAddr::V4(Ipv4Addr::LOCALHOST)),
+            desired_listening_port: Some(0),
+            max_connections: 1,
+            ..Default::default()
+        });
+        let peer_ip = peer.enable_listener().await.unwrap();
+
+        // Connect to the peer via its hostname.
+        tcp.connect_to_host("localhost", peer_ip.port()).await.unwrap();
+        assert!(tcp.is_connected(peer_ip));
+
+        // Ensure unresolvable hosts are reported as such.
+        let err = tcp.connect_to_host("snarkos.invalid", 4130).await.unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::NotFound);
+    }
+
+    #[test]
+    fn test_interleave_ip_families() {
+        let v4_1 = SocketAddr::from((Ipv4Addr::new(1, 1, 1, 1), 1));
+        let v4_2 = SocketAddr::from((Ipv4Addr::new(2, 2, 2, 2), 1));
+        let v4_3 = SocketAddr::from((Ipv4Addr::new(3, 3, 3, 3), 1));
+        let v6_1 = SocketAddr::from((Ipv6Addr::new(1, 1, 1, 1, 1, 1, 1, 1), 1));
+        let v6_2 = SocketAddr::from((Ipv6Addr::new(2, 2, 2, 2, 2, 2, 2, 2), 1));
+
+        let interleaved = interleave_ip_families(vec![v6_1, v6_2, v4_1, v4_2, v4_3]);
+        assert_eq!(interleaved, vec![v6_1, v4_1, v6_2, v4_2, v4_3]);
+        assert!(interleave_ip_families(vec![]).is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_connect_racing() {
+        let tcp = Tcp::new(Config {
+            happy_eyeballs: true,
+            happy_eyeballs_delay: Duration::from_secs(10),
+            ..Default::default()
+        });
+
+        // Obtain an address nobody is listening on, and one that doesn't respond at all.
+        let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, 0)).await.unwrap();
+        let unused_addr = listener.local_addr().unwrap();
+        drop(listener);
+        let (unresponsive_addr, _listener, _streams) = unresponsive_listener().await;
+        tcp.known_peers().add(unused_addr);
+        tcp.known_peers().add(unresponsive_addr);
+
+        // Initialize the peers.
+        let new_peer = || {
+            Tcp::new(Config {
+                listener_ip: Some(IpAddr::V4(Ipv4Addr::LOCALHOST)),
+                desired_listening_port: Some(0),
+                max_connections: 1,
+                ..Default::default()
+            })
+        };
+        let peer = new_peer();
+        let peer_ip = peer.enable_listener().await.unwrap();
+        let other_peer = new_peer();
+        let other_peer_ip = other_peer.enable_listener().await.unwrap();
+
+        // Ensure a failed attempt is followed by the next one right away, and that it's registered as a failure.
+        let start = Instant::now();
+        tcp.connect_racing(vec![unused_addr, peer_ip]).await.unwrap();
+        assert!(start.elapsed() < Duration::from_secs(1));
+        assert!(tcp.is_connected(peer_ip));
+        assert_eq!(tcp.known_peers().get(unused_addr).unwrap().failures(), 1);
+
+        // Ensure a stalled attempt is raced by the next one once the delay elapses, and that the loser is registered
+        // as a failure.
+        let tcp = Tcp::new(Config {
+            happy_eyeballs: true,
+            happy_eyeballs_delay: Duration::from_millis(100),
+            ..Default::default()
+        });
+        tcp.known_peers().add(unresponsive_addr);
+        tcp.connect_racing(vec![unresponsive_addr, other_peer_ip]).await.unwrap();
+        assert!(tcp.is_connected(other_peer_ip));
+        assert_eq!(tcp.known_peers().get(unresponsive_addr).unwrap().failures(), 1);
+
+        // Ensure no attempt is left pending.
+        assert_eq!(tcp.num_connected(), 1);
+        assert_eq!(tcp.num_connecting(), 0);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_max_recv_bytes_per_sec() {
+        let node = ReadingNode(
+            Tcp::new(Config {
+                listener_ip: Some(IpAddr::V4(Ipv4Addr::LOCALHOST)),
+                max_recv_bytes_per_sec: Some(1_000),
+                ..Default::default()
+            }),
+            Default::default(),
+        );
+        node.enable_reading().await;
+        let node_ip = node.tcp().enable_listener().await.unwrap();
+
+        // Send 5 seconds' worth of bytes at once.
+        let start = tokio::time::Instant::now();
+        let mut stream = TcpStream::connect(node_ip).await.unwrap();
+        stream.write_all(&[0u8; 5_000]).await.unwrap();
+
+        // Ensure the bytes are read no faster than allowed; the first second's worth is available immediately.
+        while node.1.load(Relaxed) != 5_000 {
+            sleep(Duration::from_millis(10)).await;
+        }
+        assert!(start.elapsed() >= Duration::from_secs(4));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_recv_limit_ban() {
+        let node = ReadingNode(
+            Tcp::new(Config {
+                listener_ip: Some(IpAddr::V4(Ipv4Addr::LOCALHOST)),
+                max_recv_bytes_per_sec: Some(1_000),
+                recv_limit_grace_period: Duration::from_secs(2),
+                recv_limit_ban_duration: Some(Duration::from_secs(60)),
+                ..Default::default()
+            }),
+            Default::default(),
+        );
+        node.enable_reading().await;
+        let node_ip = node.tcp().enable_listener().await.unwrap();
+        let mut disconnects = node.tcp().subscribe_disconnects();
+
+        // Keep sending more than the limit allows.
+        let start = tokio::time::Instant::now();
+        let mut stream = TcpStream::connect(node_ip).await.unwrap();
+        let addr = stream.local_addr().unwrap();
+        tokio::spawn(async move { while stream.write_all(&[0u8; 1_000]).await.is_ok() {} });
+
+        // Ensure the peer is banned once the grace period is over.
+        assert_eq!(disconnects.recv().await.unwrap(), (addr, DisconnectReason::Policy));
+        assert!(start.elapsed() > Duration::from_secs(2));
+        assert!(node.tcp().known_peers().is_banned(addr));
+    }
+
+    #[tokio::test]
+    async fn test_max_message_size() {
+        let node = FramingNode(
+            Tcp::new(Config {
+                listener_ip: Some(IpAddr::V4(Ipv4Addr::LOCALHOST)),
+                max_message_size: 1024,
+                ..Default::default()
+            }),
+            Default::default(),
+        );
+        node.enable_reading().await;
+        let node_ip = node.tcp().enable_listener().await.unwrap();
+
+        // Connect to the node.
+        let mut stream = TcpStream::connect(node_ip).await.unwrap();
+        let addr = stream.local_addr().unwrap();
+        while !node.tcp().is_connected(addr) {
+            sleep(Duration::from_millis(10)).await;
+        }
+
+        // Ensure a message within the limit is accepted.
+        stream.write_all(&16u32.to_le_bytes()).await.unwrap();
+        stream.write_all(&[0u8; 16]).await.unwrap();
+        sleep(Duration::from_millis(100)).await;
+        assert!(node.tcp().is_connected(addr));
+
+        // Advertise an oversized message.
+        let mut disconnects = node.tcp().subscribe_disconnects();
+        stream.write_all(&u32::MAX.to_le_bytes()).await.unwrap();
+
+        // Ensure the connection is dropped due to the read error; the record of the inbound peer is removed along
+        // with the connection, so the disconnect event is what reflects the failure.
+        assert!(matches!(stream.read(&mut [0u8; 16]).await, Ok(0) | Err(_)));
+        assert_eq!(disconnects.recv().await.unwrap(), (addr, DisconnectReason::ReadError));
+        assert!(!node.tcp().is_connected(addr));
+    }
+
+    #[tokio::test]
+    async fn test_io_timeout_read() {
+        let node = FramingNode(
+            Tcp::new(Config {
+                listener_ip: Some(IpAddr::V4(Ipv4Addr::LOCALHOST)),
+                io_timeout: Some(Duration::from_millis(100)),
+                ..Default::default()
+            }),
+            Default::default(),
+        );
+        node.enable_reading().await;
+        let node_ip = node.tcp().enable_listener().await.unwrap();
+
+        // Connect to the node.
+        let mut stream = TcpStream::connect(node_ip).await.unwrap();
+        let addr = stream.local_addr().unwrap();
+        while !node.tcp().is_connected(addr) {
+            sleep(Duration::from_millis(10)).await;
+        }
+
+        // Ensure an idle connection is not considered stuck.
+        sleep(Duration::from_millis(300)).await;
+        assert!(node.tcp().is_connected(addr));
+
+        // Ensure a message that is received slowly, but steadily, is not considered stuck.
+        stream.write_all(&8u32.to_le_bytes()).await.unwrap();
+        for i in 0..8u8 {
+            sleep(Duration::from_millis(50)).await;
+            stream.write_all(&[i]).await.unwrap();
+        }
+        while node.1.lock().is_empty() {
+            sleep(Duration::from_millis(10)).await;
+        }
+        assert_eq!(&node.1.lock()[0][..], &[0, 1, 2, 3, 4, 5, 6, 7]);
+        assert!(node.tcp().is_connected(addr));
+
+        // Send only a part of a message.
+        let mut disconnects = node.tcp().subscribe_disconnects();
+        stream.write_all(&16u32.to_le_bytes()).await.unwrap();
+        stream.write_all(&[0u8; 4]).await.unwrap();
+
+        // Ensure the connection is dropped due to the read error.
+        let disconnect = timeout(Duration::from_secs(1), disconnects.recv()).await.unwrap().unwrap();
+        assert_eq!(disconnect, (addr, DisconnectReason::ReadError));
+        assert!(!node.tcp().is_connected(addr));
+    }
+
+    #[tokio::test]
+    async fn test_io_timeout_write() {
+        let node = WritingNode(Tcp::new(Config { io_timeout: Some(Duration::from_millis(100)), ..Default::default() }));
+        node.enable_writing().await;
+
+        // Initialize a peer that doesn't read anything, and connect to it.
+        let peer = Tcp::new(Config {
+            listener_ip: Some(IpAddr::V4(Ipv4Addr::LOCALHOST)),
+            desired_listening_port: Some(0),
+            ..Default::default()
+        });
+        let peer_ip = peer.enable_listener().await.unwrap();
+        node.tcp().connect(peer_ip).await.unwrap();
+
+        // Send messages until the socket buffers fill up, and the writes get stuck.
+        let message = bytes::Bytes::from(vec![0u8; 1024 * 1024]);
+        let mut error = None;
+        for _ in 0..1024 {
+            if let Err(e) = node.unicast(peer_ip, message.clone()).unwrap().await.unwrap() {
+                error = Some(e);
+                break;
+            }
+        }
+
+        // Ensure the connection is dropped, and the failure is registered.
+        assert_eq!(error.unwrap().kind(), io::ErrorKind::TimedOut);
+        while node.tcp().is_connected(peer_ip) {
+            sleep(Duration::from_millis(10)).await;
+        }
+        assert_ne!(node.tcp().known_peers().get(peer_ip).unwrap().failures(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_outbound_queue() {
+        // The sending rate is limited, so that the writer stalls after the first message.
+        let node = WritingNode(Tcp::new(Config { max_send_bytes_per_sec: Some(1), ..Default::default() }));
+        node.enable_writing().await;
+
+        // Initialize the peer, and connect to it.
+        let peer = Tcp::new(Config {
+            listener_ip: Some(IpAddr::V4(Ipv4Addr::LOCALHOST)),
+            desired_listening_port: Some(0),
+            ..Default::default()
+        });
+        let peer_ip = peer.enable_listener().await.unwrap();
+        node.tcp().connect(peer_ip).await.unwrap();
+        assert_eq!(node.tcp().queue_depth(peer_ip), Some(0));
+
+        // Send a message, stalling the writer.
+        let message = bytes::Bytes::from_static(&[0u8; 100]);
+        node.unicast(peer_ip, message.clone()).unwrap().await.unwrap().unwrap();
+
+        // Fill the queue.
+        let mut deliveries = vec![];
+        for _ in 0..WritingNode::MESSAGE_QUEUE_DEPTH {
+            deliveries.push(node.unicast(peer_ip, message.clone()).unwrap());
+        }
+        assert_eq!(node.tcp().queue_depth(peer_ip), Some(WritingNode::MESSAGE_QUEUE_DEPTH));
+
+        // Ensure further messages are either rejected, or wait for the queue to free up.
+        assert_eq!(node.unicast(peer_ip, message.clone()).unwrap_err().kind(), io::ErrorKind::WouldBlock);
+        let backpressure = node.unicast_with_backpressure(peer_ip, message.clone());
+        assert!(timeout(Duration::from_millis(100), backpressure).await.is_err());
+
+        // Ensure the queued messages are failed upon a disconnect.
+        node.tcp().disconnect(peer_ip).await;
+        for delivery in deliveries {
+            assert_eq!(delivery.await.unwrap().unwrap_err().kind(), io::ErrorKind::NotConnected);
+        }
+        assert_eq!(node.tcp().queue_depth(peer_ip), None);
+        assert_eq!(node.unicast(peer_ip, message).unwrap_err().kind(), io::ErrorKind::NotConnected);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_priority_lanes() {
+        // The sending rate is limited, so that the messages pile up in the queues.
+        let node = WritingNode(Tcp::new(Config { max_send_bytes_per_sec: Some(1_000), ..Default::default() }));
+        node.enable_writing().await;
+
+        // Initialize the peer, and connect to it.
+        let peer = FramingNode(
+            Tcp::new(Config { listener_ip: Some(IpAddr::V4(Ipv4Addr::LOCALHOST)), ..Default::default() }),
+            Default::default(),
+        );
+        peer.enable_reading().await;
+        let peer_ip = peer.tcp().enable_listener().await.unwrap();
+        node.tcp().connect(peer_ip).await.unwrap();
+
+        // Send a large message, stalling the writer.
+        node.unicast(peer_ip, vec![0u8; 2_000].into()).unwrap().await.unwrap().unwrap();
+
+        // Queue a few low-priority messages, and then a few high-priority ones (within the queue depth of 4).
+        for i in 0..3u8 {
+            node.unicast_with_priority(peer_ip, vec![100 + i].into(), Priority::Low).unwrap();
+        }
+        node.broadcast_with_priority(vec![200].into(), Priority::High).unwrap();
+        node.unicast_with_backpressure_and_priority(peer_ip, vec![201].into(), Priority::High).await.unwrap();
+        for i in 2..4u8 {
+            node.unicast_with_priority(peer_ip, vec![200 + i].into(), Priority::High).unwrap();
+        }
+
+        // Wait for all the messages to be delivered.
+        while peer.1.lock().len() != 8 {
+            sleep(Duration::from_millis(10)).await;
+        }
+
+        // Ensure the high-priority messages were sent first, but not more than 2 in a row while
+        // low-priority ones were pending.
+        let order = peer.1.lock().iter().skip(1).map(|message| message[0]).collect::<Vec<_>>();
+        assert_eq!(order, vec![200, 201, 100, 202, 203, 101, 102]);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_max_send_bytes_per_sec() {
+        let node = WritingNode(Tcp::new(Config { max_send_bytes_per_sec: Some(1_000), ..Default::default() }));
+        node.enable_writing().await;
+
+        // Initialize the peer, and connect to it.
+        let peer = FramingNode(
+            Tcp::new(Config { listener_ip: Some(IpAddr::V4(Ipv4Addr::LOCALHOST)), ..Default::default() }),
+            Default::default(),
+        );
+        peer.enable_reading().await;
+        let peer_ip = peer.tcp().enable_listener().await.unwrap();
+        node.tcp().connect(peer_ip).await.unwrap();
+
+        // Queue a burst of 4 seconds' worth of messages at once.
+        let start = tokio::time::Instant::now();
+        let deliveries = (0..4u8).map(|i| node.unicast(peer_ip, vec![i; 1_000].into()).unwrap()).collect::<Vec<_>>();
+
+        // Ensure the messages are spread out in line with the limit; the first second's worth is sent immediately.
+        let mut arrivals = vec![];
+        while arrivals.len() != 4 {
+            if peer.1.lock().len() > arrivals.len() {
+                arrivals.push(start.elapsed());
+            }
+            sleep(Duration::from_millis(10)).await;
+        }
+        assert!(arrivals[1] < Duration::from_millis(100));
+        for (i, arrival) in arrivals.into_iter().enumerate().skip(2) {
+            let expected = Duration::from_secs(i as u64 - 1);
+            assert!(arrival >= expected && arrival < expected + Duration::from_millis(100), "{i}: {arrival:?}");
+        }
+        for delivery in deliveries {
+            delivery.await.unwrap().unwrap();
+        }
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_disconnect_flush() {
+        for disconnect_flush_timeout in [Some(Duration::from_secs(1)), None] {
+            // The sending rate is limited, so that the messages pile up in the queues.
+            let node = WritingNode(Tcp::new(Config {
+                max_send_bytes_per_sec: Some(1_000),
+                disconnect_flush_timeout,
+                ..Default::default()
+            }));
+            node.enable_writing().await;
+
+            // Initialize the peer, and connect to it.
+            let peer = FramingNode(
+                Tcp::new(Config { listener_ip: Some(IpAddr::V4(Ipv4Addr::LOCALHOST)), ..Default::default() }),
+                Default::default(),
+            );
+            peer.enable_reading().await;
+            let peer_ip = peer.tcp().enable_listener().await.unwrap();
+            node.tcp().connect(peer_ip).await.unwrap();
+
+            // Send a large message, stalling the writer, queue a few more, and disconnect right away.
+            node.unicast(peer_ip, vec![0u8; 1_000].into()).unwrap().await.unwrap().unwrap();
+            while peer.1.lock().is_empty() {
+                sleep(Duration::from_millis(1)).await;
+            }
+            for i in 0..3u8 {
+                node.unicast(peer_ip, vec![i; 100].into()).unwrap();
+            }
+            assert!(node.tcp().disconnect(peer_ip).await);
+
+            // Wait for the peer to notice the disconnect.
+            while peer.tcp().num_connected() != 0 {
+                sleep(Duration::from_millis(10)).await;
+            }
+
+            // Ensure the queued messages were only delivered if flushing is enabled.
+            let num_delivered = peer.1.lock().len();
+            match disconnect_flush_timeout {
+                Some(_) => assert_eq!(num_delivered, 4),
+                None => assert!(num_delivered < 4),
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_disconnect_with_message() {
+        let node = WritingNode(Tcp::new(Default::default()));
+        node.enable_writing().await;
+
+        // Initialize the peer, and connect to it.
+        let peer = FramingNode(
+            Tcp::new(Config { listener_ip: Some(IpAddr::V4(Ipv4Addr::LOCALHOST)), ..Default::default() }),
+            Default::default(),
+        );
+        peer.enable_reading().await;
+        let peer_ip = peer.tcp().enable_listener().await.unwrap();
+        node.tcp().connect(peer_ip).await.unwrap();
+
+        // Ensure the final message is delivered before the connection is torn down.
+        let message = bytes::Bytes::from_static(b"bye");
+        assert!(node.disconnect_with_message(peer_ip, message.clone(), Duration::from_millis(100)).await);
+        assert!(!node.tcp().is_connected(peer_ip));
+        timeout(Duration::from_secs(1), async {
+            while peer.1.lock().len() != 1 {
+                sleep(Duration::from_millis(10)).await;
+            }
+        })
+        .await
+        .unwrap();
+        assert_eq!(&peer.1.lock()[0][..], b"bye");
+
+        // Ensure the disconnect is a no-op for an address that isn't connected.
+        assert!(!node.disconnect_with_message(peer_ip, message, Duration::from_millis(100)).await);
+    }
+
+    #[tokio::test]
+    async fn test_idle_timeout() {
+        let tcp = Tcp::new(Config { idle_timeout: Some(Duration::from_millis(100)), ..Default::default() });
+
+        // Initialize the peer.
+        let peer = Tcp::new(Config {
+            listener_ip: Some(IpAddr::V4(Ipv4Addr::LOCALHOST)),
+            desired_listening_port: Some(0),
+            max_connections: 1,
+            ..Default::default()
+        });
+        let peer_ip = peer.enable_listener().await.unwrap();
+
+        // Connect to the peer, and remain silent.
+        tcp.connect(peer_ip).await.unwrap();
+        assert!(tcp.is_connected(peer_ip));
+
+        // Ensure the connection is reaped.
+        sleep(Duration::from_millis(300)).await;
+        assert!(!tcp.is_connected(peer_ip));
+        assert_eq!(tcp.num_connected(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_max_connecting_age() {
+        let tcp = Tcp::new(Config {
+            max_connecting_age: Some(Duration::from_millis(100)),
+            max_connections: 1,
+            ..Default::default()
+        });
+        let peer_ip = SocketAddr::from(([127, 0, 0, 1], 4130));
+
+        // Inject a leaked pending connection, which takes up the only slot.
+        tcp.connecting.lock().insert(peer_ip, Instant::now());
+        assert!(!tcp.can_add_connection());
+
+        // Ensure it's dropped once it gets stale.
+        sleep(Duration::from_millis(300)).await;
+        assert!(!tcp.is_connecting(peer_ip));
+        assert!(tcp.can_add_connection());
+
+        // Ensure the fresh pending connections are retained.
+        tcp.connecting.lock().insert(peer_ip, Instant::now());
+        sleep(Duration::from_millis(30)).await;
+        assert!(tcp.is_connecting(peer_ip));
+        tcp.connecting.lock().remove(&peer_ip);
+
+        // Ensure a connection whose pending entry was dropped in the meantime is not established.
+        let node = StallingNode(Tcp::new(Config {
+            max_connecting_age: Some(Duration::from_millis(100)),
+            ..Default::default()
+        }));
+        node.enable_handshake().await;
+        let peer = Tcp::new(Config { listener_ip: Some(IpAddr::V4(Ipv4Addr::LOCALHOST)), ..Default::default() });
+        let peer_ip = peer.enable_listener().await.unwrap();
+        assert_eq!(node.tcp().connect(peer_ip).await.unwrap_err().kind(), io::ErrorKind::TimedOut);
+        assert_eq!(node.tcp().num_connected(), 0);
+        assert_eq!(node.tcp().num_connecting(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_connections_by_side() {
+        let tcp = Tcp::new(Config {
+            listener_ip: Some(IpAddr::V4(Ipv4Addr::LOCALHOST)),
+            desired_listening_port: Some(0),
+            min_outbound_connections: 2,
+            ..Default::default()
+        });
+        let tcp_ip = tcp.enable_listener().await.unwrap();
+        assert_eq!(tcp.num_missing_outbound(), 2);
+
+        // Establish an outbound connection.
+        let responder = Tcp::new(Config {
+            listener_ip: Some(IpAddr::V4(Ipv4Addr::LOCALHOST)),
+            desired_listening_port: Some(0),
+            ..Default::default()
+        });
+        let responder_ip = responder.enable_listener().await.unwrap();
+        tcp.connect(responder_ip).await.unwrap();
+
+        // Establish an inbound connection.
+        let initiator = Tcp::new(Config::default());
+        initiator.connect(tcp_ip).await.unwrap();
+        timeout(Duration::from_secs(1), async {
+            while tcp.num_connected() != 2 {
+                sleep(Duration::from_millis(10)).await;
+            }
+        })
+        .await
+        .unwrap();
+
+        // Ensure the connections are told apart.
+        assert_eq!(tcp.connected_addrs_by_side(ConnectionSide::Responder), vec![responder_ip]);
+        let inbound_addrs = tcp.connected_addrs_by_side(ConnectionSide::Initiator);
+        assert_eq!(inbound_addrs.len(), 1);
+        assert_ne!(inbound_addrs[0], responder_ip);
+        assert_eq!(tcp.num_connected_by_side(ConnectionSide::Responder), 1);
+        assert_eq!(tcp.num_connected_by_side(ConnectionSide::Initiator), 1);
+
+        // Ensure only the outbound connection counts towards the minimum.
+        assert_eq!(tcp.num_missing_outbound(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_peer_demand() {
+        let tcp = Tcp::new(Config { target_connections_low: 2, target_connections_high: 3, ..Default::default() });
+        let mut demand_changes = tcp.subscribe_peer_demand();
+        assert_eq!(tcp.peer_demand(), PeerDemand::NeedMore);
+
+        // Initialize the peers.
+        let mut peer_ips = vec![];
+        for _ in 0..4 {
+            let peer = Tcp::new(Config {
+                listener_ip: Some(IpAddr::V4(Ipv4Addr::LOCALHOST)),
+                desired_listening_port: Some(0),
+                ..Default::default()
+            });
+            peer_ips.push(peer.enable_listener().await.unwrap());
+        }
+
+        // Ensure more peers are needed until the high watermark is reached.
+        for peer_ip in &peer_ips[..2] {
+            tcp.connect(*peer_ip).await.unwrap();
+            assert_eq!(tcp.peer_demand(), PeerDemand::NeedMore);
+        }
+        assert!(!demand_changes.has_changed().unwrap());
+        tcp.connect(peer_ips[2]).await.unwrap();
+        assert_eq!(tcp.peer_demand(), PeerDemand::Satisfied);
+        assert!(demand_changes.has_changed().unwrap());
+        assert_eq!(*demand_changes.borrow_and_update(), PeerDemand::Satisfied);
+
+        // Ensure exceeding the high watermark is a surplus.
+        tcp.connect(peer_ips[3]).await.unwrap();
+        assert_eq!(*demand_changes.borrow_and_update(), PeerDemand::Surplus);
+
+        // Ensure dropping to the low watermark doesn't prompt the discovery yet.
+        assert!(tcp.disconnect(peer_ips[3]).await);
+        assert!(tcp.disconnect(peer_ips[2]).await);
+        assert_eq!(tcp.peer_demand(), PeerDemand::Satisfied);
+
+        // Ensure dropping below it does.
+        assert!(tcp.disconnect(peer_ips[1]).await);
+        assert!(demand_changes.has_changed().unwrap());
+        assert_eq!(*demand_changes.borrow_and_update(), PeerDemand::NeedMore);
+
+        // Ensure hovering around the low watermark doesn't satisfy the demand.
+        tcp.connect(peer_ips[1]).await.unwrap();
+        assert_eq!(tcp.peer_demand(), PeerDemand::NeedMore);
+        assert!(!demand_changes.has_changed().unwrap());
+    }
+
+    #[test]
+    fn test_matching_prefixes() {
+        let ip = |s: &str| s.parse::<IpAddr>().unwrap();
+
+        assert!(have_matching_prefixes(ip("10.1.2.3"), ip("10.1.200.201")));
+        assert!(!have_matching_prefixes(ip("10.1.2.3"), ip("10.2.2.3")));
+        assert!(have_matching_prefixes(ip("2001:db8::1"), ip("2001:db8:ffff::1")));
+        assert!(!have_matching_prefixes(ip("2001:db8::1"), ip("2001:db9::1")));
+        assert!(have_matching_prefixes(ip("::ffff:10.1.0.1"), ip("10.1.5.5")));
+        assert!(!have_matching_prefixes(ip("10.1.0.1"), ip("a01::1")));
+    }
+
+    #[tokio::test]
+    async fn test_max_outbound_per_prefix() {
+        let tcp = Tcp::new(Config { max_outbound_per_prefix: Some(1), ..Default::default() });
+
+        // Initialize the peers; the first two share the /16 prefix.
+        let mut peer_ips = vec![];
+        for ip in [Ipv4Addr::new(127, 0, 0, 1), Ipv4Addr::new(127, 0, 1, 2), Ipv4Addr::new(127, 1, 0, 1)] {
+            let peer = Tcp::new(Config {
+                listener_ip: Some(IpAddr::V4(ip)),
+                desired_listening_port: Some(0),
+                ..Default::default()
+            });
+            peer_ips.push(peer.enable_listener().await.unwrap());
+        }
+
+        // Ensure only one outbound connection within a prefix is allowed.
+        tcp.connect(peer_ips[0]).await.unwrap();
+        assert_eq!(tcp.connect(peer_ips[1]).await.unwrap_err().kind(), io::ErrorKind::ConnectionRefused);
+        tcp.connect(peer_ips[2]).await.unwrap();
+
+        // Ensure the trusted peers are exempt.
+        tcp.add_trusted_peer(peer_ips[1]);
+        tcp.connect(peer_ips[1]).await.unwrap();
+        assert_eq!(tcp.num_connected(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_would_accept() {
+        let tcp = Tcp::new(Config {
+            listener_ip: Some(IpAddr::V4(Ipv4Addr::LOCALHOST)),
+            desired_listening_port: Some(0),
+            max_connections: 2,
+            ..Default::default()
+        });
+        let tcp_ip = tcp.enable_listener().await.unwrap();
+
+        // Initialize the peer.
+        let peer = Tcp::new(Config {
+            listener_ip: Some(IpAddr::V4(Ipv4Addr::LOCALHOST)),
+            desired_listening_port: Some(0),
+            ..Default::default()
+        });
+        let peer_ip = peer.enable_listener().await.unwrap();
+
+        assert_eq!(tcp.would_accept(tcp_ip), Err(RejectReason::SelfConnect));
+        assert_eq!(tcp.would_accept(peer_ip), Ok(()));
+
+        // Ensure the checks have no side effects.
+        assert!(!tcp.is_connecting(peer_ip));
+        assert_eq!(tcp.num_connecting(), 0);
+
+        tcp.connect(peer_ip).await.unwrap();
+        assert_eq!(tcp.would_accept(peer_ip), Err(RejectReason::AlreadyConnected));
+
+        let other_ip = SocketAddr::from(([127, 0, 0, 1], 1));
+        tcp.connecting.lock().insert(other_ip, Instant::now());
+        assert_eq!(tcp.would_accept(other_ip), Err(RejectReason::ConnectionLimit));
+        tcp.connecting.lock().remove(&other_ip);
+        assert_eq!(tcp.would_accept(other_ip), Ok(()));
+
+        tcp.ban_peer(other_ip, Duration::from_secs(60)).await;
+        assert_eq!(tcp.would_accept(other_ip), Err(RejectReason::Banned));
+
+        tcp.add_denied_ip("1.2.3.4/32".parse().unwrap());
+        let denied_ip = SocketAddr::from(([1, 2, 3, 4], 4130));
+        assert_eq!(tcp.would_accept(denied_ip), Err(RejectReason::Denied));
+
+        // Ensure the connection attempts are refused for the same reasons.
+        let err = tcp.connect(denied_ip).await.unwrap_err();
+        assert_eq!(err.kind(), RejectReason::Denied.kind());
+        assert_eq!(err.to_string(), RejectReason::Denied.to_string());
+    }
+
+    #[tokio::test]
+    async fn test_connect_labeled() {
+        let tcp = Tcp::new(Config::default());
+
+        // Initialize the peers.
+        let mut peer_ips = vec![];
+        for _ in 0..2 {
+            let peer = Tcp::new(Config {
+                listener_ip: Some(IpAddr::V4(Ipv4Addr::LOCALHOST)),
+                desired_listening_port: Some(0),
+                max_connections: 1,
+                ..Default::default()
+            });
+            peer_ips.push(peer.enable_listener().await.unwrap());
+        }
 
-    use std::net::{IpAddr, Ipv4Addr};
+        // Connect to the peers, labeling only one of the connections.
+        tcp.connect_labeled(peer_ips[0], "bootstrap".into()).await.unwrap();
+        tcp.connect(peer_ips[1]).await.unwrap();
+
+        assert_eq!(tcp.connection_label(peer_ips[0]).as_deref(), Some("bootstrap"));
+        assert_eq!(tcp.connection_label(peer_ips[1]).as_deref(), Some(""));
+        assert_eq!(tcp.connections_with_label("bootstrap"), vec![peer_ips[0]]);
+        assert!(tcp.connections_with_label("trusted-validator").is_empty());
+    }
 
     #[tokio::test]
-    async fn test_new() {
-        let tcp = Tcp::new(Config {
-            listener_ip: Some(IpAddr::V4(Ipv4Addr::LOCALHOST)),
-            max_connections: 200,
-            ..Default::default()
-        });
+    async fn test_node_type_limits() {
+        let tcp = Tcp::new(Config { max_validator_connections: Some(1), ..Default::default() });
 
-        assert_eq!(tcp.config.max_connections, 200);
-        assert_eq!(tcp.config.listener_ip, Some(IpAddr::V4(Ipv4Addr::LOCALHOST)));
-        assert_eq!(tcp.enable_listener().await.unwrap().ip(), IpAddr::V4(Ipv4Addr::LOCALHOST));
+        // Initialize the peers.
+        let mut peer_ips = vec![];
+        for _ in 0..3 {
+            let peer = Tcp::new(Config {
+                listener_ip: Some(IpAddr::V4(Ipv4Addr::LOCALHOST)),
+                desired_listening_port: Some(0),
+                max_connections: 1,
+                ..Default::default()
+            });
+            peer_ips.push(peer.enable_listener().await.unwrap());
+        }
+        for peer_ip in &peer_ips {
+            tcp.connect(*peer_ip).await.unwrap();
+        }
 
-        assert_eq!(tcp.num_connected(), 0);
-        assert_eq!(tcp.num_connecting(), 0);
+        // Ensure the connections whose type is not known yet are unaffected.
+        assert_eq!(tcp.num_connected(), 3);
+        assert!(tcp.connections_of_type(NodeType::Validator).is_empty());
+
+        // Ensure the limit is applied once the types become known.
+        assert!(tcp.set_node_type(peer_ips[0], NodeType::Validator));
+        assert!(tcp.set_node_type(peer_ips[0], NodeType::Validator));
+        assert!(tcp.set_node_type(peer_ips[1], NodeType::Client));
+        assert!(!tcp.set_node_type(peer_ips[2], NodeType::Validator));
+        assert_eq!(tcp.connections_of_type(NodeType::Validator), vec![peer_ips[0]]);
+        assert_eq!(tcp.connections_of_type(NodeType::Client), vec![peer_ips[1]]);
+
+        // Ensure the excess connection is dropped.
+        while tcp.is_connected(peer_ips[2]) {
+            sleep(Duration::from_millis(10)).await;
+        }
+        assert_eq!(tcp.num_connected(), 2);
     }
 
     #[tokio::test]
-    async fn test_connect() {
-        let tcp = Tcp::new(Config::default());
-        let node_ip = tcp.enable_listener().await.unwrap();
+    async fn test_peer_scores() {
+        let tcp = Tcp::new(Config { peer_score_range: -2..=2, ..Default::default() });
 
-        // Ensure self-connecting is not possible.
-        tcp.connect(node_ip).await.unwrap_err();
-        assert_eq!(tcp.num_connected(), 0);
-        assert_eq!(tcp.num_connecting(), 0);
-        assert!(!tcp.is_connected(node_ip));
-        assert!(!tcp.is_connecting(node_ip));
+        // Initialize the peers.
+        let mut peer_ips = vec![];
+        for _ in 0..3 {
+            let peer = Tcp::new(Config {
+                listener_ip: Some(IpAddr::V4(Ipv4Addr::LOCALHOST)),
+                desired_listening_port: Some(0),
+                max_connections: 1,
+                ..Default::default()
+            });
+            let peer_ip = peer.enable_listener().await.unwrap();
+            tcp.connect(peer_ip).await.unwrap();
+            peer_ips.push(peer_ip);
+        }
 
-        // Initialize the peer.
-        let peer = Tcp::new(Config {
-            listener_ip: Some(IpAddr::V4(Ipv4Addr::LOCALHOST)),
-            desired_listening_port: Some(0),
-            max_connections: 1,
-            ..Default::default()
-        });
-        let peer_ip = peer.enable_listener().await.unwrap();
+        // Ensure the scores are clamped.
+        assert_eq!(tcp.known_peers().adjust_score(peer_ips[0], 5), Some(2));
+        assert_eq!(tcp.known_peers().adjust_score(peer_ips[1], -1), Some(-1));
+        for _ in 0..3 {
+            tcp.known_peers().register_failure(peer_ips[2]);
+        }
+        assert_eq!(tcp.known_peers().score(peer_ips[2]), Some(-2));
+        assert_eq!(tcp.known_peers().snapshot()[&peer_ips[2]].score(), -2);
 
-        // Connect to the peer.
-        tcp.connect(peer_ip).await.unwrap();
-        assert_eq!(tcp.num_connected(), 1);
-        assert_eq!(tcp.num_connecting(), 0);
-        assert!(tcp.is_connected(peer_ip));
-        assert!(!tcp.is_connecting(peer_ip));
+        // Ensure the lowest-scoring peers come first.
+        assert_eq!(tcp.lowest_scoring_peers(2), vec![peer_ips[2], peer_ips[1]]);
+        assert_eq!(tcp.lowest_scoring_peers(5).len(), 3);
     }
 
     #[tokio::test]
@@ -586,6 +5143,120 @@ mod tests {
         assert!(!tcp.is_connecting(peer_ip));
     }
 
+    #[tokio::test]
+    async fn test_closed() {
+        let tcp = Tcp::new(Default::default());
+
+        // Initialize the peer, and connect to it.
+        let peer = Tcp::new(Config {
+            listener_ip: Some(IpAddr::V4(Ipv4Addr::LOCALHOST)),
+            desired_listening_port: Some(0),
+            ..Default::default()
+        });
+        let peer_ip = peer.enable_listener().await.unwrap();
+
+        // Ensure the future resolves right away if there is no connection.
+        timeout(Duration::from_millis(100), tcp.closed(peer_ip)).await.unwrap();
+
+        // Ensure it's pending while the connection is active.
+        tcp.connect(peer_ip).await.unwrap();
+        let closed = tcp.closed(peer_ip);
+        tokio::pin!(closed);
+        assert!(timeout(Duration::from_millis(100), &mut closed).await.is_err());
+
+        // Ensure it resolves once the connection is closed.
+        let tcp_clone = tcp.clone();
+        tokio::spawn(async move {
+            sleep(Duration::from_millis(50)).await;
+            tcp_clone.disconnect(peer_ip).await;
+        });
+        timeout(Duration::from_secs(1), closed).await.unwrap();
+        assert!(!tcp.is_connected(peer_ip));
+    }
+
+    #[tokio::test]
+    async fn test_disconnect_reasons() {
+        let node = FramingNode(
+            Tcp::new(Config { listener_ip: Some(IpAddr::V4(Ipv4Addr::LOCALHOST)), ..Default::default() }),
+            Default::default(),
+        );
+        node.enable_reading().await;
+        let node_ip = node.tcp().enable_listener().await.unwrap();
+        let mut disconnects = node.tcp().subscribe_disconnects();
+
+        // Connects a new peer to the node, and returns it along with its address as seen by the node.
+        let node_tcp = node.tcp();
+        let connect_peer = || async move {
+            let peer = Tcp::new(Default::default());
+            peer.connect(node_ip).await.unwrap();
+            while node_tcp.num_connected() != 1 {
+                sleep(Duration::from_millis(10)).await;
+            }
+            let peer_addr = node_tcp.connected_addrs()[0];
+            (peer, peer_addr)
+        };
+        async fn next_disconnect(
+            disconnects: &mut broadcast::Receiver<(SocketAddr, DisconnectReason)>,
+        ) -> (SocketAddr, DisconnectReason) {
+            timeout(Duration::from_secs(1), disconnects.recv()).await.unwrap().unwrap()
+        }
+
+        // Ensure a local disconnect is reported.
+        let (_peer, peer_addr) = connect_peer().await;
+        node.tcp().disconnect(peer_addr).await;
+        assert_eq!(next_disconnect(&mut disconnects).await, (peer_addr, DisconnectReason::Local));
+
+        // Ensure a peer closing the connection is reported.
+        let (peer, peer_addr) = connect_peer().await;
+        peer.disconnect(node_ip).await;
+        assert_eq!(next_disconnect(&mut disconnects).await, (peer_addr, DisconnectReason::PeerClosed));
+
+        // Ensure a policy-based disconnect is reported.
+        let (_peer, peer_addr) = connect_peer().await;
+        node.tcp().ban_peer(peer_addr, Duration::from_secs(60)).await;
+        assert_eq!(next_disconnect(&mut disconnects).await, (peer_addr, DisconnectReason::Policy));
+        // note: the ban would also apply to the next peers, which share the IP
+        node.tcp().known_peers().unban(peer_addr);
+
+        // Ensure the disconnects caused by a shutdown are reported.
+        let (_peer, peer_addr) = connect_peer().await;
+        node.tcp().shut_down().await;
+        assert_eq!(next_disconnect(&mut disconnects).await, (peer_addr, DisconnectReason::Shutdown));
+    }
+
+    #[tokio::test]
+    async fn test_disconnect_all() {
+        let node = DisconnectingNode(
+            Tcp::new(Config { disconnect_concurrency: 2, ..Default::default() }),
+            Default::default(),
+            Default::default(),
+        );
+        node.enable_disconnect().await;
+
+        // Connect to a few peers.
+        let mut peers = vec![];
+        for _ in 0..5 {
+            let peer = Tcp::new(Config {
+                listener_ip: Some(IpAddr::V4(Ipv4Addr::LOCALHOST)),
+                desired_listening_port: Some(0),
+                ..Default::default()
+            });
+            let peer_ip = peer.enable_listener().await.unwrap();
+            node.tcp().connect(peer_ip).await.unwrap();
+            peers.push(peer);
+        }
+        assert_eq!(node.tcp().num_connected(), 5);
+
+        // Ensure all the disconnect handlers conclude, within the concurrency limit.
+        assert_eq!(node.tcp().disconnect_all().await, 5);
+        assert_eq!(node.tcp().num_connected(), 0);
+        assert_eq!(node.1.load(SeqCst), 0);
+        assert_eq!(node.2.load(SeqCst), 2);
+
+        // Ensure there is nothing more to disconnect from.
+        assert_eq!(node.tcp().disconnect_all().await, 0);
+    }
+
     #[tokio::test]
     async fn test_can_add_connection() {
         let tcp = Tcp::new(Config { max_connections: 1, ..Default::default() });
@@ -603,7 +5274,7 @@ mod tests {
 
         // Simulate an active connection.
         let stream = TcpStream::connect(peer_ip).await.unwrap();
-        tcp.connections.add(Connection::new(peer_ip, stream, ConnectionSide::Initiator));
+        tcp.connections.add(Connection::new(peer_ip, stream.into(), ConnectionSide::Initiator));
         assert!(!tcp.can_add_connection());
 
         // Remove the active connection.
@@ -611,7 +5282,7 @@ mod tests {
         assert!(tcp.can_add_connection());
 
         // Simulate a pending connection.
-        tcp.connecting.lock().insert(peer_ip);
+        tcp.connecting.lock().insert(peer_ip, Instant::now());
         assert!(!tcp.can_add_connection());
 
         // Remove the pending connection.
@@ -620,8 +5291,8 @@ mod tests {
 
         // Simulate an active and a pending connection (this case should never occur).
         let stream = TcpStream::connect(peer_ip).await.unwrap();
-        tcp.connections.add(Connection::new(peer_ip, stream, ConnectionSide::Responder));
-        tcp.connecting.lock().insert(peer_ip);
+        tcp.connections.add(Connection::new(peer_ip, stream.into(), ConnectionSide::Responder));
+        tcp.connecting.lock().insert(peer_ip, Instant::now());
         assert!(!tcp.can_add_connection());
 
         // Remove the active and pending connection.
@@ -630,6 +5301,124 @@ mod tests {
         assert!(tcp.can_add_connection());
     }
 
+    #[tokio::test]
+    async fn test_can_add_connection_from() {
+        let tcp = Tcp::new(Config { max_connections_per_ip: Some(1), ..Default::default() });
+
+        // Initialize the peer.
+        let peer = Tcp::new(Config {
+            listener_ip: Some(IpAddr::V4(Ipv4Addr::LOCALHOST)),
+            desired_listening_port: Some(0),
+            max_connections: 1,
+            ..Default::default()
+        });
+        let peer_ip = peer.enable_listener().await.unwrap();
+        let other_port = SocketAddr::new(peer_ip.ip(), peer_ip.port().wrapping_add(1));
+
+        assert!(tcp.can_add_connection_from(peer_ip.ip()));
+
+        // Simulate an active connection.
+        let stream = TcpStream::connect(peer_ip).await.unwrap();
+        tcp.connections.add(Connection::new(peer_ip, stream.into(), ConnectionSide::Initiator));
+        assert!(!tcp.can_add_connection_from(peer_ip.ip()));
+
+        // Ensure other IPs are unaffected.
+        assert!(tcp.can_add_connection_from(IpAddr::V4(Ipv4Addr::new(1, 2, 3, 4))));
+
+        // Remove the active connection and simulate a pending one from a different port.
+        tcp.connections.remove(peer_ip);
+        tcp.connecting.lock().insert(other_port, Instant::now());
+        assert!(!tcp.can_add_connection_from(peer_ip.ip()));
+
+        // Remove the pending connection.
+        tcp.connecting.lock().remove(&other_port);
+        assert!(tcp.can_add_connection_from(peer_ip.ip()));
+
+        // Ensure loopback addresses can be exempted.
+        let tcp = Tcp::new(Config {
+            max_connections_per_ip: Some(0),
+            exempt_loopback_from_ip_limit: true,
+            ..Default::default()
+        });
+        assert!(tcp.can_add_connection_from(peer_ip.ip()));
+        assert!(!tcp.can_add_connection_from(IpAddr::V4(Ipv4Addr::new(1, 2, 3, 4))));
+    }
+
+    #[tokio::test]
+    async fn test_control_message_flood() {
+        let node = ControlNode(Tcp::new(Config { max_control_messages_per_sec: Some(5), ..Default::default() }));
+        node.enable_reading().await;
+        let peer =
+            WritingNode(Tcp::new(Config { listener_ip: Some(IpAddr::V4(Ipv4Addr::LOCALHOST)), ..Default::default() }));
+        peer.enable_writing().await;
+        let peer_ip = peer.tcp().enable_listener().await.unwrap();
+        node.tcp().connect(peer_ip).await.unwrap();
+        while peer.tcp().num_connected() != 1 {
+            sleep(Duration::from_millis(10)).await;
+        }
+        let node_addr = peer.tcp().connected_addrs()[0];
+        let send_messages = |num_messages: usize| {
+            let peer = peer.clone();
+            async move {
+                for _ in 0..num_messages {
+                    if let Ok(sent) = peer.unicast(node_addr, vec![0u8; 8].into()) {
+                        let _ = sent.await;
+                    }
+                }
+            }
+        };
+
+        // Ensure the control messages within the limit are accepted.
+        send_messages(5).await;
+        sleep(Duration::from_millis(100)).await;
+        assert!(node.tcp().is_connected(peer_ip));
+
+        // Ensure a flooding peer is dropped, and that the failure is registered.
+        send_messages(20).await;
+        timeout(Duration::from_secs(1), async {
+            while node.tcp().is_connected(peer_ip) {
+                sleep(Duration::from_millis(10)).await;
+            }
+        })
+        .await
+        .unwrap();
+        assert_eq!(node.tcp().known_peers().get(peer_ip).unwrap().failures(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_max_inbound_connections_per_sec() {
+        let tcp = Tcp::new(Config {
+            listener_ip: Some(IpAddr::V4(Ipv4Addr::LOCALHOST)),
+            desired_listening_port: Some(0),
+            max_inbound_connections_per_sec: Some(2),
+            ..Default::default()
+        });
+        let tcp_ip = tcp.enable_listener().await.unwrap();
+
+        // Open connections faster than the limit allows.
+        let mut streams = vec![];
+        for _ in 0..5 {
+            streams.push(TcpStream::connect(tcp_ip).await.unwrap());
+        }
+        timeout(Duration::from_secs(1), async {
+            while tcp.num_connected() != 2 {
+                sleep(Duration::from_millis(10)).await;
+            }
+        })
+        .await
+        .unwrap();
+
+        // Ensure the excess connections were refused, i.e. closed right away.
+        let mut num_refused = 0;
+        for stream in &mut streams {
+            if let Ok(Ok(0)) = timeout(Duration::from_millis(100), stream.read(&mut [0u8; 1])).await {
+                num_refused += 1;
+            }
+        }
+        assert_eq!(num_refused, 3);
+        assert_eq!(tcp.num_connected(), 2);
+    }
+
     #[tokio::test]
     async fn test_handle_connection() {
         let tcp = Tcp::new(Config {
@@ -649,7 +5438,7 @@ mod tests {
 
         // Simulate an active connection.
         let stream = TcpStream::connect(peer1_ip).await.unwrap();
-        tcp.connections.add(Connection::new(peer1_ip, stream, ConnectionSide::Responder));
+        tcp.connections.add(Connection::new(peer1_ip, stream.into(), ConnectionSide::Responder));
         assert!(!tcp.can_add_connection());
         assert_eq!(tcp.num_connected(), 1);
         assert_eq!(tcp.num_connecting(), 0);
@@ -677,6 +5466,40 @@ mod tests {
         assert!(!tcp.is_connecting(peer2_ip));
     }
 
+    #[tokio::test]
+    async fn test_eviction_candidate() {
+        let trusted_ip = IpAddr::V4(Ipv4Addr::new(1, 2, 3, 4));
+        let tcp = Tcp::new(Config {
+            max_connections: 2,
+            allow_eviction: true,
+            trusted_ips: vec![trusted_ip],
+            ..Default::default()
+        });
+
+        // Initialize the peers.
+        let mut peer_ips = vec![];
+        for _ in 0..2 {
+            let peer = Tcp::new(Config {
+                listener_ip: Some(IpAddr::V4(Ipv4Addr::LOCALHOST)),
+                desired_listening_port: Some(0),
+                max_connections: 1,
+                ..Default::default()
+            });
+            let peer_ip = peer.enable_listener().await.unwrap();
+            tcp.connect(peer_ip).await.unwrap();
+            peer_ips.push(peer_ip);
+        }
+        tcp.known_peers().adjust_score(peer_ips[1], -10);
+
+        // Ensure only trusted peers can cause an eviction, and that the lowest-scoring peer is picked.
+        assert_eq!(tcp.eviction_candidate(IpAddr::V4(Ipv4Addr::new(5, 6, 7, 8))), None);
+        assert_eq!(tcp.eviction_candidate(trusted_ip), Some(peer_ips[1]));
+
+        // Ensure no peer is evicted mid-handshake.
+        tcp.connecting.lock().insert(peer_ips[1], Instant::now());
+        assert_eq!(tcp.eviction_candidate(trusted_ip), Some(peer_ips[0]));
+    }
+
     #[tokio::test]
     async fn test_adapt_stream() {
         let tcp = Tcp::new(Config { max_connections: 1, ..Default::default() });
@@ -691,7 +5514,7 @@ mod tests {
         let peer_ip = peer.enable_listener().await.unwrap();
 
         // Simulate a pending connection.
-        tcp.connecting.lock().insert(peer_ip);
+        tcp.connecting.lock().insert(peer_ip, Instant::now());
         assert_eq!(tcp.num_connected(), 0);
         assert_eq!(tcp.num_connecting(), 1);
         assert!(!tcp.is_connected(peer_ip));
@@ -699,10 +5522,458 @@ mod tests {
 
         // Simulate a new connection.
         let stream = TcpStream::connect(peer_ip).await.unwrap();
-        tcp.adapt_stream(stream, peer_ip, ConnectionSide::Responder).await.unwrap();
+        tcp.adapt_stream(stream.into(), peer_ip, ConnectionSide::Responder, String::new()).await.unwrap();
         assert_eq!(tcp.num_connected(), 1);
         assert_eq!(tcp.num_connecting(), 0);
         assert!(tcp.is_connected(peer_ip));
         assert!(!tcp.is_connecting(peer_ip));
     }
+
+    #[tokio::test]
+    async fn test_set_socket_options() {
+        let tcp =
+            Tcp::new(Config { tcp_keepalive: Some(Duration::from_secs(30)), tcp_nodelay: true, ..Default::default() });
+
+        // Initialize the peer.
+        let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, 0)).await.unwrap();
+        let peer_ip = listener.local_addr().unwrap();
+
+        // Ensure the options are applied to the stream.
+        let stream = TcpStream::connect(peer_ip).await.unwrap();
+        tcp.set_socket_options(&stream, peer_ip);
+        let socket = socket2::SockRef::from(&stream);
+        assert!(socket.keepalive().unwrap());
+        assert!(socket.nodelay().unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_socket_buffer_sizes() {
+        const BUFFER_SIZE: usize = 256 * 1024;
+
+        let config = || Config {
+            listener_ip: Some(IpAddr::V4(Ipv4Addr::LOCALHOST)),
+            desired_listening_port: Some(0),
+            so_sndbuf: Some(BUFFER_SIZE),
+            so_rcvbuf: Some(BUFFER_SIZE),
+            ..Default::default()
+        };
+        let initiator = BufferNode(Tcp::new(config()), Default::default());
+        let responder = BufferNode(Tcp::new(config()), Default::default());
+        initiator.enable_handshake().await;
+        responder.enable_handshake().await;
+        let responder_ip = responder.tcp().enable_listener().await.unwrap();
+
+        // Ensure the handshake concludes on both sides.
+        initiator.tcp().connect(responder_ip).await.unwrap();
+        timeout(Duration::from_secs(1), async {
+            while responder.tcp().num_connected() != 1 {
+                sleep(Duration::from_millis(10)).await;
+            }
+        })
+        .await
+        .unwrap();
+
+        // Ensure the buffer sizes were applied before the handshake on both sides; the kernel may grant more.
+        for node in [&initiator, &responder] {
+            let sizes = node.1.lock().clone();
+            assert_eq!(sizes.len(), 1);
+            assert!(sizes[0].0 >= BUFFER_SIZE, "the send buffer is too small: {}", sizes[0].0);
+            assert!(sizes[0].1 >= BUFFER_SIZE, "the receive buffer is too small: {}", sizes[0].1);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_peer_capabilities() {
+        // Include a bit that isn't defined yet, as if advertised by a more recent node.
+        let unknown = 1 << 63;
+        let config = || Config {
+            listener_ip: Some(IpAddr::V4(Ipv4Addr::LOCALHOST)),
+            desired_listening_port: Some(0),
+            ..Default::default()
+        };
+        let initiator = CapabilityNode(Tcp::new(config()), Capabilities::COMPRESSION);
+        let responder = CapabilityNode(
+            Tcp::new(config()),
+            Capabilities::COMPRESSION | Capabilities::PONG_TIMESTAMPS | Capabilities::from_bits_retain(unknown),
+        );
+        initiator.enable_handshake().await;
+        responder.enable_handshake().await;
+        let responder_ip = responder.tcp().enable_listener().await.unwrap();
+
+        // The capabilities are unknown for peers that aren't connected.
+        assert!(initiator.tcp().peer_capabilities(responder_ip).is_none());
+
+        initiator.tcp().connect(responder_ip).await.unwrap();
+        let initiator_addr = timeout(Duration::from_secs(1), async {
+            loop {
+                if let Some(addr) = responder.tcp().connected_addrs().pop() {
+                    break addr;
+                }
+                sleep(Duration::from_millis(10)).await;
+            }
+        })
+        .await
+        .unwrap();
+
+        // Ensure the capabilities were registered on both sides, and that the unknown bit is retained, but ignored.
+        let capabilities = initiator.tcp().peer_capabilities(responder_ip).unwrap();
+        assert!(capabilities.contains(Capabilities::COMPRESSION | Capabilities::PONG_TIMESTAMPS));
+        assert_eq!(capabilities.bits() & unknown, unknown);
+        assert_eq!(capabilities & Capabilities::all(), Capabilities::COMPRESSION | Capabilities::PONG_TIMESTAMPS);
+
+        let capabilities = responder.tcp().peer_capabilities(initiator_addr).unwrap();
+        assert_eq!(capabilities, Capabilities::COMPRESSION);
+        assert!(!capabilities.contains(Capabilities::PONG_TIMESTAMPS));
+    }
+
+    #[tokio::test]
+    async fn test_peer_identity() {
+        let responder = IdentityNode(
+            Tcp::new(Config {
+                listener_ip: Some(IpAddr::V4(Ipv4Addr::LOCALHOST)),
+                require_peer_identity: true,
+                ..Default::default()
+            }),
+            1,
+        );
+        responder.enable_handshake().await;
+        let responder_ip = responder.tcp().enable_listener().await.unwrap();
+
+        // Ensure the identity of the peer is registered on both sides.
+        let initiator = IdentityNode(Tcp::new(Config::default()), 2);
+        initiator.enable_handshake().await;
+        initiator.tcp().connect(responder_ip).await.unwrap();
+        assert_eq!(initiator.tcp().peer_identity::<u64>(responder_ip), Some(1));
+        assert_eq!(initiator.tcp().peer_identity::<String>(responder_ip), None);
+        let initiator_addr = timeout(Duration::from_secs(1), async {
+            loop {
+                if let Some(addr) = responder.tcp().connected_addrs().pop() {
+                    break addr;
+                }
+                sleep(Duration::from_millis(10)).await;
+            }
+        })
+        .await
+        .unwrap();
+        assert_eq!(responder.tcp().peer_identity::<u64>(initiator_addr), Some(2));
+
+        // Ensure the peers that don't prove their identity are rejected.
+        let anonymous = IdentityNode(Tcp::new(Config::default()), 0);
+        anonymous.enable_handshake().await;
+        let _ = anonymous.tcp().connect(responder_ip).await;
+        sleep(Duration::from_millis(100)).await;
+        assert_eq!(responder.tcp().num_connected(), 1);
+        assert_ne!(responder.tcp().stats().handshake_failures(), 0);
+    }
+
+    /// Issues a certificate for "localhost" signed by the given authority, and returns the TLS setup presenting it.
+    fn tls_config(ca: &rcgen::Certificate, trusted_ca: &rcgen::Certificate) -> TlsConfig {
+        let cert =
+            rcgen::Certificate::from_params(rcgen::CertificateParams::new(vec!["localhost".to_string()])).unwrap();
+        let cert_chain = vec![cert.serialize_der_with_signer(ca).unwrap().into()];
+        let key = rustls::pki_types::PrivatePkcs8KeyDer::from(cert.serialize_private_key_der()).into();
+        let trusted_roots = vec![trusted_ca.serialize_der().unwrap().into()];
+
+        TlsConfig::new(cert_chain, key, trusted_roots, true).unwrap().with_server_name("localhost".to_string())
+    }
+
+    #[tokio::test]
+    async fn test_tls() {
+        let new_ca = || {
+            let mut params = rcgen::CertificateParams::new(vec![]);
+            params.is_ca = rcgen::IsCa::Ca(rcgen::BasicConstraints::Unconstrained);
+            rcgen::Certificate::from_params(params).unwrap()
+        };
+        let ca = new_ca();
+        let rogue_ca = new_ca();
+
+        // Initialize the nodes trusting the same authority, and connect them.
+        let node = WritingNode(Tcp::new(Config { tls: Some(tls_config(&ca, &ca)), ..Default::default() }));
+        node.enable_writing().await;
+        let peer = FramingNode(
+            Tcp::new(Config {
+                listener_ip: Some(IpAddr::V4(Ipv4Addr::LOCALHOST)),
+                tls: Some(tls_config(&ca, &ca)),
+                ..Default::default()
+            }),
+            Default::default(),
+        );
+        peer.enable_reading().await;
+        let peer_ip = peer.tcp().enable_listener().await.unwrap();
+        node.tcp().connect(peer_ip).await.unwrap();
+
+        // Ensure the messages are delivered over the secured connection.
+        node.unicast(peer_ip, vec![7u8; 100].into()).unwrap().await.unwrap().unwrap();
+        while peer.1.lock().is_empty() {
+            sleep(Duration::from_millis(10)).await;
+        }
+        assert_eq!(&peer.1.lock()[0][..], &[7u8; 100][..]);
+
+        // Ensure the certificates signed by an untrusted authority are rejected.
+        let rogue = Tcp::new(Config { tls: Some(tls_config(&rogue_ca, &rogue_ca)), ..Default::default() });
+        assert!(rogue.connect(peer_ip).await.is_err());
+        assert!(!rogue.is_connected(peer_ip));
+        sleep(Duration::from_millis(100)).await;
+        assert_eq!(peer.tcp().num_connected(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_read_buffer_capacity() {
+        const NUM_MESSAGES: usize = 8;
+        const MESSAGE_SIZE: usize = 256 * 1024;
+
+        // Returns the number of read buffer growths needed to receive a batch of big messages.
+        async fn count_buffer_growths(read_buffer_capacity: Option<usize>) -> usize {
+            let node = WritingNode(Tcp::new(Default::default()));
+            node.enable_writing().await;
+            let peer = CapacityNode(
+                Tcp::new(Config {
+                    listener_ip: Some(IpAddr::V4(Ipv4Addr::LOCALHOST)),
+                    read_buffer_capacity,
+                    ..Default::default()
+                }),
+                Default::default(),
+                Default::default(),
+            );
+            peer.enable_reading().await;
+            let peer_ip = peer.tcp().enable_listener().await.unwrap();
+            node.tcp().connect(peer_ip).await.unwrap();
+
+            for _ in 0..NUM_MESSAGES {
+                node.unicast(peer_ip, vec![0u8; MESSAGE_SIZE].into()).unwrap().await.unwrap().unwrap();
+            }
+            while peer.1.load(Relaxed) != NUM_MESSAGES {
+                sleep(Duration::from_millis(10)).await;
+            }
+
+            peer.2.load(Relaxed)
+        }
+
+        // Ensure a buffer that can fit the messages from the start doesn't need to be reallocated.
+        let small_buffer_growths = count_buffer_growths(Some(0)).await;
+        let large_buffer_growths = count_buffer_growths(Some(1024 * 1024)).await;
+        assert!(small_buffer_growths > 0);
+        assert_eq!(large_buffer_growths, 0);
+
+        // Ensure the default capacity falls back to the one of the Reading protocol.
+        assert_eq!(count_buffer_growths(None).await, 0);
+    }
+
+    #[tokio::test]
+    async fn test_task_names() {
+        let node = FramingNode(
+            Tcp::new(Config { listener_ip: Some(IpAddr::V4(Ipv4Addr::LOCALHOST)), ..Default::default() }),
+            Default::default(),
+        );
+        node.enable_reading().await;
+        let node_ip = node.tcp().enable_listener().await.unwrap();
+        let peer = WritingNode(Tcp::new(Default::default()));
+        peer.enable_writing().await;
+        peer.tcp().connect(node_ip).await.unwrap();
+
+        // Ensure the node-wide and the per-connection tasks are named.
+        assert_eq!(peer.tcp().task_names(), vec!["writing handler".to_owned(), format!("writer for {node_ip}")]);
+        while node.tcp().num_connected() != 1 {
+            sleep(Duration::from_millis(10)).await;
+        }
+        let node_names = node.tcp().task_names();
+        assert!(node_names.contains(&format!("listener on {node_ip}")));
+        assert!(node_names.contains(&"reading handler".to_owned()));
+        assert!(node_names.iter().any(|name| name.starts_with("reader for")));
+
+        // Ensure the names of the tasks of a connection are gone along with it.
+        peer.tcp().disconnect(node_ip).await;
+        assert_eq!(peer.tcp().task_names(), vec!["writing handler"]);
+    }
+
+    #[tokio::test]
+    async fn test_warmup_buffer() {
+        let peer = FramingNode(
+            Tcp::new(Config { listener_ip: Some(IpAddr::V4(Ipv4Addr::LOCALHOST)), ..Default::default() }),
+            Default::default(),
+        );
+        peer.enable_reading().await;
+        let peer_ip = peer.tcp().enable_listener().await.unwrap();
+        let node = WarmupNode(Tcp::new(Config { warmup_buffer_size: Some(3), ..Default::default() }));
+        node.enable_handshake().await;
+        node.enable_writing().await;
+
+        // Starts connecting to the peer, and waits until the connection is pending.
+        async fn start_connecting(node: &Tcp, peer_ip: SocketAddr) -> tokio::task::JoinHandle<io::Result<()>> {
+            let tcp = node.clone();
+            let connect = tokio::spawn(async move { tcp.connect(peer_ip).await });
+            while !node.is_connecting(peer_ip) {
+                sleep(Duration::from_millis(1)).await;
+            }
+            connect
+        }
+
+        // Ensure the messages sent before the connection is ready are delivered in order once it is.
+        let connect = start_connecting(node.tcp(), peer_ip).await;
+        let deliveries = (0..3u8).map(|i| node.unicast(peer_ip, vec![i; 8].into()).unwrap()).collect::<Vec<_>>();
+        connect.await.unwrap().unwrap();
+        for delivery in deliveries {
+            delivery.await.unwrap().unwrap();
+        }
+        while peer.1.lock().len() != 3 {
+            sleep(Duration::from_millis(10)).await;
+        }
+        assert_eq!(peer.1.lock().iter().map(|message| message[0]).collect::<Vec<_>>(), vec![0, 1, 2]);
+
+        // Ensure the connection is dropped if too many messages are sent before it's ready.
+        node.tcp().disconnect(peer_ip).await;
+        let connect = start_connecting(node.tcp(), peer_ip).await;
+        for i in 0..3u8 {
+            node.unicast(peer_ip, vec![i; 8].into()).unwrap();
+        }
+        assert_eq!(node.unicast(peer_ip, vec![3; 8].into()).unwrap_err().kind(), io::ErrorKind::WouldBlock);
+        assert_eq!(connect.await.unwrap().unwrap_err().kind(), io::ErrorKind::ConnectionAborted);
+        assert!(!node.tcp().is_connected(peer_ip));
+    }
+
+    #[tokio::test]
+    async fn test_rebind_listener() {
+        let node = Tcp::new(Config { listener_ip: Some(IpAddr::V4(Ipv4Addr::LOCALHOST)), ..Default::default() });
+        let old_ip = node.enable_listener().await.unwrap();
+        let peer = Tcp::new(Default::default());
+        peer.connect(old_ip).await.unwrap();
+        while node.num_connected() != 1 {
+            sleep(Duration::from_millis(10)).await;
+        }
+
+        // Ensure the old listener remains in place if the new address can't be bound.
+        let occupied = std::net::TcpListener::bind((Ipv4Addr::LOCALHOST, 0)).unwrap();
+        assert!(node.rebind_listener(occupied.local_addr().unwrap()).await.is_err());
+        assert_eq!(node.listening_addr().unwrap(), old_ip);
+
+        // Ensure the new inbound connections land on the new address, and the existing ones are retained.
+        let new_ip = node.rebind_listener(SocketAddr::from((Ipv4Addr::LOCALHOST, 0))).await.unwrap();
+        assert_ne!(new_ip, old_ip);
+        assert_eq!(node.listening_addr().unwrap(), new_ip);
+        let listener_names =
+            node.task_names().into_iter().filter(|name| name.starts_with("listener")).collect::<Vec<_>>();
+        assert_eq!(listener_names, vec![format!("listener on {new_ip}")]);
+        let new_peer = Tcp::new(Default::default());
+        new_peer.connect(new_ip).await.unwrap();
+        while node.num_connected() != 2 {
+            sleep(Duration::from_millis(10)).await;
+        }
+        assert!(peer.is_connected(old_ip));
+
+        // Ensure the old address is no longer listened on.
+        timeout(Duration::from_secs(1), async {
+            while TcpStream::connect(old_ip).await.is_ok() {
+                sleep(Duration::from_millis(10)).await;
+            }
+        })
+        .await
+        .unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_pause_listener() {
+        let node = Tcp::new(Config { listener_ip: Some(IpAddr::V4(Ipv4Addr::LOCALHOST)), ..Default::default() });
+        let node_ip = node.enable_listener().await.unwrap();
+        let new_peer = || Tcp::new(Config { listener_ip: Some(IpAddr::V4(Ipv4Addr::LOCALHOST)), ..Default::default() });
+
+        // Connect a peer before pausing the listener.
+        let peer = new_peer();
+        peer.connect(node_ip).await.unwrap();
+        while node.num_connected() != 1 {
+            sleep(Duration::from_millis(10)).await;
+        }
+
+        // Ensure the inbound connections are refused while the listener is paused.
+        node.pause_listener();
+        assert!(node.is_listening_paused());
+        let refused_peer = new_peer();
+        let _ = refused_peer.connect(node_ip).await;
+        sleep(Duration::from_millis(100)).await;
+        assert_eq!(node.num_connected(), 1);
+        assert_eq!(node.num_connecting(), 0);
+
+        // Ensure the existing connections and the outbound ones are unaffected.
+        let outbound_peer = new_peer();
+        let outbound_peer_ip = outbound_peer.enable_listener().await.unwrap();
+        node.connect(outbound_peer_ip).await.unwrap();
+        assert_eq!(node.num_connected(), 2);
+
+        // Ensure the inbound connections are accepted again once the listener is resumed.
+        node.resume_listener();
+        assert!(!node.is_listening_paused());
+        let accepted_peer = new_peer();
+        accepted_peer.connect(node_ip).await.unwrap();
+        while node.num_connected() != 3 {
+            sleep(Duration::from_millis(10)).await;
+        }
+    }
+
+    #[tokio::test]
+    async fn test_network_magic() {
+        let config = |network_magic: [u8; 4]| Config {
+            listener_ip: Some(IpAddr::V4(Ipv4Addr::LOCALHOST)),
+            network_magic: Some(network_magic),
+            ..Default::default()
+        };
+        let node = Tcp::new(config(*b"TEST"));
+        let node_ip = node.enable_listener().await.unwrap();
+
+        // Ensure the connections with a matching magic are established.
+        let peer = Tcp::new(config(*b"TEST"));
+        peer.connect(node_ip).await.unwrap();
+        assert!(peer.is_connected(node_ip));
+
+        // Ensure the ones from another network are dropped.
+        let foreign_peer = Tcp::new(config(*b"MAIN"));
+        let err = foreign_peer.connect(node_ip).await.unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        assert_ne!(foreign_peer.known_peers().get(node_ip).unwrap().failures(), 0);
+
+        // Ensure that a stream of garbage is rejected right after its first bytes.
+        let mut stream = TcpStream::connect(node_ip).await.unwrap();
+        let garbage_addr = stream.local_addr().unwrap();
+        stream.write_all(b"GET ").await.unwrap();
+        let mut received = Vec::new();
+        timeout(Duration::from_millis(500), stream.read_to_end(&mut received)).await.unwrap().unwrap();
+        assert_eq!(&received[..], b"TEST");
+        while node.known_peers().get(garbage_addr).map_or(true, |stats| stats.failures() == 0) {
+            sleep(Duration::from_millis(10)).await;
+        }
+        assert_eq!(node.num_connected(), 1);
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_unix_sockets() {
+        let path = std::env::temp_dir().join(format!("snarkos-tcp-test-{}.sock", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+
+        // Initialize a node listening solely on a Unix domain socket.
+        let tcp = Tcp::new(Config { max_connections: 1, ..Default::default() });
+        tcp.enable_unix_listener(&path).await.unwrap();
+        assert_eq!(tcp.listen_endpoints(), vec![ListenEndpoint::Unix(path.clone())]);
+        assert_eq!(tcp.listening_addr().unwrap_err().kind(), io::ErrorKind::AddrNotAvailable);
+
+        // Connect to the node.
+        let peer = Tcp::new(Config { max_connections: 1, ..Default::default() });
+        let node_addr = peer.connect_unix(&path).await.unwrap();
+        assert!(peer.is_connected(node_addr));
+        assert!(!peer.is_connecting(node_addr));
+
+        // Ensure the inbound connection gets registered too.
+        while tcp.num_connected() == 0 {
+            sleep(Duration::from_millis(10)).await;
+        }
+        let peer_addr = tcp.connected_addrs()[0];
+        assert_ne!(peer_addr, node_addr);
+
+        // Ensure the placeholder addresses can be used to disconnect.
+        assert!(peer.disconnect(node_addr).await);
+        assert_eq!(peer.num_connected(), 0);
+
+        // Ensure the socket file is cleaned up.
+        tcp.shut_down().await;
+        assert!(!path.exists());
+        assert!(tcp.listen_endpoints().is_empty());
+    }
 }
@@ -0,0 +1,83 @@
+// Copyright (C) 2019-2023 Aleo Systems Inc.
+// This file is part of the snarkOS library.
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at:
+// http://www.apache.org/licenses/LICENSE-2.0
+
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Helpers simplifying the tests involving connected [`Tcp`]s.
+
+use std::{
+    net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr},
+    time::Duration,
+};
+
+use tokio::time::{sleep, timeout};
+
+use crate::{Config, Tcp};
+
+/// The maximum time allowed for a connection between two test nodes to become ready.
+pub const CONNECTION_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Creates two nodes with the given configurations, and connects the first one to the second one, returning
+/// once the connection is ready on both sides. If [`Config::listener_ip`] is not set for the second node, it
+/// listens on localhost.
+///
+/// note: As the protocols have to be enabled before connecting, use [`connect_nodes`] with the nodes that
+/// implement any.
+///
+/// # Panics
+///
+/// Panics if the connection is not established within [`CONNECTION_TIMEOUT`].
+pub async fn connected_pair(config_a: Config, mut config_b: Config) -> (Tcp, Tcp) {
+    if config_b.listener_ip.is_none() {
+        config_b.listener_ip = Some(IpAddr::V4(Ipv4Addr::LOCALHOST));
+    }
+
+    let a = Tcp::new(config_a);
+    let b = Tcp::new(config_b);
+    connect_nodes(&a, &b).await;
+
+    (a, b)
+}
+
+/// Connects the first node to the second one, which needs to have its listener enabled (or have it configured,
+/// in which case it gets enabled), returning once the connection is ready on both sides.
+///
+/// # Panics
+///
+/// Panics if the connection is not established within [`CONNECTION_TIMEOUT`].
+pub async fn connect_nodes(a: &Tcp, b: &Tcp) {
+    let b_addr = match b.listening_addr() {
+        Ok(addr) => addr,
+        Err(_) => {
+            b.enable_listener().await.unwrap_or_else(|e| panic!("couldn't enable the listener of {}: {e}", b.name()))
+        }
+    };
+    // A node listening on an unspecified address can be reached via the loopback one.
+    let b_addr = match b_addr.ip() {
+        IpAddr::V4(ip) if ip.is_unspecified() => SocketAddr::from((Ipv4Addr::LOCALHOST, b_addr.port())),
+        IpAddr::V6(ip) if ip.is_unspecified() => SocketAddr::from((Ipv6Addr::LOCALHOST, b_addr.port())),
+        _ => b_addr,
+    };
+
+    let num_ready = || b.connected_addrs().into_iter().filter(|addr| b.is_connection_ready(*addr)).count();
+    let initial_num_ready = num_ready();
+
+    let connection = async {
+        a.connect(b_addr).await.unwrap_or_else(|e| panic!("{} couldn't connect to {}: {e}", a.name(), b.name()));
+        while !a.is_connection_ready(b_addr) || num_ready() == initial_num_ready {
+            sleep(Duration::from_millis(10)).await;
+        }
+    };
+    if timeout(CONNECTION_TIMEOUT, connection).await.is_err() {
+        panic!("the connection between {} and {} wasn't ready within {CONNECTION_TIMEOUT:?}", a.name(), b.name());
+    }
+}
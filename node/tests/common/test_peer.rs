@@ -15,7 +15,7 @@
 use snarkos_account::Account;
 use snarkos_node_router::{
     expect_message,
-    messages::{ChallengeRequest, ChallengeResponse, Message, MessageCodec, MessageTrait, NodeType},
+    messages::{Capabilities, ChallengeRequest, ChallengeResponse, Message, MessageCodec, MessageTrait, NodeType},
 };
 use snarkvm::{
     ledger::narwhal::Data,
@@ -38,6 +38,7 @@ use pea2pea::{
     Pea2Pea,
 };
 use rand::Rng;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
 use tokio_util::codec::Framed;
 use tracing::*;
 
@@ -58,6 +59,7 @@ pub struct TestPeer {
     node: Node,
     node_type: NodeType,
     account: Account<CurrentNetwork>,
+    node_nonce: u64,
 }
 
 impl Pea2Pea for TestPeer {
@@ -80,6 +82,11 @@ impl TestPeer {
     }
 
     pub async fn new(node_type: NodeType, account: Account<CurrentNetwork>) -> Self {
+        Self::new_with_node_nonce(node_type, account, rand::random()).await
+    }
+
+    /// Creates a test peer which advertises the given node nonce during the handshake.
+    pub async fn new_with_node_nonce(node_type: NodeType, account: Account<CurrentNetwork>, node_nonce: u64) -> Self {
         let peer = Self {
             node: Node::new(Config {
                 max_connections: 200,
@@ -88,6 +95,7 @@ impl TestPeer {
             }),
             node_type,
             account,
+            node_nonce,
         };
 
         peer.enable_handshake().await;
@@ -122,6 +130,13 @@ impl Handshake for TestPeer {
         let peer_addr = conn.addr();
         let node_side = !conn.side();
         let stream = self.borrow_stream(&mut conn);
+
+        // Exchange the network magic.
+        stream.write_all(&Message::<CurrentNetwork>::NETWORK_MAGIC).await?;
+        let mut peer_magic = [0u8; 4];
+        stream.read_exact(&mut peer_magic).await?;
+        assert_eq!(peer_magic, Message::<CurrentNetwork>::NETWORK_MAGIC);
+
         let mut framed = Framed::new(stream, MessageCodec::<CurrentNetwork>::default());
 
         // Retrieve the genesis block header.
@@ -131,7 +146,14 @@ impl Handshake for TestPeer {
         match node_side {
             ConnectionSide::Initiator => {
                 // Send a challenge request to the peer.
-                let our_request = ChallengeRequest::new(local_ip.port(), self.node_type(), self.address(), rng.gen());
+                let our_request = ChallengeRequest::new(
+                    local_ip.port(),
+                    self.node_type(),
+                    self.address(),
+                    rng.gen(),
+                    self.node_nonce,
+                    Capabilities::empty(),
+                );
                 framed.send(Message::ChallengeRequest(our_request)).await?;
 
                 // Receive the peer's challenge bundle.
@@ -161,7 +183,14 @@ impl Handshake for TestPeer {
                 let our_response =
                     ChallengeResponse { genesis_header, signature: Data::Object(signature), nonce: response_nonce };
                 framed.send(Message::ChallengeResponse(our_response)).await?;
-                let our_request = ChallengeRequest::new(local_ip.port(), self.node_type(), self.address(), rng.gen());
+                let our_request = ChallengeRequest::new(
+                    local_ip.port(),
+                    self.node_type(),
+                    self.address(),
+                    rng.gen(),
+                    self.node_nonce,
+                    Capabilities::empty(),
+                );
                 framed.send(Message::ChallengeRequest(our_request)).await?;
 
                 // Listen for the challenge response.
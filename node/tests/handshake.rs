@@ -19,7 +19,7 @@ mod common;
 use common::{node::*, test_peer::TestPeer};
 
 use snarkos_node::{Client, Prover, Validator};
-use snarkos_node_router::Outbound;
+use snarkos_node_router::{messages::NodeType, Outbound};
 use snarkos_node_tcp::P2P;
 use snarkvm::prelude::{store::helpers::memory::ConsensusMemory, MainnetV0 as CurrentNetwork};
 
@@ -286,3 +286,21 @@ async fn duplicate_connection_attempts() {
     assert_eq!(node1.router().number_of_connected_peers(), 1);
     assert_eq!(node2.router().number_of_connected_peers(), 1);
 }
+
+#[tokio::test]
+async fn self_connect_via_node_nonce() {
+    // Spin up a full node.
+    let node = client().await;
+
+    // Spin up a test peer impersonating the node, i.e. using its nonce.
+    let peer = TestPeer::new_with_node_nonce(NodeType::Client, common::sample_account(), node.tcp().nonce()).await;
+
+    // Ensure the connection is rejected in both directions, even though the addresses differ.
+    assert!(node.connect(peer.listening_addr()).await.is_err());
+    assert!(peer.connect(node.listening_addr()).await.is_err());
+    // A small anti-flakiness buffer.
+    sleep(Duration::from_millis(200)).await;
+
+    assert_eq!(node.tcp().num_connected(), 0);
+    assert_eq!(node.router().number_of_connected_peers(), 0);
+}
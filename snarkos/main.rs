@@ -12,9 +12,11 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use snarkos_cli::{commands::CLI, helpers::Updater};
+use snarkos_cli::{
+    commands::{Command, CLI},
+    helpers::Updater,
+};
 
-use clap::Parser;
 use std::process::exit;
 
 #[cfg(all(target_os = "linux", target_arch = "x86_64"))]
@@ -24,11 +26,28 @@ use tikv_jemallocator::Jemalloc;
 #[global_allocator]
 static GLOBAL: Jemalloc = Jemalloc;
 
+// Note: The heap profiling can't be enabled at runtime, so it is enabled on startup but kept inactive
+// until the `--memory-profile` flag activates it; the overhead of an inactive profiler is negligible.
+#[cfg(all(target_os = "linux", target_arch = "x86_64"))]
+#[allow(non_upper_case_globals)]
+#[export_name = "_rjem_malloc_conf"]
+pub static malloc_conf: &[u8; 28] = b"prof:true,prof_active:false\0";
+
 fn main() -> anyhow::Result<()> {
-    // Parse the given arguments.
-    let cli = CLI::parse();
-    // Run the updater.
-    println!("{}", Updater::print_cli());
+    // Parse the given arguments, layered over the config file (if one is specified).
+    let cli = match CLI::parse_with_config() {
+        Ok(cli) => cli,
+        Err(error) => {
+            println!("⚠️  {error}\n");
+            exit(1);
+        }
+    };
+    // Run the updater, unless the command already reports on the updates, its output is meant to be piped,
+    // or it talks to a running node.
+    if !matches!(cli.command, Command::Completions(_) | Command::LogLevel(_) | Command::Status(_) | Command::Update(_))
+    {
+        println!("{}", Updater::print_cli(cli.command.release_channel()));
+    }
     // Run the CLI.
     match cli.command.parse() {
         Ok(output) => println!("{output}\n"),